@@ -0,0 +1,53 @@
+use bevy_generative_grammars::{generator::*, tracery::*};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+criterion_group!(benches, selection_strategies);
+criterion_main!(benches);
+
+const GRAMMAR_DEFINITION : &[(&str, &[&str])] = &[
+	("name", &["Arjun","Yuuma","Darcy","Mia","Chiaki","Izzi","Azra","Lina"]),
+	("animal", &["unicorn","raven","sparrow","scorpion","coyote","eagle","owl","lizard","zebra","duck","kitten"]),
+	("mood", &["vexed","indignant","impassioned","wistful","astute","courteous"]),
+	("origin", &["#name# traveled with her pet #animal#.  #name# was never #mood#, for the #animal# was always too #mood#."])
+];
+
+const RULES: &[&str] = &["name", "animal", "mood"];
+
+fn grammar(strategy: &str) -> TraceryGrammar {
+    let mut grammar = TraceryGrammar::new(GRAMMAR_DEFINITION, None);
+    for rule in RULES {
+        match strategy {
+            "weighted" => {
+                let options = GRAMMAR_DEFINITION
+                    .iter()
+                    .find(|(key, _)| key == rule)
+                    .map_or(0, |(_, options)| options.len());
+                grammar.set_weights(*rule, (1..=options as u32).collect::<Vec<_>>());
+            }
+            "no_repeat" => grammar.set_selection_mode(*rule, SelectionMode::NoImmediateRepeat),
+            _ => {}
+        }
+    }
+    grammar
+}
+
+fn selection_strategies(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("selection_strategies");
+    group.warm_up_time(std::time::Duration::from_millis(500));
+    group.measurement_time(std::time::Duration::from_secs(4));
+
+    for strategy in ["uniform", "weighted", "no_repeat"] {
+        let grammar = grammar(strategy);
+        group.bench_function(format!("2000_generated_stories_{strategy}"), |bencher| {
+            bencher.iter(|| {
+                let mut rng = SeededRng::new(1234);
+                let mut generator = StatefulStringGenerator::clone_grammar(&grammar);
+                for _ in 0..2000 {
+                    let _ = generator.generate(&mut rng);
+                }
+            });
+        });
+    }
+
+    group.finish();
+}
@@ -0,0 +1,95 @@
+use bevy_generative_grammars::{generator::*, tracery::*};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+criterion_group!(benches, tokenizer);
+criterion_main!(benches);
+
+const GRAMMAR_DEFINITION : &[(&str, &[&str])] = &[
+	("hero", &["Arjun","Yuuma","Darcy","Mia"]),
+	("heroPet", &["unicorn","raven","sparrow","owl"]),
+	("mood", &["vexed","indignant","impassioned","wistful","astute","courteous"]),
+	("story", &["#hero# traveled with her pet #heroPet#.  #hero# was never #mood#, for the #heroPet# was always too #mood#."]),
+	("origin", &["#story#"])
+];
+
+/// The same rules as the tracery grammar, with each option split into tokens ahead of time
+#[derive(Default)]
+struct PreTokenized {
+    keys: Vec<String>,
+    options: Vec<Vec<SimplePhrase<String, String>>>,
+}
+
+impl PreTokenized {
+    fn new() -> Self {
+        let tokenize = |option: &str| {
+            option
+                .split('#')
+                .enumerate()
+                .filter(|(_, part)| !part.is_empty())
+                .map(|(index, part)| match index % 2 {
+                    0 => SimpleToken::Terminal(part.to_string()),
+                    _ => SimpleToken::Rule(part.to_string()),
+                })
+                .collect::<Vec<_>>()
+        };
+        Self {
+            keys: GRAMMAR_DEFINITION
+                .iter()
+                .map(|(key, _)| key.to_string())
+                .collect(),
+            options: GRAMMAR_DEFINITION
+                .iter()
+                .map(|(_, options)| options.iter().map(|option| tokenize(option)).collect())
+                .collect(),
+        }
+    }
+}
+
+impl SimpleGrammar for PreTokenized {
+    type Key = String;
+    type Terminal = String;
+
+    fn rule_keys(&self) -> &Vec<String> {
+        &self.keys
+    }
+
+    fn rule_options(&self, rule: &String) -> Option<&Vec<SimplePhrase<String, String>>> {
+        let index = self.keys.iter().position(|key| key == rule)?;
+        self.options.get(index)
+    }
+
+    fn default_starting_point(&self) -> &String {
+        &self.keys[self.keys.len() - 1]
+    }
+}
+
+fn tokenizer(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("tokenizer");
+    group.warm_up_time(std::time::Duration::from_millis(500));
+    group.measurement_time(std::time::Duration::from_secs(4));
+
+    let grammar = TraceryGrammar::new(GRAMMAR_DEFINITION, None);
+    group.bench_function(
+        "2000_generated_stories_tokenized_while_generating",
+        |bencher| {
+            bencher.iter(|| {
+                let mut rng = SeededRng::new(1234);
+                for _ in 0..2000 {
+                    let _ = StringGenerator::generate(&grammar, &mut rng);
+                }
+            });
+        },
+    );
+
+    let grammar = PreTokenized::new();
+    group.bench_function("2000_generated_stories_pre_tokenized", |bencher| {
+        bencher.iter(|| {
+            let mut rng = SeededRng::new(1234);
+            for _ in 0..2000 {
+                let _ = SimpleGenerator::generate(&grammar, &mut rng);
+            }
+        });
+    });
+
+    group.finish();
+}
@@ -1,7 +1,12 @@
 use bevy_generative_grammars::{generator::*, tracery::*};
 use criterion::{criterion_group, criterion_main, Criterion};
 
-criterion_group!(benches, stateful_generator_complex, stateful_generator);
+criterion_group!(
+    benches,
+    stateful_generator_complex,
+    stateful_generator,
+    depth_first_processing_complex
+);
 criterion_main!(benches);
 
 const COMPLEX_GRAMMAR_DEFINITION : &[(&str, &[&str])] = &[
@@ -79,3 +84,37 @@ fn stateful_generator(criterion: &mut Criterion) {
 
     group.finish();
 }
+
+/// Processes the complex grammar depth first directly, so the processing itself is measured without the generator around it
+fn depth_first_processing_complex(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("depth_first_processing_complex");
+    group.warm_up_time(std::time::Duration::from_millis(500));
+    group.measurement_time(std::time::Duration::from_secs(4));
+
+    for num_runs in (1..5).map(|i| i * 2 * 1000) {
+        group.bench_function(format!("{num_runs}_processed_complex_stories"), |bencher| {
+            bencher.iter(|| {
+                let mut next_value = 0;
+                let mut rng = |len| {
+                    let value = next_value;
+                    if next_value + 1 < len {
+                        next_value += 1;
+                    } else {
+                        next_value = 0;
+                    }
+
+                    value
+                };
+                let grammar = TraceryGrammar::new(COMPLEX_GRAMMAR_DEFINITION, None);
+                let origin = "#origin#".to_string();
+                for _ in 0..num_runs {
+                    let mut temporary_grammar = TraceryGrammar::empty();
+                    let _ =
+                        grammar.depth_first_processing(&origin, &mut temporary_grammar, &mut rng);
+                }
+            });
+        });
+    }
+
+    group.finish();
+}
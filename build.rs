@@ -1,5 +1,7 @@
 fn main() {
-    if cfg!(target_os = "linux") {
+    // Only bevy needs vulkan, so builds without it - like servers and tooling - don't have to link it
+    let bevy = std::env::var_os("CARGO_FEATURE_BEVY").is_some();
+    if bevy && std::env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("linux") {
         println!("cargo:rustc-link-lib=vulkan");
     }
 }
@@ -1,7 +1,11 @@
 use bevy::prelude::*;
 use bevy_generative_grammars::{
     generator::*,
-    tracery::{tracery_asset::TraceryAssetPlugin, StatefulStringGenerator, TraceryGrammar},
+    tracery::{
+        tracery_asset::TraceryAssetPlugin,
+        tracery_commands::{GeneratorReady, SpawnGeneratorExt},
+        StatefulStringGenerator,
+    },
 };
 use bevy_turborand::rng::Rng;
 
@@ -29,41 +33,24 @@ fn main() {
         .run();
 }
 
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let grammar = GrammarHandle(asset_server.load("story.json"), false);
-    commands.insert_resource(grammar);
+fn setup(mut commands: Commands) {
+    println!("Loading Grammar File...");
+    commands
+        .spawn_generator("story.json", "origin")
+        .insert(NextPrompt("origin".to_string()));
 }
 
-fn loaded_grammar(
-    mut commands: Commands,
-    mut handle: ResMut<GrammarHandle>,
-    grammars: ResMut<Assets<TraceryGrammar>>,
-) {
-    if handle.1 {
-        return;
-    }
-
-    if let Some(grammar) = grammars.get(&handle.0) {
-        handle.1 = true;
+fn loaded_grammar(ready: Query<(), Added<GeneratorReady>>) {
+    for _ in ready.iter() {
         println!("Starting New Story!");
-        commands.spawn((
-            StatefulStringGenerator::clone_grammar(grammar),
-            NextPrompt("origin".to_string()),
-        ));
-    } else {
-        println!("Loading Grammar File...");
     }
 }
 
 #[derive(Component)]
 struct NextPrompt(String);
 
-#[derive(Resource)]
-struct GrammarHandle(Handle<TraceryGrammar>, bool);
-
 fn progress_story(
     mut commands: Commands,
-    mut handle: ResMut<GrammarHandle>,
     mut query: Query<(Entity, &mut StatefulStringGenerator, &mut NextPrompt)>,
 ) {
     let mut rng = TurboRandOwned::new(Rng::new());
@@ -81,7 +68,9 @@ fn progress_story(
                     commands.entity(entity).despawn();
 
                     println!("Story Complete...");
-                    handle.1 = false;
+                    commands
+                        .spawn_generator("story.json", "origin")
+                        .insert(NextPrompt("origin".to_string()));
                 }
             }
         } else {
@@ -0,0 +1,105 @@
+//! This simulates a rollback networked game, in the style of GGRS. Each frame's state - including the narrator's generator
+//! and random number generator - is saved, so when a late input arrives the game rewinds and simulates the frames again.
+//! At the end, both peers compare the hashes of their narrators to check they didn't desync.
+
+use bevy_generative_grammars::{
+    generator::*,
+    tracery::{GeneratorSnapshot, SelectionMode, StatefulStringGenerator, TraceryGrammar},
+};
+
+const RULES: &[(&str, &[&str])] = &[
+    ("origin", &["[foe:#monster#]A #foe# appears!"]),
+    ("monster", &["goblin", "troll", "wyvern", "slime"]),
+    (
+        "attack",
+        &[
+            "You strike the #foe#.",
+            "The #foe# staggers.",
+            "A clean hit on the #foe#!",
+        ],
+    ),
+    ("wait", &["You circle the #foe#.", "The #foe# growls."]),
+];
+
+/// Everything that needs to be rewound when rolling back
+#[derive(Clone)]
+struct SavedFrame {
+    narrator: GeneratorSnapshot,
+    rng: SeededRng,
+}
+
+struct Peer {
+    narrator: StatefulStringGenerator,
+    rng: SeededRng,
+    saved: Vec<SavedFrame>,
+    log: Vec<String>,
+}
+
+impl Peer {
+    fn new() -> Self {
+        let grammar =
+            TraceryGrammar::new(RULES, None).with_selection_mode("attack", SelectionMode::Shuffle);
+        let mut narrator = StatefulStringGenerator::from_grammar(grammar);
+        let mut rng = SeededRng::new(2024);
+        let intro = narrator.generate(&mut rng).unwrap_or_default();
+        Self {
+            narrator,
+            rng,
+            saved: vec![],
+            log: vec![intro],
+        }
+    }
+
+    /// Simulates a single frame, saving the state it started from
+    fn advance_frame(&mut self, attacking: bool) {
+        self.saved.push(SavedFrame {
+            narrator: self.narrator.snapshot(),
+            rng: self.rng.clone(),
+        });
+        let rule = if attacking { "attack" } else { "wait" };
+        if let Some(line) = self.narrator.generate_at(&rule.to_string(), &mut self.rng) {
+            self.log.push(line);
+        }
+    }
+
+    /// Rewinds to the start of a frame, dropping everything simulated since
+    fn load_frame(&mut self, frame: usize) {
+        let saved = self.saved[frame].clone();
+        self.narrator.restore(&saved.narrator);
+        self.rng = saved.rng;
+        self.saved.truncate(frame);
+        self.log.truncate(frame + 1);
+    }
+}
+
+fn main() {
+    let inputs = [false, true, true, false, true, true];
+
+    // This peer receives every input on time
+    let mut host = Peer::new();
+    for attacking in inputs {
+        host.advance_frame(attacking);
+    }
+
+    // This peer predicts that the inputs for frames 2 and 3 repeat the previous one, and rolls back once the real inputs arrive
+    let mut client = Peer::new();
+    let mut predicted = inputs;
+    predicted[2] = predicted[1];
+    predicted[3] = predicted[1];
+    for attacking in predicted.iter().take(4) {
+        client.advance_frame(*attacking);
+    }
+    println!("Frame 3 - the inputs for frames 2 and 3 arrive late, so the client rolls back");
+    client.load_frame(2);
+    for attacking in inputs.iter().skip(2) {
+        client.advance_frame(*attacking);
+    }
+
+    for line in client.log.iter() {
+        println!("{line}");
+    }
+    let (host_hash, client_hash) = (host.narrator.state_hash(), client.narrator.state_hash());
+    println!("Host state: {host_hash:016x}, client state: {client_hash:016x}");
+    assert_eq!(host.log, client.log);
+    assert_eq!(host_hash, client_hash, "the peers desynced");
+}
@@ -0,0 +1,89 @@
+//! This is a story toy for the browser - every click on the page generates a new story. It doesn't use bevy, so it stays small.
+//! Build it, and generate the bindings using `wasm-bindgen`:
+//!
+//! ```sh
+//! cargo build --example wasm_story --target wasm32-unknown-unknown --no-default-features
+//! wasm-bindgen --out-dir examples/wasm --target web target/wasm32-unknown-unknown/debug/examples/wasm_story.wasm
+//! ```
+//!
+//! Then serve the `examples/wasm` folder using any static file server, and open it's `index.html`.
+//! Running the example natively prints a few stories instead.
+
+use bevy_generative_grammars::{
+    generator::*,
+    tracery::{StatefulStringGenerator, VariableLifetime},
+};
+
+/// Every story picks a new hero and pet, so the variables only last for one generation
+fn story_generator() -> StatefulStringGenerator {
+    StatefulStringGenerator::new(
+        &[
+            (
+                "origin",
+                &["[hero:#name#][pet:#animal#]#story#"],
+            ),
+            (
+                "story",
+                &[
+                    "#hero# and their #pet# set out to find the #treasure#. After #trials#, #hero# finally found it #place#.",
+                    "Nobody believed #hero# when they said a #pet# had stolen the #treasure#. So #hero# followed it #place#, and #ending#.",
+                ],
+            ),
+            ("name", &["Ada", "Bram", "Cleo", "Dov", "Esme"]),
+            ("animal", &["fox", "crow", "tortoise", "goat"]),
+            (
+                "treasure",
+                &["lost crown", "last lantern", "map of the moon", "silver kettle"],
+            ),
+            (
+                "trials",
+                &["three storms", "a very long nap", "an argument with a bridge"],
+            ),
+            (
+                "place",
+                &["under the old mill", "inside a cloud", "at the bottom of the well"],
+            ),
+            (
+                "ending",
+                &["they became friends", "the #pet# kept it anyway", "everyone had tea"],
+            ),
+        ],
+        None,
+    )
+    .with_variable_lifetime(VariableLifetime::Generation)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    use wasm_bindgen::{closure::Closure, JsCast};
+
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .expect("the example needs to run in a web page");
+    let story = document
+        .get_element_by_id("story")
+        .expect("the page needs an element with the `story` id");
+    let mut generator = story_generator();
+    let mut rng = SeededRng::new(js_sys::Date::now() as u64);
+    let mut tell = move || {
+        story.set_text_content(generator.generate(&mut rng).as_deref());
+    };
+    tell();
+    let on_click = Closure::<dyn FnMut()>::new(tell);
+    document
+        .add_event_listener_with_callback("click", on_click.as_ref().unchecked_ref())
+        .expect("couldn't listen for clicks");
+    // The listener lives as long as the page, so it is never dropped
+    on_click.forget();
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    println!("This example is meant for the browser - see the top of examples/wasm_story.rs for how to build it.");
+    let mut generator = story_generator();
+    for seed in 0..3 {
+        if let Some(story) = generator.generate(&mut SeededRng::new(seed)) {
+            println!("\n{story}");
+        }
+    }
+}
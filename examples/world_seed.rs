@@ -0,0 +1,157 @@
+//! This generates a small world from a single seed - the names of it's regions, the people living there and the quests they give.
+//! The world seed is held in a `GrammarRng`, and every grammar gets it's own stream of it - so the same seed always produces the same world,
+//! and changing one grammar never shifts the output of the others.
+//!
+//! Like the other examples, this is a terminal demo - the app runs without a window, printing the world to the terminal using
+//! a custom runner. Press Enter to show the world again, type `r` to reroll it, type a number to use it as the seed, or type
+//! `exit` to exit.
+
+use bevy::prelude::*;
+use bevy_generative_grammars::{
+    generator::*,
+    tracery::{StatefulStringGenerator, StringGenerator, TraceryGrammar},
+};
+
+const NAME_RULES: &[(&str, &[&str])] = &[
+    ("origin", &["#start##end#", "#start##middle##end#"]),
+    (
+        "start",
+        &["Al", "Bri", "Cor", "Da", "El", "Fen", "Gar", "Is"],
+    ),
+    ("middle", &["an", "e", "ol", "ri"]),
+    ("end", &["a", "dric", "wyn", "os", "eth", "ia"]),
+];
+
+const REGION_RULES: &[(&str, &[&str])] = &[
+    ("origin", &["#prefix##suffix#", "The #adjective# #place#"]),
+    (
+        "prefix",
+        &["Ash", "Bright", "Cold", "Elder", "Grim", "Raven"],
+    ),
+    (
+        "suffix",
+        &["ford", "hollow", "mere", "moor", "vale", "watch"],
+    ),
+    ("adjective", &["Drowned", "Silent", "Burning", "Endless"]),
+    ("place", &["Marshes", "Peaks", "Woods", "Wastes"]),
+];
+
+const QUEST_RULES: &[(&str, &[&str])] = &[
+    (
+        "origin",
+        &[
+            "#giver# asks you to #task# near #region#.",
+            "In #region#, #giver# needs someone to #task#.",
+        ],
+    ),
+    (
+        "task",
+        &[
+            "recover a stolen #item#",
+            "drive off the #monster.s#",
+            "escort a caravan of #item.s#",
+        ],
+    ),
+    ("item", &["relic", "lantern", "crown", "map"]),
+    ("monster", &["boar", "bandit", "wraith", "troll"]),
+];
+
+/// The streams each grammar draws from. New grammars get new streams, so they never change existing content.
+const NAME_STREAM: u64 = u64::MAX - 1;
+const REGION_STREAM: u64 = u64::MAX - 2;
+const QUEST_STREAM: u64 = u64::MAX - 3;
+
+const REGIONS: u64 = 3;
+
+/// Creates the random number generator for one grammar, within one region of the world
+fn region_rng(seed: &GrammarRng, stream: u64, region: u64) -> SeededRng {
+    SeededRng::for_stream(seed.stream(stream).next_u64(), region)
+}
+
+/// The grammars used to generate the world
+#[derive(Resource)]
+struct WorldGrammars {
+    names: TraceryGrammar,
+    regions: TraceryGrammar,
+    quests: TraceryGrammar,
+}
+
+/// What the player reads, printed to the terminal by `show_world`
+#[derive(Resource, Default)]
+struct Display(Vec<String>);
+
+fn terminal_runner(mut app: App) {
+    println!("Press Enter to show the world, type 'r' to reroll it, type a number to use it as the seed, or type 'exit' to exit");
+    app.update();
+    for line in std::io::stdin().lines() {
+        let typed = line.unwrap_or_default();
+        match typed.trim() {
+            "exit" => return,
+            "r" => {
+                let mut seed = app.world.resource_mut::<GrammarRng>();
+                let next = SeededRng::new(seed.seed()).next_u64();
+                seed.reseed(next);
+            }
+            typed => {
+                if let Ok(number) = typed.parse() {
+                    app.world.resource_mut::<GrammarRng>().reseed(number);
+                }
+            }
+        }
+        app.update();
+    }
+}
+
+fn main() {
+    App::new()
+        .set_runner(terminal_runner)
+        .insert_resource(GrammarRng::new(2024))
+        .insert_resource(WorldGrammars {
+            names: TraceryGrammar::new(NAME_RULES, None),
+            regions: TraceryGrammar::new(REGION_RULES, None),
+            quests: TraceryGrammar::new(QUEST_RULES, None),
+        })
+        .init_resource::<Display>()
+        .add_systems(
+            Update,
+            (
+                generate_world.run_if(resource_changed::<GrammarRng>()),
+                show_world,
+            )
+                .chain(),
+        )
+        .run();
+}
+
+fn generate_world(
+    seed: Res<GrammarRng>,
+    grammars: Res<WorldGrammars>,
+    mut display: ResMut<Display>,
+) {
+    display.0 = vec![format!("World {}", seed.seed())];
+    for region in 0..REGIONS {
+        let name = StringGenerator::generate(
+            &grammars.regions,
+            &mut region_rng(&seed, REGION_STREAM, region),
+        )
+        .unwrap_or_default();
+        let giver =
+            StringGenerator::generate(&grammars.names, &mut region_rng(&seed, NAME_STREAM, region))
+                .unwrap_or_default();
+        // The quest refers to the region and it's quest giver, so they are provided as tokens
+        let mut quests = StatefulStringGenerator::clone_grammar(&grammars.quests);
+        quests.set_token("region", name.clone());
+        quests.set_token("giver", giver.clone());
+        let quest = quests
+            .generate(&mut region_rng(&seed, QUEST_STREAM, region))
+            .unwrap_or_default();
+        display.0.push(format!("  {name} - home of {giver}"));
+        display.0.push(format!("    {quest}"));
+    }
+}
+
+fn show_world(display: Res<Display>) {
+    for line in display.0.iter() {
+        println!("{line}");
+    }
+}
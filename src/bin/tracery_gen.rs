@@ -0,0 +1,218 @@
+//! `tracery-gen` loads a grammar file, validates it and prints a few generations from it - so grammars can be tried out
+//! without launching a bevy app. Run `tracery-gen --help` for it's options.
+
+use std::{path::Path, process::ExitCode};
+
+use bevy_generative_grammars::{
+    generator::*,
+    tracery::{GrammarDefinition, StringGenerator, TraceryGrammar},
+};
+
+const USAGE: &str = "Usage: tracery-gen <grammar> [options]
+
+Loads a JSON or RON grammar, validates it and prints generations from it.
+Word lists referenced using `@file(...)` are read relative to the grammar.
+
+Options:
+  -n, --count <count>      The number of generations to print (default: 5)
+  -s, --seed <seed>        The seed to generate from (default: 0)
+  -r, --rule <rule>        The rule to generate from (default: the grammar's starting point)
+  -f, --format <format>    The grammar's format - json, ron or definition (default: from the extension)
+      --check              Only validate the grammar, without generating
+      --strict             Fail if validation finds any problems
+  -h, --help               Print this message";
+
+/// The formats grammars can be written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// A `TraceryGrammar` or a `GrammarDefinition` written in JSON
+    Json,
+    /// A `TraceryGrammar` or a `GrammarDefinition` written in RON
+    Ron,
+    /// A `GrammarDefinition` written in RON
+    Definition,
+}
+
+impl Format {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "json" => Ok(Self::Json),
+            "ron" => Ok(Self::Ron),
+            "definition" => Ok(Self::Definition),
+            _ => Err(format!("unknown format `{name}`")),
+        }
+    }
+
+    /// Picks the format from the grammar's extension. Either format can hold a `TraceryGrammar` or a `GrammarDefinition`.
+    fn from_path(path: &Path) -> Result<Self, String> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Ok(Self::Json),
+            Some("ron") => Ok(Self::Ron),
+            _ => Err(format!(
+                "couldn't tell the format of `{}` - use --format",
+                path.display()
+            )),
+        }
+    }
+}
+
+/// The options the CLI was run with
+#[derive(Debug)]
+struct Options {
+    grammar: String,
+    count: usize,
+    seed: u64,
+    rule: Option<String>,
+    format: Option<Format>,
+    check: bool,
+    strict: bool,
+}
+
+impl Options {
+    /// Parses the options, returning `None` if help was requested
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut grammar = None;
+        let mut options = Options {
+            grammar: String::new(),
+            count: 5,
+            seed: 0,
+            rule: None,
+            format: None,
+            check: false,
+            strict: false,
+        };
+        while let Some(arg) = args.next() {
+            let mut value =
+                |name: &str| args.next().ok_or_else(|| format!("`{name}` needs a value"));
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "-n" | "--count" => {
+                    options.count = value(&arg)?
+                        .parse()
+                        .map_err(|_| "the count should be a number".to_string())?;
+                }
+                "-s" | "--seed" => {
+                    options.seed = value(&arg)?
+                        .parse()
+                        .map_err(|_| "the seed should be a number".to_string())?;
+                }
+                "-r" | "--rule" => options.rule = Some(value(&arg)?),
+                "-f" | "--format" => options.format = Some(Format::parse(&value(&arg)?)?),
+                "--check" => options.check = true,
+                "--strict" => options.strict = true,
+                _ if arg.starts_with('-') => return Err(format!("unknown option `{arg}`")),
+                _ if grammar.is_none() => grammar = Some(arg),
+                _ => return Err(format!("unexpected argument `{arg}`")),
+            }
+        }
+        options.grammar = grammar.ok_or_else(|| "no grammar was provided".to_string())?;
+        Ok(Some(options))
+    }
+}
+
+/// Loads the grammar, resolving any word lists it references
+fn load(path: &Path, format: Format) -> Result<TraceryGrammar, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|error| format!("couldn't read `{}`: {error}", path.display()))?;
+    let mut grammar = match format {
+        Format::Json => {
+            TraceryGrammar::from_json_file_str(&content).map_err(|error| error.to_string())
+        }
+        Format::Ron => {
+            TraceryGrammar::from_ron_file_str(&content).map_err(|error| error.to_string())
+        }
+        Format::Definition => GrammarDefinition::from_ron(&content)
+            .map(Into::into)
+            .map_err(|error| error.to_string()),
+    }
+    .map_err(|error| format!("couldn't parse `{}`: {error}", path.display()))?;
+    let directory = path.parent().unwrap_or(Path::new(""));
+    grammar
+        .resolve_word_lists(|list| std::fs::read_to_string(directory.join(list)).ok())
+        .map_err(|error| error.to_string())?;
+    Ok(grammar)
+}
+
+fn run(options: Options) -> Result<(), String> {
+    let path = Path::new(&options.grammar);
+    let format = match options.format {
+        Some(format) => format,
+        None => Format::from_path(path)?,
+    };
+    let grammar = load(path, format)?;
+    let rule = options
+        .rule
+        .unwrap_or_else(|| grammar.default_starting_point().clone());
+    if !grammar.has_rule(&rule) {
+        return Err(format!("the rule `{rule}` doesn't exist"));
+    }
+
+    let findings = grammar.validate();
+    for finding in findings.iter() {
+        eprintln!("warning: {finding}");
+    }
+    if options.strict && !findings.is_empty() {
+        return Err(format!("validation found {} problem(s)", findings.len()));
+    }
+    if options.check {
+        println!("`{}` is valid", path.display());
+        return Ok(());
+    }
+
+    // Like `generate_batch`, each generation gets it's own stream of the seed - so a generation doesn't change when the count does
+    for index in 0..options.count {
+        let mut rng = SeededRng::for_stream(options.seed, index as u64);
+        match StringGenerator::try_generate_at(&rule, &grammar, &mut rng) {
+            Ok(result) => println!("{result}"),
+            Err(issue) => eprintln!("error: generation {index} failed: {issue}"),
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(error) => {
+            eprintln!("error: {error}\n\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match run(options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Options>, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    pub fn options_are_parsed() {
+        let options = parse(&["story.ron", "-n", "2", "--seed", "7", "--strict"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(options.grammar, "story.ron");
+        assert_eq!((options.count, options.seed), (2, 7));
+        assert!(options.strict && !options.check);
+        assert_eq!(
+            Format::from_path(Path::new(&options.grammar)),
+            Ok(Format::Ron)
+        );
+        assert!(parse(&["story.json", "--help"]).unwrap().is_none());
+        assert!(parse(&["--count", "many", "story.json"]).is_err());
+        assert!(parse(&["-n", "2"]).is_err());
+    }
+}
@@ -0,0 +1,347 @@
+use std::fmt::Display;
+
+#[cfg(feature = "bevy")]
+use bevy::utils::HashMap;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(not(feature = "bevy"))]
+use std::collections::HashMap;
+
+use crate::generator::{
+    Generator, GrammarProcessingDirection, GrammarRandomNumberGenerator, SimpleGenerator,
+    SimpleGrammar, SimplePhrase, SimpleToken,
+};
+
+/// This is a single piece of a dungeon, generated by a `DungeonGrammar`. Rooms are connected by a corridor to the room before them,
+/// so a phrase of pieces describes a path through the dungeon - with branches leading off it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DungeonPiece {
+    /// A room of the provided kind - written as `room:kind`
+    Room(String),
+    /// A decoration placed in the previous room - written as `decor:name`
+    Decoration(String),
+    /// Starts a side branch from the previous room - written as `[`
+    Branch,
+    /// Ends the current side branch, so the following rooms connect back to the room it started from - written as `]`
+    EndBranch,
+}
+
+impl DungeonPiece {
+    /// Parses a single piece, or a `#rule#` reference - returning `None` if it isn't written in one of those forms
+    pub fn parse(text: &str) -> Option<SimpleToken<String, DungeonPiece>> {
+        let piece = match text {
+            "[" => DungeonPiece::Branch,
+            "]" => DungeonPiece::EndBranch,
+            _ => {
+                if let Some(rule) = text
+                    .strip_prefix('#')
+                    .and_then(|text| text.strip_suffix('#'))
+                {
+                    return (!rule.is_empty()).then(|| SimpleToken::Rule(rule.to_string()));
+                }
+                match text.split_once(':') {
+                    Some(("room", kind)) if !kind.is_empty() => {
+                        DungeonPiece::Room(kind.to_string())
+                    }
+                    Some(("decor", name)) if !name.is_empty() => {
+                        DungeonPiece::Decoration(name.to_string())
+                    }
+                    _ => return None,
+                }
+            }
+        };
+        Some(SimpleToken::Terminal(piece))
+    }
+}
+
+/// This is a problem found while parsing a `DungeonGrammar`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DungeonGrammarError {
+    /// One of a rule's options contains a piece that isn't a room, decoration, branch or rule reference - holding the rule and the piece
+    UnknownPiece(String, String),
+}
+
+impl Display for DungeonGrammarError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DungeonGrammarError::UnknownPiece(rule, piece) => {
+                write!(f, "rule `{rule}` contains the unknown piece `{piece}`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DungeonGrammarError {}
+
+/// This is a grammar generating dungeons, rather than text. Each option is a list of pieces separated by whitespace - such as
+/// `room:entrance [ room:closet decor:cobwebs ] #halls# room:boss`. It is processed breadth first, so each pass grows every part
+/// of the dungeon at once, and `max_depth` limits how many passes are made - rules still unexpanded after that are left out.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DungeonGrammar {
+    keys: Vec<String>,
+    rules: HashMap<String, Vec<SimplePhrase<String, DungeonPiece>>>,
+    starting_point: String,
+    max_depth: Option<usize>,
+}
+
+impl DungeonGrammar {
+    /// Parses a dungeon grammar from it's rules. If no starting point is provided, the first rule is used.
+    pub fn new(
+        rules: &[(&str, &[&str])],
+        starting_point: Option<&str>,
+    ) -> Result<Self, DungeonGrammarError> {
+        let mut grammar = Self::default();
+        for (key, options) in rules {
+            let options = options
+                .iter()
+                .map(|option| {
+                    option
+                        .split_whitespace()
+                        .map(|piece| {
+                            DungeonPiece::parse(piece).ok_or_else(|| {
+                                DungeonGrammarError::UnknownPiece(
+                                    key.to_string(),
+                                    piece.to_string(),
+                                )
+                            })
+                        })
+                        .collect::<Result<_, _>>()
+                })
+                .collect::<Result<_, _>>()?;
+            grammar.keys.push(key.to_string());
+            grammar.rules.insert(key.to_string(), options);
+        }
+        grammar.starting_point = starting_point
+            .map(str::to_string)
+            .or_else(|| grammar.keys.first().cloned())
+            .unwrap_or_default();
+        Ok(grammar)
+    }
+
+    /// A small grammar generating a dungeon with an entrance, a few halls with side rooms, and a boss room at the end
+    pub fn classic() -> Self {
+        Self::new(
+            &[
+                ("dungeon", &["room:entrance #halls# room:boss #loot#"]),
+                ("halls", &["#hall#", "#hall# #hall#", "#hall# #halls#"]),
+                (
+                    "hall",
+                    &[
+                        "room:hall #decor#",
+                        "room:hall [ #side# ]",
+                        "room:crossroads [ #side# ] [ #side# ]",
+                    ],
+                ),
+                (
+                    "side",
+                    &[
+                        "room:closet #decor#",
+                        "room:treasury #loot#",
+                        "room:shrine",
+                        "#hall#",
+                    ],
+                ),
+                (
+                    "decor",
+                    &[
+                        "decor:cobwebs",
+                        "decor:torch",
+                        "decor:bones",
+                        "decor:torch decor:banner",
+                    ],
+                ),
+                (
+                    "loot",
+                    &["decor:chest", "decor:gold", "decor:chest decor:gold"],
+                ),
+            ],
+            None,
+        )
+        .expect("the classic dungeon grammar only contains known pieces")
+        .with_max_depth(12)
+    }
+
+    /// This sets the maximum number of passes made while expanding the grammar, returning the updated grammar
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+}
+
+impl SimpleGrammar for DungeonGrammar {
+    type Key = String;
+    type Terminal = DungeonPiece;
+
+    fn rule_keys(&self) -> &Vec<String> {
+        &self.keys
+    }
+
+    fn rule_options(&self, rule: &String) -> Option<&Vec<SimplePhrase<String, DungeonPiece>>> {
+        self.rules.get(rule)
+    }
+
+    fn default_starting_point(&self) -> &String {
+        &self.starting_point
+    }
+
+    fn processing_direction(&self) -> GrammarProcessingDirection {
+        GrammarProcessingDirection::BreadthFirst
+    }
+
+    fn max_depth(&self) -> usize {
+        self.max_depth.unwrap_or(50)
+    }
+}
+
+/// This is a single room of a `DungeonLayout`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DungeonRoom {
+    /// The kind of room, like `entrance` or `treasury`
+    pub kind: String,
+    /// The decorations placed in the room, in the order they were generated
+    pub decorations: Vec<String>,
+}
+
+/// This is a generated dungeon - a graph of rooms, connected by corridors
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DungeonLayout {
+    /// The rooms, in the order they were generated - the first room is the dungeon's entrance
+    pub rooms: Vec<DungeonRoom>,
+    /// The corridors, each connecting the indices of two rooms within `rooms`
+    pub corridors: Vec<(usize, usize)>,
+}
+
+impl DungeonLayout {
+    /// Builds the layout described by a list of pieces. Rules left unexpanded are skipped, as are decorations placed before any room.
+    pub fn from_pieces(pieces: &SimplePhrase<String, DungeonPiece>) -> Self {
+        let mut layout = Self::default();
+        let mut current = None;
+        let mut branches = vec![];
+        for piece in pieces {
+            let SimpleToken::Terminal(piece) = piece else {
+                continue;
+            };
+            match piece {
+                DungeonPiece::Room(kind) => {
+                    let room = layout.rooms.len();
+                    layout.rooms.push(DungeonRoom {
+                        kind: kind.clone(),
+                        decorations: vec![],
+                    });
+                    if let Some(previous) = current {
+                        layout.corridors.push((previous, room));
+                    }
+                    current = Some(room);
+                }
+                DungeonPiece::Decoration(name) => {
+                    if let Some(room) = current.and_then(|room| layout.rooms.get_mut(room)) {
+                        room.decorations.push(name.clone());
+                    }
+                }
+                DungeonPiece::Branch => branches.push(current),
+                DungeonPiece::EndBranch => {
+                    if let Some(start) = branches.pop() {
+                        current = start;
+                    }
+                }
+            }
+        }
+        layout
+    }
+
+    /// Lists the indices of the rooms connected to a room by a corridor
+    pub fn neighbours(&self, room: usize) -> impl Iterator<Item = usize> + '_ {
+        self.corridors
+            .iter()
+            .filter_map(move |(from, to)| match (*from == room, *to == room) {
+                (true, _) => Some(*to),
+                (_, true) => Some(*from),
+                _ => None,
+            })
+    }
+}
+
+/// This generates `DungeonLayout`s from a `DungeonGrammar`
+pub struct DungeonGenerator;
+
+impl DungeonGenerator {
+    /// Generates a dungeon from the grammar's starting point, or `None` if it has no such rule
+    pub fn generate<R: GrammarRandomNumberGenerator>(
+        grammar: &DungeonGrammar,
+        rng: &mut R,
+    ) -> Option<DungeonLayout> {
+        SimpleGenerator::generate(grammar, rng).map(|pieces| DungeonLayout::from_pieces(&pieces))
+    }
+
+    /// Generates a dungeon from the provided rule, or `None` if there is no such rule
+    pub fn generate_at<R: GrammarRandomNumberGenerator>(
+        key: &str,
+        grammar: &DungeonGrammar,
+        rng: &mut R,
+    ) -> Option<DungeonLayout> {
+        SimpleGenerator::generate_at(&key.to_string(), grammar, rng)
+            .map(|pieces| DungeonLayout::from_pieces(&pieces))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::SeededRng;
+
+    #[test]
+    pub fn dungeons_are_generated_as_room_graphs() {
+        let grammar = DungeonGrammar::new(
+            &[
+                ("dungeon", &["room:entrance #wing# room:boss decor:throne"]),
+                ("wing", &["room:hall [ #side# ] decor:torch"]),
+                ("side", &["room:closet #loot#"]),
+                ("loot", &["decor:chest"]),
+            ],
+            None,
+        )
+        .unwrap();
+        let layout = DungeonGenerator::generate(&grammar, &mut 0).unwrap();
+        let rooms = layout
+            .rooms
+            .iter()
+            .map(|room| (room.kind.as_str(), room.decorations.join(",")))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rooms,
+            vec![
+                ("entrance", "".to_string()),
+                ("hall", "torch".to_string()),
+                ("closet", "chest".to_string()),
+                ("boss", "throne".to_string())
+            ]
+        );
+        // The boss room connects back to the hall the closet branched off from
+        assert_eq!(layout.corridors, vec![(0, 1), (1, 2), (1, 3)]);
+        assert_eq!(layout.neighbours(1).collect::<Vec<_>>(), vec![0, 2, 3]);
+
+        assert_eq!(
+            DungeonGrammar::new(&[("dungeon", &["room:entrance trapdoor"])], None),
+            Err(DungeonGrammarError::UnknownPiece(
+                "dungeon".to_string(),
+                "trapdoor".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    pub fn recursive_dungeons_stop_growing_at_the_max_depth() {
+        let grammar = DungeonGrammar::new(&[("rooms", &["room:hall #rooms#"])], None)
+            .unwrap()
+            .with_max_depth(4);
+        let layout = DungeonGenerator::generate(&grammar, &mut 0).unwrap();
+        assert_eq!(layout.rooms.len(), 5);
+
+        let layout =
+            DungeonGenerator::generate(&DungeonGrammar::classic(), &mut SeededRng::new(7)).unwrap();
+        assert_eq!(layout.rooms.first().unwrap().kind, "entrance");
+        assert_eq!(layout.corridors.len(), layout.rooms.len() - 1);
+    }
+}
@@ -0,0 +1,4 @@
+/// This module induces candidate grammars from example sentences
+pub mod induction;
+
+pub use induction::{CorpusComparison, GrammarInducer};
@@ -0,0 +1,488 @@
+use std::fmt::Display;
+
+use crate::{
+    generator::Grammar,
+    tracery::{StringGenerator, TraceryGrammar},
+};
+
+/// An example sentence split into words, along with the number of times it appeared
+type Example<'a> = (Vec<&'a str>, u32);
+
+/// This induces a candidate grammar from example sentences, giving authors a starting point they can refine by hand.
+/// The grammars it produces are a rough draft, and the way they are induced may change in any release.
+///
+/// Sentences are compared word by word. Sentences of the same length that share words in the same positions become a
+/// template, with the spans that vary between them becoming rules - so "The cat sleeps" and "The dog sleeps" become
+/// `The #slot_1# sleeps`. Sentences that don't line up are grouped by their first or last word instead, with the common
+/// prefix and suffix becoming the template. The spans that vary are induced the same way, so rules can nest. Options that
+/// appear more than once are weighted by how often they appear.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrammarInducer {
+    /// The fewest distinct sentences needed to form a template
+    pub min_group_size: usize,
+    /// The prefix for the keys of induced rules, followed by a number
+    pub rule_prefix: String,
+}
+
+impl Default for GrammarInducer {
+    fn default() -> Self {
+        Self {
+            min_group_size: 2,
+            rule_prefix: "slot_".to_string(),
+        }
+    }
+}
+
+impl GrammarInducer {
+    /// Creates an inducer with the default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the fewest distinct sentences needed to form a template
+    pub fn with_min_group_size(mut self, min_group_size: usize) -> Self {
+        self.min_group_size = min_group_size.max(2);
+        self
+    }
+
+    /// Sets the prefix for the keys of induced rules
+    pub fn with_rule_prefix(mut self, rule_prefix: impl Into<String>) -> Self {
+        self.rule_prefix = rule_prefix.into();
+        self
+    }
+
+    /// Induces a grammar generating the examples, starting from `origin`. Empty examples are skipped.
+    /// Examples are used as they are, so any characters Tracery treats specially - like `#` or `[` - should be removed first.
+    pub fn induce<T: AsRef<str>>(&self, examples: impl IntoIterator<Item = T>) -> TraceryGrammar {
+        let examples = examples.into_iter().collect::<Vec<_>>();
+        let examples = examples
+            .iter()
+            .map(|example| (example.as_ref().split_whitespace().collect(), 1))
+            .filter(|(words, _): &Example| !words.is_empty())
+            .collect();
+        let mut induction = Induction {
+            inducer: self,
+            rules: vec![],
+        };
+        let origin = induction.options(examples);
+        let mut grammar = TraceryGrammar::empty();
+        for (key, options) in induction
+            .rules
+            .into_iter()
+            .chain([("origin".to_string(), origin)])
+        {
+            let weights = options.iter().map(|(_, count)| *count).collect::<Vec<_>>();
+            let options = options
+                .into_iter()
+                .map(|(option, _)| option)
+                .collect::<Vec<_>>();
+            if weights.iter().any(|weight| *weight != 1) {
+                grammar.set_weights(key.clone(), weights);
+            }
+            grammar.set_additional_rules(key, &options);
+        }
+        grammar
+    }
+}
+
+impl TraceryGrammar {
+    /// This induces a candidate grammar from example sentences using the default `GrammarInducer`. This is experimental.
+    pub fn induce<T: AsRef<str>>(examples: impl IntoIterator<Item = T>) -> Self {
+        GrammarInducer::new().induce(examples)
+    }
+}
+
+/// This compares the outputs of a grammar - usually an induced one - with the corpus it should reproduce, so authors can
+/// judge whether it is faithful before adopting it. Sentences are compared with their whitespace normalized.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorpusComparison {
+    /// The number of distinct sentences in the corpus
+    pub corpus_size: usize,
+    /// The corpus sentences the grammar can't generate, in the order they first appear
+    pub uncovered: Vec<String>,
+    /// The share of the distinct corpus sentences the grammar can generate, from 0 to 1
+    pub coverage: f64,
+    /// The number of outputs sampled from the grammar
+    pub samples: usize,
+    /// The sampled outputs that aren't in the corpus, along with how often they were sampled - most frequent first
+    pub novel: Vec<(String, usize)>,
+    /// The share of the samples that aren't in the corpus, from 0 to 1
+    pub novelty: f64,
+    /// How far the frequencies of the samples are from the frequencies of the corpus, from 0 when they match to 1 when
+    /// they don't share any sentences. This is the total variation distance between them.
+    pub distance: f64,
+}
+
+impl Display for CorpusComparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "coverage: {:.1}% of {} corpus sentences",
+            self.coverage * 100.,
+            self.corpus_size
+        )?;
+        writeln!(
+            f,
+            "novelty: {:.1}% of {} samples",
+            self.novelty * 100.,
+            self.samples
+        )?;
+        writeln!(f, "distance: {:.3}", self.distance)?;
+        if !self.uncovered.is_empty() {
+            writeln!(f, "missing:")?;
+            for sentence in self.uncovered.iter() {
+                writeln!(f, "  - {sentence}")?;
+            }
+        }
+        if !self.novel.is_empty() {
+            writeln!(f, "novel:")?;
+            for (sentence, count) in self.novel.iter() {
+                writeln!(f, "  {count}x {sentence}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TraceryGrammar {
+    /// This compares the grammar's outputs with a corpus - checking which of the corpus sentences the grammar can generate,
+    /// and sampling `samples` outputs from the seed using `StringGenerator::generate_batch` to find the ones that are novel.
+    pub fn compare_with_corpus<T: AsRef<str>>(
+        &self,
+        corpus: impl IntoIterator<Item = T>,
+        samples: usize,
+        seed: u64,
+    ) -> CorpusComparison {
+        let normalize = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let corpus = count(
+            corpus
+                .into_iter()
+                .map(|sentence| normalize(sentence.as_ref())),
+        );
+        let outputs = count(
+            StringGenerator::generate_batch(self, samples, seed)
+                .iter()
+                .map(|output| normalize(output)),
+        );
+        let corpus_total = corpus.iter().map(|(_, count)| *count).sum::<usize>();
+        let output_total = outputs.iter().map(|(_, count)| *count).sum::<usize>();
+        let frequency = |counts: &[(String, usize)], total: usize, sentence: &str| {
+            counts
+                .iter()
+                .find(|(existing, _)| existing == sentence)
+                .map_or(0., |(_, count)| *count as f64 / total.max(1) as f64)
+        };
+
+        let uncovered = corpus
+            .iter()
+            .filter(|(sentence, _)| self.recognize(sentence).is_none())
+            .map(|(sentence, _)| sentence.clone())
+            .collect::<Vec<_>>();
+        let mut novel = outputs
+            .iter()
+            .filter(|(output, _)| corpus.iter().all(|(sentence, _)| sentence != output))
+            .cloned()
+            .collect::<Vec<_>>();
+        novel.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        let novel_samples = novel.iter().map(|(_, count)| *count).sum::<usize>();
+        let distance = corpus
+            .iter()
+            .chain(novel.iter())
+            .map(|(sentence, _)| {
+                (frequency(&corpus, corpus_total, sentence)
+                    - frequency(&outputs, output_total, sentence))
+                .abs()
+            })
+            .sum::<f64>()
+            / 2.;
+        CorpusComparison {
+            corpus_size: corpus.len(),
+            coverage: match corpus.len() {
+                0 => 1.,
+                len => (len - uncovered.len()) as f64 / len as f64,
+            },
+            uncovered,
+            samples: output_total,
+            novelty: match output_total {
+                0 => 0.,
+                total => novel_samples as f64 / total as f64,
+            },
+            novel,
+            distance,
+        }
+    }
+}
+
+/// Counts how often each sentence appears, keeping the order they first appeared in. Empty sentences are skipped.
+fn count(sentences: impl Iterator<Item = String>) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = vec![];
+    for sentence in sentences.filter(|sentence| !sentence.is_empty()) {
+        match counts
+            .iter_mut()
+            .find(|(existing, _)| *existing == sentence)
+        {
+            Some((_, count)) => *count += 1,
+            None => counts.push((sentence, 1)),
+        }
+    }
+    counts
+}
+
+/// The state of a single induction - the rules induced so far, with each option's count
+struct Induction<'a> {
+    inducer: &'a GrammarInducer,
+    rules: Vec<(String, Vec<(String, u32)>)>,
+}
+
+impl Induction<'_> {
+    /// Induces the options generating a set of examples, adding rules for the spans that vary between them
+    fn options(&mut self, examples: Vec<Example>) -> Vec<(String, u32)> {
+        let mut merged: Vec<Example> = vec![];
+        for (words, count) in examples {
+            match merged.iter_mut().find(|(existing, _)| *existing == words) {
+                Some((_, existing)) => *existing = existing.saturating_add(count),
+                None => merged.push((words, count)),
+            }
+        }
+        let min = self.inducer.min_group_size;
+        let mut options = vec![];
+
+        // Sentences of the same length that share words in the same positions
+        let mut remaining = vec![];
+        for group in group_by(merged, |words| Some(words.len())) {
+            let first = &group[0].0;
+            let constant = (0..first.len())
+                .map(|index| group.iter().all(|(words, _)| words[index] == first[index]))
+                .collect::<Vec<_>>();
+            if group.len() >= min && constant.contains(&true) {
+                options.push(self.aligned(group, &constant));
+            } else {
+                remaining.extend(group);
+            }
+        }
+
+        // Sentences sharing a prefix or suffix
+        let mut leftover = vec![];
+        for group in group_by(remaining, |words| words.first().copied()) {
+            match (group.len() >= min)
+                .then(|| self.affixed(&group, true))
+                .flatten()
+            {
+                Some(option) => options.push(option),
+                None => leftover.extend(group),
+            }
+        }
+        for group in group_by(leftover, |words| words.last().copied()) {
+            match (group.len() >= min)
+                .then(|| self.affixed(&group, false))
+                .flatten()
+            {
+                Some(option) => options.push(option),
+                None => options.extend(
+                    group
+                        .into_iter()
+                        .map(|(words, count)| (words.join(" "), count)),
+                ),
+            }
+        }
+        options
+    }
+
+    /// Builds a template from sentences of the same length, replacing each run of varying words with a rule
+    fn aligned(&mut self, group: Vec<Example>, constant: &[bool]) -> (String, u32) {
+        let count = group
+            .iter()
+            .map(|(_, count)| *count)
+            .fold(0, u32::saturating_add);
+        let mut template = vec![];
+        let mut index = 0;
+        while index < constant.len() {
+            if constant[index] {
+                template.push(group[0].0[index].to_string());
+                index += 1;
+                continue;
+            }
+            let end = (index..constant.len())
+                .find(|end| constant[*end])
+                .unwrap_or(constant.len());
+            let spans = group
+                .iter()
+                .map(|(words, count)| (words[index..end].to_vec(), *count))
+                .collect();
+            template.push(self.rule(spans));
+            index = end;
+        }
+        (template.join(" "), count)
+    }
+
+    /// Builds a template from sentences sharing their first or last word, replacing the words between their common prefix
+    /// and suffix with a rule. Every sentence keeps at least one word in the rule, so it never expands to nothing.
+    /// Returns `None` if the sentences don't have a prefix or suffix left in common.
+    fn affixed(&mut self, group: &[Example], by_prefix: bool) -> Option<(String, u32)> {
+        let count = group
+            .iter()
+            .map(|(_, count)| *count)
+            .fold(0, u32::saturating_add);
+        let shortest = group
+            .iter()
+            .map(|(words, _)| words.len())
+            .min()
+            .unwrap_or(0);
+        let first = &group[0].0;
+        let common = |word: &dyn Fn(&[&str], usize) -> Option<String>| {
+            (0..shortest)
+                .take_while(|index| {
+                    group
+                        .iter()
+                        .all(|(words, _)| word(words, *index) == word(first, *index))
+                })
+                .count()
+        };
+        let mut prefix = common(&|words, index| words.get(index).map(|word| word.to_string()));
+        let mut suffix =
+            common(&|words, index| words.iter().rev().nth(index).map(|word| word.to_string()));
+        // Every sentence keeps a word in the rule, so the affix that wasn't used for grouping gives way first
+        while prefix + suffix >= shortest {
+            let reduce_suffix = match by_prefix {
+                true => suffix > 0,
+                false => prefix == 0,
+            };
+            match reduce_suffix {
+                true => suffix -= 1,
+                false => prefix -= 1,
+            }
+        }
+        if prefix + suffix == 0 {
+            return None;
+        }
+        let spans = group
+            .iter()
+            .map(|(words, count)| (words[prefix..words.len() - suffix].to_vec(), *count))
+            .collect();
+        let mut template = first[..prefix]
+            .iter()
+            .map(|word| word.to_string())
+            .collect::<Vec<_>>();
+        template.push(self.rule(spans));
+        template.extend(
+            first[first.len() - suffix..]
+                .iter()
+                .map(|word| word.to_string()),
+        );
+        Some((template.join(" "), count))
+    }
+
+    /// Induces a rule for a set of spans, reusing an existing rule with the same options. Returns the reference to it.
+    fn rule(&mut self, spans: Vec<Example>) -> String {
+        let options = self.options(spans);
+        let key = match self.rules.iter().find(|(_, existing)| *existing == options) {
+            Some((key, _)) => key.clone(),
+            None => {
+                let key = format!("{}{}", self.inducer.rule_prefix, self.rules.len() + 1);
+                self.rules.push((key.clone(), options));
+                key
+            }
+        };
+        format!("#{key}#")
+    }
+}
+
+/// Groups examples by a key, keeping the order they first appeared in. Examples without a key are placed in groups of their own.
+fn group_by<'a, K: PartialEq>(
+    examples: Vec<Example<'a>>,
+    key: impl Fn(&[&'a str]) -> Option<K>,
+) -> Vec<Vec<Example<'a>>> {
+    let mut groups: Vec<(Option<K>, Vec<Example<'a>>)> = vec![];
+    for example in examples {
+        let example_key = key(&example.0);
+        match groups
+            .iter_mut()
+            .find(|(group_key, _)| group_key.is_some() && *group_key == example_key)
+        {
+            Some((_, group)) => group.push(example),
+            None => groups.push((example_key, vec![example])),
+        }
+    }
+    groups.into_iter().map(|(_, group)| group).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(grammar: &TraceryGrammar, rule: &str) -> Vec<String> {
+        grammar
+            .get_rule_options(&rule.to_string())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    #[test]
+    pub fn varying_spans_become_rules() {
+        let grammar = TraceryGrammar::induce([
+            "The cat sleeps on the mat",
+            "The dog sleeps on the rug",
+            "The owl sleeps on the mat",
+            "A storm is coming",
+            "A storm is coming",
+            "A big storm is coming",
+            "Hello there",
+        ]);
+        assert_eq!(
+            options(&grammar, "origin"),
+            vec![
+                "The #slot_1# sleeps on the #slot_2#",
+                "A #slot_3# is coming",
+                "Hello there"
+            ]
+        );
+        assert_eq!(options(&grammar, "slot_1"), vec!["cat", "dog", "owl"]);
+        assert_eq!(options(&grammar, "slot_2"), vec!["mat", "rug"]);
+        assert_eq!(options(&grammar, "slot_3"), vec!["storm", "big storm"]);
+        assert_eq!(
+            grammar
+                .rule_settings("slot_2")
+                .map(|settings| &settings.weights),
+            Some(&vec![2, 1])
+        );
+        assert_eq!(
+            grammar
+                .rule_settings("origin")
+                .map(|settings| &settings.weights),
+            Some(&vec![3, 3, 1])
+        );
+    }
+
+    #[test]
+    pub fn induced_grammars_are_compared_with_their_corpus() {
+        let corpus = [
+            "The cat  sleeps",
+            "The dog sleeps",
+            "The cat sleeps",
+            "A bird sings",
+        ];
+        let comparison = TraceryGrammar::new(
+            &[
+                ("origin", &["The #animal# sleeps"]),
+                ("animal", &["cat", "dog", "owl"]),
+            ],
+            None,
+        )
+        .compare_with_corpus(corpus, 300, 1);
+        assert_eq!(comparison.corpus_size, 3);
+        assert_eq!(comparison.uncovered, vec!["A bird sings"]);
+        assert!((comparison.coverage - 2. / 3.).abs() < f64::EPSILON);
+        assert_eq!(comparison.samples, 300);
+        assert_eq!(comparison.novel.len(), 1);
+        assert_eq!(comparison.novel[0].0, "The owl sleeps");
+        assert!(comparison.novelty > 0.2 && comparison.novelty < 0.45);
+        assert!(comparison.distance > 0.3 && comparison.distance < 0.7);
+        assert!(comparison
+            .to_string()
+            .contains("missing:\n  - A bird sings"));
+
+        let faithful = TraceryGrammar::induce(corpus).compare_with_corpus(corpus, 100, 1);
+        assert_eq!(faithful.coverage, 1.);
+        assert!(faithful.novel.is_empty());
+    }
+}
@@ -1,12 +1,42 @@
+#[cfg(feature = "async_generation")]
+mod async_generation;
+mod context;
+mod dynamic_grammar;
+#[cfg(feature = "bevy")]
+mod generation_queue;
 #[cfg(feature = "rand")]
 mod grammar_rng_rand;
+#[cfg(feature = "bevy")]
+mod grammar_rng_resource;
+mod grammar_rng_seeded;
 #[cfg(feature = "turborand")]
 mod grammar_rng_turborand;
-
+mod processing;
+mod simple_grammar;
+mod token_grammar;
+#[cfg(feature = "bevy")]
+mod token_spawning;
+mod trace;
+
+#[cfg(feature = "async_generation")]
+pub use async_generation::*;
+pub use context::*;
+pub use dynamic_grammar::*;
+#[cfg(feature = "bevy")]
+pub use generation_queue::*;
 #[cfg(feature = "rand")]
 pub use grammar_rng_rand::*;
+#[cfg(feature = "bevy")]
+pub use grammar_rng_resource::*;
+pub use grammar_rng_seeded::*;
 #[cfg(feature = "turborand")]
 pub use grammar_rng_turborand::*;
+pub use processing::BreadthFirstExpansion;
+pub use simple_grammar::*;
+pub use token_grammar::*;
+#[cfg(feature = "bevy")]
+pub use token_spawning::*;
+pub use trace::*;
 
 use std::fmt::Debug;
 
@@ -21,6 +51,8 @@ pub enum Replacable<RuleKeyType: Clone + PartialEq + Debug, ResultType: Clone +
     ImmediateMeta(RuleKeyType, ResultType),
     /// The value is a meta rule for delayed processing - basically aliasing the rule
     DelayedMeta(RuleKeyType, ResultType),
+    /// The value is one of the provided alternatives, chosen when it's processed - like an anonymous rule
+    Alternatives(Vec<ResultType>),
 }
 
 /// This sets the direction of processing for the grammar
@@ -78,6 +110,12 @@ pub trait Grammar<
     /// Gets the default starting key - used if no other key is set
     fn default_starting_point(&self) -> &RuleKeyType;
 
+    /// Selects the key generation starts from when using `generate_weighted_start`. Grammars with several weighted
+    /// starting points can override this - by default, it's always the default starting point.
+    fn select_starting_point<R: GrammarRandomNumberGenerator>(&self, _rng: &mut R) -> &RuleKeyType {
+        self.default_starting_point()
+    }
+
     /// Parses a token stream and determines a) whether there are any tokens to replace and b) if so, which
     /// The bool is true if there are no more tokens that need replacing
     fn check_token_stream(
@@ -92,15 +130,78 @@ pub trait Grammar<
         rule: &RuleKeyType,
         rng: &mut R,
     ) -> Option<&ResultType> {
-        if let Some(options) = self.get_rule_options(rule) {
-            let len = options.len();
-            let max = len.checked_sub(1).unwrap_or_default();
-            let rng = rng.get_number(len);
-            let index = max.min(rng);
-            options.get(index)
-        } else {
-            None
+        let index = self.select_index_from_rule(rule, rng)?;
+        self.get_rule_options(rule)?.get(index)
+    }
+
+    /// Selects the index of an element from a rule's options, in the same way as `select_from_rule`.
+    fn select_index_from_rule<R: GrammarRandomNumberGenerator>(
+        &self,
+        rule: &RuleKeyType,
+        rng: &mut R,
+    ) -> Option<usize> {
+        let options = self.get_rule_options(rule)?;
+        let len = options.len();
+        if len == 0 {
+            return None;
         }
+        Some((len - 1).min(rng.get_number(len)))
+    }
+
+    /// Selects the value used to replace a rule while processing a stream, along with the index of the option it came from (if any).
+    /// It checks the temporary grammar first, then this grammar. It returns `None` if the rule can't be found,
+    /// in which case processing keeps the rule's default result.
+    /// Grammars that track their selections between calls can override this.
+    fn select_for_processing<R: GrammarRandomNumberGenerator>(
+        &self,
+        rule: &RuleKeyType,
+        rng: &mut R,
+        temporary_grammar: &mut Self,
+    ) -> Option<(ResultType, Option<usize>)> {
+        let grammar = if temporary_grammar.has_rule(rule) {
+            &*temporary_grammar
+        } else {
+            self
+        };
+        let index = grammar.select_index_from_rule(rule, rng)?;
+        let value = grammar.get_rule_options(rule)?.get(index)?;
+        Some((value.clone(), Some(index)))
+    }
+
+    /// Splits the modifiers off a rule key, returning the key of the rule itself and the modifiers to apply to its expansion.
+    /// It returns `None` if the key has no modifiers - which is the default.
+    fn split_modifiers(&self, _rule: &RuleKeyType) -> Option<(RuleKeyType, RuleKeyType)> {
+        None
+    }
+
+    /// Splits the actions off the start of a rule key - such as `[hero:#name#]story` in tracery. It returns the actions as meta tokens,
+    /// and the key of the rule to expand once they are applied, if there is one. The actions are undone once that rule is expanded.
+    /// It returns `None` if the key has no actions - which is the default.
+    fn split_actions(
+        &self,
+        _rule: &RuleKeyType,
+    ) -> Option<(
+        Vec<Replacable<RuleKeyType, ResultType>>,
+        Option<RuleKeyType>,
+    )> {
+        None
+    }
+
+    /// Applies the modifiers split from a rule key to the fully expanded stream of that rule.
+    /// By default, the stream is returned unchanged.
+    fn apply_modifiers(&self, _modifiers: &RuleKeyType, stream: StreamType) -> StreamType {
+        stream
+    }
+
+    /// Applies modifiers like `apply_modifiers`, with access to the temporary grammar holding the variables assigned so far -
+    /// so modifiers can depend on them. This is what processing calls, and by default it ignores the temporary grammar.
+    fn apply_modifiers_in_context(
+        &self,
+        modifiers: &RuleKeyType,
+        stream: StreamType,
+        _temporary_grammar: &Self,
+    ) -> StreamType {
+        self.apply_modifiers(modifiers, stream)
     }
 
     /// Converts a rule key to a default result, in case no matching rule is found in the grammar.
@@ -112,12 +213,34 @@ pub trait Grammar<
     /// Converts a stream to a vec of result type
     fn stream_to_result(&self, stream: &StreamType) -> Vec<ResultType>;
 
+    /// Converts a single result to a stream, taking ownership of it. Grammars can override this to avoid copying the result.
+    fn result_into_stream(&self, result: ResultType) -> StreamType {
+        self.result_to_stream(std::slice::from_ref(&result))
+    }
+
+    /// Converts a stream to a vec of result type, taking ownership of it. Grammars can override this to avoid copying the stream.
+    fn stream_into_result(&self, stream: StreamType) -> Vec<ResultType> {
+        self.stream_to_result(&stream)
+    }
+
     /// determines if the grammar should be processed breadth-first or depth-first
     fn processing_direction(&self) -> GrammarProcessingDirection;
 
     /// This is a function for setting a new rule. The expectation is that it overrides the original.
     fn set_additional_rules(&mut self, rule: RuleKeyType, values: &[ResultType]);
 
+    /// This removes a rule set using `set_additional_rules`, and is used to undo actions once they go out of scope.
+    /// By default, nothing is removed.
+    fn remove_additional_rules(&mut self, _rule: &RuleKeyType) {}
+
+    /// This removes every rule from the grammar, so it can be re-used as a temporary grammar. By default, it removes each rule
+    /// using `remove_additional_rules`.
+    fn clear_additional_rules(&mut self) {
+        for rule in self.rule_keys().clone() {
+            self.remove_additional_rules(&rule);
+        }
+    }
+
     /// This is used to clone all the roles from another grammar into this one. This is used by stateful generators to update their state.
     fn copy_and_replace_rules(&mut self, other: &Self) {
         for rule in other.rule_keys() {
@@ -128,12 +251,37 @@ pub trait Grammar<
         }
     }
 
-    /// Provides the maximum depth (number of iterations) allowed for the generator. It will always quit early if it stabilizes,
-    /// but otherwise it will conclude when it reaches the provided depth.
+    /// Provides the maximum depth allowed for the generator. When processing breadth first, this is the number of iterations - it will always quit early if it stabilizes,
+    /// but otherwise it will conclude when it reaches the provided depth. When processing depth first, this is how deeply rules can be nested within each other.
     fn max_depth(&self) -> usize {
         50
     }
 
+    /// Provides the maximum number of rules that can be expanded while processing a single stream depth first.
+    /// Once it is reached, any remaining rules are left as their default result.
+    fn max_expansions(&self) -> usize {
+        10_000
+    }
+
+    /// Called whenever processing a stream depth first expands a rule, with how deeply it is nested within other rules - so grammars
+    /// can keep statistics about their expansions in the temporary grammar. By default, nothing is recorded.
+    fn record_expansion(&self, _temporary_grammar: &mut Self, _depth: usize) {}
+
+    /// Called as the value of an assignment like `[hero:#name#]` starts being processed, and again once it was processed - just before
+    /// it is assigned. This lets grammars keep track of which parts of a generation belong to each variable, such as for re-rolling
+    /// a single variable later. By default, nothing is recorded.
+    fn begin_assignment(&self, _temporary_grammar: &mut Self, _key: &RuleKeyType) {}
+
+    /// Called once the value of an assignment was processed, just before it is assigned. See `begin_assignment`.
+    fn end_assignment(&self, _temporary_grammar: &mut Self, _key: &RuleKeyType) {}
+
+    /// Provides the maximum number of tokens that can be waiting to be processed at once. Once it is reached, processing depth first
+    /// leaves any further rules as their default result, while processing breadth first stops iterating - so grammars whose
+    /// expansions grow exponentially can't exhaust memory.
+    fn max_queue_size(&self) -> usize {
+        100_000
+    }
+
     /// Takes a token stream, checks it for replacements, and then applies them by using select from rule.
     /// It returns a bool indicating whether it had to make any replacements this round, and a vec of the results.
     fn process_stream<R: GrammarRandomNumberGenerator>(
@@ -152,192 +300,45 @@ pub trait Grammar<
         }
     }
 
-    /// Processes a stream breadth first, regardless of the settings of the grammar
-    fn breadth_first_processing<R: GrammarRandomNumberGenerator>(
+    /// Processes a stream like `process_stream`, recording every rule it replaced into the trace.
+    fn process_stream_traced<R: GrammarRandomNumberGenerator>(
         &self,
         stream: &StreamType,
-        temporary_grammar: &mut Self,
         rng: &mut R,
+        temporary_grammar: &mut Self,
+        trace: &mut ExpansionTrace<RuleKeyType>,
     ) -> StreamType {
-        let max_depth = self.max_depth();
-        let (skippable, initial) = self.check_token_stream(stream);
-        if skippable {
-            return stream.clone();
-        }
-
-        let mut queue: Vec<(
-            Option<RuleKeyType>,
-            Vec<Replacable<RuleKeyType, ResultType>>,
-        )> = vec![(None, initial)];
-        let mut append_to_queue = vec![];
-        let mut depth = 0;
-        let mut result = stream.clone();
-        let mut tmp_result = None;
-        while let Some((target, current)) = queue.pop() {
-            let next = current
-                .into_iter()
-                .filter_map(|token| {
-                    let result = match token {
-                        Replacable::Ready(v) => Some(v),
-                        Replacable::Replace(key) => {
-                            if let Some(result) = temporary_grammar.select_from_rule(&key, rng) {
-                                Some(result.clone())
-                            } else if let Some(result) = self.select_from_rule(&key, rng) {
-                                Some(result.clone())
-                            } else {
-                                Some(self.rule_to_default_result(&key))
-                            }
-                        }
-                        Replacable::ImmediateMeta(key, value) => {
-                            let stream = self.result_to_stream(&[value.clone()]);
-                            let (skippable, replaceables) = self.check_token_stream(&stream);
-                            if skippable {
-                                temporary_grammar.set_additional_rules(key, &[value]);
-                            } else {
-                                append_to_queue.push((Some(key), replaceables));
-                            }
-                            None
-                        }
-                        Replacable::DelayedMeta(key, value) => {
-                            temporary_grammar.set_additional_rules(key, &[value]);
-                            None
-                        }
-                    };
-                    result
-                })
-                .collect::<Vec<_>>();
-
-            let next = self.result_to_stream(&next);
-
-            if let Some(target) = &target {
-                if let Some(tmp) = &tmp_result {
-                    if tmp == &next {
-                        temporary_grammar
-                            .set_additional_rules(target.clone(), &self.stream_to_result(&next));
-                        tmp_result = None;
-                        continue;
-                    }
-                }
-                tmp_result = Some(next.clone());
-            } else if result == next {
-                break;
-            } else {
-                result = next.clone();
-            }
-
-            depth += 1;
-            if depth >= max_depth {
-                break;
+        let trace = Some(&mut trace.roots);
+        match self.processing_direction() {
+            GrammarProcessingDirection::BreadthFirst => {
+                processing::breadth_first(self, stream, temporary_grammar, rng, trace)
             }
-
-            let (skippable, next) = self.check_token_stream(&next);
-            if skippable {
-                if let (Some(target), Some(tmp)) = (&target, tmp_result) {
-                    temporary_grammar
-                        .set_additional_rules(target.clone(), &self.stream_to_result(&tmp));
-                    tmp_result = None;
-                    continue;
-                } else {
-                    break;
-                }
+            GrammarProcessingDirection::DepthFirst => {
+                processing::depth_first(self, stream, temporary_grammar, rng, trace)
             }
-            queue.push((target, next));
-            queue.append(&mut append_to_queue);
         }
-        result
     }
 
-    /// Processes a stream depth first, regardless of the settings of the grammar
-    fn depth_first_processing<R: GrammarRandomNumberGenerator>(
+    /// Processes a stream breadth first, regardless of the settings of the grammar
+    fn breadth_first_processing<R: GrammarRandomNumberGenerator>(
         &self,
         stream: &StreamType,
         temporary_grammar: &mut Self,
         rng: &mut R,
     ) -> StreamType {
-        let max_depth = self.max_depth();
-        let (skippable, initial) = self.check_token_stream(stream);
-        if skippable {
-            return stream.clone();
-        }
-
-        let mut queue: Vec<(Option<RuleKeyType>, Replacable<RuleKeyType, ResultType>)> =
-            initial.into_iter().map(|v| (None, v)).collect();
-        queue.reverse();
-        let mut results: Vec<(Option<RuleKeyType>, Vec<ResultType>)> = vec![(None, vec![])];
-        let mut depth = 0;
-        while let Some((target, item)) = queue.pop() {
-            if results.len() > 1 {
-                let mut remove_last_result = false;
-
-                if let Some(last_result) = results.last() {
-                    if last_result.0 != target {
-                        remove_last_result = true;
-                    }
-                }
-
-                if remove_last_result {
-                    if let Some((Some(target), values)) = results.pop() {
-                        // This two way conversion allows a grammar to potentially collapse multiple values into a single one,
-                        // if that is the desired result.
-                        let stream = self.result_to_stream(&values);
-                        let values = self.stream_to_result(&stream);
-
-                        temporary_grammar.set_additional_rules(target, &values);
-                    }
-                }
-            }
-
-            let mut create_new_result_stream = None;
-
-            match item {
-                Replacable::Ready(value) => {
-                    if let Some(stream) = results.last_mut() {
-                        stream.1.push(value);
-                    }
-                }
-                Replacable::Replace(key) => {
-                    let result = if let Some(result) = temporary_grammar.select_from_rule(&key, rng)
-                    {
-                        result.clone()
-                    } else if let Some(result) = self.select_from_rule(&key, rng) {
-                        result.clone()
-                    } else {
-                        self.rule_to_default_result(&key)
-                    };
-                    let result = self.result_to_stream(&[result]);
-                    let (_, mut next) = self.check_token_stream(&result);
-                    next.reverse();
-                    for item in next.into_iter() {
-                        queue.push((target.clone(), item));
-                    }
-                }
-                Replacable::ImmediateMeta(key, result) => {
-                    let result = self.result_to_stream(&[result.clone()]);
-                    create_new_result_stream = Some(key.clone());
-                    let (_, mut next) = self.check_token_stream(&result);
-                    next.reverse();
-                    for item in next.into_iter() {
-                        queue.push((Some(key.clone()), item));
-                    }
-                }
-                Replacable::DelayedMeta(key, value) => {
-                    temporary_grammar.set_additional_rules(key.clone(), &[value.clone()]);
-                }
-            }
+        processing::breadth_first(self, stream, temporary_grammar, rng, None)
+    }
 
-            if let Some(key) = create_new_result_stream {
-                results.push((Some(key), vec![]));
-            }
-            depth += 1;
-            if depth >= max_depth {
-                break;
-            }
-        }
-        if let Some(result) = results.first() {
-            self.result_to_stream(&result.1)
-        } else {
-            stream.clone()
-        }
+    /// Processes a stream depth first, regardless of the settings of the grammar.
+    /// Each expansion and immediate meta rule opens a new scope, which is closed once all of it's tokens are processed -
+    /// at which point it's modifiers are applied, or it's value is assigned.
+    fn depth_first_processing<R: GrammarRandomNumberGenerator>(
+        &self,
+        stream: &StreamType,
+        temporary_grammar: &mut Self,
+        rng: &mut R,
+    ) -> StreamType {
+        processing::depth_first(self, stream, temporary_grammar, rng, None)
     }
 }
 
@@ -362,6 +363,25 @@ pub trait Generator<
         rng: &mut R,
     ) -> Option<StreamType>;
 
+    /// This function generates a new value of `StreamType`, starting from a key chosen by `Grammar::select_starting_point` -
+    /// so grammars with several weighted starting points can start from any of them
+    fn generate_weighted_start<R: GrammarRandomNumberGenerator>(
+        grammar: &GrammarType,
+        rng: &mut R,
+    ) -> Option<StreamType> {
+        let key = grammar.select_starting_point(rng).clone();
+        Self::generate_at(&key, grammar, rng)
+    }
+
+    /// This function generates a new value of `StreamType` from the grammar's default rule, along with a trace of how it was expanded.
+    /// By default the trace is empty, for generators that can't record their expansions.
+    fn generate_traced<R: GrammarRandomNumberGenerator>(
+        grammar: &GrammarType,
+        rng: &mut R,
+    ) -> Option<(StreamType, ExpansionTrace<RuleKeyType>)> {
+        Self::generate(grammar, rng).map(|result| (result, ExpansionTrace::default()))
+    }
+
     /// This function generates a new value of `StreamType`, starting by processing an initial input of `StreamType`
     fn expand_from<R: GrammarRandomNumberGenerator>(
         initial: &StreamType,
@@ -405,10 +425,41 @@ pub trait StatefulGenerator<
         rng: &mut R,
     ) -> Option<StreamType>;
 
+    /// This function generates a new value of `StreamType`, starting from a key chosen by `Grammar::select_starting_point` -
+    /// so grammars with several weighted starting points can start from any of them
+    fn generate_weighted_start<R: GrammarRandomNumberGenerator>(
+        &mut self,
+        rng: &mut R,
+    ) -> Option<StreamType> {
+        let key = self.get_grammar().select_starting_point(rng).clone();
+        self.generate_at(&key, rng)
+    }
+
+    /// This function generates a new value of `StreamType` from the grammar's default rule, along with a trace of how it was expanded.
+    /// By default the trace is empty, for generators that can't record their expansions.
+    fn generate_traced<R: GrammarRandomNumberGenerator>(
+        &mut self,
+        rng: &mut R,
+    ) -> Option<(StreamType, ExpansionTrace<RuleKeyType>)> {
+        self.generate(rng)
+            .map(|result| (result, ExpansionTrace::default()))
+    }
+
     /// This function generates a new value of `StreamType`, starting by processing an initial input of `StreamType`
     fn expand_from<R: GrammarRandomNumberGenerator>(
         &mut self,
         initial: &StreamType,
         rng: &mut R,
     ) -> StreamType;
+
+    /// This works like `expand_from`, but stores temporary rules in the provided context instead of allocating a new grammar.
+    /// Re-using the same context across calls avoids those allocations. By default the context is unused, and this calls `expand_from`.
+    fn expand_from_with_context<R: GrammarRandomNumberGenerator>(
+        &mut self,
+        initial: &StreamType,
+        rng: &mut R,
+        _context: &mut ExpansionContext<GrammarType>,
+    ) -> StreamType {
+        self.expand_from(initial, rng)
+    }
 }
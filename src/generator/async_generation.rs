@@ -0,0 +1,128 @@
+use std::{fmt::Debug, marker::PhantomData};
+
+use bevy::{
+    prelude::*,
+    tasks::{block_on, AsyncComputeTaskPool, Task},
+};
+
+use super::{Generated, GenerationFailed, Generator, Grammar, GrammarRng, SeededRng};
+
+/// This component runs a generation on the `AsyncComputeTaskPool`, so long expansions don't block a frame.
+/// Once it completes, it is replaced by a `Generated` component holding the result - or `GenerationFailed` if nothing was generated.
+/// It requires the `AsyncGenerationPlugin` for the same stream type.
+#[derive(Component)]
+pub struct AsyncGeneration<StreamType: Send + Sync + 'static> {
+    task: Task<Option<StreamType>>,
+}
+
+impl<StreamType: Send + Sync + 'static> AsyncGeneration<StreamType> {
+    /// Spawns a task running the provided generation
+    pub fn spawn(generate: impl FnOnce() -> Option<StreamType> + Send + 'static) -> Self {
+        Self {
+            task: AsyncComputeTaskPool::get().spawn(async move { generate() }),
+        }
+    }
+
+    /// Spawns a task generating from the grammar's starting point using a stateless generator, such as `StringGenerator`.
+    /// The generation uses a `SeededRng`, so the same seed always produces the same result - use `GrammarRng::fork_seed` to derive
+    /// it from the world seed.
+    pub fn from_generator<RuleKeyType, GrammarResultType, GrammarType, GeneratorType>(
+        _generator: GeneratorType,
+        grammar: GrammarType,
+        seed: u64,
+    ) -> Self
+    where
+        RuleKeyType: Clone + PartialEq + Debug + 'static,
+        GrammarResultType: Clone + PartialEq + Debug + 'static,
+        StreamType: Clone + PartialEq + Debug,
+        GrammarType: Grammar<RuleKeyType, GrammarResultType, StreamType> + Send + 'static,
+        GeneratorType: Generator<RuleKeyType, GrammarResultType, StreamType, GrammarType> + 'static,
+    {
+        Self::spawn(move || GeneratorType::generate(&grammar, &mut SeededRng::new(seed)))
+    }
+
+    /// Checks whether the generation has completed
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+/// This plugin completes `AsyncGeneration` components producing the provided stream type - `String` for tracery grammars.
+/// It relies on the `TaskPoolPlugin`, which is part of bevy's default and minimal plugins, and adds a `GrammarRng` seeded with 0 unless the app already has one.
+pub struct AsyncGenerationPlugin<StreamType>(PhantomData<StreamType>);
+
+impl<StreamType> Default for AsyncGenerationPlugin<StreamType> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<StreamType: Send + Sync + 'static> Plugin for AsyncGenerationPlugin<StreamType> {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GrammarRng>()
+            .add_systems(Update, complete_async_generations::<StreamType>);
+    }
+}
+
+fn complete_async_generations<StreamType: Send + Sync + 'static>(
+    mut commands: Commands,
+    mut generations: Query<(Entity, &mut AsyncGeneration<StreamType>)>,
+) {
+    for (entity, mut generation) in generations.iter_mut() {
+        if !generation.is_finished() {
+            continue;
+        }
+        let result = block_on(&mut generation.task);
+        let mut entity = commands.entity(entity);
+        entity.remove::<AsyncGeneration<StreamType>>();
+        match result {
+            Some(result) => entity.insert(Generated(result)),
+            None => entity.insert(GenerationFailed),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracery::{StringGenerator, TraceryGrammar};
+    use bevy::core::TaskPoolPlugin;
+
+    #[test]
+    pub fn async_generations_attach_their_result_to_the_entity() {
+        let mut app = App::new();
+        app.add_plugins((
+            TaskPoolPlugin::default(),
+            AsyncGenerationPlugin::<String>::default(),
+        ));
+        let grammar =
+            TraceryGrammar::new(&[("origin", &["Hello #name#"]), ("name", &["Sam"])], None);
+        let generated = app
+            .world
+            .spawn(AsyncGeneration::from_generator(StringGenerator, grammar, 3))
+            .id();
+        let failed = app
+            .world
+            .spawn(AsyncGeneration::<String>::spawn(|| None))
+            .id();
+
+        for _ in 0..100 {
+            app.update();
+            if !app
+                .world
+                .query::<&AsyncGeneration<String>>()
+                .iter(&app.world)
+                .any(|_| true)
+            {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+
+        assert_eq!(
+            app.world.get::<Generated<String>>(generated),
+            Some(&Generated("Hello Sam".to_string()))
+        );
+        assert!(app.world.get::<GenerationFailed>(failed).is_some());
+    }
+}
@@ -0,0 +1,34 @@
+use std::fmt::Debug;
+
+use super::Grammar;
+
+/// This holds the temporary grammar used while expanding a stream - which stores any rules set by actions until the generation is complete.
+/// Stateful generators clear it between expansions instead of allocating a new one, so re-using a context avoids allocations in hot loops.
+#[derive(Debug, Clone, Default)]
+pub struct ExpansionContext<GrammarType> {
+    temporary: GrammarType,
+}
+
+impl<GrammarType> ExpansionContext<GrammarType> {
+    /// Creates a context using the provided grammar to store temporary rules. The grammar is cleared before each expansion.
+    pub fn new(temporary: GrammarType) -> Self {
+        Self { temporary }
+    }
+
+    /// Provides the temporary rules left over from the last expansion using this context
+    pub fn temporary_grammar(&self) -> &GrammarType {
+        &self.temporary
+    }
+
+    /// Clears the rules left over from the previous expansion, and provides the temporary grammar for a new one
+    pub fn prepare<RuleKeyType, GrammarResultType, StreamType>(&mut self) -> &mut GrammarType
+    where
+        RuleKeyType: Clone + PartialEq + Debug,
+        GrammarResultType: Clone + PartialEq + Debug,
+        StreamType: Clone + PartialEq + Debug,
+        GrammarType: Grammar<RuleKeyType, GrammarResultType, StreamType>,
+    {
+        self.temporary.clear_additional_rules();
+        &mut self.temporary
+    }
+}
@@ -0,0 +1,200 @@
+use std::sync::{Arc, OnceLock};
+
+use super::{
+    Generator, GrammarRandomNumberGenerator, SimpleGenerator, SimpleGrammar, SimplePhrase,
+    SimpleToken,
+};
+
+/// This provides the options for the rules of a `DynamicGrammar` - such as a database, or a key-value store on disk.
+/// Closures taking a rule key and returning it's options implement it, so a store can be as simple as a lookup function.
+pub trait RuleStore: Send + Sync {
+    /// Fetches all the options of a rule, or `None` if the store doesn't have it
+    fn fetch(&self, rule: &str) -> Option<Vec<String>>;
+}
+
+impl<F: Fn(&str) -> Option<Vec<String>> + Send + Sync> RuleStore for F {
+    fn fetch(&self, rule: &str) -> Option<Vec<String>> {
+        self(rule)
+    }
+}
+
+/// A store without any rules, used by the default `DynamicGrammar`
+struct EmptyStore;
+
+impl RuleStore for EmptyStore {
+    fn fetch(&self, _: &str) -> Option<Vec<String>> {
+        None
+    }
+}
+
+/// This is a grammar whose options are fetched lazily from a `RuleStore`, so huge vocabularies - like every city name in
+/// the world - don't need to be resident in memory. Only the rule keys are provided up front. Each rule is fetched the first
+/// time it is expanded, and cached until `clear_cache` is called.
+///
+/// Options refer to other rules the same way as Tracery - `the #animal# sleeps` - but don't support modifiers or actions.
+#[derive(Clone)]
+pub struct DynamicGrammar {
+    keys: Vec<String>,
+    starting_point: String,
+    store: Arc<dyn RuleStore>,
+    cache: Vec<OnceLock<Option<Vec<SimplePhrase<String, String>>>>>,
+}
+
+impl Default for DynamicGrammar {
+    fn default() -> Self {
+        Self {
+            keys: vec![],
+            starting_point: "origin".to_string(),
+            store: Arc::new(EmptyStore),
+            cache: vec![],
+        }
+    }
+}
+
+impl std::fmt::Debug for DynamicGrammar {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DynamicGrammar")
+            .field("keys", &self.keys)
+            .field("starting_point", &self.starting_point)
+            .field("cached_rules", &self.cached_rules())
+            .finish()
+    }
+}
+
+impl DynamicGrammar {
+    /// Creates a grammar with the provided rule keys, fetching their options from the store. It starts from `origin` by default.
+    pub fn new<T: Into<String>>(
+        keys: impl IntoIterator<Item = T>,
+        store: impl RuleStore + 'static,
+    ) -> Self {
+        let keys = keys.into_iter().map(Into::into).collect::<Vec<String>>();
+        let cache = keys.iter().map(|_| OnceLock::new()).collect();
+        Self {
+            keys,
+            store: Arc::new(store),
+            cache,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the rule generation starts from by default
+    pub fn with_starting_point(mut self, starting_point: impl Into<String>) -> Self {
+        self.starting_point = starting_point.into();
+        self
+    }
+
+    /// The number of rules whose options are currently cached
+    pub fn cached_rules(&self) -> usize {
+        self.cache
+            .iter()
+            .filter(|rule| rule.get().is_some())
+            .count()
+    }
+
+    /// Drops all the cached options, so they are fetched from the store again the next time they are needed
+    pub fn clear_cache(&mut self) {
+        for rule in self.cache.iter_mut() {
+            rule.take();
+        }
+    }
+
+    /// Generates text starting from the default starting point. Rules that couldn't be expanded are left as `#rule#`.
+    pub fn generate<R: GrammarRandomNumberGenerator>(&self, rng: &mut R) -> Option<String> {
+        SimpleGenerator::generate(self, rng).map(|phrase| phrase_to_string(&phrase))
+    }
+
+    /// Generates text starting from the provided rule. Rules that couldn't be expanded are left as `#rule#`.
+    pub fn generate_at<R: GrammarRandomNumberGenerator>(
+        &self,
+        rule: &str,
+        rng: &mut R,
+    ) -> Option<String> {
+        SimpleGenerator::generate_at(&rule.to_string(), self, rng)
+            .map(|phrase| phrase_to_string(&phrase))
+    }
+}
+
+/// Splits an option into it's text and the rules it refers to
+fn parse_option(option: &str) -> SimplePhrase<String, String> {
+    option
+        .split('#')
+        .enumerate()
+        .filter(|(_, part)| !part.is_empty())
+        .map(|(index, part)| match index % 2 {
+            0 => SimpleToken::Terminal(part.to_string()),
+            _ => SimpleToken::Rule(part.to_string()),
+        })
+        .collect()
+}
+
+fn phrase_to_string(phrase: &SimplePhrase<String, String>) -> String {
+    phrase
+        .iter()
+        .map(|token| match token {
+            SimpleToken::Terminal(text) => text.clone(),
+            SimpleToken::Rule(rule) => format!("#{rule}#"),
+        })
+        .collect()
+}
+
+impl SimpleGrammar for DynamicGrammar {
+    type Key = String;
+    type Terminal = String;
+
+    fn rule_keys(&self) -> &Vec<String> {
+        &self.keys
+    }
+
+    fn rule_options(&self, rule: &String) -> Option<&Vec<SimplePhrase<String, String>>> {
+        let index = self.keys.iter().position(|key| key == rule)?;
+        self.cache[index]
+            .get_or_init(|| {
+                self.store
+                    .fetch(rule)
+                    .map(|options| options.iter().map(|option| parse_option(option)).collect())
+            })
+            .as_ref()
+    }
+
+    fn default_starting_point(&self) -> &String {
+        &self.starting_point
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    pub fn dynamic_grammars_fetch_rules_once_when_needed() {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let counter = fetches.clone();
+        let mut grammar = DynamicGrammar::new(["origin", "city", "country"], move |rule: &str| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            match rule {
+                "origin" => Some(vec!["Welcome to #city#, #planet#".to_string()]),
+                "city" => Some(vec!["Lima".to_string(), "Oslo".to_string()]),
+                _ => None,
+            }
+        });
+        assert_eq!(
+            grammar.generate(&mut 1).unwrap(),
+            "Welcome to Oslo, #planet#"
+        );
+        assert_eq!(
+            grammar.generate(&mut 0).unwrap(),
+            "Welcome to Lima, #planet#"
+        );
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+        assert_eq!(grammar.cached_rules(), 2);
+        assert!(grammar.generate_at("country", &mut 0).is_none());
+        assert_eq!(fetches.load(Ordering::SeqCst), 3);
+
+        grammar.clear_cache();
+        assert_eq!(grammar.cached_rules(), 0);
+        assert_eq!(grammar.generate_at("city", &mut 0).unwrap(), "Lima");
+        assert_eq!(fetches.load(Ordering::SeqCst), 4);
+    }
+}
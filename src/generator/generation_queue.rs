@@ -0,0 +1,374 @@
+use std::{
+    any::TypeId,
+    collections::VecDeque,
+    fmt::Debug,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    time::Duration,
+};
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+    utils::{get_short_name, Instant},
+};
+
+use super::{Generator, Grammar, GrammarRng, SeededRng};
+
+/// This component holds the result of a generation that ran in the background - such as an `AsyncGeneration` or a `GenerateRequest`
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Generated<StreamType: Send + Sync + 'static>(pub StreamType);
+
+/// This component marks entities whose background generation completed without a result
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GenerationFailed;
+
+type Generate<StreamType> = Box<dyn FnOnce() -> Option<StreamType> + Send + Sync>;
+
+/// This event queues a generation with the `GenerationQueuePlugin`. Once it has run, the target entity gets a `Generated` component
+/// holding the result - or `GenerationFailed` if nothing was generated.
+#[derive(Event)]
+pub struct GenerateRequest<StreamType: Send + Sync + 'static> {
+    /// The entity receiving the result
+    pub target: Entity,
+    /// Requests with a higher priority run first, while requests with the same priority run in the order they were sent.
+    /// By default, this is 0 - so player facing text can use a higher priority to skip ahead of background content.
+    pub priority: i32,
+    generate: Generate<StreamType>,
+    queued_at: Option<Instant>,
+}
+
+impl<StreamType: Send + Sync + 'static> GenerateRequest<StreamType> {
+    /// Creates a request running the provided generation
+    pub fn new(
+        target: Entity,
+        generate: impl FnOnce() -> Option<StreamType> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            target,
+            priority: 0,
+            generate: Box::new(generate),
+            queued_at: None,
+        }
+    }
+
+    /// Sets the request's priority, returning the updated request
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Creates a request generating from the grammar's starting point using a stateless generator, such as `StringGenerator`.
+    /// The generation uses a `SeededRng`, so the same seed always produces the same result - use `GrammarRng::fork_seed` to derive
+    /// it from the world seed.
+    pub fn from_generator<RuleKeyType, GrammarResultType, GrammarType, GeneratorType>(
+        target: Entity,
+        _generator: GeneratorType,
+        grammar: GrammarType,
+        seed: u64,
+    ) -> Self
+    where
+        RuleKeyType: Clone + PartialEq + Debug + 'static,
+        GrammarResultType: Clone + PartialEq + Debug + 'static,
+        StreamType: Clone + PartialEq + Debug,
+        GrammarType: Grammar<RuleKeyType, GrammarResultType, StreamType> + Send + Sync + 'static,
+        GeneratorType: Generator<RuleKeyType, GrammarResultType, StreamType, GrammarType> + 'static,
+    {
+        Self::new(target, move || {
+            GeneratorType::generate(&grammar, &mut SeededRng::new(seed))
+        })
+    }
+}
+
+/// This resource holds the generation requests that haven't run yet, ordered by priority, and how much time can be spent on them each frame
+#[derive(Resource)]
+pub struct GenerationQueue<StreamType: Send + Sync + 'static> {
+    /// The time spent running generations each frame. At least one generation runs each frame, even if it takes longer.
+    pub budget: Duration,
+    pending: VecDeque<GenerateRequest<StreamType>>,
+    latency: Option<Duration>,
+}
+
+impl<StreamType: Send + Sync + 'static> GenerationQueue<StreamType> {
+    /// The number of requests that haven't run yet
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Checks whether every request has run
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// The average time between a request being sent and it running, for the requests that ran this frame.
+    /// This is `None` if no requests ran this frame.
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency
+    }
+}
+
+/// This plugin runs `GenerateRequest` events producing the provided stream type - `String` for tracery grammars.
+/// Rather than running every request as soon as it's sent, they are queued and only run until the per-frame budget is used up,
+/// so large batches - like seeding a world - are spread across several frames instead of blocking a single update.
+/// It also adds a `GrammarRng` seeded with 0, unless the app already has one.
+pub struct GenerationQueuePlugin<StreamType> {
+    budget: Duration,
+    stream: PhantomData<StreamType>,
+}
+
+impl<StreamType> Default for GenerationQueuePlugin<StreamType> {
+    fn default() -> Self {
+        Self {
+            budget: Duration::from_micros(500),
+            stream: PhantomData,
+        }
+    }
+}
+
+impl<StreamType> GenerationQueuePlugin<StreamType> {
+    /// Sets the time spent running generations each frame. By default, this is half a millisecond.
+    pub fn with_budget(mut self, budget: Duration) -> Self {
+        self.budget = budget;
+        self
+    }
+}
+
+impl<StreamType: Send + Sync + 'static> Plugin for GenerationQueuePlugin<StreamType> {
+    fn build(&self, app: &mut App) {
+        app.add_event::<GenerateRequest<StreamType>>()
+            .init_resource::<GrammarRng>()
+            .insert_resource(GenerationQueue::<StreamType> {
+                budget: self.budget,
+                pending: VecDeque::new(),
+                latency: None,
+            })
+            .add_systems(
+                Update,
+                (
+                    queue_generate_requests::<StreamType>,
+                    run_queued_generations::<StreamType>,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn queue_generate_requests<StreamType: Send + Sync + 'static>(
+    mut requests: ResMut<Events<GenerateRequest<StreamType>>>,
+    mut queue: ResMut<GenerationQueue<StreamType>>,
+) {
+    let now = Instant::now();
+    for mut request in requests.drain() {
+        request.queued_at = Some(now);
+        let position = queue
+            .pending
+            .partition_point(|queued| queued.priority >= request.priority);
+        queue.pending.insert(position, request);
+    }
+}
+
+fn run_queued_generations<StreamType: Send + Sync + 'static>(
+    mut commands: Commands,
+    mut queue: ResMut<GenerationQueue<StreamType>>,
+) {
+    let start = Instant::now();
+    let mut latency = Duration::ZERO;
+    let mut completed = 0;
+    while let Some(request) = queue.pending.pop_front() {
+        let result = (request.generate)();
+        if let Some(queued_at) = request.queued_at {
+            latency += queued_at.elapsed();
+            completed += 1;
+        }
+        if let Some(mut entity) = commands.get_entity(request.target) {
+            match result {
+                Some(result) => entity.insert(Generated(result)),
+                None => entity.insert(GenerationFailed),
+            };
+        }
+        if start.elapsed() >= queue.budget {
+            break;
+        }
+    }
+    queue.latency = (completed > 0).then(|| latency / completed);
+}
+
+/// This plugin records diagnostics for the `GenerationQueue` of the provided stream type - the number of pending requests,
+/// and the average latency between requests being sent and running - to help tune budgets and priorities.
+/// It should be added alongside the `GenerationQueuePlugin` for the same stream type.
+pub struct GenerationQueueDiagnosticsPlugin<StreamType>(PhantomData<StreamType>);
+
+impl<StreamType> Default for GenerationQueueDiagnosticsPlugin<StreamType> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<StreamType: 'static> GenerationQueueDiagnosticsPlugin<StreamType> {
+    /// The number of requests waiting in the queue
+    pub fn pending() -> DiagnosticId {
+        Self::diagnostic_id(205713829472659823447029118529733406321)
+    }
+
+    /// The average time, in milliseconds, between requests being sent and running
+    pub fn latency() -> DiagnosticId {
+        Self::diagnostic_id(112378940356721034853902157624881973546)
+    }
+
+    /// Derives a diagnostic's id from the stream type, so queues for different stream types record separate diagnostics
+    fn diagnostic_id(base: u128) -> DiagnosticId {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        TypeId::of::<StreamType>().hash(&mut hasher);
+        DiagnosticId::from_u128(base ^ hasher.finish() as u128)
+    }
+
+    /// Names a diagnostic after the stream type, like `generation_queue_pending<String>`
+    fn diagnostic_name(name: &str) -> String {
+        let stream_type = get_short_name(std::any::type_name::<StreamType>());
+        format!("{name}<{stream_type}>")
+    }
+}
+
+impl<StreamType: Send + Sync + 'static> Plugin for GenerationQueueDiagnosticsPlugin<StreamType> {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(
+            Self::pending(),
+            Self::diagnostic_name("generation_queue_pending"),
+            20,
+        ))
+        .register_diagnostic(
+            Diagnostic::new(
+                Self::latency(),
+                Self::diagnostic_name("generation_queue_latency"),
+                20,
+            )
+            .with_suffix("ms"),
+        )
+        .add_systems(
+            Update,
+            generation_queue_diagnostics::<StreamType>.after(run_queued_generations::<StreamType>),
+        );
+    }
+}
+
+fn generation_queue_diagnostics<StreamType: Send + Sync + 'static>(
+    mut diagnostics: Diagnostics,
+    queue: Res<GenerationQueue<StreamType>>,
+) {
+    diagnostics.add_measurement(
+        GenerationQueueDiagnosticsPlugin::<StreamType>::pending(),
+        || queue.len() as f64,
+    );
+    if let Some(latency) = queue.latency() {
+        diagnostics.add_measurement(
+            GenerationQueueDiagnosticsPlugin::<StreamType>::latency(),
+            || latency.as_secs_f64() * 1000.0,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracery::{StringGenerator, TraceryGrammar};
+    use bevy::diagnostic::DiagnosticsStore;
+
+    #[test]
+    pub fn queued_generations_are_spread_across_frames() {
+        let mut app = App::new();
+        app.add_plugins(GenerationQueuePlugin::<String>::default().with_budget(Duration::ZERO));
+        let grammar =
+            TraceryGrammar::new(&[("origin", &["Hello #name#"]), ("name", &["Sam"])], None);
+        let targets = (0..3)
+            .map(|_| app.world.spawn_empty().id())
+            .collect::<Vec<_>>();
+        for target in targets.iter() {
+            app.world.send_event(GenerateRequest::from_generator(
+                *target,
+                StringGenerator,
+                grammar.clone(),
+                3,
+            ));
+        }
+        app.world
+            .send_event(GenerateRequest::<String>::new(targets[0], || None));
+
+        app.update();
+        assert_eq!(
+            app.world.get::<Generated<String>>(targets[0]),
+            Some(&Generated("Hello Sam".to_string()))
+        );
+        assert!(app.world.get::<Generated<String>>(targets[1]).is_none());
+        assert_eq!(app.world.resource::<GenerationQueue<String>>().len(), 3);
+
+        for _ in 0..3 {
+            app.update();
+        }
+        assert!(app.world.get::<Generated<String>>(targets[2]).is_some());
+        assert!(app.world.get::<GenerationFailed>(targets[0]).is_some());
+        assert!(app.world.resource::<GenerationQueue<String>>().is_empty());
+    }
+
+    #[test]
+    pub fn higher_priority_requests_run_first() {
+        let mut app = App::new();
+        app.add_plugins(GenerationQueuePlugin::<String>::default().with_budget(Duration::ZERO));
+        let background = app.world.spawn_empty().id();
+        let flavor = app.world.spawn_empty().id();
+        let dialogue = app.world.spawn_empty().id();
+        app.world.send_event(GenerateRequest::new(background, || {
+            Some("background".to_string())
+        }));
+        app.world
+            .send_event(GenerateRequest::new(flavor, || Some("flavor".to_string())));
+        app.world.send_event(
+            GenerateRequest::new(dialogue, || Some("dialogue".to_string())).with_priority(10),
+        );
+
+        app.update();
+        assert!(app.world.get::<Generated<String>>(dialogue).is_some());
+        assert!(app.world.get::<Generated<String>>(background).is_none());
+        app.update();
+        assert!(app.world.get::<Generated<String>>(background).is_some());
+        assert!(app.world.get::<Generated<String>>(flavor).is_none());
+    }
+
+    #[test]
+    pub fn queue_diagnostics_record_pending_requests_and_latency() {
+        let mut app = App::new();
+        app.add_plugins((
+            GenerationQueuePlugin::<String>::default().with_budget(Duration::ZERO),
+            GenerationQueueDiagnosticsPlugin::<String>::default(),
+            GenerationQueuePlugin::<Vec<String>>::default().with_budget(Duration::ZERO),
+            GenerationQueueDiagnosticsPlugin::<Vec<String>>::default(),
+        ));
+        for _ in 0..3 {
+            let target = app.world.spawn_empty().id();
+            app.world
+                .send_event(GenerateRequest::new(target, || Some("text".to_string())));
+        }
+        let target = app.world.spawn_empty().id();
+        app.world.send_event(GenerateRequest::new(target, || {
+            Some(vec!["text".to_string()])
+        }));
+
+        app.update();
+        let store = app.world.resource::<DiagnosticsStore>();
+        let pending = store.get_measurement(GenerationQueueDiagnosticsPlugin::<String>::pending());
+        assert_eq!(pending.unwrap().value, 2.);
+        assert!(store
+            .get_measurement(GenerationQueueDiagnosticsPlugin::<String>::latency())
+            .is_some());
+        // Each stream type's queue records it's own diagnostics
+        let pending =
+            store.get_measurement(GenerationQueueDiagnosticsPlugin::<Vec<String>>::pending());
+        assert_eq!(pending.unwrap().value, 0.);
+        assert_eq!(
+            store
+                .get(GenerationQueueDiagnosticsPlugin::<Vec<String>>::pending())
+                .unwrap()
+                .name,
+            "generation_queue_pending<Vec<String>>"
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use bevy::prelude::Resource;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::SeededRng;
+
+/// This resource holds the world seed every generation in the app is derived from. Rather than sharing one random number generator,
+/// each generation gets it's own `SeededRng` from `fork` - so generations stay reproducible from the seed, and running one more
+/// or one less generation never changes what the others produce. With the `serde` feature, it can be saved alongside the world.
+#[derive(Resource, Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GrammarRng {
+    seed: u64,
+    forks: u64,
+}
+
+impl GrammarRng {
+    /// Creates the resource from a world seed
+    pub fn new(seed: u64) -> Self {
+        Self { seed, forks: 0 }
+    }
+
+    /// Gets the world seed
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Replaces the world seed, and starts forking from the beginning again - so the same seed forks the same generators
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.forks = 0;
+    }
+
+    /// Gets the number of generators forked since the seed was set
+    pub fn forks(&self) -> u64 {
+        self.forks
+    }
+
+    /// Forks an independent generator from the world seed. Each fork gets the next stream of the seed, so the generators are
+    /// the same every time the forks happen in the same order.
+    pub fn fork(&mut self) -> SeededRng {
+        SeededRng::new(self.fork_seed())
+    }
+
+    /// Forks a seed from the world seed, for the APIs that take a seed rather than a generator - such as `GenerateRequest::from_generator`
+    pub fn fork_seed(&mut self) -> u64 {
+        let seed = self.stream(self.forks).next_u64();
+        self.forks = self.forks.wrapping_add(1);
+        seed
+    }
+
+    /// Gets the generator for one of the world seed's streams, without forking. Unlike forks, streams don't depend on the order
+    /// they are requested in - so giving each grammar or system it's own stream keeps it's output stable as others are added.
+    /// Forks use the streams counting up from 0, so fixed streams are best picked from the other end - such as `u64::MAX - 1`.
+    pub fn stream(&self, stream: u64) -> SeededRng {
+        SeededRng::for_stream(self.seed, stream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::GrammarRandomNumberGenerator;
+
+    #[test]
+    pub fn forks_are_independent_and_reproducible() {
+        let numbers = |mut rng: SeededRng| (0..8).map(|_| rng.get_number(100)).collect::<Vec<_>>();
+        let mut world = GrammarRng::new(42);
+        let first = numbers(world.fork());
+        let second = numbers(world.fork());
+        assert_ne!(first, second);
+        assert_eq!(world.forks(), 2);
+
+        world.reseed(42);
+        assert_eq!(numbers(world.fork()), first);
+        assert_eq!(numbers(GrammarRng::new(42).fork()), first);
+        assert_ne!(numbers(GrammarRng::new(43).fork()), first);
+        assert_eq!(
+            numbers(world.stream(7)),
+            numbers(GrammarRng::new(42).stream(7))
+        );
+    }
+}
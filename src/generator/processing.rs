@@ -0,0 +1,880 @@
+use std::fmt::Debug;
+
+use super::{Grammar, GrammarRandomNumberGenerator, Replacable, TraceNode};
+
+/// Processes a stream breadth first. If a trace is provided, every replaced rule gets recorded into it.
+/// Since breadth first processing replaces the rules of a whole stream in each iteration, nested rules are only
+/// recorded as children when they had to be expanded on their own - for modifiers or actions.
+pub(crate) fn breadth_first<
+    RuleKeyType: Clone + PartialEq + Debug,
+    ResultType: Clone + PartialEq + Debug,
+    StreamType: Clone + PartialEq + Debug,
+    G: Grammar<RuleKeyType, ResultType, StreamType> + ?Sized,
+    R: GrammarRandomNumberGenerator,
+>(
+    grammar: &G,
+    stream: &StreamType,
+    temporary_grammar: &mut G,
+    rng: &mut R,
+    mut trace: Option<&mut Vec<TraceNode<RuleKeyType>>>,
+) -> StreamType {
+    let (skippable, frontier) = grammar.check_token_stream(stream);
+    if skippable {
+        return stream.clone();
+    }
+    converge(
+        grammar,
+        stream.clone(),
+        frontier,
+        temporary_grammar,
+        rng,
+        &mut trace,
+        &mut BreadthFirstLimits::new(grammar),
+    )
+}
+
+/// This processes a stream breadth first one iteration at a time, so each intermediate stream can be inspected - such as for
+/// visualizing an L-system or a dungeon growing frame by frame. Each call to `step` replaces every rule of the stream once,
+/// and the values of any variables assigned along the way are processed fully within the same step. It stops in the same
+/// places as `Grammar::breadth_first_processing` - once the stream is stable, or the grammar's limits are reached.
+pub struct BreadthFirstExpansion<
+    'a,
+    RuleKeyType: Clone + PartialEq + Debug,
+    ResultType: Clone + PartialEq + Debug,
+    StreamType,
+    G,
+> {
+    grammar: &'a G,
+    temporary_grammar: G,
+    current: StreamType,
+    frontier: Option<Vec<Replacable<RuleKeyType, ResultType>>>,
+    limits: BreadthFirstLimits,
+}
+
+impl<
+        'a,
+        RuleKeyType: Clone + PartialEq + Debug,
+        ResultType: Clone + PartialEq + Debug,
+        StreamType: Clone + PartialEq + Debug,
+        G: Grammar<RuleKeyType, ResultType, StreamType>,
+    > BreadthFirstExpansion<'a, RuleKeyType, ResultType, StreamType, G>
+{
+    /// Starts expanding a stream, using the grammar's `Default` value as the temporary grammar
+    pub fn new(grammar: &'a G, stream: StreamType) -> Self
+    where
+        G: Default,
+    {
+        Self::with_temporary_grammar(grammar, stream, G::default())
+    }
+
+    /// Starts expanding a stream, using the provided grammar to store the variables assigned along the way - such as
+    /// `TraceryGrammar::empty()`
+    pub fn with_temporary_grammar(
+        grammar: &'a G,
+        stream: StreamType,
+        temporary_grammar: G,
+    ) -> Self {
+        let (skippable, frontier) = grammar.check_token_stream(&stream);
+        Self {
+            grammar,
+            temporary_grammar,
+            current: stream,
+            frontier: (!skippable).then_some(frontier),
+            limits: BreadthFirstLimits::new(grammar),
+        }
+    }
+
+    /// Makes a single iteration, returning the resulting stream - or `None` once the stream is stable, and nothing changes
+    pub fn step<R: GrammarRandomNumberGenerator>(&mut self, rng: &mut R) -> Option<&StreamType> {
+        let frontier = self.frontier.take()?;
+        let next = iterate(
+            self.grammar,
+            frontier,
+            &mut self.temporary_grammar,
+            rng,
+            &mut None,
+            &mut self.limits,
+        );
+        let changed = next != self.current;
+        self.frontier = next_frontier(self.grammar, &self.current, &next, &mut self.limits);
+        self.current = next;
+        changed.then_some(&self.current)
+    }
+
+    /// Makes every remaining iteration, returning the final stream
+    pub fn finish<R: GrammarRandomNumberGenerator>(mut self, rng: &mut R) -> StreamType {
+        while self.step(rng).is_some() {}
+        self.current
+    }
+
+    /// Gets the stream as it is after the iterations made so far
+    pub fn stream(&self) -> &StreamType {
+        &self.current
+    }
+
+    /// Gets the number of iterations made so far, including those made while processing variables
+    pub fn iterations(&self) -> usize {
+        self.limits.iterations
+    }
+
+    /// Checks whether the expansion is complete, so further steps won't change the stream
+    pub fn is_stable(&self) -> bool {
+        self.frontier.is_none()
+    }
+
+    /// Gets the temporary grammar, holding the variables assigned so far
+    pub fn temporary_grammar(&self) -> &G {
+        &self.temporary_grammar
+    }
+}
+
+/// The limits shared by every frontier converged while processing a stream breadth first - including the expansions processed
+/// on their own for modifiers and actions, so recursive rules can't nest them without end
+struct BreadthFirstLimits {
+    /// The number of iterations made so far
+    iterations: usize,
+    /// The number of iterations allowed before processing stops, which also limits how deeply expansions can be nested
+    max_iterations: usize,
+    /// The number of tokens a frontier can hold before processing stops
+    max_queue_size: usize,
+    /// How deeply the expansion currently being processed on it's own is nested
+    nesting: usize,
+    /// The number of rules expanded within nested expansions so far
+    expansions: usize,
+    /// The number of rules that can be expanded within nested expansions, after which any remaining rules are left as their
+    /// default result
+    max_expansions: usize,
+}
+
+impl BreadthFirstLimits {
+    fn new<
+        RuleKeyType: Clone + PartialEq + Debug,
+        ResultType: Clone + PartialEq + Debug,
+        StreamType: Clone + PartialEq + Debug,
+        G: Grammar<RuleKeyType, ResultType, StreamType> + ?Sized,
+    >(
+        grammar: &G,
+    ) -> Self {
+        Self {
+            iterations: 0,
+            max_iterations: grammar.max_depth(),
+            max_queue_size: grammar.max_queue_size(),
+            nesting: 0,
+            expansions: 0,
+            max_expansions: grammar.max_expansions(),
+        }
+    }
+}
+
+/// Iterates a stream towards a fixed point. It stops once an iteration leaves the stream unchanged, no tokens are left to
+/// replace, or the limits are reached.
+fn converge<
+    RuleKeyType: Clone + PartialEq + Debug,
+    ResultType: Clone + PartialEq + Debug,
+    StreamType: Clone + PartialEq + Debug,
+    G: Grammar<RuleKeyType, ResultType, StreamType> + ?Sized,
+    R: GrammarRandomNumberGenerator,
+>(
+    grammar: &G,
+    mut current: StreamType,
+    mut frontier: Vec<Replacable<RuleKeyType, ResultType>>,
+    temporary_grammar: &mut G,
+    rng: &mut R,
+    trace: &mut Option<&mut Vec<TraceNode<RuleKeyType>>>,
+    limits: &mut BreadthFirstLimits,
+) -> StreamType {
+    loop {
+        let next = iterate(grammar, frontier, temporary_grammar, rng, trace, limits);
+        let next_tokens = next_frontier(grammar, &current, &next, limits);
+        current = next;
+        match next_tokens {
+            Some(tokens) => frontier = tokens,
+            None => return current,
+        }
+    }
+}
+
+/// Makes a single iteration - replacing every token of the frontier once, and then converging the variables assigned
+/// within it, so they are ready before the next iteration refers to them
+fn iterate<
+    RuleKeyType: Clone + PartialEq + Debug,
+    ResultType: Clone + PartialEq + Debug,
+    StreamType: Clone + PartialEq + Debug,
+    G: Grammar<RuleKeyType, ResultType, StreamType> + ?Sized,
+    R: GrammarRandomNumberGenerator,
+>(
+    grammar: &G,
+    frontier: Vec<Replacable<RuleKeyType, ResultType>>,
+    temporary_grammar: &mut G,
+    rng: &mut R,
+    trace: &mut Option<&mut Vec<TraceNode<RuleKeyType>>>,
+    limits: &mut BreadthFirstLimits,
+) -> StreamType {
+    let mut assignments = vec![];
+    let next = expand_frontier(
+        grammar,
+        frontier,
+        temporary_grammar,
+        rng,
+        trace.as_deref_mut(),
+        &mut assignments,
+        limits,
+    );
+    for (key, value, tokens) in assignments {
+        grammar.begin_assignment(temporary_grammar, &key);
+        let value = converge(
+            grammar,
+            value,
+            tokens,
+            temporary_grammar,
+            rng,
+            trace,
+            limits,
+        );
+        grammar.end_assignment(temporary_grammar, &key);
+        temporary_grammar.set_additional_rules(key, &grammar.stream_to_result(&value));
+    }
+    next
+}
+
+/// Counts an iteration that turned the current stream into the next one, returning the tokens the following iteration
+/// replaces - or `None` if the stream is stable, or the limits were reached
+fn next_frontier<
+    RuleKeyType: Clone + PartialEq + Debug,
+    ResultType: Clone + PartialEq + Debug,
+    StreamType: Clone + PartialEq + Debug,
+    G: Grammar<RuleKeyType, ResultType, StreamType> + ?Sized,
+>(
+    grammar: &G,
+    current: &StreamType,
+    next: &StreamType,
+    limits: &mut BreadthFirstLimits,
+) -> Option<Vec<Replacable<RuleKeyType, ResultType>>> {
+    if next == current {
+        return None;
+    }
+    limits.iterations = limits.iterations.saturating_add(1);
+    if limits.iterations >= limits.max_iterations {
+        return None;
+    }
+    let (skippable, tokens) = grammar.check_token_stream(next);
+    if skippable || tokens.len() > limits.max_queue_size {
+        return None;
+    }
+    Some(tokens)
+}
+
+/// Processes a stream on it's own within an iteration - like the expansion modifiers apply to, or the value of an action -
+/// sharing the limits of the stream around it. Once expansions are nested as deeply as the grammar's maximum depth, the
+/// stream is left as it is.
+fn nested<
+    RuleKeyType: Clone + PartialEq + Debug,
+    ResultType: Clone + PartialEq + Debug,
+    StreamType: Clone + PartialEq + Debug,
+    G: Grammar<RuleKeyType, ResultType, StreamType> + ?Sized,
+    R: GrammarRandomNumberGenerator,
+>(
+    grammar: &G,
+    stream: StreamType,
+    temporary_grammar: &mut G,
+    rng: &mut R,
+    mut trace: Option<&mut Vec<TraceNode<RuleKeyType>>>,
+    limits: &mut BreadthFirstLimits,
+) -> StreamType {
+    if limits.nesting >= limits.max_iterations {
+        return stream;
+    }
+    let (skippable, frontier) = grammar.check_token_stream(&stream);
+    if skippable {
+        return stream;
+    }
+    limits.nesting += 1;
+    let result = converge(
+        grammar,
+        stream,
+        frontier,
+        temporary_grammar,
+        rng,
+        &mut trace,
+        limits,
+    );
+    limits.nesting -= 1;
+    result
+}
+
+/// Replaces every token of a frontier once, returning the resulting stream. Assignments whose values still need processing
+/// are collected with their value and it's tokens, rather than being set straight away.
+fn expand_frontier<
+    RuleKeyType: Clone + PartialEq + Debug,
+    ResultType: Clone + PartialEq + Debug,
+    StreamType: Clone + PartialEq + Debug,
+    G: Grammar<RuleKeyType, ResultType, StreamType> + ?Sized,
+    R: GrammarRandomNumberGenerator,
+>(
+    grammar: &G,
+    frontier: Vec<Replacable<RuleKeyType, ResultType>>,
+    temporary_grammar: &mut G,
+    rng: &mut R,
+    mut trace: Option<&mut Vec<TraceNode<RuleKeyType>>>,
+    assignments: &mut Vec<(
+        RuleKeyType,
+        StreamType,
+        Vec<Replacable<RuleKeyType, ResultType>>,
+    )>,
+    limits: &mut BreadthFirstLimits,
+) -> StreamType {
+    let tracing = trace.is_some();
+    let next = frontier
+        .into_iter()
+        .flat_map(|token| match token {
+            Replacable::Ready(v) => vec![v],
+            Replacable::Replace(key) => {
+                let (actions, rule) = match grammar.split_actions(&key) {
+                    Some(split) => split,
+                    None => (vec![], Some(key.clone())),
+                };
+                let mut children = vec![];
+                // Actions only apply while their rule is expanded, so the previous values are kept aside
+                let restore = actions
+                    .into_iter()
+                    .filter_map(|action| match action {
+                        Replacable::ImmediateMeta(target, value) => {
+                            let previous = temporary_grammar.get_rule_options(&target).cloned();
+                            grammar.begin_assignment(temporary_grammar, &target);
+                            let stream = nested(
+                                grammar,
+                                grammar.result_into_stream(value),
+                                temporary_grammar,
+                                rng,
+                                tracing.then_some(&mut children),
+                                limits,
+                            );
+                            grammar.end_assignment(temporary_grammar, &target);
+                            temporary_grammar.set_additional_rules(
+                                target.clone(),
+                                &grammar.stream_into_result(stream),
+                            );
+                            Some((target, previous))
+                        }
+                        Replacable::DelayedMeta(target, value) => {
+                            let previous = temporary_grammar.get_rule_options(&target).cloned();
+                            temporary_grammar.set_additional_rules(target.clone(), &[value]);
+                            Some((target, previous))
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                let traced_rule = rule.clone();
+                let (rule, modifiers) = match rule {
+                    Some(rule) => match grammar.split_modifiers(&rule) {
+                        Some((rule, modifiers)) => (Some(rule), Some(modifiers)),
+                        None => (Some(rule), None),
+                    },
+                    None => (None, None),
+                };
+                let selected = rule
+                    .filter(|_| limits.nesting == 0 || limits.expansions < limits.max_expansions)
+                    .and_then(|rule| {
+                        if limits.nesting > 0 {
+                            limits.expansions = limits.expansions.saturating_add(1);
+                        }
+                        grammar.select_for_processing(&rule, rng, temporary_grammar)
+                    });
+                let option = selected.as_ref().and_then(|(_, option)| *option);
+                let result = match selected {
+                    Some((value, _)) if modifiers.is_none() && restore.is_empty() => {
+                        vec![value]
+                    }
+                    Some((value, _)) => {
+                        // Modifiers and actions apply to the rule's full expansion, so it gets processed on it's own first
+                        let stream = nested(
+                            grammar,
+                            grammar.result_into_stream(value),
+                            temporary_grammar,
+                            rng,
+                            tracing.then_some(&mut children),
+                            limits,
+                        );
+                        let stream = match modifiers {
+                            Some(modifiers) => grammar.apply_modifiers_in_context(
+                                &modifiers,
+                                stream,
+                                temporary_grammar,
+                            ),
+                            None => stream,
+                        };
+                        grammar.stream_into_result(stream)
+                    }
+                    None if restore.is_empty() => vec![grammar.rule_to_default_result(&key)],
+                    None => vec![],
+                };
+                restore_rules(temporary_grammar, restore);
+                if let Some(trace) = trace.as_deref_mut() {
+                    match traced_rule {
+                        Some(rule) => trace.push(TraceNode {
+                            rule,
+                            option,
+                            children,
+                        }),
+                        None => trace.append(&mut children),
+                    }
+                }
+                result
+            }
+            Replacable::ImmediateMeta(key, value) => {
+                let stream = grammar.result_to_stream(std::slice::from_ref(&value));
+                let (skippable, tokens) = grammar.check_token_stream(&stream);
+                if skippable {
+                    temporary_grammar.set_additional_rules(key, &[value]);
+                } else {
+                    assignments.push((key, stream, tokens));
+                }
+                vec![]
+            }
+            Replacable::DelayedMeta(key, value) => {
+                temporary_grammar.set_additional_rules(key, &[value]);
+                vec![]
+            }
+            Replacable::Alternatives(alternatives) => {
+                choose_alternative(alternatives, rng).into_iter().collect()
+            }
+        })
+        .collect::<Vec<_>>();
+    grammar.result_to_stream(&next)
+}
+
+/// Processes a stream depth first. If a trace is provided, every replaced rule gets recorded into it, with the rules replaced
+/// within it's expansion as it's children.
+///
+/// The tokens waiting to be processed, the output and the traced rules of every open scope are kept in three shared arenas, with
+/// each scope's frame holding where it's part of them starts - so opening a scope doesn't allocate, and closing one leaves it's
+/// output in place for the scope around it.
+pub(crate) fn depth_first<
+    RuleKeyType: Clone + PartialEq + Debug,
+    ResultType: Clone + PartialEq + Debug,
+    StreamType: Clone + PartialEq + Debug,
+    G: Grammar<RuleKeyType, ResultType, StreamType> + ?Sized,
+    R: GrammarRandomNumberGenerator,
+>(
+    grammar: &G,
+    stream: &StreamType,
+    temporary_grammar: &mut G,
+    rng: &mut R,
+    trace: Option<&mut Vec<TraceNode<RuleKeyType>>>,
+) -> StreamType {
+    let max_depth = grammar.max_depth();
+    let max_expansions = grammar.max_expansions();
+    let max_queue_size = grammar.max_queue_size();
+    let tracing = trace.is_some();
+    let (skippable, mut tokens) = grammar.check_token_stream(stream);
+    if skippable {
+        return stream.clone();
+    }
+    // Tokens are popped off the end of each scope, so they are stored in reverse order - and since every token waiting to be
+    // processed is in the arena, it's length is the size of the queue
+    tokens.reverse();
+    let mut output: Vec<ResultType> = vec![];
+    let mut children: Vec<TraceNode<RuleKeyType>> = vec![];
+
+    let mut frames = vec![DepthFirstFrame {
+        scope: DepthFirstScope::Root,
+        tokens: 0,
+        output: 0,
+        children: 0,
+    }];
+    let mut expansions = 0usize;
+    loop {
+        let depth = frames.len();
+        let Some(frame) = frames.last() else {
+            return stream.clone();
+        };
+        let token = match tokens.len() > frame.tokens {
+            true => tokens.pop(),
+            false => None,
+        };
+        let Some(token) = token else {
+            let Some(frame) = frames.pop() else {
+                return stream.clone();
+            };
+            // This two way conversion allows a grammar to potentially collapse multiple values into a single one,
+            // if that is the desired result.
+            let result = grammar.result_to_stream(&output[frame.output..]);
+            output.truncate(frame.output);
+            match frame.scope {
+                DepthFirstScope::Root => {
+                    if let Some(trace) = trace {
+                        trace.append(&mut children);
+                    }
+                    return result;
+                }
+                DepthFirstScope::Expansion { modifiers, traced } => {
+                    let result = match modifiers {
+                        Some(modifiers) => grammar.apply_modifiers_in_context(
+                            &modifiers,
+                            result,
+                            temporary_grammar,
+                        ),
+                        None => result,
+                    };
+                    output.append(&mut grammar.stream_into_result(result));
+                    match traced {
+                        Some((rule, option)) => {
+                            let nested = children.split_off(frame.children);
+                            children.push(TraceNode {
+                                rule,
+                                option,
+                                children: nested,
+                            });
+                        }
+                        None => children.truncate(frame.children),
+                    }
+                }
+                DepthFirstScope::Assignment(key) => {
+                    grammar.end_assignment(temporary_grammar, &key);
+                    temporary_grammar
+                        .set_additional_rules(key, &grammar.stream_into_result(result));
+                }
+                DepthFirstScope::Actions(restore) => {
+                    output.append(&mut grammar.stream_into_result(result));
+                    restore_rules(temporary_grammar, restore);
+                }
+            }
+            continue;
+        };
+        // A new scope's tokens start at the end of the arena, once the token being processed was taken off it
+        let mut frame = DepthFirstFrame {
+            scope: DepthFirstScope::Root,
+            tokens: tokens.len(),
+            output: output.len(),
+            children: children.len(),
+        };
+
+        match token {
+            Replacable::Ready(value) => output.push(value),
+            Replacable::Replace(key) => {
+                if let Some((actions, rule)) = grammar.split_actions(&key) {
+                    // Actions are undone once their rule is expanded, so the previous values are kept aside
+                    let restore = actions
+                        .iter()
+                        .filter_map(|action| match action {
+                            Replacable::ImmediateMeta(target, _)
+                            | Replacable::DelayedMeta(target, _) => Some(target.clone()),
+                            _ => None,
+                        })
+                        .map(|target| {
+                            let previous = temporary_grammar.get_rule_options(&target).cloned();
+                            (target, previous)
+                        })
+                        .collect();
+                    tokens.extend(rule.map(Replacable::Replace));
+                    tokens.extend(actions.into_iter().rev());
+                    frame.scope = DepthFirstScope::Actions(restore);
+                    frames.push(frame);
+                    continue;
+                }
+                let (rule, modifiers) = match grammar.split_modifiers(&key) {
+                    Some((rule, modifiers)) => (Some(rule), Some(modifiers)),
+                    None => (None, None),
+                };
+                let selected = if depth > max_depth
+                    || expansions >= max_expansions
+                    || tokens.len() >= max_queue_size
+                {
+                    None
+                } else {
+                    expansions = expansions.saturating_add(1);
+                    let rule = rule.as_ref().unwrap_or(&key);
+                    grammar.select_for_processing(rule, rng, temporary_grammar)
+                };
+                match selected {
+                    Some((value, option)) => {
+                        grammar.record_expansion(temporary_grammar, depth);
+                        let stream = grammar.result_into_stream(value);
+                        let (_, expanded) = grammar.check_token_stream(&stream);
+                        tokens.extend(expanded.into_iter().rev());
+                        let traced = tracing.then_some((key, option));
+                        frame.scope = DepthFirstScope::Expansion { modifiers, traced };
+                        frames.push(frame);
+                    }
+                    None => {
+                        output.push(grammar.rule_to_default_result(&key));
+                        if tracing {
+                            children.push(TraceNode {
+                                rule: key,
+                                option: None,
+                                children: vec![],
+                            });
+                        }
+                    }
+                }
+            }
+            Replacable::ImmediateMeta(key, value) => {
+                grammar.begin_assignment(temporary_grammar, &key);
+                let stream = grammar.result_into_stream(value);
+                let (_, value_tokens) = grammar.check_token_stream(&stream);
+                tokens.extend(value_tokens.into_iter().rev());
+                frame.scope = DepthFirstScope::Assignment(key);
+                frames.push(frame);
+            }
+            Replacable::DelayedMeta(key, value) => {
+                temporary_grammar.set_additional_rules(key, &[value]);
+            }
+            Replacable::Alternatives(alternatives) => {
+                let Some(value) = choose_alternative(alternatives, rng) else {
+                    continue;
+                };
+                if depth > max_depth || tokens.len() >= max_queue_size {
+                    output.push(value);
+                    continue;
+                }
+                let stream = grammar.result_into_stream(value);
+                let (_, chosen) = grammar.check_token_stream(&stream);
+                tokens.extend(chosen.into_iter().rev());
+                frame.scope = DepthFirstScope::Expansion {
+                    modifiers: None,
+                    traced: None,
+                };
+                frames.push(frame);
+            }
+        }
+    }
+}
+
+/// Chooses one of an inline set of alternatives
+fn choose_alternative<ResultType, R: GrammarRandomNumberGenerator>(
+    mut alternatives: Vec<ResultType>,
+    rng: &mut R,
+) -> Option<ResultType> {
+    let len = alternatives.len();
+    if len == 0 {
+        return None;
+    }
+    Some(alternatives.swap_remove(rng.get_number(len).min(len - 1)))
+}
+
+/// Restores the rules replaced by actions, in reverse order so the earliest value wins if a rule was replaced more than once
+fn restore_rules<
+    RuleKeyType: Clone + PartialEq + Debug,
+    ResultType: Clone + PartialEq + Debug,
+    StreamType: Clone + PartialEq + Debug,
+    G: Grammar<RuleKeyType, ResultType, StreamType> + ?Sized,
+>(
+    grammar: &mut G,
+    restore: Vec<(RuleKeyType, Option<Vec<ResultType>>)>,
+) {
+    for (rule, previous) in restore.into_iter().rev() {
+        match previous {
+            Some(values) => grammar.set_additional_rules(rule, &values),
+            None => grammar.remove_additional_rules(&rule),
+        }
+    }
+}
+
+/// The kinds of scopes opened while processing a stream depth first
+enum DepthFirstScope<RuleKeyType, ResultType> {
+    /// The initial stream
+    Root,
+    /// The expansion of a rule, with any modifiers that should be applied to it, and the key and option recorded when tracing
+    Expansion {
+        modifiers: Option<RuleKeyType>,
+        traced: Option<(RuleKeyType, Option<usize>)>,
+    },
+    /// The value of an immediate meta rule, which gets assigned to the key once it's processed
+    Assignment(RuleKeyType),
+    /// A rule expanded with actions, holding the values the actions replaced so they can be restored once it's processed
+    Actions(Vec<(RuleKeyType, Option<Vec<ResultType>>)>),
+}
+
+/// A scope opened while processing a stream depth first. It's tokens, output and traced rules are the parts of the shared
+/// arenas from these positions onwards.
+struct DepthFirstFrame<RuleKeyType, ResultType> {
+    scope: DepthFirstScope<RuleKeyType, ResultType>,
+    tokens: usize,
+    output: usize,
+    children: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        generator::{BreadthFirstExpansion, Grammar, GrammarRandomNumberGenerator, SeededRng},
+        tracery::TraceryGrammar,
+    };
+
+    /// Builds a random grammar without variables. Rules only refer to rules defined after them, so it always finishes.
+    fn random_grammar(seed: u64) -> TraceryGrammar {
+        let mut rng = SeededRng::new(seed);
+        let words = ["owl", "fox", "sea", "old", "red"];
+        let rules = (0..6)
+            .map(|rule| {
+                let options = (0..1 + rng.get_number(3))
+                    .map(|_| {
+                        (0..1 + rng.get_number(4))
+                            .map(|_| match (rng.get_number(6), rule < 5) {
+                                (0 | 1, true) => {
+                                    format!("#r{}#", rule + 1 + rng.get_number(5 - rule))
+                                }
+                                (2, true) => format!("#r{}.capitalize#", rule + 1),
+                                (3, _) => format!("({}|{})", words[rule % 5], words[4 - rule % 5]),
+                                _ => words[rng.get_number(words.len())].to_string(),
+                            })
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    })
+                    .collect::<Vec<_>>();
+                (format!("r{rule}"), options)
+            })
+            .collect::<Vec<_>>();
+        let mut grammar = TraceryGrammar::empty();
+        for (rule, options) in rules {
+            grammar.set_additional_rules(rule, &options);
+        }
+        grammar
+    }
+
+    #[test]
+    pub fn breadth_and_depth_first_agree_without_variables() {
+        for seed in 0..200 {
+            let grammar = random_grammar(seed);
+            for choice in 0..3 {
+                // Choices only depend on the number of options, so both directions choose the same ones in any order
+                let mut rng = |len: usize| (choice + len / 2) % len;
+                let stream = "#r0#".to_string();
+                let breadth_first = grammar.breadth_first_processing(
+                    &stream,
+                    &mut TraceryGrammar::empty(),
+                    &mut rng,
+                );
+                let depth_first =
+                    grammar.depth_first_processing(&stream, &mut TraceryGrammar::empty(), &mut rng);
+                assert_eq!(
+                    breadth_first, depth_first,
+                    "grammar {seed}, choice {choice}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    pub fn breadth_first_processing_stops_at_a_fixed_point() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["[hero:#name#]#hero# met #hero# #missing#"]),
+                ("name", &["#first# #last#"]),
+                ("first", &["Ana"]),
+                ("last", &["Bell"]),
+                ("more", &["x#more#"]),
+            ],
+            None,
+        );
+        let process = |grammar: &TraceryGrammar, stream: &str| {
+            grammar.breadth_first_processing(
+                &stream.to_string(),
+                &mut TraceryGrammar::empty(),
+                &mut 0,
+            )
+        };
+        // Variables converge before they are used, and unknown rules are left as they are
+        assert_eq!(
+            process(&grammar, "#origin#"),
+            "Ana Bell met Ana Bell #missing#"
+        );
+        // Every iteration replaces the whole frontier once, until the limit is reached
+        assert_eq!(process(&grammar.with_max_depth(3), "#more#"), "xxx#more#");
+    }
+
+    #[test]
+    pub fn breadth_first_expansions_can_be_stepped_through() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["[hero:#name#]#hero# saw #place#"]),
+                ("name", &["#first# #last#"]),
+                ("first", &["Ana"]),
+                ("last", &["Bell"]),
+                ("place", &["the #adjective# sea"]),
+                ("adjective", &["grey"]),
+            ],
+            None,
+        );
+        let mut expansion = BreadthFirstExpansion::with_temporary_grammar(
+            &grammar,
+            "#origin#".to_string(),
+            TraceryGrammar::empty(),
+        );
+        let mut steps = vec![];
+        while let Some(stream) = expansion.step(&mut 0) {
+            steps.push(stream.clone());
+        }
+        assert_eq!(
+            steps,
+            vec![
+                "[hero:#name#]#hero# saw #place#",
+                // The variable's value is processed fully in the step assigning it, and used in the next
+                "#hero# saw the #adjective# sea",
+                "Ana Bell saw the grey sea",
+            ]
+        );
+        assert!(expansion.is_stable());
+        assert_eq!(expansion.step(&mut 0), None);
+        // Stepping through reaches the same stream as processing it all at once
+        assert_eq!(
+            expansion.stream(),
+            &grammar.breadth_first_processing(
+                &"#origin#".to_string(),
+                &mut TraceryGrammar::empty(),
+                &mut 0
+            )
+        );
+    }
+
+    #[test]
+    pub fn nested_breadth_first_expansions_stop_at_the_max_depth() {
+        // Modifiers apply to a rule's full expansion, so it's processed on it's own - which used to recurse without end
+        let process = |grammar: &TraceryGrammar| {
+            grammar.breadth_first_processing(
+                &"#a#".to_string(),
+                &mut TraceryGrammar::empty(),
+                &mut 0,
+            )
+        };
+        let grammar = TraceryGrammar::new(&[("a", &["x#a.capitalize#"])], Some("a"));
+        let result = process(&grammar);
+        assert!(result.starts_with("xX") && result.ends_with("X#a.capitalize#"));
+        assert_eq!(
+            process(&grammar.with_max_depth(4)),
+            "xXXXXXXX#a.capitalize#"
+        );
+
+        // So are rules following an action within their tag
+        let grammar = TraceryGrammar::new(&[("a", &["x#[h:1]a#"])], Some("a"));
+        let result = process(&grammar);
+        assert!(result.starts_with("xx") && result.ends_with("x#[h:1]a#"));
+        assert_eq!(process(&grammar.with_max_depth(4)), "xxxxxxxx#[h:1]a#");
+    }
+
+    #[test]
+    pub fn stepping_through_nested_expansions_stops_at_the_max_depth() {
+        let grammar =
+            TraceryGrammar::new(&[("a", &["x#a.capitalize#"])], Some("a")).with_max_depth(4);
+        let mut expansion = BreadthFirstExpansion::with_temporary_grammar(
+            &grammar,
+            "#a#".to_string(),
+            TraceryGrammar::empty(),
+        );
+        let mut steps = vec![];
+        while let Some(stream) = expansion.step(&mut 0) {
+            steps.push(stream.clone());
+        }
+        // The modified rule is expanded on it's own within the second step, as far as the max depth allows
+        assert_eq!(steps, vec!["x#a.capitalize#", "xXXXXXXX#a.capitalize#"]);
+        assert!(expansion.is_stable());
+        assert_eq!(
+            expansion.stream(),
+            &grammar.breadth_first_processing(
+                &"#a#".to_string(),
+                &mut TraceryGrammar::empty(),
+                &mut 0
+            )
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use std::fmt::Debug;
+
+use super::{
+    ExpansionTrace, Generator, Grammar, GrammarProcessingDirection, GrammarRandomNumberGenerator,
+    Replacable,
+};
+
+/// This is a single part of an option provided by a `SimpleGrammar` - either finished output, or a rule that still needs expanding
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimpleToken<Key, Terminal> {
+    /// Output that is in it's final form
+    Terminal(Terminal),
+    /// A rule that gets replaced by one of it's options
+    Rule(Key),
+}
+
+/// This is a sequence of tokens - used for the options of a `SimpleGrammar`, and for the streams it generates
+pub type SimplePhrase<Key, Terminal> = Vec<SimpleToken<Key, Terminal>>;
+
+/// This trait is the minimal surface needed to integrate a completely custom grammar backend - such as vocabulary from a database,
+/// or rules synced over the network. Backends only provide their rules, with each option already split into `SimpleToken`s,
+/// and every type implementing it implements `Grammar` - so it can be processed, or generated from using the `SimpleGenerator`.
+/// There is no parsing or meta rule machinery involved, so it doesn't support variables or modifiers.
+pub trait SimpleGrammar {
+    /// The type used for rule keys
+    type Key: Clone + PartialEq + Debug;
+    /// The type of the output generated by the grammar's options
+    type Terminal: Clone + PartialEq + Debug;
+
+    /// Gets all of the grammar's rule keys
+    fn rule_keys(&self) -> &Vec<Self::Key>;
+    /// Gets all the options of a rule, or `None` if the rule doesn't exist
+    fn rule_options(
+        &self,
+        rule: &Self::Key,
+    ) -> Option<&Vec<SimplePhrase<Self::Key, Self::Terminal>>>;
+    /// Gets the rule generation starts from by default
+    fn default_starting_point(&self) -> &Self::Key;
+
+    /// Sets the options of a rule, replacing any it had before. By default, nothing is set - which is enough for
+    /// grammars that are only generated from, since simple grammars never set rules while processing.
+    fn set_rule(&mut self, _rule: Self::Key, _options: &[SimplePhrase<Self::Key, Self::Terminal>]) {
+    }
+
+    /// Determines if the grammar should be processed breadth-first or depth-first - depth first, by default
+    fn processing_direction(&self) -> GrammarProcessingDirection {
+        GrammarProcessingDirection::DepthFirst
+    }
+
+    /// Provides the maximum depth of rules expanded within each other - or, processing breadth first, the maximum number of
+    /// passes. Once it is reached, any remaining rules are left unexpanded. By default, this is 50.
+    fn max_depth(&self) -> usize {
+        50
+    }
+}
+
+impl<G: SimpleGrammar>
+    Grammar<G::Key, SimplePhrase<G::Key, G::Terminal>, SimplePhrase<G::Key, G::Terminal>> for G
+{
+    fn rule_keys(&self) -> &Vec<G::Key> {
+        SimpleGrammar::rule_keys(self)
+    }
+
+    fn has_rule(&self, rule: &G::Key) -> bool {
+        self.rule_options(rule).is_some()
+    }
+
+    fn get_rule_options(&self, rule: &G::Key) -> Option<&Vec<SimplePhrase<G::Key, G::Terminal>>> {
+        self.rule_options(rule)
+    }
+
+    fn default_starting_point(&self) -> &G::Key {
+        SimpleGrammar::default_starting_point(self)
+    }
+
+    fn check_token_stream(
+        &self,
+        stream: &SimplePhrase<G::Key, G::Terminal>,
+    ) -> (
+        bool,
+        Vec<Replacable<G::Key, SimplePhrase<G::Key, G::Terminal>>>,
+    ) {
+        let skippable = stream
+            .iter()
+            .all(|token| matches!(token, SimpleToken::Terminal(_)));
+        let tokens = stream
+            .iter()
+            .map(|token| match token {
+                SimpleToken::Terminal(_) => Replacable::Ready(vec![token.clone()]),
+                SimpleToken::Rule(key) => Replacable::Replace(key.clone()),
+            })
+            .collect();
+        (skippable, tokens)
+    }
+
+    fn rule_to_default_result(&self, rule: &G::Key) -> SimplePhrase<G::Key, G::Terminal> {
+        vec![SimpleToken::Rule(rule.clone())]
+    }
+
+    fn result_to_stream(
+        &self,
+        result: &[SimplePhrase<G::Key, G::Terminal>],
+    ) -> SimplePhrase<G::Key, G::Terminal> {
+        result.concat()
+    }
+
+    fn stream_to_result(
+        &self,
+        stream: &SimplePhrase<G::Key, G::Terminal>,
+    ) -> Vec<SimplePhrase<G::Key, G::Terminal>> {
+        vec![stream.clone()]
+    }
+
+    fn result_into_stream(
+        &self,
+        result: SimplePhrase<G::Key, G::Terminal>,
+    ) -> SimplePhrase<G::Key, G::Terminal> {
+        result
+    }
+
+    fn stream_into_result(
+        &self,
+        stream: SimplePhrase<G::Key, G::Terminal>,
+    ) -> Vec<SimplePhrase<G::Key, G::Terminal>> {
+        vec![stream]
+    }
+
+    fn processing_direction(&self) -> GrammarProcessingDirection {
+        SimpleGrammar::processing_direction(self)
+    }
+
+    fn max_depth(&self) -> usize {
+        SimpleGrammar::max_depth(self)
+    }
+
+    fn set_additional_rules(&mut self, rule: G::Key, values: &[SimplePhrase<G::Key, G::Terminal>]) {
+        self.set_rule(rule, values);
+    }
+}
+
+/// This is a stateless generator for any `SimpleGrammar`. It uses the grammar's `Default` value as the temporary grammar
+/// while processing, so backends should make their default value empty.
+pub struct SimpleGenerator;
+
+impl<G: SimpleGrammar + Default>
+    Generator<G::Key, SimplePhrase<G::Key, G::Terminal>, SimplePhrase<G::Key, G::Terminal>, G>
+    for SimpleGenerator
+{
+    fn generate<R: GrammarRandomNumberGenerator>(
+        grammar: &G,
+        rng: &mut R,
+    ) -> Option<SimplePhrase<G::Key, G::Terminal>> {
+        Self::generate_at(SimpleGrammar::default_starting_point(grammar), grammar, rng)
+    }
+
+    fn generate_at<R: GrammarRandomNumberGenerator>(
+        key: &G::Key,
+        grammar: &G,
+        rng: &mut R,
+    ) -> Option<SimplePhrase<G::Key, G::Terminal>> {
+        let initial = grammar.select_from_rule(key, rng)?.clone();
+        Some(Self::expand_from(&initial, grammar, rng))
+    }
+
+    fn generate_traced<R: GrammarRandomNumberGenerator>(
+        grammar: &G,
+        rng: &mut R,
+    ) -> Option<(SimplePhrase<G::Key, G::Terminal>, ExpansionTrace<G::Key>)> {
+        let key = SimpleGrammar::default_starting_point(grammar);
+        let index = grammar.select_index_from_rule(key, rng)?;
+        let initial = grammar.rule_options(key)?.get(index)?.clone();
+        let mut trace = ExpansionTrace::default();
+        let result = grammar.process_stream_traced(&initial, rng, &mut G::default(), &mut trace);
+        Some((result, trace.into_rooted_at(key.clone(), index)))
+    }
+
+    fn expand_from<R: GrammarRandomNumberGenerator>(
+        initial: &SimplePhrase<G::Key, G::Terminal>,
+        grammar: &G,
+        rng: &mut R,
+    ) -> SimplePhrase<G::Key, G::Terminal> {
+        grammar.process_stream(initial, rng, &mut G::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A backend holding word lists, like one loaded from a database
+    #[derive(Default)]
+    struct WordLists {
+        keys: Vec<&'static str>,
+        lists: Vec<Vec<SimplePhrase<&'static str, &'static str>>>,
+    }
+
+    impl SimpleGrammar for WordLists {
+        type Key = &'static str;
+        type Terminal = &'static str;
+
+        fn rule_keys(&self) -> &Vec<Self::Key> {
+            &self.keys
+        }
+
+        fn rule_options(
+            &self,
+            rule: &Self::Key,
+        ) -> Option<&Vec<SimplePhrase<Self::Key, Self::Terminal>>> {
+            let index = self.keys.iter().position(|key| key == rule)?;
+            self.lists.get(index)
+        }
+
+        fn default_starting_point(&self) -> &Self::Key {
+            &"origin"
+        }
+    }
+
+    #[test]
+    pub fn simple_grammars_can_be_generated_from() {
+        use SimpleToken::*;
+        let grammar = WordLists {
+            keys: vec!["origin", "animal"],
+            lists: vec![
+                vec![vec![
+                    Terminal("the "),
+                    Rule("animal"),
+                    Terminal(" "),
+                    Rule("sound"),
+                ]],
+                vec![vec![Terminal("cat")], vec![Terminal("dog")]],
+            ],
+        };
+        assert_eq!(
+            SimpleGenerator::generate(&grammar, &mut 1).unwrap(),
+            vec![
+                Terminal("the "),
+                Terminal("dog"),
+                Terminal(" "),
+                Rule("sound")
+            ]
+        );
+        let (_, trace) = SimpleGenerator::generate_traced(&grammar, &mut 0).unwrap();
+        assert_eq!(
+            trace.to_string(),
+            "origin -> 0\n  animal -> 0\n  sound -> unresolved\n"
+        );
+        assert!(SimpleGenerator::generate_at(&"missing", &grammar, &mut 0).is_none());
+    }
+}
@@ -0,0 +1,203 @@
+use std::{fmt::Debug, hash::Hash};
+
+#[cfg(feature = "bevy")]
+use bevy::utils::HashMap;
+#[cfg(not(feature = "bevy"))]
+use std::collections::HashMap;
+
+use super::{GrammarProcessingDirection, SimpleGrammar, SimplePhrase};
+
+/// This is a ready-made `SimpleGrammar`, holding its rules in a `HashMap` - so token rewriting grammars, like L-systems, don't
+/// need a backend of their own. Options are either provided as phrases, or parsed from text using a tokenizer closure that
+/// splits an option into `SimpleToken`s. Like every `SimpleGrammar`, it implements `Grammar` and is generated from using the
+/// `SimpleGenerator`. It is processed depth first by default.
+#[derive(Debug, Clone)]
+pub struct TokenGrammar<Key, Terminal> {
+    keys: Vec<Key>,
+    rules: HashMap<Key, Vec<SimplePhrase<Key, Terminal>>>,
+    starting_point: Key,
+    direction: GrammarProcessingDirection,
+    max_depth: usize,
+}
+
+impl<Key: Default, Terminal> Default for TokenGrammar<Key, Terminal> {
+    fn default() -> Self {
+        Self::new(Key::default())
+    }
+}
+
+impl<Key, Terminal> TokenGrammar<Key, Terminal> {
+    /// Creates a grammar without any rules, starting from the provided rule
+    pub fn new(starting_point: Key) -> Self {
+        Self {
+            keys: vec![],
+            rules: Default::default(),
+            starting_point,
+            direction: GrammarProcessingDirection::DepthFirst,
+            max_depth: 50,
+        }
+    }
+
+    /// Sets the rule generation starts from by default, returning the updated grammar
+    pub fn with_starting_point(mut self, starting_point: Key) -> Self {
+        self.starting_point = starting_point;
+        self
+    }
+
+    /// Sets whether the grammar is processed breadth first or depth first, returning the updated grammar
+    pub fn with_processing_direction(mut self, direction: GrammarProcessingDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Sets the maximum depth of rules expanded within each other - or, processing breadth first, the maximum number of passes.
+    /// Returns the updated grammar.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+}
+
+impl<Key: Clone + Eq + Hash, Terminal> TokenGrammar<Key, Terminal> {
+    /// Creates a grammar from rules written as text, splitting each option into tokens using the tokenizer. Generation starts
+    /// from the first rule.
+    ///
+    /// ```rust
+    /// use bevy_generative_grammars::generator::{BreadthFirstExpansion, SimpleToken, TokenGrammar};
+    ///
+    /// // An L-system, where `F` and `X` are rewritten and every other character is kept as it is
+    /// let grammar: TokenGrammar<char, char> =
+    ///     TokenGrammar::parse(&[('X', &["F+[[X]-X]-F[-FX]+X"]), ('F', &["FF"])], |option| {
+    ///         option
+    ///             .chars()
+    ///             .map(|c| match c {
+    ///                 'F' | 'X' => SimpleToken::Rule(c),
+    ///                 _ => SimpleToken::Terminal(c),
+    ///             })
+    ///             .collect()
+    ///     });
+    /// let mut expansion = BreadthFirstExpansion::new(&grammar, vec![SimpleToken::Rule('F')]);
+    /// assert_eq!(
+    ///     expansion.step(&mut 0),
+    ///     Some(&vec![SimpleToken::Rule('F'), SimpleToken::Rule('F')])
+    /// );
+    /// ```
+    pub fn parse<T: AsRef<str>>(
+        rules: &[(Key, &[T])],
+        tokenizer: impl Fn(&str) -> SimplePhrase<Key, Terminal>,
+    ) -> Self
+    where
+        Key: Default,
+    {
+        let mut grammar = Self::default();
+        for (key, options) in rules {
+            let options = options
+                .iter()
+                .map(|option| tokenizer(option.as_ref()))
+                .collect::<Vec<_>>();
+            grammar.set_rule_options(key.clone(), options);
+        }
+        if let Some(first) = grammar.keys.first() {
+            grammar.starting_point = first.clone();
+        }
+        grammar
+    }
+
+    /// Adds a rule, replacing any options it had before, and returns the updated grammar
+    pub fn with_rule(
+        mut self,
+        key: Key,
+        options: impl IntoIterator<Item = SimplePhrase<Key, Terminal>>,
+    ) -> Self {
+        self.set_rule_options(key, options.into_iter().collect());
+        self
+    }
+
+    /// Sets the options of a rule, replacing any it had before
+    pub fn set_rule_options(&mut self, key: Key, options: Vec<SimplePhrase<Key, Terminal>>) {
+        if self.rules.insert(key.clone(), options).is_none() {
+            self.keys.push(key);
+        }
+    }
+}
+
+impl<Key, Terminal> SimpleGrammar for TokenGrammar<Key, Terminal>
+where
+    Key: Clone + PartialEq + Debug + Eq + Hash,
+    Terminal: Clone + PartialEq + Debug,
+{
+    type Key = Key;
+    type Terminal = Terminal;
+
+    fn rule_keys(&self) -> &Vec<Key> {
+        &self.keys
+    }
+
+    fn rule_options(&self, rule: &Key) -> Option<&Vec<SimplePhrase<Key, Terminal>>> {
+        self.rules.get(rule)
+    }
+
+    fn default_starting_point(&self) -> &Key {
+        &self.starting_point
+    }
+
+    fn set_rule(&mut self, rule: Key, options: &[SimplePhrase<Key, Terminal>]) {
+        self.set_rule_options(rule, options.to_vec());
+    }
+
+    fn processing_direction(&self) -> GrammarProcessingDirection {
+        self.direction
+    }
+
+    fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{BreadthFirstExpansion, Generator, SimpleGenerator, SimpleToken};
+
+    fn render(phrase: &SimplePhrase<char, char>) -> String {
+        phrase
+            .iter()
+            .map(|token| match token {
+                SimpleToken::Rule(c) | SimpleToken::Terminal(c) => *c,
+            })
+            .collect()
+    }
+
+    #[test]
+    pub fn token_grammars_rewrite_parsed_rules() {
+        // Lindenmayer's algae, where every character is rewritten on each pass
+        let algae = TokenGrammar::parse(&[('A', &["AB"]), ('B', &["A"])], |option| {
+            option.chars().map(SimpleToken::Rule).collect()
+        })
+        .with_processing_direction(GrammarProcessingDirection::BreadthFirst)
+        .with_max_depth(4);
+        let mut expansion = BreadthFirstExpansion::new(&algae, vec![SimpleToken::Rule('A')]);
+        let mut generations = vec![];
+        while let Some(phrase) = expansion.step(&mut 0) {
+            generations.push(render(phrase));
+        }
+        assert_eq!(generations, vec!["AB", "ABA", "ABAAB", "ABAABABA"]);
+
+        let greeting = TokenGrammar::new("origin")
+            .with_rule(
+                "origin",
+                [vec![
+                    SimpleToken::Terminal("hello "),
+                    SimpleToken::Rule("name"),
+                ]],
+            )
+            .with_rule("name", [vec![SimpleToken::Terminal("Ana")]]);
+        assert_eq!(
+            SimpleGenerator::generate(&greeting, &mut 0).unwrap(),
+            vec![
+                SimpleToken::Terminal("hello "),
+                SimpleToken::Terminal("Ana")
+            ]
+        );
+    }
+}
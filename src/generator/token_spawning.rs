@@ -0,0 +1,201 @@
+use std::{fmt::Debug, marker::PhantomData};
+
+use bevy::prelude::*;
+
+use super::{Generated, SimplePhrase, SimpleToken};
+
+/// This trait turns the tokens generated by a grammar into entities - so a `SimpleGrammar` whose terminals are tokens like
+/// `Spawn("goblin", 3, 4)` can populate a level, rather than generating text. Closures taking a token and `Commands` implement it.
+pub trait TokenInterpreter<Token>: Send + Sync + 'static {
+    /// Spawns the entity a single token describes, returning it - or `None` if the token doesn't spawn anything
+    fn interpret(&self, token: &Token, commands: &mut Commands) -> Option<Entity>;
+}
+
+impl<Token, F: Fn(&Token, &mut Commands) -> Option<Entity> + Send + Sync + 'static>
+    TokenInterpreter<Token> for F
+{
+    fn interpret(&self, token: &Token, commands: &mut Commands) -> Option<Entity> {
+        self(token, commands)
+    }
+}
+
+/// Spawns the entities described by a phrase generated from a `SimpleGrammar`, returning them in the order they were generated.
+/// Rules that were left unexpanded don't spawn anything.
+pub fn spawn_phrase<Key, Token>(
+    phrase: &SimplePhrase<Key, Token>,
+    interpreter: &impl TokenInterpreter<Token>,
+    commands: &mut Commands,
+) -> Vec<Entity> {
+    phrase
+        .iter()
+        .filter_map(|token| match token {
+            SimpleToken::Terminal(token) => interpreter.interpret(token, commands),
+            SimpleToken::Rule(_) => None,
+        })
+        .collect()
+}
+
+/// This component lists the entities spawned from the phrase generated for an entity, in the order they were generated
+#[derive(Component, Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpawnedFromGrammar(pub Vec<Entity>);
+
+/// This plugin spawns entities from the phrases generated in the background for the `GenerationQueuePlugin` - whenever an entity
+/// gets a `Generated` phrase of the plugin's token type, each of it's tokens is passed to the interpreter, and the entity gets a
+/// `SpawnedFromGrammar` listing what was spawned. Send a `GenerateRequest` using the `SimpleGenerator` to populate a level chunk.
+pub struct TokenSpawningPlugin<Key, Token, Interpreter> {
+    interpreter: Interpreter,
+    tokens: PhantomData<fn() -> (Key, Token)>,
+}
+
+impl<Key, Token, Interpreter: TokenInterpreter<Token> + Clone>
+    TokenSpawningPlugin<Key, Token, Interpreter>
+{
+    /// Creates the plugin, spawning tokens using the provided interpreter
+    pub fn new(interpreter: Interpreter) -> Self {
+        Self {
+            interpreter,
+            tokens: PhantomData,
+        }
+    }
+}
+
+impl<Key, Token, Interpreter> Plugin for TokenSpawningPlugin<Key, Token, Interpreter>
+where
+    Key: Clone + PartialEq + Debug + Send + Sync + 'static,
+    Token: Clone + PartialEq + Debug + Send + Sync + 'static,
+    Interpreter: TokenInterpreter<Token> + Clone,
+{
+    fn build(&self, app: &mut App) {
+        let interpreter = self.interpreter.clone();
+        let spawn_generated =
+            move |mut commands: Commands, generated: GeneratedPhrases<Key, Token>| {
+                for (entity, Generated(phrase)) in generated.iter() {
+                    let spawned = spawn_phrase(phrase, &interpreter, &mut commands);
+                    commands.entity(entity).insert(SpawnedFromGrammar(spawned));
+                }
+            };
+        app.add_systems(PostUpdate, spawn_generated);
+    }
+}
+
+type GeneratedPhrases<'w, 's, Key, Token> = Query<
+    'w,
+    's,
+    (Entity, &'static Generated<SimplePhrase<Key, Token>>),
+    Changed<Generated<SimplePhrase<Key, Token>>>,
+>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{
+        GenerateRequest, GenerationQueuePlugin, SimpleGenerator, SimpleGrammar,
+    };
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum LevelToken {
+        Spawn(&'static str, i32, i32),
+        Note(&'static str),
+    }
+
+    #[derive(Component, Debug, PartialEq)]
+    struct Monster {
+        kind: &'static str,
+        position: (i32, i32),
+    }
+
+    #[derive(Default)]
+    struct Room {
+        keys: Vec<&'static str>,
+        options: Vec<Vec<SimplePhrase<&'static str, LevelToken>>>,
+    }
+
+    impl SimpleGrammar for Room {
+        type Key = &'static str;
+        type Terminal = LevelToken;
+
+        fn rule_keys(&self) -> &Vec<Self::Key> {
+            &self.keys
+        }
+
+        fn rule_options(
+            &self,
+            rule: &Self::Key,
+        ) -> Option<&Vec<SimplePhrase<Self::Key, Self::Terminal>>> {
+            let index = self.keys.iter().position(|key| key == rule)?;
+            self.options.get(index)
+        }
+
+        fn default_starting_point(&self) -> &Self::Key {
+            &self.keys[0]
+        }
+    }
+
+    fn spawn_monster(token: &LevelToken, commands: &mut Commands) -> Option<Entity> {
+        match token {
+            LevelToken::Spawn(kind, x, y) => Some(
+                commands
+                    .spawn(Monster {
+                        kind,
+                        position: (*x, *y),
+                    })
+                    .id(),
+            ),
+            LevelToken::Note(_) => None,
+        }
+    }
+
+    #[test]
+    pub fn generated_tokens_are_spawned_as_entities() {
+        let room = Room {
+            keys: vec!["room", "guard"],
+            options: vec![
+                vec![vec![
+                    SimpleToken::Rule("guard"),
+                    SimpleToken::Terminal(LevelToken::Note("treasure")),
+                    SimpleToken::Terminal(LevelToken::Spawn("slime", 5, 1)),
+                ]],
+                vec![vec![SimpleToken::Terminal(LevelToken::Spawn(
+                    "goblin", 2, 3,
+                ))]],
+            ],
+        };
+        let mut app = App::new();
+        app.add_plugins((
+            GenerationQueuePlugin::<SimplePhrase<&'static str, LevelToken>>::default(),
+            TokenSpawningPlugin::<&'static str, LevelToken, _>::new(spawn_monster),
+        ));
+        let chunk = app.world.spawn_empty().id();
+        app.world.send_event(GenerateRequest::from_generator(
+            chunk,
+            SimpleGenerator,
+            room,
+            0,
+        ));
+        app.update();
+
+        let spawned = app
+            .world
+            .get::<SpawnedFromGrammar>(chunk)
+            .unwrap()
+            .0
+            .clone();
+        let monsters = spawned
+            .iter()
+            .map(|entity| app.world.get::<Monster>(*entity).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            monsters,
+            vec![
+                &Monster {
+                    kind: "goblin",
+                    position: (2, 3)
+                },
+                &Monster {
+                    kind: "slime",
+                    position: (5, 1)
+                }
+            ]
+        );
+    }
+}
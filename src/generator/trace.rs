@@ -0,0 +1,73 @@
+use std::fmt::{Debug, Display};
+
+/// This records how a stream was expanded - a tree of the rules that were replaced, the option chosen for each of them,
+/// and the rules replaced within that option. It is useful for working out why a grammar produced a particular output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpansionTrace<RuleKeyType> {
+    /// The rules replaced directly in the processed stream
+    pub roots: Vec<TraceNode<RuleKeyType>>,
+}
+
+impl<RuleKeyType> Default for ExpansionTrace<RuleKeyType> {
+    fn default() -> Self {
+        Self { roots: vec![] }
+    }
+}
+
+/// This is a single rule replaced during an expansion
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceNode<RuleKeyType> {
+    /// The key that was replaced, including any modifiers
+    pub rule: RuleKeyType,
+    /// The index of the chosen option. This is `None` if the value didn't come from one of the rule's options - for example,
+    /// if the rule couldn't be found.
+    pub option: Option<usize>,
+    /// The rules replaced within the chosen option
+    pub children: Vec<TraceNode<RuleKeyType>>,
+}
+
+impl<RuleKeyType> ExpansionTrace<RuleKeyType> {
+    /// Checks whether any rules were recorded
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// Wraps the recorded rules in a single root - used by generators to record the starting rule they selected an option from
+    pub fn into_rooted_at(self, rule: RuleKeyType, option: usize) -> Self {
+        Self {
+            roots: vec![TraceNode {
+                rule,
+                option: Some(option),
+                children: self.roots,
+            }],
+        }
+    }
+
+    /// Iterates over every recorded node in the order they were expanded, along with how deeply they were nested
+    pub fn nodes(&self) -> impl Iterator<Item = (usize, &TraceNode<RuleKeyType>)> {
+        let mut stack = self
+            .roots
+            .iter()
+            .rev()
+            .map(|node| (0, node))
+            .collect::<Vec<_>>();
+        std::iter::from_fn(move || {
+            let (depth, node) = stack.pop()?;
+            stack.extend(node.children.iter().rev().map(|child| (depth + 1, child)));
+            Some((depth, node))
+        })
+    }
+}
+
+impl<RuleKeyType: Display> Display for ExpansionTrace<RuleKeyType> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (depth, node) in self.nodes() {
+            let indent = depth * 2;
+            match node.option {
+                Some(option) => writeln!(f, "{:indent$}{} -> {option}", "", node.rule)?,
+                None => writeln!(f, "{:indent$}{} -> unresolved", "", node.rule)?,
+            }
+        }
+        Ok(())
+    }
+}
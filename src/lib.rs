@@ -1,9 +1,18 @@
-#![forbid(missing_docs)]
-#![forbid(unsafe_code)]
-#![warn(clippy::doc_markdown)]
-#![doc = include_str!("../README.md")]
-
-/// Generator Traits
-pub mod generator;
-/// Tracery Generator
-pub mod tracery;
+#![forbid(missing_docs)]
+#![forbid(unsafe_code)]
+#![warn(clippy::doc_markdown)]
+#![doc = include_str!("../README.md")]
+
+/// Dungeon Generator - generates graphs of rooms rather than text, and requires the `dungeon` feature
+#[cfg(feature = "dungeon")]
+pub mod dungeon;
+/// Experimental Generators - these require the `experimental` feature, and can change in any release
+#[cfg(feature = "experimental")]
+pub mod experimental;
+/// Generator Traits
+pub mod generator;
+/// Test Utilities - for writing regression tests against grammars
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+/// Tracery Generator
+pub mod tracery;
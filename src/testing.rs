@@ -0,0 +1,147 @@
+use crate::{
+    generator::{Grammar, GrammarRandomNumberGenerator, SeededRng},
+    tracery::{StatefulStringGenerator, StringGenerator, TraceryGrammar},
+};
+
+/// This is a random number generator that returns a fixed sequence of numbers, looping back to the start once it runs out.
+/// Each number is wrapped to fit the number of options available, so `SequenceRng::new([0, 1])` alternates between the first
+/// and second option of every rule. An empty sequence always chooses the first option.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SequenceRng {
+    numbers: Vec<usize>,
+    position: usize,
+}
+
+impl SequenceRng {
+    /// Creates a generator returning the provided numbers, in order
+    pub fn new(numbers: impl Into<Vec<usize>>) -> Self {
+        Self {
+            numbers: numbers.into(),
+            position: 0,
+        }
+    }
+}
+
+impl GrammarRandomNumberGenerator for SequenceRng {
+    fn get_number(&mut self, len: usize) -> usize {
+        if len == 0 || self.numbers.is_empty() {
+            return 0;
+        }
+        let number = self.numbers[self.position % self.numbers.len()];
+        self.position = (self.position + 1) % self.numbers.len();
+        number % len
+    }
+}
+
+/// Asserts that the grammar generates the expected text from it's starting point, using a `SeededRng` created from the seed
+#[track_caller]
+pub fn assert_generates(grammar: &TraceryGrammar, seed: u64, expected: &str) {
+    assert_generates_at(grammar, grammar.default_starting_point(), seed, expected);
+}
+
+/// Asserts that the grammar generates the expected text from the provided rule, using a `SeededRng` created from the seed
+#[track_caller]
+pub fn assert_generates_at(grammar: &TraceryGrammar, rule: &str, seed: u64, expected: &str) {
+    match StringGenerator::try_generate_at(rule, grammar, &mut SeededRng::new(seed)) {
+        Ok(result) => assert_eq!(
+            result, expected,
+            "{rule} generated unexpected text with seed {seed}"
+        ),
+        Err(issue) => panic!("{rule} couldn't generate with seed {seed}: {issue}"),
+    }
+}
+
+/// Asserts that a stateful generator produces the expected texts from it's starting point, in order - sharing a single
+/// `SeededRng` created from the seed between the generations
+#[track_caller]
+pub fn assert_generates_sequence(
+    generator: &mut StatefulStringGenerator,
+    seed: u64,
+    expected: &[&str],
+) {
+    let mut rng = SeededRng::new(seed);
+    for (index, expected) in expected.iter().enumerate() {
+        match generator.try_generate(&mut rng) {
+            Ok(result) => assert_eq!(
+                &result, expected,
+                "generation {index} was unexpected with seed {seed}"
+            ),
+            Err(issue) => panic!("generation {index} failed with seed {seed}: {issue}"),
+        }
+    }
+}
+
+/// Generates a snapshot of the grammar's output - one line for each of `count` generations from the seed, using
+/// `StringGenerator::generate_batch`. Snapshots can be stored alongside a grammar asset, and compared in regression tests.
+pub fn snapshot(grammar: &TraceryGrammar, seed: u64, count: usize) -> String {
+    StringGenerator::generate_batch(grammar, count, seed).join("\n")
+}
+
+/// Asserts that the grammar's snapshot matches a stored one, ignoring any trailing newline
+#[track_caller]
+pub fn assert_snapshot(grammar: &TraceryGrammar, seed: u64, count: usize, expected: &str) {
+    assert_eq!(
+        snapshot(grammar, seed, count),
+        expected.trim_end_matches('\n'),
+        "the snapshot with seed {seed} changed"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::StatefulGenerator;
+
+    fn grammar() -> TraceryGrammar {
+        TraceryGrammar::new(
+            &[
+                ("origin", &["#hero# met #hero#"]),
+                ("hero", &["Ana", "Bo", "Cy"]),
+            ],
+            None,
+        )
+    }
+
+    #[test]
+    pub fn sequence_rng_loops_through_its_numbers() {
+        let mut rng = SequenceRng::new([0, 2, 4]);
+        let numbers = (0..4).map(|_| rng.get_number(3)).collect::<Vec<_>>();
+        assert_eq!(numbers, vec![0, 2, 1, 0]);
+        assert_eq!(SequenceRng::new([]).get_number(3), 0);
+    }
+
+    #[test]
+    pub fn grammars_can_be_checked_against_snapshots() {
+        let grammar = grammar();
+        let mut rng = SequenceRng::new([0, 2]);
+        assert_eq!(
+            StringGenerator::try_generate(&grammar, &mut rng).unwrap(),
+            "Cy met Ana"
+        );
+        let first = StringGenerator::try_generate(&grammar, &mut SeededRng::new(5)).unwrap();
+        assert_generates(&grammar, 5, &first);
+        let hero =
+            StringGenerator::try_generate_at("hero", &grammar, &mut SeededRng::new(5)).unwrap();
+        assert_generates_at(&grammar, "hero", 5, &hero);
+        assert_snapshot(&grammar, 3, 4, &format!("{}\n", snapshot(&grammar, 3, 4)));
+        assert_eq!(snapshot(&grammar, 3, 4).lines().count(), 4);
+
+        let mut generator = StatefulStringGenerator::from_grammar(grammar.clone());
+        let mut rng = SeededRng::new(9);
+        let expected = (0..3)
+            .map(|_| generator.generate(&mut rng).unwrap())
+            .collect::<Vec<_>>();
+        let expected = expected.iter().map(String::as_str).collect::<Vec<_>>();
+        assert_generates_sequence(
+            &mut StatefulStringGenerator::from_grammar(grammar),
+            9,
+            &expected,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "generated unexpected text with seed 1")]
+    pub fn unexpected_output_fails_the_assertion() {
+        assert_generates(&grammar(), 1, "nobody met nobody");
+    }
+}
@@ -1,17 +1,196 @@
+/// This module provides output profiles transforming generated text, such as screen reader friendly variants
+pub mod accessibility;
+/// This module provides the agreement metadata used to choose pronouns and conjugate verbs, such as `#hero.they#`
+pub mod agreement;
+/// This module analyses grammars as a whole, such as finding options that generate the same output
+pub mod analysis;
+/// This module parses the annotations that can prefix a rule's options, such as guards
+pub mod annotations;
+/// This module provides the assertions checking that variables are set before they are used, such as `[assert:hero]`
+pub mod assertions;
+#[cfg(feature = "bevy")]
+/// This module provides a blackboard of variables shared by every stateful generator in the world
+pub mod blackboard;
+/// This module provides the budgets limiting how much work a single generation can do
+pub mod budget;
+/// This module provides a builder for assembling tracery grammars one rule at a time
+pub mod builder;
+/// This module provides the captures recording which option a rule chose, such as `#noun._index#`
+pub mod captures;
+/// This module provides interactive generation, pausing at choice points so the caller can choose how it continues
+pub mod choices;
+/// This module provides completions for keys typed in grammar editors
+pub mod completion;
+/// This module provides the constraints between the options chosen by different rules
+pub mod constraints;
+/// This module filters generated text, re-rolling outputs containing banned words or failing custom checks
+pub mod content_filter;
+#[cfg(feature = "serde")]
+/// This module provides `GrammarDefinition`, the full featured format for authoring grammars - usually written in RON
+pub mod definition;
+/// This module records statistics about generations, and reports them as bevy diagnostics
+pub mod diagnostics;
+/// This module compiles grammars into a static tree of every expansion they can generate, for auditing or exporting to dialogue tools
+pub mod dialogue_tree;
+/// This module compares two versions of a grammar, listing the rules and options that changed
+pub mod diff;
+#[cfg(any(
+    feature = "json_source",
+    feature = "ron_source",
+    feature = "toml_source"
+))]
+/// This module parses grammars from JSON, RON or TOML text, without needing bevy
+pub mod formats;
+#[doc(hidden)]
+pub mod grammar_macro;
+/// This module provides the hooks called as a stateful generator expands rules
+pub mod hooks;
+/// This module maps the inline icons placed in generated text to the glyphs or images shown for them
+pub mod icons;
+/// This module layers grammars over a base grammar, such as themes overriding some of it's vocabulary
+pub mod inheritance;
+/// This module provides the issues that can be encountered while generating
+pub mod issues;
+/// This module provides the ways the pieces of each expansion are joined together, such as smart joining
+pub mod joining;
+/// This module provides the directives structuring generated text into lines and paragraphs, such as `[br]` and `[p]`
+pub mod layout;
+/// This module provides grammars holding rules for several locales, which fall back on each other for missing translations
+pub mod localization;
+/// This module provides the modifiers that can be applied to a rule's expansion, such as `#animal.capitalize#`
+pub mod modifiers;
+/// This module provides the operations for changing a grammar's rules after it was created, such as renaming a rule
+pub mod mutation;
+/// This module builds grammars for procedural names from syllables and patterns
+pub mod names;
+/// This module sources rule options from asynchronous option sources registered by the app, such as `@source(saved_heroes)`
+pub mod option_sources;
+#[cfg(all(feature = "packs", feature = "bevy"))]
+/// This module switches grammar packs on and off while the game is running, such as seasonal or DLC content
+pub mod pack_toggles;
+#[cfg(feature = "packs")]
+/// This module loads grammar packs from their manifests, and merges them in dependency order
+pub mod packs;
+/// This module provides the arguments passed into rules, such as `#describe(hero)#`
+pub mod parameters;
+#[cfg(feature = "persistence")]
+/// This module saves and loads the full state of stateful generators, so it can be kept between sessions
+pub mod persistence;
+/// This module provides the post-processors cleaning up generated text, like collapsing spaces and fixing articles
+pub mod post_processing;
+/// This module provides previews of expansions for editors, with rules that couldn't be expanded listed as placeholders
+pub mod preview;
+/// This module imports classic tabletop random tables, like d100 tables, as weighted rules
+pub mod random_tables;
+/// This module recognizes whether text could have been generated by a grammar, finding the options that generate it
+pub mod recognition;
+/// This module re-rolls single variables of a stateful generator's last generation, re-rendering the text that referenced them
+pub mod rerolls;
+/// This module provides snapshots of stateful generators, for rolling them back in deterministic lockstep games
+pub mod rollback;
+mod runtime;
+/// This module provides the limits applied to grammars from untrusted sources, like mods
+pub mod sandbox;
+/// This module provides the selection modes used to choose between a rule's options
+pub mod selection;
+/// This module provides the policies for variables that share a key with one of the grammar's base rules
+pub mod shadowing;
+#[cfg(feature = "bevy")]
+/// This module provides the global story generator resource, and the `StoryGen` system param for one-off generations
+pub mod story;
+/// This module keeps generation in sync between peers, by replicating requests instead of the text they generate
+pub mod sync;
 #[cfg(feature = "asset")]
 /// This module provides an asset loader for tracery grammars, allowing them to be used as assets as well
 pub mod tracery_asset;
+#[cfg(feature = "asset")]
+/// This module provides commands for spawning generators from grammar assets in a single call
+pub mod tracery_commands;
+#[cfg(feature = "tracery_compat")]
+/// This module loads grammars written in the canonical Tracery JSON format
+pub mod tracery_compat;
+#[cfg(feature = "egui-debug")]
+/// This module provides an egui window for inspecting grammars and previewing their output while the game is running
+pub mod tracery_debug;
+#[cfg(feature = "bevy")]
+/// This module provides tokens that follow the bevy clock, such as `#time_of_day#`
+pub mod tracery_time;
+/// This module provides the casing and punctuation conventions of the locales grammars are written in
+pub mod typography;
+/// This module checks grammars for likely problems, such as references to missing rules or typos
+pub mod validation;
+/// This module provides the store for variables remembered by stateful generators
+pub mod variables;
+/// This module sources rule options from sidecar word lists, such as `@file(creatures.txt)`
+pub mod word_lists;
 
 use crate::generator::*;
+pub use accessibility::OutputProfile;
+use agreement::{Agreement, AGREE_MODIFIER, PRONOUN_MODIFIERS};
+pub use analysis::{ChoicePath, DuplicateOutput, DuplicateSearch, GrammarStatistics, OutputCount};
+use annotations::{
+    guards_pass, option_cooldown, option_name, option_text, GuardContext, GENERATION_COUNT_KEY,
+};
+use assertions::{asserted_variable, assertion_key, failed_assertions};
+pub use assertions::{AssertionFailure, AssertionPolicy};
 #[cfg(feature = "bevy")]
 use bevy::{
     prelude::{Component, Resource},
-    utils::HashMap,
+    utils::{HashMap, HashSet},
 };
+pub use budget::{BudgetLimit, GenerationBudget};
+pub use builder::{RuleBuilder, TraceryGrammarBuilder};
+use captures::{
+    capture_key, is_capture_key, split_key_and_modifiers, EMITTED_CAPTURE, INDEX_CAPTURE,
+    NAME_CAPTURE,
+};
+pub use choices::{ChoiceOption, ChoicePoint, ChoiceToken, InteractiveStep};
+pub use completion::{CompletionCandidate, CompletionKind};
+use constraints::refers_to;
+pub use constraints::Exclusion;
+pub use content_filter::{ContentFilter, FilterPolicy, OutputCheck};
+#[cfg(feature = "serde")]
+pub use definition::{GrammarDefinition, OptionDefinition, RuleDefinition};
+pub use diagnostics::GenerationStats;
+#[cfg(feature = "bevy")]
+pub use diagnostics::GrammarDiagnosticsPlugin;
+use diagnostics::UnreportedStats;
+pub use dialogue_tree::{DialogueTree, TreeNode, TreeOption};
+pub use diff::{GrammarDiff, RuleDiff};
+use hooks::ExpansionHooks;
+pub use hooks::RuleExpanded;
+pub use icons::{Icon, IconMap, IconSpan};
+pub use issues::{GenerationIssue, GrammarError};
+pub use joining::JoinMode;
+pub use localization::LocalizedTraceryGrammar;
+use modifiers::{built_in_modifier, parse_modifiers, Modifier};
+pub use names::NameGrammar;
+pub use option_sources::OptionSources;
+#[cfg(all(feature = "packs", feature = "bevy"))]
+pub use pack_toggles::{GrammarPacks, GrammarPacksPlugin, PackToggled, UsesGrammarPacks};
+#[cfg(feature = "packs")]
+pub use packs::{GrammarPack, GrammarPackLoader, PackError, PackGrammar, PackManifest};
+use parameters::RuleCall;
+pub use parameters::POSITIONAL_PREFIX;
+pub use post_processing::{PostProcessing, PostProcessor};
+pub use preview::{ExpansionPreview, Placeholder};
+pub use random_tables::{RandomTable, RandomTableError, TableEntry};
+use rerolls::RerollRecord;
+pub use rollback::GeneratorSnapshot;
+use runtime::{OptionFilter, RuntimeState};
+pub use sandbox::{SandboxPolicy, SandboxViolation};
+pub use selection::{Cooldown, RuleSettings, SelectionMode, SelectionState};
 #[cfg(feature = "serde")]
 use serde::Serialize;
+pub use shadowing::ShadowingPolicy;
 #[cfg(not(feature = "bevy"))]
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "bevy")]
+pub use story::{GlobalStoryGenerator, StoryGen};
+pub use sync::{GenerationRequest, GenerationTransport, SyncError, SyncedGenerator};
+pub use typography::Typography;
+pub use validation::{GrammarValidator, LintFinding, LintKind};
+pub use variables::{VariableLifetime, VariableSnapshot, VariableStore, GLOBAL_PREFIX};
 
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "bevy", derive(Component, Resource))]
@@ -28,17 +207,288 @@ pub struct TraceryGrammar {
     #[cfg_attr(feature = "serde", serde(skip_serializing))]
     keys: Vec<String>,
     starting_point: String,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    starting_points: Vec<(String, u32)>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "HashMap::is_empty"))]
+    settings: HashMap<String, RuleSettings>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "HashMap::is_empty"))]
+    aliases: HashMap<String, String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "ShadowingPolicy::is_default")
+    )]
+    shadowing: ShadowingPolicy,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "AssertionPolicy::is_default")
+    )]
+    assertions: AssertionPolicy,
+    #[cfg_attr(feature = "serde", serde(skip_serializing))]
+    runtime: RuntimeState,
+    #[cfg_attr(feature = "serde", serde(skip_serializing))]
+    modifiers: HashMap<String, Modifier>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    exclusions: Vec<Exclusion>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    sandbox: Option<SandboxPolicy>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    max_depth: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "String::is_empty"))]
+    separator: String,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "JoinMode::is_default"))]
+    join: JoinMode,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    locale: Option<String>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    budget: Option<GenerationBudget>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    extends: Option<String>,
 }
 
 #[cfg(feature = "serde")]
 mod deserialize {
     use super::*;
+    use serde::de::{
+        self,
+        value::{MapAccessDeserializer, MapDeserializer, SeqDeserializer},
+        IntoDeserializer, Visitor,
+    };
     use serde::Deserialize;
+    use std::marker::PhantomData;
 
-    #[derive(Deserialize)]
+    /// This is a value read from a grammar before it's format is known - so it can be read as a setting once the grammar
+    /// turns out to hold it's rules under a `rules` key, or as a rule otherwise
+    enum Buffered {
+        Bool(bool),
+        I64(i64),
+        U64(u64),
+        F64(f64),
+        String(String),
+        Unit,
+        Some(Box<Buffered>),
+        Seq(Vec<Buffered>),
+        Map(Vec<(Buffered, Buffered)>),
+    }
+
+    struct BufferedVisitor;
+
+    impl<'de> Visitor<'de> for BufferedVisitor {
+        type Value = Buffered;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("any value")
+        }
+
+        fn visit_bool<E>(self, value: bool) -> Result<Buffered, E> {
+            Ok(Buffered::Bool(value))
+        }
+
+        fn visit_i64<E>(self, value: i64) -> Result<Buffered, E> {
+            Ok(Buffered::I64(value))
+        }
+
+        fn visit_u64<E>(self, value: u64) -> Result<Buffered, E> {
+            Ok(Buffered::U64(value))
+        }
+
+        fn visit_f64<E>(self, value: f64) -> Result<Buffered, E> {
+            Ok(Buffered::F64(value))
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Buffered, E> {
+            Ok(Buffered::String(value.to_string()))
+        }
+
+        fn visit_string<E>(self, value: String) -> Result<Buffered, E> {
+            Ok(Buffered::String(value))
+        }
+
+        fn visit_unit<E>(self) -> Result<Buffered, E> {
+            Ok(Buffered::Unit)
+        }
+
+        fn visit_none<E>(self) -> Result<Buffered, E> {
+            Ok(Buffered::Unit)
+        }
+
+        fn visit_some<D: de::Deserializer<'de>>(
+            self,
+            deserializer: D,
+        ) -> Result<Buffered, D::Error> {
+            Buffered::deserialize(deserializer).map(|value| Buffered::Some(Box::new(value)))
+        }
+
+        fn visit_newtype_struct<D: de::Deserializer<'de>>(
+            self,
+            deserializer: D,
+        ) -> Result<Buffered, D::Error> {
+            Buffered::deserialize(deserializer)
+        }
+
+        fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Buffered, A::Error> {
+            let mut values = vec![];
+            while let Some(value) = seq.next_element()? {
+                values.push(value);
+            }
+            Ok(Buffered::Seq(values))
+        }
+
+        fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Buffered, A::Error> {
+            let mut entries = vec![];
+            while let Some(entry) = map.next_entry()? {
+                entries.push(entry);
+            }
+            Ok(Buffered::Map(entries))
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Buffered {
+        fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_any(BufferedVisitor)
+        }
+    }
+
+    struct BufferedDeserializer<E> {
+        value: Buffered,
+        error: PhantomData<E>,
+    }
+
+    impl<'de, E: de::Error> IntoDeserializer<'de, E> for Buffered {
+        type Deserializer = BufferedDeserializer<E>;
+
+        fn into_deserializer(self) -> Self::Deserializer {
+            BufferedDeserializer {
+                value: self,
+                error: PhantomData,
+            }
+        }
+    }
+
+    impl<'de, E: de::Error> de::Deserializer<'de> for BufferedDeserializer<E> {
+        type Error = E;
+
+        fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, E> {
+            match self.value {
+                Buffered::Bool(value) => visitor.visit_bool(value),
+                Buffered::I64(value) => visitor.visit_i64(value),
+                Buffered::U64(value) => visitor.visit_u64(value),
+                Buffered::F64(value) => visitor.visit_f64(value),
+                Buffered::String(value) => visitor.visit_string(value),
+                Buffered::Unit => visitor.visit_unit(),
+                Buffered::Some(value) => visitor.visit_some(value.into_deserializer()),
+                Buffered::Seq(values) => {
+                    visitor.visit_seq(SeqDeserializer::new(values.into_iter()))
+                }
+                Buffered::Map(entries) => {
+                    visitor.visit_map(MapDeserializer::new(entries.into_iter()))
+                }
+            }
+        }
+
+        fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, E> {
+            match self.value {
+                Buffered::Unit => visitor.visit_none(),
+                Buffered::Some(value) => visitor.visit_some(value.into_deserializer()),
+                _ => visitor.visit_some(self),
+            }
+        }
+
+        fn deserialize_enum<V: Visitor<'de>>(
+            self,
+            name: &'static str,
+            variants: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, E> {
+            match self.value {
+                Buffered::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+                // Variants holding data are written as a map, from the variant to it's data
+                Buffered::Map(entries) if entries.len() == 1 => visitor.visit_enum(
+                    MapAccessDeserializer::new(MapDeserializer::<_, E>::new(entries.into_iter())),
+                ),
+                _ => Err(E::custom(format!(
+                    "expected one of the variants of `{name}`, written as text: {variants:?}"
+                ))),
+            }
+        }
+
+        fn deserialize_newtype_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            visitor: V,
+        ) -> Result<V::Value, E> {
+            visitor.visit_newtype_struct(self)
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf unit unit_struct seq
+            tuple tuple_struct map struct identifier ignored_any
+        }
+    }
+
+    #[derive(Deserialize, Default)]
     struct TraceryGrammarContent {
-        rules: HashMap<String, Vec<String>>,
+        rules: HashMap<String, RuleContent>,
         starting_point: Option<String>,
+        #[serde(default)]
+        starting_points: Vec<(String, u32)>,
+        #[serde(default)]
+        settings: HashMap<String, RuleSettings>,
+        #[serde(default)]
+        aliases: HashMap<String, String>,
+        #[serde(default)]
+        shadowing: ShadowingPolicy,
+        #[serde(default)]
+        assertions: AssertionPolicy,
+        #[serde(default)]
+        exclusions: Vec<Exclusion>,
+        #[serde(default)]
+        sandbox: Option<SandboxPolicy>,
+        #[serde(default)]
+        max_depth: Option<usize>,
+        #[serde(default)]
+        separator: String,
+        #[serde(default)]
+        join: JoinMode,
+        #[serde(default)]
+        locale: Option<String>,
+        #[serde(default)]
+        budget: Option<GenerationBudget>,
+        #[serde(default)]
+        extends: Option<String>,
+    }
+
+    /// Rules can be written as a single option, or a list of options
+    #[derive(Deserialize)]
+    #[serde(untagged, expecting = "an option, or a list of options")]
+    enum RuleContent {
+        Single(OptionContent),
+        List(Vec<OptionContent>),
+    }
+
+    impl RuleContent {
+        fn into_options(self) -> Vec<String> {
+            match self {
+                RuleContent::Single(option) => vec![option.into()],
+                RuleContent::List(options) => options.into_iter().map(String::from).collect(),
+            }
+        }
+    }
+
+    /// Options can be written as plain text, or as an object holding the text along with it's tags
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OptionContent {
+        Text(String),
+        Tagged { text: String, tags: Vec<String> },
+    }
+
+    impl From<OptionContent> for String {
+        fn from(option: OptionContent) -> Self {
+            match option {
+                OptionContent::Text(text) => text,
+                OptionContent::Tagged { text, tags } => annotations::tagged_option(text, &tags),
+            }
+        }
     }
 
     impl<'de> Deserialize<'de> for TraceryGrammar {
@@ -46,18 +496,92 @@ mod deserialize {
         where
             D: serde::Deserializer<'de>,
         {
-            match TraceryGrammarContent::deserialize(deserializer) {
+            // Grammars holding their rules under a `rules` key are read as an object of rules and settings, and any other
+            // grammar as the plain object of rules used by the original Tracery
+            let Buffered::Map(entries) = Buffered::deserialize(deserializer)? else {
+                return Err(de::Error::invalid_type(
+                    de::Unexpected::Other("a value that isn't an object"),
+                    &"a grammar object",
+                ));
+            };
+            let content = if entries
+                .iter()
+                .any(|(key, _)| matches!(key, Buffered::String(key) if key == "rules"))
+            {
+                TraceryGrammarContent::deserialize(MapDeserializer::new(entries.into_iter()))
+            } else {
+                entries
+                    .into_iter()
+                    .map(|(key, value)| match key {
+                        Buffered::String(key) => Ok((key, value)),
+                        _ => Err(de::Error::custom("rule keys should be text")),
+                    })
+                    .collect::<Result<Vec<_>, D::Error>>()?
+                    .into_iter()
+                    // Some Tracery tools keep the modifiers they use alongside the rules, as a nested object
+                    .filter(|(key, value)| key != "modifiers" || !matches!(value, Buffered::Map(_)))
+                    .map(|(key, value)| {
+                        RuleContent::deserialize(value.into_deserializer())
+                            .map(|rule| (key.clone(), rule))
+                            .map_err(|error: D::Error| {
+                                de::Error::custom(format!("invalid rule `{key}`: {error}"))
+                            })
+                    })
+                    .collect::<Result<_, _>>()
+                    .map(|rules| TraceryGrammarContent {
+                        rules,
+                        ..Default::default()
+                    })
+            };
+            match content {
                 Ok(TraceryGrammarContent {
                     rules,
                     starting_point,
+                    starting_points,
+                    settings,
+                    aliases,
+                    shadowing,
+                    assertions,
+                    exclusions,
+                    sandbox,
+                    max_depth,
+                    separator,
+                    join,
+                    locale,
+                    budget,
+                    extends,
                 }) => {
+                    let rules = rules
+                        .into_iter()
+                        .map(|(key, rule)| (key, rule.into_options()))
+                        .collect::<HashMap<_, Vec<_>>>();
                     let keys = rules.keys().cloned().collect();
                     let starting_point = starting_point.unwrap_or("origin".to_string());
-                    Ok(TraceryGrammar {
+                    let grammar = TraceryGrammar {
                         rules,
                         keys,
                         starting_point,
-                    })
+                        starting_points,
+                        settings,
+                        aliases,
+                        shadowing,
+                        assertions,
+                        runtime: Default::default(),
+                        modifiers: Default::default(),
+                        exclusions,
+                        sandbox: None,
+                        max_depth,
+                        separator,
+                        join,
+                        locale,
+                        budget,
+                        extends,
+                    };
+                    // A sandboxed grammar is checked against it's policy again, since it's definition could have been edited
+                    match sandbox {
+                        Some(policy) => grammar.sandboxed(policy).map_err(de::Error::custom),
+                        None => Ok(grammar),
+                    }
                 }
                 Err(err) => Err(err),
             }
@@ -73,6 +597,21 @@ impl TraceryGrammar {
             rules: Default::default(),
             keys: vec![],
             starting_point: "origin".to_string(),
+            starting_points: vec![],
+            settings: Default::default(),
+            aliases: Default::default(),
+            shadowing: Default::default(),
+            assertions: Default::default(),
+            runtime: Default::default(),
+            modifiers: Default::default(),
+            exclusions: Default::default(),
+            sandbox: None,
+            max_depth: None,
+            separator: String::new(),
+            join: JoinMode::Separator,
+            locale: None,
+            budget: None,
+            extends: None,
         }
     }
     /// This provides a new tracery grammar.
@@ -95,338 +634,2625 @@ impl TraceryGrammar {
             } else {
                 "origin".into()
             },
+            starting_points: vec![],
+            settings: Default::default(),
+            aliases: Default::default(),
+            shadowing: Default::default(),
+            assertions: Default::default(),
+            runtime: Default::default(),
+            modifiers: Default::default(),
+            exclusions: Default::default(),
+            sandbox: None,
+            max_depth: None,
+            separator: String::new(),
+            join: JoinMode::Separator,
+            locale: None,
+            budget: None,
+            extends: None,
         }
     }
-}
 
-impl Grammar<String, String, String> for TraceryGrammar {
-    fn rule_keys(&self) -> &Vec<String> {
-        &self.keys
+    /// This sets the selection mode used by a rule, returning the updated grammar.
+    pub fn with_selection_mode(mut self, rule: impl Into<String>, mode: SelectionMode) -> Self {
+        self.set_selection_mode(rule, mode);
+        self
     }
 
-    fn has_rule(&self, rule: &String) -> bool {
-        self.rules.contains_key(rule)
+    /// This sets the selection mode used by a rule.
+    pub fn set_selection_mode(&mut self, rule: impl Into<String>, mode: SelectionMode) {
+        self.settings.entry(rule.into()).or_default().selection = mode;
     }
 
-    fn default_starting_point(&self) -> &String {
-        &self.starting_point
+    /// This gets the selection mode used by a rule - rules without settings use `SelectionMode::Random`.
+    pub fn selection_mode(&self, rule: &str) -> SelectionMode {
+        self.settings
+            .get(rule)
+            .map(|settings| settings.selection)
+            .unwrap_or_default()
     }
 
-    fn get_rule_options(&self, rule: &String) -> Option<&Vec<String>> {
-        self.rules.get(rule)
+    /// This adds a rule to a link group, returning the updated grammar. See `set_link`.
+    pub fn with_link(mut self, rule: impl Into<String>, link: impl Into<String>) -> Self {
+        self.set_link(rule, link);
+        self
     }
 
-    fn check_token_stream(&self, stream: &String) -> (bool, Vec<Replacable<String, String>>) {
-        let mut has_replacements = false;
-        let mut has_meta = false;
-        let mut inside = false;
-        let result = stream
-            .split('[')
-            .flat_map(|v| {
-                if inside {
-                    has_meta = true;
-                    let mut result = vec![];
-                    let mut split = v.split(']');
-                    if let Some(inner) = split.next() {
-                        let mut split = inner.split_inclusive(&[':', '|']);
-                        if let (Some(key), Some(value)) = (split.next(), split.next()) {
-                            if key.ends_with(':') {
-                                result.push(MetaRuleProcessingResult::ImmediateMeta(
-                                    &key[0..key.len() - 1],
-                                    value,
-                                ));
-                            } else {
-                                result.push(MetaRuleProcessingResult::DelayedMeta(
-                                    &key[0..key.len() - 1],
-                                    value,
-                                ));
-                            }
-                        } else {
-                            result.push(MetaRuleProcessingResult::Raw(inner));
-                        }
-                    } else {
-                        result.push(MetaRuleProcessingResult::Raw(v));
-                    }
-                    for v in split {
-                        result.push(MetaRuleProcessingResult::Raw(v))
-                    }
-                    result
-                } else {
-                    inside = true;
-                    vec![MetaRuleProcessingResult::Raw(v)]
-                }
-            })
-            .flat_map(|v| match v {
-                MetaRuleProcessingResult::Raw(v) => {
-                    let mut ready = true;
-                    v.split('#')
-                        .filter_map(|v| {
-                            if ready {
-                                ready = false;
-                                if v.is_empty() {
-                                    return None;
-                                }
-                                Some(Replacable::Ready(v.to_string()))
-                            } else {
-                                ready = true;
-                                has_replacements = true;
-                                Some(Replacable::Replace(v.to_string()))
-                            }
-                        })
-                        .collect::<Vec<_>>()
-                }
-                MetaRuleProcessingResult::ImmediateMeta(key, val) => {
-                    vec![Replacable::ImmediateMeta(key.to_string(), val.to_string())]
-                }
-                MetaRuleProcessingResult::DelayedMeta(key, val) => {
-                    vec![Replacable::DelayedMeta(key.to_string(), val.to_string())]
-                }
-            })
-            .collect::<Vec<_>>();
+    /// This adds a rule to a link group. Within a single generation, every rule in the group chooses the same option index -
+    /// so rules like `country` and `capital` can be written as parallel lists that always match.
+    pub fn set_link(&mut self, rule: impl Into<String>, link: impl Into<String>) {
+        self.settings.entry(rule.into()).or_default().link = Some(link.into());
+    }
 
-        (!has_replacements && !has_meta, result)
+    /// This makes a rule avoid choosing the same option twice within a single generation, returning the updated grammar.
+    pub fn with_distinct(mut self, rule: impl Into<String>) -> Self {
+        self.set_distinct(rule, true);
+        self
     }
 
-    fn rule_to_default_result(&self, rule: &String) -> String {
-        format!("#{rule}#")
+    /// This sets whether a rule avoids choosing the same option twice within a single generation.
+    pub fn set_distinct(&mut self, rule: impl Into<String>, distinct: bool) {
+        self.settings.entry(rule.into()).or_default().distinct = distinct;
     }
 
-    fn processing_direction(&self) -> GrammarProcessingDirection {
-        GrammarProcessingDirection::DepthFirst
+    /// This sets the relative weights of a rule's options, returning the updated grammar. See `set_weights`.
+    pub fn with_weights(mut self, rule: impl Into<String>, weights: impl Into<Vec<u32>>) -> Self {
+        self.set_weights(rule, weights);
+        self
     }
 
-    fn result_to_stream(&self, result: &[String]) -> String {
-        result.join("")
+    /// This sets the relative weights of a rule's options, used when it's selection mode is `Random`.
+    /// Options without a weight have a weight of 1, and options with a weight of 0 are never chosen.
+    pub fn set_weights(&mut self, rule: impl Into<String>, weights: impl Into<Vec<u32>>) {
+        self.settings.entry(rule.into()).or_default().weights = weights.into();
     }
 
-    fn set_additional_rules(&mut self, rule: String, values: &[String]) {
-        self.keys.push(rule.clone());
-        self.rules.insert(rule, values.into());
+    /// This makes a rule emit data, returning the updated grammar. See `set_emitted`.
+    pub fn with_emitted(mut self, rule: impl Into<String>) -> Self {
+        self.set_emitted(rule, true);
+        self
     }
 
-    fn stream_to_result(&self, stream: &String) -> Vec<String> {
-        vec![stream.clone()]
+    /// This sets whether a rule emits data. Whenever the rule is expanded from one of it's options, the full expansion
+    /// is collected alongside the generated text - keyed by the rule, with the last expansion winning if it is expanded more than once.
+    pub fn set_emitted(&mut self, rule: impl Into<String>, emit: bool) {
+        self.settings.entry(rule.into()).or_default().emit = emit;
     }
-}
 
-/// This is a stateless string generator based on the tracery grammar. Note that, since it's stateless, it does not support variables.
-pub struct StringGenerator;
+    /// This checks whether a rule emits data
+    pub fn emits(&self, rule: &str) -> bool {
+        self.settings
+            .get(self.resolve_alias(rule))
+            .is_some_and(|settings| settings.emit)
+    }
 
-impl Generator<String, String, String, TraceryGrammar> for StringGenerator {
-    fn generate<R: GrammarRandomNumberGenerator>(
-        grammar: &TraceryGrammar,
-        rng: &mut R,
-    ) -> Option<String> {
-        Self::generate_at(&grammar.default_starting_point().clone(), grammar, rng)
+    /// This adds an exclusion between two options, returning the updated grammar. See `add_exclusion`.
+    pub fn with_exclusion(mut self, exclusion: Exclusion) -> Self {
+        self.add_exclusion(exclusion);
+        self
     }
 
-    fn generate_at<R: GrammarRandomNumberGenerator>(
-        key: &String,
-        grammar: &TraceryGrammar,
-        rng: &mut R,
-    ) -> Option<String> {
-        let initial = grammar.select_from_rule(key, rng);
-        initial.map(|initial| Self::expand_from(initial, grammar, rng))
+    /// This adds an exclusion, stopping two options of different rules from being chosen within the same generation.
+    /// When one of them was chosen, the other is treated as if it's guards failed.
+    pub fn add_exclusion(&mut self, exclusion: Exclusion) {
+        self.exclusions.push(exclusion);
     }
 
-    fn expand_from<R: GrammarRandomNumberGenerator>(
-        initial: &String,
-        grammar: &TraceryGrammar,
-        rng: &mut R,
-    ) -> String {
-        let mut tmp = TraceryGrammar::empty();
-        grammar.process_stream(initial, rng, &mut tmp)
+    /// This gets the exclusions between the grammar's options
+    pub fn exclusions(&self) -> &[Exclusion] {
+        &self.exclusions
     }
-}
 
-/// This is a stateful string generator based on the tracery grammar. Note that since it is stateful, it does support variables.
-#[derive(Debug, Clone)]
-#[cfg_attr(feature = "bevy", derive(Component, Resource))]
-pub struct StatefulStringGenerator(TraceryGrammar);
+    /// This gets the settings for a rule, if any were provided.
+    pub fn rule_settings(&self, rule: &str) -> Option<&RuleSettings> {
+        self.settings.get(rule)
+    }
 
-impl StatefulStringGenerator {
-    /// This provides a new stateful generating using tracery grammar.
-    /// You provide a set of rules as `(Key, &[Values])` and optionally a starting point.
-    /// If no starting point is provided, we fall back on "origin"
-    pub fn new<T: Clone + Into<String>>(rules: &[(T, &[T])], starting_point: Option<T>) -> Self {
-        let grammar = TraceryGrammar::new(rules, starting_point);
-        Self(grammar)
+    /// This declares an alias for a rule, returning the updated grammar. See `add_alias`.
+    pub fn with_alias(mut self, alias: impl Into<String>, rule: impl Into<String>) -> Self {
+        self.add_alias(alias, rule);
+        self
     }
 
-    /// This creates a new stateful string generator by cloning an existing tracery grammar.
-    pub fn clone_grammar(grammar: &TraceryGrammar) -> Self {
-        Self(grammar.clone())
+    /// This declares an alias for a rule - so with an alias from `colour` to `color`, `#colour#` expands the `color` rule.
+    /// Aliases can refer to other aliases, and rules take precedence over aliases with the same key.
+    pub fn add_alias(&mut self, alias: impl Into<String>, rule: impl Into<String>) {
+        self.aliases.insert(alias.into(), rule.into());
     }
 
-    /// This creates a stateful generator wrapping an existing grammar.
-    pub fn from_grammar(grammar: TraceryGrammar) -> Self {
-        Self(grammar)
+    /// This gets the key of the rule an alias refers to, following chains of aliases.
+    /// Keys that aren't aliases are returned as they are.
+    pub fn resolve_alias<'a>(&'a self, key: &'a str) -> &'a str {
+        let mut key = key;
+        // Limiting the steps to the number of aliases stops cycles from looping forever
+        for _ in 0..self.aliases.len() {
+            if self.rules.contains_key(key) {
+                break;
+            }
+            match self.aliases.get(key) {
+                Some(rule) => key = rule,
+                None => break,
+            }
+        }
+        key
     }
-}
 
-impl StatefulGenerator<String, String, String, TraceryGrammar> for StatefulStringGenerator {
-    fn generate<R: GrammarRandomNumberGenerator>(&mut self, rng: &mut R) -> Option<String> {
-        let key = self.get_grammar().default_starting_point().clone();
-        self.generate_at(&key, rng)
+    /// This sets the policy for variables assigned over the grammar's base rules, returning the updated grammar.
+    pub fn with_shadowing_policy(mut self, policy: ShadowingPolicy) -> Self {
+        self.set_shadowing_policy(policy);
+        self
     }
 
-    fn generate_at<R: GrammarRandomNumberGenerator>(
-        &mut self,
-        key: &String,
-        rng: &mut R,
-    ) -> Option<String> {
-        let initial = self.get_grammar().select_from_rule(key, rng);
-        initial
-            .cloned()
-            .map(|initial| self.expand_from(&initial, rng))
+    /// This sets the policy for variables assigned over the grammar's base rules.
+    pub fn set_shadowing_policy(&mut self, policy: ShadowingPolicy) {
+        self.shadowing = policy;
     }
 
-    fn expand_from<R: GrammarRandomNumberGenerator>(
-        &mut self,
-        initial: &String,
-        rng: &mut R,
-    ) -> String {
-        let mut tmp = TraceryGrammar::empty();
-        let result = self.get_grammar().process_stream(initial, rng, &mut tmp);
-        self.get_grammar_mut().copy_and_replace_rules(&tmp);
-        result
+    /// This gets the policy for variables assigned over the grammar's base rules.
+    pub fn shadowing_policy(&self) -> ShadowingPolicy {
+        self.shadowing
     }
 
-    fn set_grammar(&mut self, grammar: &TraceryGrammar) {
-        self.0 = grammar.clone()
+    /// This sets the policy for assertions like `[assert:hero]`, returning the updated grammar.
+    pub fn with_assertion_policy(mut self, policy: AssertionPolicy) -> Self {
+        self.set_assertion_policy(policy);
+        self
     }
 
-    fn get_grammar(&self) -> &TraceryGrammar {
-        &self.0
+    /// This sets the policy for assertions like `[assert:hero]`, which check that a variable is set by the time they are reached.
+    /// Assertions are skipped unless the policy is `Warn` or `Error` - `if cfg!(debug_assertions)` is a good place to enable them.
+    pub fn set_assertion_policy(&mut self, policy: AssertionPolicy) {
+        self.assertions = policy;
     }
 
-    fn get_grammar_mut(&mut self) -> &mut TraceryGrammar {
-        &mut self.0
+    /// This gets the policy for assertions like `[assert:hero]`.
+    pub fn assertion_policy(&self) -> AssertionPolicy {
+        self.assertions
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// This registers a custom modifier, returning the updated grammar.
+    pub fn with_modifier(mut self, name: impl Into<String>, modifier: Modifier) -> Self {
+        self.add_modifier(name, modifier);
+        self
+    }
 
-    #[test]
-    pub fn can_choose_a_single_element_from_a_list() {
-        let rule = TraceryGrammar::new(&[("default", &["One", "Two"])], Some("default"));
+    /// This registers a custom modifier, usable as `#rule.name#`. Custom modifiers take precedence over the built in ones.
+    pub fn add_modifier(&mut self, name: impl Into<String>, modifier: Modifier) {
+        self.modifiers.insert(name.into(), modifier);
+    }
 
-        assert_eq!(StringGenerator::generate(&rule, &mut 0).unwrap(), "One");
-        assert_eq!(StringGenerator::generate(&rule, &mut 1).unwrap(), "Two");
-        assert_eq!(StringGenerator::generate(&rule, &mut 2).unwrap(), "Two");
+    /// This sets alternative starting points along with their relative weights, returning the updated grammar. See `set_starting_points`.
+    pub fn with_starting_points<K: Into<String>>(
+        mut self,
+        starting_points: impl IntoIterator<Item = (K, u32)>,
+    ) -> Self {
+        self.set_starting_points(starting_points);
+        self
     }
 
-    #[test]
-    pub fn element_replacer_can_replace_elements_with_other_valid_elements() {
-        let rule = TraceryGrammar::new(
-            &[("default", &["One", "#Two#"]), ("Two", &["Three", "Four"])],
-            Some("default"),
-        );
-        let selection = StringGenerator::generate(&rule, &mut 1);
-        assert_eq!(selection.unwrap(), "Four");
+    /// This sets alternative starting points along with their relative weights - so `origin` with a weight of 4 and `rare_origin`
+    /// with a weight of 1 start from `rare_origin` one time in five. They are used by `generate_weighted_start`, and the default
+    /// starting point is used instead while none are set.
+    pub fn set_starting_points<K: Into<String>>(
+        &mut self,
+        starting_points: impl IntoIterator<Item = (K, u32)>,
+    ) {
+        self.starting_points = starting_points
+            .into_iter()
+            .map(|(key, weight)| (key.into(), weight))
+            .collect();
     }
 
-    #[test]
-    pub fn element_replacer_can_replace_elements_with_other_valid_elements_at_depth() {
-        let rule = TraceryGrammar::new(
-            &[
-                ("default", &["One", "#Two#"]),
-                ("Two", &["Three", "#Four# is up"]),
-                ("Four", &["What"]),
-            ],
-            Some("default"),
-        );
-        let selection = StringGenerator::generate(&rule, &mut 1);
-        assert_eq!(selection.unwrap(), "What is up");
+    /// This gets the alternative starting points, along with their relative weights
+    pub fn starting_points(&self) -> &[(String, u32)] {
+        &self.starting_points
     }
 
-    #[test]
-    pub fn stateful_can_choose_a_single_element_from_a_list() {
-        let rule = TraceryGrammar::new(&[("default", &["One", "Two"])], Some("default"));
-        let mut generator = StatefulStringGenerator(rule);
+    /// This sets the separator placed between the pieces of each expansion, returning the updated grammar. See `set_separator`.
+    pub fn with_separator(mut self, separator: impl Into<String>) -> Self {
+        self.set_separator(separator);
+        self
+    }
 
-        assert_eq!(generator.generate(&mut 0).unwrap(), "One");
-        assert_eq!(generator.generate(&mut 1).unwrap(), "Two");
-        assert_eq!(generator.generate(&mut 2).unwrap(), "Two");
+    /// This sets the separator placed between the pieces of each expansion - the text written directly in an option, and the
+    /// expansions of the rules it references. It's empty by default, so `#a##b#` becomes `ab` - while a separator of `"\n"` puts each
+    /// expansion on it's own line, for list-like outputs such as logs.
+    pub fn set_separator(&mut self, separator: impl Into<String>) {
+        self.separator = separator.into();
     }
 
-    #[test]
-    pub fn stateful_element_replacer_can_replace_elements_with_other_valid_elements() {
-        let rule = TraceryGrammar::new(
-            &[("default", &["One", "#Two#"]), ("Two", &["Three", "Four"])],
-            Some("default"),
-        );
-        let selection = StatefulStringGenerator(rule).generate(&mut 1);
-        assert_eq!(selection.unwrap(), "Four");
+    /// This gets the separator placed between the pieces of each expansion
+    pub fn separator(&self) -> &str {
+        &self.separator
     }
 
-    #[test]
-    pub fn stateful_element_replacer_can_replace_elements_with_other_valid_elements_at_depth() {
-        let rule = TraceryGrammar::new(
-            &[
-                ("default", &["One", "#Two#"]),
-                ("Two", &["Three", "#Four#"]),
-                ("Four", &["What"]),
-            ],
-            Some("default"),
-        );
-        let selection = StatefulStringGenerator(rule).generate(&mut 1);
-        assert_eq!(selection.unwrap(), "What");
+    /// This sets how the pieces of each expansion are joined together, returning the updated grammar. See `set_join_mode`.
+    pub fn with_join_mode(mut self, join: JoinMode) -> Self {
+        self.set_join_mode(join);
+        self
     }
 
-    #[test]
-    pub fn stateful_generator_can_set_value_and_use_it_later() {
-        let rule = TraceryGrammar::new(
-            &[
-                ("default", &["One", "[val:#Two#]Hi #val#"]),
-                ("Two", &["Three", "#Four#"]),
-                ("Four", &["What is going on?"]),
-                ("Five", &["Hey there"]),
-            ],
-            Some("default"),
-        );
-        let mut stateful_string_generator = StatefulStringGenerator(rule);
-        let selection = stateful_string_generator.generate(&mut 1);
-        assert_eq!(selection.unwrap(), "Hi What is going on?");
+    /// This sets how the pieces of each expansion are joined together. By default they are joined using the separator exactly as
+    /// written, while `JoinMode::Smart` keeps the spacing written in each option - so rules that expand to nothing, or actions
+    /// that are stripped out, don't leave doubled spaces or words running together.
+    pub fn set_join_mode(&mut self, join: JoinMode) {
+        self.join = join;
     }
 
-    #[test]
-    pub fn stateful_generator_can_set_value_at_depth_and_use_it_later() {
+    /// This gets how the pieces of each expansion are joined together
+    pub fn join_mode(&self) -> JoinMode {
+        self.join
+    }
+
+    /// This sets how deeply rules can be nested within each other while generating, returning the updated grammar. See `set_max_depth`.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.set_max_depth(max_depth);
+        self
+    }
+
+    /// This sets how deeply rules can be nested within each other while generating - replacing the default from `Grammar::max_depth`.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = Some(max_depth);
+    }
+
+    /// This checks the grammar against a sandbox policy, and limits it's generations using the policy if it passes.
+    /// Use this for grammars from untrusted sources, like mods - so they can't exhaust memory or tamper with the world.
+    pub fn sandboxed(mut self, policy: SandboxPolicy) -> Result<Self, SandboxViolation> {
+        policy.check(&self)?;
+        self.sandbox = Some(policy);
+        Ok(self)
+    }
+
+    /// This gets the sandbox policy limiting the grammar, if it was sandboxed
+    pub fn sandbox(&self) -> Option<&SandboxPolicy> {
+        self.sandbox.as_ref()
+    }
+
+    /// Checks whether the grammar can read tokens and shared variables
+    fn allows_external_values(&self) -> bool {
+        self.sandbox
+            .as_ref()
+            .is_none_or(|sandbox| sandbox.allow_external_values)
+    }
+
+    /// Applies a chain of modifiers to the expansion of a rule. Custom modifiers take precedence over the agreement modifiers,
+    /// which take precedence over the built in ones - following the conventions of the grammar's locale. `agree` can only read variables when the temporary grammar is provided.
+    fn modify(&self, modifiers: &str, stream: String, tmp: Option<&TraceryGrammar>) -> String {
+        parse_modifiers(modifiers)
+            .into_iter()
+            .fold(stream, |stream, (name, params)| {
+                if let Some(modifier) = self.modifiers.get(name) {
+                    return modifier(&stream, &params);
+                }
+                if PRONOUN_MODIFIERS.contains(&name) {
+                    return match self.agreement_of(&stream).pronoun(name) {
+                        Some(pronoun) => pronoun.to_string(),
+                        None => stream,
+                    };
+                }
+                if name == AGREE_MODIFIER {
+                    let subject = params
+                        .first()
+                        .map(|key| self.subject_agreement(key.trim(), tmp));
+                    return subject.unwrap_or_default().conjugate(&stream);
+                }
+                if let Some(modified) = self.typography().modify(name, &stream) {
+                    return modified;
+                }
+                // Like in the original Tracery, unknown modifiers leave the text unchanged
+                match built_in_modifier(name) {
+                    Some(modifier) => modifier(&stream, &params),
+                    None => stream,
+                }
+            })
+    }
+
+    /// Finds the agreement of some text, from the agreement annotations of the options it was generated from. If the text isn't
+    /// exactly one of those options, the one appearing first within it is used - so `Ana the Bold` agrees with `Ana`.
+    fn agreement_of(&self, text: &str) -> Agreement {
+        let mut first = None;
+        for option in self.rules.values().flatten() {
+            let Some(agreement) = Agreement::from_option(option) else {
+                continue;
+            };
+            let option = option_text(option);
+            if option == text {
+                return agreement;
+            }
+            if let Some(position) = text.find(option).filter(|_| !option.is_empty()) {
+                if first.is_none_or(|(first, _)| position < first) {
+                    first = Some((position, agreement));
+                }
+            }
+        }
+        first.map(|(_, agreement)| agreement).unwrap_or_default()
+    }
+
+    /// Finds the agreement of the subject a verb refers to - a variable assigned during the generation, a stored variable,
+    /// or the option a rule chose most recently
+    fn subject_agreement(&self, key: &str, tmp: Option<&TraceryGrammar>) -> Agreement {
+        let Some(tmp) = tmp else {
+            return Agreement::default();
+        };
+        let variable = tmp
+            .rules
+            .get(key)
+            .or_else(|| tmp.runtime.variables.get_var(key))
+            .and_then(|values| values.first());
+        if let Some(value) = variable {
+            return self.agreement_of(value);
+        }
+        let rule = self.resolve_alias(key);
+        tmp.runtime
+            .selection
+            .last_choice(rule)
+            .and_then(|index| self.rules.get(rule)?.get(index))
+            .and_then(|option| Agreement::from_option(option))
+            .unwrap_or_default()
+    }
+
+    /// Selects the value a rule, variable or token is replaced with while generating, along with the index of the chosen option
+    fn select_replacement<R: GrammarRandomNumberGenerator>(
+        &self,
+        rule: &String,
+        rng: &mut R,
+        temporary_grammar: &mut Self,
+    ) -> Option<(String, Option<usize>)> {
+        if let Some(variable) = asserted_variable(rule) {
+            // Assertions expand to nothing, and only get an option if they held - so failures can be found in the trace
+            let held = self.is_variable_set(variable, temporary_grammar);
+            return Some((String::new(), held.then_some(0)));
+        }
+        if let Some(index) = temporary_grammar.select_index_from_rule(rule, rng) {
+            let value = temporary_grammar.rules.get(rule)?.get(index)?;
+            return Some((value.clone(), Some(index)));
+        }
+        let runtime = &mut temporary_grammar.runtime;
+        let external = self.allows_external_values();
+        if let Some((index, value)) = runtime.variables.select(rule, rng) {
+            return Some((value.clone(), Some(index)));
+        }
+        if let Some((index, value)) = runtime.shared.select(rule, rng).filter(|_| external) {
+            return Some((value.clone(), Some(index)));
+        }
+        if runtime.choices.is_some() && self.is_choice_point(rule) {
+            return self.select_choice(rule, runtime);
+        }
+        if self.has_rule(rule) {
+            // If all of the rule's options are guarded out, the rule expands to nothing
+            return Some(match self.select_option(rule, runtime, rng) {
+                Some((index, value)) if self.emits(rule) => {
+                    (self.wrap_emitted(rule, value), Some(index))
+                }
+                Some((index, value)) => (value, Some(index)),
+                None => (String::new(), None),
+            });
+        }
+        if let Some(value) = self.capture_value(rule, &runtime.selection) {
+            return Some((value, None));
+        }
+        if let Some(value) = runtime.tokens.get(rule).filter(|_| external) {
+            return Some((value.clone(), None));
+        }
+        if rule == GENERATION_COUNT_KEY {
+            return Some((runtime.selection.generation().to_string(), None));
+        }
+        None
+    }
+
+    /// Checks a generation's output against the sandbox policy, if there is one
+    fn check_output_length(&self, result: &str) -> Result<(), GenerationIssue> {
+        match &self.sandbox {
+            Some(sandbox) if result.len() > sandbox.max_output_length => {
+                Err(GenerationIssue::OutputTooLong(result.len()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks whether a variable has been set - assigned during the generation, stored by an earlier one, or provided as a token
+    fn is_variable_set(&self, variable: &str, tmp: &TraceryGrammar) -> bool {
+        let external = self.allows_external_values();
+        tmp.rules.contains_key(variable)
+            || tmp.runtime.variables.contains(variable)
+            || (external
+                && (tmp.runtime.shared.contains(variable)
+                    || tmp.runtime.tokens.contains_key(variable)))
+    }
+
+    /// Checks the assertions recorded in a generation's trace against the assertion policy. Warnings are returned as an issue.
+    fn check_assertions(
+        &self,
+        trace: Option<&ExpansionTrace<String>>,
+        root: Option<&str>,
+    ) -> Result<Option<GenerationIssue>, GenerationIssue> {
+        let failures = match trace {
+            Some(trace) if self.assertions != AssertionPolicy::Ignore => {
+                failed_assertions(trace, root)
+            }
+            _ => return Ok(None),
+        };
+        if failures.is_empty() {
+            return Ok(None);
+        }
+        let issue = GenerationIssue::FailedAssertions(failures);
+        match self.assertions {
+            AssertionPolicy::Error => Err(issue),
+            _ => {
+                #[cfg(feature = "bevy")]
+                bevy::log::warn!("{issue}");
+                Ok(Some(issue))
+            }
+        }
+    }
+
+    /// Parses the content of an action - `key:value` for immediate meta rules, and `key|value` for delayed ones.
+    /// Layout directives like `[br]` and `[icon:sword]` are replaced with their text, assertions like `[assert:hero]` are checked when they are reached,
+    /// and content that isn't an action is kept as is.
+    fn parse_action(&self, action: &str) -> Vec<Replacable<String, String>> {
+        if let Some(text) = layout::directive_text(action) {
+            return vec![Replacable::Ready(text)];
+        }
+        if let Some(key) = assertion_key(action) {
+            return match self.assertions {
+                AssertionPolicy::Ignore => vec![],
+                _ => vec![Replacable::Replace(key)],
+            };
+        }
+        match action.find([':', '|']) {
+            Some(split) if action[split..].starts_with(':') => vec![Replacable::ImmediateMeta(
+                action[..split].to_string(),
+                action[split + 1..].to_string(),
+            )],
+            Some(split) => vec![Replacable::DelayedMeta(
+                action[..split].to_string(),
+                action[split + 1..].to_string(),
+            )],
+            None => self.check_token_stream(&action.to_string()).1,
+        }
+    }
+
+    /// Selects one of a rule's options using the rule's selection mode, skipping options whose guards fail or whose tags are filtered out.
+    /// Options that are still cooling down are skipped as well, unless every remaining option is.
+    /// The selected option is returned without its annotations, along with its index.
+    fn select_option<R: GrammarRandomNumberGenerator>(
+        &self,
+        rule: &str,
+        runtime: &mut RuntimeState,
+        rng: &mut R,
+    ) -> Option<(usize, String)> {
+        let RuntimeState {
+            selection: state,
+            filter,
+            ..
+        } = runtime;
+        let rule = self.resolve_alias(rule);
+        let options = self.rules.get(rule)?;
+        let settings = self.settings.get(rule);
+        let link = settings.and_then(|settings| settings.link.as_deref());
+        let distinct = settings.is_some_and(|settings| settings.distinct);
+        let weights = settings.map_or(&[][..], |settings| settings.weights.as_slice());
+        let constrained = self
+            .exclusions
+            .iter()
+            .any(|exclusion| exclusion.involves(rule));
+        // Linked rules follow the index chosen for their group, ignoring guards and cooldowns
+        if let Some(index) = link
+            .and_then(|link| state.linked_index(link))
+            .filter(|index| *index < options.len())
+        {
+            state.record_choice(rule, index);
+            if constrained {
+                state.record_chosen(rule, index);
+            }
+            return Some((index, option_text(&options[index]).to_string()));
+        }
+        let mode = self.selection_mode(rule);
+        let weighted = !weights.is_empty() && mode == SelectionMode::Random;
+        // Options without annotations can't be guarded out or cooling down, so most rules can skip checking them
+        if !distinct
+            && !constrained
+            && filter.is_none()
+            && !options.iter().any(|option| option.starts_with('{'))
+        {
+            let index = match weighted {
+                true => state.select_weighted_where(rule, weights, options.len(), |_| true, rng)?,
+                false => state.select(rule, mode, options.len(), rng)?,
+            };
+            if let Some(link) = link {
+                state.set_linked_index(link, index);
+            }
+            return Some((index, option_text(&options[index]).to_string()));
+        }
+        let context = GuardContext {
+            generation: state.generation(),
+        };
+        let passes_guards = options
+            .iter()
+            .map(|option| {
+                guards_pass(option, &context)
+                    && filter.as_ref().is_none_or(|filter| filter.allows(option))
+                    && !(constrained && self.is_excluded(rule, option, state))
+            })
+            .collect::<Vec<_>>();
+        let cooldowns = options
+            .iter()
+            .map(|option| option_cooldown(option))
+            .collect::<Vec<_>>();
+        let fresh = passes_guards
+            .iter()
+            .enumerate()
+            .map(|(index, passes)| *passes && !(distinct && state.was_chosen(rule, index)))
+            .collect::<Vec<_>>();
+        let available = cooldowns
+            .iter()
+            .enumerate()
+            .map(|(index, cooldown)| {
+                fresh[index]
+                    && !cooldown
+                        .is_some_and(|cooldown| state.is_cooling_down(rule, index, cooldown))
+            })
+            .collect::<Vec<_>>();
+        // Cooldowns are dropped first, followed by distinctness, if they leave nothing to choose from
+        let eligible = [&available, &fresh]
+            .into_iter()
+            .find(|eligible| eligible.contains(&true))
+            .unwrap_or(&passes_guards);
+        let is_eligible = |index: usize| eligible[index];
+        let index = match weighted {
+            true => state.select_weighted_where(rule, weights, options.len(), is_eligible, rng)?,
+            false => state.select_where(rule, mode, options.len(), is_eligible, rng)?,
+        };
+        if cooldowns[index].is_some() {
+            state.record_use(rule, index);
+        }
+        if distinct || constrained {
+            state.record_chosen(rule, index);
+        }
+        if let Some(link) = link {
+            state.set_linked_index(link, index);
+        }
+        Some((index, option_text(&options[index]).to_string()))
+    }
+
+    /// Checks whether an option is excluded by an option chosen earlier in the current generation
+    fn is_excluded(&self, rule: &str, option: &str, state: &SelectionState) -> bool {
+        self.exclusions
+            .iter()
+            .filter_map(|exclusion| exclusion.other_side(rule, option))
+            .any(|(other_rule, other_option)| {
+                self.rules.get(other_rule).is_some_and(|options| {
+                    options.iter().enumerate().any(|(index, option)| {
+                        refers_to(option, other_option) && state.was_chosen(other_rule, index)
+                    })
+                })
+            })
+    }
+
+    /// Gets the value of a capture like `noun._index` or `noun._name`, from the options chosen so far
+    fn capture_value(&self, key: &str, state: &SelectionState) -> Option<String> {
+        let (rule, capture) = key.rsplit_once('.')?;
+        let rule = self.resolve_alias(rule);
+        let index = state.last_choice(rule)?;
+        match capture {
+            INDEX_CAPTURE => Some(index.to_string()),
+            NAME_CAPTURE => Some(self.option_name(rule, index)),
+            _ => None,
+        }
+    }
+
+    /// Gets the name of one of a rule's options - declared using the `{name:value}` annotation, or it's index otherwise
+    fn option_name(&self, rule: &str, index: usize) -> String {
+        self.rules
+            .get(rule)
+            .and_then(|options| options.get(index))
+            .and_then(|option| option_name(option))
+            .map_or_else(|| index.to_string(), str::to_string)
+    }
+
+    /// Selects an option from the rule a generation starts at, explaining why if that isn't possible.
+    fn select_starting_option<R: GrammarRandomNumberGenerator>(
+        &self,
+        rule: &str,
+        runtime: &mut RuntimeState,
+        rng: &mut R,
+    ) -> Result<(usize, String), GenerationIssue> {
+        if !self.rules.contains_key(self.resolve_alias(rule)) {
+            return Err(GenerationIssue::UnknownRule(rule.to_string()));
+        }
+        self.select_option(rule, runtime, rng)
+            .ok_or_else(|| GenerationIssue::NoAvailableOption(rule.to_string()))
+    }
+
+    /// Wraps the option chosen by a rule that emits data, so it's expansion is assigned to the rule's `_emitted` capture as well
+    fn wrap_emitted(&self, rule: &str, value: String) -> String {
+        let key = capture_key(self.resolve_alias(rule), EMITTED_CAPTURE);
+        format!("[{key}:{value}]#{key}#")
+    }
+
+    /// Removes the data emitted during an expansion from `tmp`, returning it keyed by the rule that emitted it.
+    /// If the expansion started at a rule that emits data, it's result is included as well.
+    fn take_emitted(
+        &self,
+        tmp: &mut TraceryGrammar,
+        root: Option<&str>,
+        result: &str,
+    ) -> HashMap<String, String> {
+        let mut emitted = HashMap::default();
+        if let Some(root) = root.filter(|root| self.emits(root)) {
+            emitted.insert(self.resolve_alias(root).to_string(), result.to_string());
+        }
+        let keys = tmp
+            .keys
+            .iter()
+            .filter(|key| is_capture_key(key))
+            .filter_map(|key| key.strip_suffix(EMITTED_CAPTURE)?.strip_suffix('.'))
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        for rule in keys {
+            let key = capture_key(&rule, EMITTED_CAPTURE);
+            if let Some(values) = tmp.rules.get(&key) {
+                emitted.insert(rule, values.concat());
+            }
+            tmp.remove_additional_rules(&key);
+        }
+        emitted
+    }
+
+    /// Removes the lookups of `_emitted` captures from a trace, so it only records the rules the grammar author wrote
+    fn strip_emitted_lookups(&self, trace: &mut ExpansionTrace<String>) {
+        fn strip(nodes: &mut Vec<TraceNode<String>>) {
+            nodes.retain(|node| {
+                !(is_capture_key(&node.rule) && node.rule.ends_with(EMITTED_CAPTURE))
+            });
+            for node in nodes.iter_mut() {
+                strip(&mut node.children);
+            }
+        }
+        if self.settings.values().any(|settings| settings.emit) {
+            strip(&mut trace.roots);
+        }
+    }
+
+    /// Applies the shadowing policy to the variables assigned in `tmp`. Keys in `variables` were assigned by earlier generations,
+    /// so they aren't treated as base rules. Scoped shadows are removed from `tmp`, and warnings are returned as an issue.
+    fn apply_shadowing_policy(
+        &self,
+        tmp: &mut TraceryGrammar,
+        variables: &VariableStore,
+    ) -> Result<Option<GenerationIssue>, GenerationIssue> {
+        let mut shadowed = tmp
+            .rules
+            .keys()
+            .filter(|key| self.rules.contains_key(*key) && !variables.contains(key))
+            .cloned()
+            .collect::<Vec<_>>();
+        if shadowed.is_empty() {
+            return Ok(None);
+        }
+        shadowed.sort();
+        match self.shadowing {
+            ShadowingPolicy::Allow => Ok(None),
+            ShadowingPolicy::Warn => {
+                let issue = GenerationIssue::ShadowedRules(shadowed);
+                #[cfg(feature = "bevy")]
+                bevy::log::warn!("{issue}");
+                Ok(Some(issue))
+            }
+            ShadowingPolicy::Scoped => {
+                for key in shadowed.iter() {
+                    tmp.remove_additional_rules(key);
+                }
+                Ok(None)
+            }
+            ShadowingPolicy::Error => Err(GenerationIssue::ShadowedRules(shadowed)),
+        }
+    }
+}
+
+impl Grammar<String, String, String> for TraceryGrammar {
+    fn rule_keys(&self) -> &Vec<String> {
+        &self.keys
+    }
+
+    fn has_rule(&self, rule: &String) -> bool {
+        self.rules.contains_key(self.resolve_alias(rule))
+    }
+
+    fn default_starting_point(&self) -> &String {
+        &self.starting_point
+    }
+
+    fn select_starting_point<R: GrammarRandomNumberGenerator>(&self, rng: &mut R) -> &String {
+        let total = self
+            .starting_points
+            .iter()
+            .map(|(_, weight)| *weight as usize)
+            .fold(0, usize::saturating_add);
+        if total == 0 {
+            return &self.starting_point;
+        }
+        let mut pick = rng.get_number(total).min(total - 1);
+        self.starting_points
+            .iter()
+            .find(|(_, weight)| {
+                let weight = *weight as usize;
+                if pick < weight {
+                    return true;
+                }
+                pick -= weight;
+                false
+            })
+            .map_or(&self.starting_point, |(key, _)| key)
+    }
+
+    fn get_rule_options(&self, rule: &String) -> Option<&Vec<String>> {
+        self.rules.get(self.resolve_alias(rule))
+    }
+
+    fn check_token_stream(&self, stream: &String) -> (bool, Vec<Replacable<String, String>>) {
+        let mut result = vec![];
+        let mut skippable = true;
+        let mut rest = stream.as_str();
+        // The length of the start of `rest` that is known to be plain text - like brackets that aren't inline alternatives
+        let mut plain = 0;
+        while let Some(start) = rest[plain..]
+            .find(['#', '[', '('])
+            .map(|start| start + plain)
+        {
+            let opening = &rest[start..start + 1];
+            let inner = &rest[start + 1..];
+            let (end, mut tokens) = match opening {
+                "#" => {
+                    let end = find_closing(inner, '#');
+                    (end, vec![Replacable::Replace(inner[..end].to_string())])
+                }
+                "[" => {
+                    let end = find_closing(inner, ']');
+                    (end, self.parse_action(&inner[..end]))
+                }
+                _ => match split_alternatives(inner) {
+                    Some((end, alternatives)) => (
+                        end,
+                        vec![Replacable::Alternatives(
+                            alternatives.into_iter().map(str::to_string).collect(),
+                        )],
+                    ),
+                    None => {
+                        plain = start + 1;
+                        continue;
+                    }
+                },
+            };
+            skippable = false;
+            if start > 0 {
+                result.push(Replacable::Ready(rest[..start].to_string()));
+            }
+            result.append(&mut tokens);
+            rest = inner.get(end + 1..).unwrap_or_default();
+            plain = 0;
+        }
+        if !rest.is_empty() {
+            result.push(Replacable::Ready(rest.to_string()));
+        }
+        (skippable, result)
+    }
+
+    fn select_for_processing<R: GrammarRandomNumberGenerator>(
+        &self,
+        rule: &String,
+        rng: &mut R,
+        temporary_grammar: &mut Self,
+    ) -> Option<(String, Option<usize>)> {
+        if !self.charge_replacement(&mut temporary_grammar.runtime) {
+            return None;
+        }
+        let selected = self.select_replacement(rule, rng, temporary_grammar)?;
+        match self.charge_output(&mut temporary_grammar.runtime, &selected.0) {
+            true => Some(selected),
+            false => None,
+        }
+    }
+
+    fn split_actions(
+        &self,
+        rule: &String,
+    ) -> Option<(Vec<Replacable<String, String>>, Option<String>)> {
+        if !rule.starts_with('[') && RuleCall::parse(rule).is_none() {
+            return None;
+        }
+        let mut actions = vec![];
+        let mut rest = rule.as_str();
+        while let Some(inner) = rest.strip_prefix('[') {
+            let end = find_closing(inner, ']');
+            actions.append(&mut self.parse_action(&inner[..end]));
+            rest = inner.get(end + 1..).unwrap_or_default();
+        }
+        // Arguments are bound like actions, so they only apply while the rule is expanded
+        if let Some(call) = RuleCall::parse(rest) {
+            actions.extend(call.bindings());
+            return Some((actions, Some(call.rule_key())));
+        }
+        Some((actions, (!rest.is_empty()).then(|| rest.to_string())))
+    }
+
+    fn split_modifiers(&self, rule: &String) -> Option<(String, String)> {
+        split_key_and_modifiers(rule)
+            .map(|(rule, modifiers)| (rule.to_string(), modifiers.to_string()))
+    }
+
+    fn apply_modifiers(&self, modifiers: &String, stream: String) -> String {
+        self.modify(modifiers, stream, None)
+    }
+
+    fn apply_modifiers_in_context(
+        &self,
+        modifiers: &String,
+        stream: String,
+        temporary_grammar: &Self,
+    ) -> String {
+        self.modify(modifiers, stream, Some(temporary_grammar))
+    }
+
+    fn rule_to_default_result(&self, rule: &String) -> String {
+        format!("#{rule}#")
+    }
+
+    fn processing_direction(&self) -> GrammarProcessingDirection {
+        GrammarProcessingDirection::DepthFirst
+    }
+
+    fn max_depth(&self) -> usize {
+        self.max_depth.unwrap_or(50)
+    }
+
+    fn record_expansion(&self, temporary_grammar: &mut Self, depth: usize) {
+        temporary_grammar.runtime.stats.record_expansion(depth);
+    }
+
+    fn begin_assignment(&self, temporary_grammar: &mut Self, key: &String) {
+        if let Some(rerolls) = &temporary_grammar.runtime.rerolls {
+            rerolls.begin_assignment(key);
+        }
+    }
+
+    fn end_assignment(&self, temporary_grammar: &mut Self, _key: &String) {
+        if let Some(rerolls) = &temporary_grammar.runtime.rerolls {
+            rerolls.end_assignment();
+        }
+    }
+
+    fn max_expansions(&self) -> usize {
+        match (&self.sandbox, &self.budget) {
+            (Some(sandbox), _) => sandbox.max_expansions,
+            // One more expansion is allowed than the budget, so going over it can be noticed
+            (None, Some(budget)) => budget.max_replacements.saturating_add(1),
+            (None, None) => 10_000,
+        }
+    }
+
+    fn result_to_stream(&self, result: &[String]) -> String {
+        self.join.join(result, &self.separator)
+    }
+
+    fn set_additional_rules(&mut self, rule: String, values: &[String]) {
+        if self.rules.insert(rule.clone(), values.into()).is_none() {
+            self.keys.push(rule);
+        }
+    }
+
+    fn remove_additional_rules(&mut self, rule: &String) {
+        self.keys.retain(|key| key != rule);
+        self.rules.remove(rule);
+    }
+
+    fn clear_additional_rules(&mut self) {
+        self.keys.clear();
+        self.rules.clear();
+    }
+
+    fn stream_to_result(&self, stream: &String) -> Vec<String> {
+        vec![stream.clone()]
+    }
+
+    fn result_into_stream(&self, result: String) -> String {
+        result
+    }
+
+    fn stream_into_result(&self, stream: String) -> Vec<String> {
+        vec![stream]
+    }
+}
+
+/// Finds the position of the character closing a tag or action, skipping over any nested actions.
+/// If it isn't closed, the tag or action continues until the end of the text.
+fn find_closing(text: &str, closing: char) -> usize {
+    let mut depth = 0usize;
+    for (index, character) in text.char_indices() {
+        match character {
+            '[' => depth += 1,
+            ']' if depth > 0 => depth -= 1,
+            character if character == closing && depth == 0 => return index,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// Splits an inline set of alternatives like `(tiny|huge)`, given the text following the opening bracket.
+/// Returns the index of the closing bracket along with the alternatives, or `None` if the brackets don't hold alternatives.
+fn split_alternatives(text: &str) -> Option<(usize, Vec<&str>)> {
+    let mut depth = 0usize;
+    let mut in_rule = false;
+    let mut start = 0;
+    let mut alternatives = vec![];
+    for (index, character) in text.char_indices() {
+        match character {
+            '#' => in_rule = !in_rule,
+            _ if in_rule => {}
+            '(' | '[' => depth += 1,
+            ')' | ']' if depth > 0 => depth -= 1,
+            ')' if alternatives.is_empty() => return None,
+            ')' => {
+                alternatives.push(&text[start..index]);
+                return Some((index, alternatives));
+            }
+            '|' if depth == 0 => {
+                alternatives.push(&text[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// This is a stateless string generator based on the tracery grammar. Note that, since it's stateless, it does not support variables.
+pub struct StringGenerator;
+
+impl StringGenerator {
+    /// This generates from the grammar's starting point, explaining why if it couldn't.
+    pub fn try_generate<R: GrammarRandomNumberGenerator>(
+        grammar: &TraceryGrammar,
+        rng: &mut R,
+    ) -> Result<String, GenerationIssue> {
+        Self::try_generate_at(grammar.default_starting_point(), grammar, rng)
+    }
+
+    /// This generates from the provided key, explaining why if it couldn't.
+    pub fn try_generate_at<R: GrammarRandomNumberGenerator>(
+        key: &str,
+        grammar: &TraceryGrammar,
+        rng: &mut R,
+    ) -> Result<String, GenerationIssue> {
+        Self::try_generate_with(key, grammar, rng, None, Default::default())
+            .map(|(_, result, _)| result)
+    }
+
+    /// This generates from the grammar's starting point, and then applies an output profile to the result - such as
+    /// `OutputProfile::screen_reader`, for players using a screen reader.
+    pub fn try_generate_for_profile<R: GrammarRandomNumberGenerator>(
+        grammar: &TraceryGrammar,
+        rng: &mut R,
+        profile: &OutputProfile,
+    ) -> Result<String, GenerationIssue> {
+        Self::try_generate(grammar, rng).map(|result| profile.apply(&result))
+    }
+
+    /// This generates from the provided key, only choosing options whose tags pass the filter - so the same grammar can serve different
+    /// biomes or tones. Options are tagged using the `{tags:fantasy,dangerous}` annotation, and options without tags are passed an empty list.
+    pub fn generate_with_filter<R: GrammarRandomNumberGenerator>(
+        key: &str,
+        grammar: &TraceryGrammar,
+        rng: &mut R,
+        filter: impl Fn(&[&str]) -> bool + Send + Sync + 'static,
+    ) -> Option<String> {
+        let runtime = RuntimeState {
+            filter: Some(OptionFilter::new(filter)),
+            ..Default::default()
+        };
+        let (_, result, _) = Self::try_generate_with(key, grammar, rng, None, runtime).ok()?;
+        Some(result)
+    }
+
+    /// This generates from the grammar's starting point, along with the data emitted by rules set up using `set_emitted` - keyed by rule.
+    pub fn generate_with_data<R: GrammarRandomNumberGenerator>(
+        grammar: &TraceryGrammar,
+        rng: &mut R,
+    ) -> Option<(String, HashMap<String, String>)> {
+        let key = grammar.default_starting_point();
+        let (_, result, emitted) =
+            Self::try_generate_with(key, grammar, rng, None, Default::default()).ok()?;
+        Some((result, emitted))
+    }
+
+    /// This expands the provided text for previewing in an editor. Rules that couldn't be expanded are listed as placeholders,
+    /// rather than being left in the text.
+    pub fn preview<R: GrammarRandomNumberGenerator>(
+        initial: &str,
+        grammar: &TraceryGrammar,
+        rng: &mut R,
+    ) -> ExpansionPreview {
+        ExpansionPreview::from_expansion(&Self::expand_from(&initial.to_string(), grammar, rng))
+    }
+
+    /// This generates `count` outputs from the grammar's starting point, each using an independent `SeededRng` derived from the seed.
+    /// The same seed always produces the same outputs. With the `parallel` feature, the outputs are generated in parallel using rayon -
+    /// except on `wasm32`, which doesn't have threads.
+    /// If the grammar can't generate from it's starting point, the result is empty.
+    pub fn generate_batch(grammar: &TraceryGrammar, count: usize, seed: u64) -> Vec<String> {
+        let generate = |index: usize| {
+            let mut rng = SeededRng::for_stream(seed, index as u64);
+            Self::generate(grammar, &mut rng)
+        };
+        #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+        {
+            use rayon::prelude::*;
+            (0..count).into_par_iter().filter_map(generate).collect()
+        }
+        #[cfg(not(all(feature = "parallel", not(target_arch = "wasm32"))))]
+        {
+            (0..count).filter_map(generate).collect()
+        }
+    }
+
+    /// This generates up to `count` distinct outputs from the provided key, rerolling any duplicates - such as when naming a
+    /// batch of quests. It gives up after `max_attempts` generations in total, so the result is shorter than `count` when the
+    /// grammar can't produce enough distinct outputs. Outputs are returned in the order they were first generated.
+    pub fn generate_unique<R: GrammarRandomNumberGenerator>(
+        key: &str,
+        grammar: &TraceryGrammar,
+        count: usize,
+        max_attempts: usize,
+        rng: &mut R,
+    ) -> Vec<String> {
+        let mut seen: HashSet<String> = HashSet::default();
+        let mut outputs = vec![];
+        for _ in 0..max_attempts {
+            if outputs.len() >= count {
+                break;
+            }
+            if let Ok(output) = Self::try_generate_at(key, grammar, rng) {
+                if seen.insert(output.clone()) {
+                    outputs.push(output);
+                }
+            }
+        }
+        outputs
+    }
+
+    /// Generates from the provided key - optionally recording a trace, or filtering options by their tags - and applies the shadowing policy.
+    /// Returns the index of the starting option, the result and the data emitted during the generation.
+    fn try_generate_with<R: GrammarRandomNumberGenerator>(
+        key: &str,
+        grammar: &TraceryGrammar,
+        rng: &mut R,
+        trace: Option<&mut ExpansionTrace<String>>,
+        runtime: RuntimeState,
+    ) -> Result<(usize, String, HashMap<String, String>), GenerationIssue> {
+        let mut tmp = TraceryGrammar::empty();
+        tmp.runtime = runtime;
+        let (index, initial) = grammar.select_starting_option(key, &mut tmp.runtime, rng)?;
+        // Failed assertions are found in a trace of the expansion, even if the caller didn't ask for one
+        let mut assertion_trace =
+            (grammar.assertions != AssertionPolicy::Ignore).then(ExpansionTrace::default);
+        let mut trace = trace.or(assertion_trace.as_mut());
+        let result = match trace.as_deref_mut() {
+            Some(trace) => {
+                let result = grammar.process_stream_traced(&initial, rng, &mut tmp, trace);
+                grammar.strip_emitted_lookups(trace);
+                result
+            }
+            None => grammar.process_stream(&initial, rng, &mut tmp),
+        };
+        let result = grammar.typography().apply(&result);
+        grammar.check_budget(&tmp.runtime, &result)?;
+        grammar.check_output_length(&result)?;
+        grammar.check_assertions(trace.as_deref(), Some(key))?;
+        let emitted = grammar.take_emitted(&mut tmp, Some(key), &result);
+        grammar.apply_shadowing_policy(&mut tmp, &VariableStore::default())?;
+        Ok((index, result, emitted))
+    }
+}
+
+impl Generator<String, String, String, TraceryGrammar> for StringGenerator {
+    fn generate<R: GrammarRandomNumberGenerator>(
+        grammar: &TraceryGrammar,
+        rng: &mut R,
+    ) -> Option<String> {
+        Self::generate_at(&grammar.default_starting_point().clone(), grammar, rng)
+    }
+
+    fn generate_at<R: GrammarRandomNumberGenerator>(
+        key: &String,
+        grammar: &TraceryGrammar,
+        rng: &mut R,
+    ) -> Option<String> {
+        Self::try_generate_at(key, grammar, rng).ok()
+    }
+
+    fn generate_traced<R: GrammarRandomNumberGenerator>(
+        grammar: &TraceryGrammar,
+        rng: &mut R,
+    ) -> Option<(String, ExpansionTrace<String>)> {
+        let key = grammar.default_starting_point();
+        let mut trace = ExpansionTrace::default();
+        let (index, result, _) =
+            Self::try_generate_with(key, grammar, rng, Some(&mut trace), Default::default())
+                .ok()?;
+        Some((result, trace.into_rooted_at(key.clone(), index)))
+    }
+
+    fn expand_from<R: GrammarRandomNumberGenerator>(
+        initial: &String,
+        grammar: &TraceryGrammar,
+        rng: &mut R,
+    ) -> String {
+        let mut tmp = TraceryGrammar::empty();
+        grammar.process_stream(initial, rng, &mut tmp)
+    }
+}
+
+/// This is a stateful string generator based on the tracery grammar. Note that since it is stateful, it does support variables.
+/// It also remembers the options each rule selected, so rules using a `SelectionMode` other than `Random` avoid repeats across generations.
+/// With the `serde` feature, it can be serialized along with it's variables and selection memory - so it can be embedded in a save file.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "bevy", derive(Component, Resource))]
+#[cfg_attr(feature = "serde", derive(Serialize, serde::Deserialize))]
+pub struct StatefulStringGenerator {
+    grammar: TraceryGrammar,
+    #[cfg_attr(feature = "serde", serde(default))]
+    runtime: RuntimeState,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    issues: Vec<GenerationIssue>,
+    #[cfg_attr(feature = "serde", serde(skip, default = "empty_context"))]
+    context: ExpansionContext<TraceryGrammar>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hooks: ExpansionHooks,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    emitted: HashMap<String, String>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    global_writes: VariableStore,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "VariableLifetime::is_default")
+    )]
+    variable_lifetime: VariableLifetime,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    output_profile: Option<OutputProfile>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    post_processing: Option<PostProcessing>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    content_filter: Option<ContentFilter>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    unreported: UnreportedStats,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    rerollable: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    last_generation: Option<RerollRecord>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    trace: Option<ExpansionTrace<String>>,
+    #[cfg(feature = "asset")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pending: Option<bevy::asset::Handle<TraceryGrammar>>,
+}
+
+fn empty_context() -> ExpansionContext<TraceryGrammar> {
+    ExpansionContext::new(TraceryGrammar::empty())
+}
+
+impl StatefulStringGenerator {
+    /// This provides a new stateful generating using tracery grammar.
+    /// You provide a set of rules as `(Key, &[Values])` and optionally a starting point.
+    /// If no starting point is provided, we fall back on "origin"
+    pub fn new<T: Clone + Into<String>>(rules: &[(T, &[T])], starting_point: Option<T>) -> Self {
+        let grammar = TraceryGrammar::new(rules, starting_point);
+        Self::from_grammar(grammar)
+    }
+
+    /// This creates a new stateful string generator by cloning an existing tracery grammar.
+    pub fn clone_grammar(grammar: &TraceryGrammar) -> Self {
+        Self::from_grammar(grammar.clone())
+    }
+
+    /// This creates a stateful generator wrapping an existing grammar.
+    pub fn from_grammar(grammar: TraceryGrammar) -> Self {
+        Self {
+            grammar,
+            runtime: Default::default(),
+            issues: vec![],
+            context: empty_context(),
+            hooks: Default::default(),
+            emitted: Default::default(),
+            global_writes: Default::default(),
+            unreported: Default::default(),
+            variable_lifetime: Default::default(),
+            output_profile: None,
+            post_processing: None,
+            content_filter: None,
+            rerollable: false,
+            last_generation: None,
+            trace: None,
+            #[cfg(feature = "asset")]
+            pending: None,
+        }
+    }
+
+    /// This sets how long the variables assigned during a generation are kept - see `VariableLifetime`
+    pub fn with_variable_lifetime(mut self, lifetime: VariableLifetime) -> Self {
+        self.variable_lifetime = lifetime;
+        self
+    }
+
+    /// This sets how long the variables assigned during a generation are kept - see `VariableLifetime`
+    pub fn set_variable_lifetime(&mut self, lifetime: VariableLifetime) {
+        self.variable_lifetime = lifetime;
+    }
+
+    /// This gets how long the variables assigned during a generation are kept
+    pub fn variable_lifetime(&self) -> VariableLifetime {
+        self.variable_lifetime
+    }
+
+    /// This sets the output profile applied to the generator's outputs, returning the updated generator. See `set_output_profile`.
+    pub fn with_output_profile(mut self, profile: OutputProfile) -> Self {
+        self.set_output_profile(Some(profile));
+        self
+    }
+
+    /// This sets the output profile applied to the text the generator generates - such as `OutputProfile::screen_reader` - or removes it.
+    /// Only the final output is transformed, so the variables it remembers are kept as the grammar wrote them.
+    pub fn set_output_profile(&mut self, profile: Option<OutputProfile>) {
+        self.output_profile = profile;
+    }
+
+    /// This gets the output profile applied to the generator's outputs, if there is one
+    pub fn output_profile(&self) -> Option<&OutputProfile> {
+        self.output_profile.as_ref()
+    }
+
+    /// This sets the post-processing applied to the generator's outputs, returning the updated generator. See `set_post_processing`.
+    pub fn with_post_processing(mut self, post_processing: PostProcessing) -> Self {
+        self.set_post_processing(Some(post_processing));
+        self
+    }
+
+    /// This sets the chain of post-processors cleaning up the text the generator generates - such as `PostProcessing::standard` -
+    /// or removes it. It runs before the output profile, and only the final output is processed.
+    pub fn set_post_processing(&mut self, post_processing: Option<PostProcessing>) {
+        self.post_processing = post_processing;
+    }
+
+    /// This gets the post-processing applied to the generator's outputs, if there is any
+    pub fn post_processing(&self) -> Option<&PostProcessing> {
+        self.post_processing.as_ref()
+    }
+
+    /// This bakes the generator's current variables into a standalone copy of it's grammar, so it can be serialized or shared
+    /// with other generators - like a grammar personalised to a single NPC. Variables replace any base rules sharing their key,
+    /// along with those rules' settings. Tokens and the memory of previously selected options aren't included.
+    pub fn freeze(&self) -> TraceryGrammar {
+        let mut grammar = self.grammar.clone();
+        for (key, values) in self.runtime.variables.iter() {
+            grammar.settings.remove(key);
+            grammar.set_additional_rules(key.clone(), values);
+        }
+        grammar
+    }
+
+    /// This sets the current time in seconds, used for cooldowns measured in seconds like `{cooldown:10s}`.
+    /// When using the `GrammarTimePlugin`, this is kept in sync with the bevy clock.
+    pub fn set_elapsed_seconds(&mut self, seconds: f64) {
+        self.runtime.selection.set_time(seconds);
+    }
+
+    /// This gets the number of generations this generator has completed.
+    /// Grammars can reference it as `#gen_count#`, or use it in guards like `{if:gen_count>=3}`.
+    pub fn generation_count(&self) -> usize {
+        self.runtime.selection.generation()
+    }
+
+    /// This sets the value of a token. Tokens are provided from outside the grammar, and are used
+    /// when a key doesn't match any variable or rule - so `#time_of_day#` can be kept in sync with the game.
+    pub fn set_token(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.runtime.tokens.insert(key.into(), value.into());
+    }
+
+    /// This gets the current value of a token
+    pub fn get_token(&self, key: &str) -> Option<&String> {
+        self.runtime.tokens.get(key)
+    }
+
+    /// This removes a token, returning its previous value
+    pub fn remove_token(&mut self, key: &str) -> Option<String> {
+        self.runtime.tokens.remove(key)
+    }
+
+    /// This gets the variables kept from previous generations
+    pub fn variables(&self) -> &VariableStore {
+        &self.runtime.variables
+    }
+
+    /// This gets a mutable reference to the variables kept from previous generations, so they can be changed, cleared or restored from a snapshot
+    pub fn variables_mut(&mut self) -> &mut VariableStore {
+        &mut self.runtime.variables
+    }
+
+    /// This gets the variables shared with this generator from outside of it - such as world facts from a `GrammarBlackboard`.
+    /// They are used when a key doesn't match any of the generator's own variables, and take precedence over the grammar's rules.
+    pub fn shared_variables(&self) -> &VariableStore {
+        &self.runtime.shared
+    }
+
+    /// This replaces the variables shared with this generator. They aren't saved along with the generator's own state.
+    pub fn set_shared_variables(&mut self, variables: VariableStore) {
+        self.runtime.shared = variables;
+    }
+
+    /// This gets the shared variables written by the grammar using assignments like `[global.season:winter]`, since they were last taken.
+    /// They are applied to the generator's own shared variables straight away.
+    pub fn global_writes(&self) -> &VariableStore {
+        &self.global_writes
+    }
+
+    /// This takes the shared variables written by the grammar, so they can be applied to the world - the `GrammarBlackboardPlugin`
+    /// does this automatically.
+    pub fn take_global_writes(&mut self) -> VariableStore {
+        std::mem::take(&mut self.global_writes)
+    }
+
+    /// This registers a hook, called for every rule expanded by a successful generation - along with the option it chose,
+    /// and how deeply it was nested. This lets game code react to specific rules, such as spawning an entity whenever `#monster#`
+    /// is expanded, without parsing the generated text. Hooks are called once the generation is complete, in the order the rules were expanded.
+    pub fn on_rule_expanded(&mut self, hook: impl Fn(&RuleExpanded) + Send + Sync + 'static) {
+        self.hooks.add(hook);
+    }
+
+    /// This removes every hook registered using `on_rule_expanded`
+    pub fn clear_hooks(&mut self) {
+        self.hooks.clear();
+    }
+
+    /// This iterates over the tokens currently provided to the grammar
+    pub fn tokens(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.runtime.tokens.iter()
+    }
+
+    /// This gets the index of the option a rule chose most recently - the same value as its `rule._index` capture
+    pub fn chosen_index(&self, rule: &str) -> Option<usize> {
+        self.runtime
+            .selection
+            .last_choice(self.grammar.resolve_alias(rule))
+    }
+
+    /// This gets the name of the option a rule chose most recently - the same value as its `rule._name` capture
+    pub fn chosen_name(&self, rule: &str) -> Option<String> {
+        let index = self.chosen_index(rule)?;
+        Some(
+            self.grammar
+                .option_name(self.grammar.resolve_alias(rule), index),
+        )
+    }
+
+    /// This gets the issues reported by the last generation, such as warnings about shadowed rules
+    pub fn last_issues(&self) -> &[GenerationIssue] {
+        &self.issues
+    }
+
+    /// This gets statistics about the last generation - the number of rules it expanded, how deeply they were nested, and how
+    /// long it took. See `GrammarDiagnosticsPlugin` to report them as bevy diagnostics.
+    pub fn last_generation_stats(&self) -> GenerationStats {
+        self.runtime.stats
+    }
+
+    /// This gets the data emitted by the last generation, keyed by the rule that emitted it. Rules emit data when set up using `set_emitted`.
+    pub fn last_emitted(&self) -> &HashMap<String, String> {
+        &self.emitted
+    }
+
+    /// This generates from the grammar's starting point, explaining why if it couldn't.
+    /// If the generation is rejected by the shadowing policy, none of it's variables are kept.
+    pub fn try_generate<R: GrammarRandomNumberGenerator>(
+        &mut self,
+        rng: &mut R,
+    ) -> Result<String, GenerationIssue> {
+        let key = self.grammar.default_starting_point().clone();
+        self.try_generate_at(&key, rng)
+    }
+
+    /// This generates from the provided key, explaining why if it couldn't.
+    /// If the generation is rejected by the shadowing policy or the content filter, none of it's variables are kept.
+    pub fn try_generate_at<R: GrammarRandomNumberGenerator>(
+        &mut self,
+        key: &str,
+        rng: &mut R,
+    ) -> Result<String, GenerationIssue> {
+        self.generate_filtered(key, rng)
+    }
+
+    /// Generates from the provided key, applying the post-processing and output profile to the result.
+    /// While a trace is being collected, it is replaced by the trace of this generation.
+    fn generate_from<R: GrammarRandomNumberGenerator>(
+        &mut self,
+        key: &str,
+        rng: &mut R,
+    ) -> Result<String, GenerationIssue> {
+        self.check_ready()?;
+        let variable = match self.runtime.variables.select(key, rng) {
+            Some(variable) => Some(variable),
+            None => self.runtime.shared.select(key, rng),
+        };
+        let (index, initial) = match variable {
+            Some((index, value)) => (index, value.clone()),
+            None => self
+                .grammar
+                .select_starting_option(key, &mut self.runtime, rng)?,
+        };
+        let mut trace = self.trace.is_some().then(ExpansionTrace::default);
+        let result = self
+            .expand(
+                &initial,
+                rng,
+                Some((key, Some(index))),
+                trace.as_mut(),
+                None,
+            )
+            .map_err(|(_, issue)| issue)?;
+        if let Some(trace) = trace {
+            self.trace = Some(trace.into_rooted_at(key.to_string(), index));
+        }
+        let result = match self.post_processing.as_ref() {
+            Some(post_processing) => post_processing.apply(&result),
+            None => result,
+        };
+        Ok(match self.output_profile.as_ref() {
+            Some(profile) => profile.apply(&result),
+            None => result,
+        })
+    }
+
+    /// This generates from the provided key, only choosing options whose tags pass the filter. See `StringGenerator::generate_with_filter`.
+    pub fn generate_with_filter<R: GrammarRandomNumberGenerator>(
+        &mut self,
+        key: &str,
+        rng: &mut R,
+        filter: impl Fn(&[&str]) -> bool + Send + Sync + 'static,
+    ) -> Option<String> {
+        self.runtime.filter = Some(OptionFilter::new(filter));
+        let result = self.try_generate_at(key, rng);
+        self.runtime.filter = None;
+        result.ok()
+    }
+
+    /// This expands the provided stream, failing if the shadowing policy rejects it.
+    /// If it is rejected, none of it's variables are kept.
+    pub fn try_expand_from<R: GrammarRandomNumberGenerator>(
+        &mut self,
+        initial: &String,
+        rng: &mut R,
+    ) -> Result<String, GenerationIssue> {
+        self.expand(initial, rng, None, None, None)
+            .map_err(|(_, issue)| issue)
+    }
+
+    /// Checks whether the generator's grammar is ready, which it isn't while it waits for a grammar asset to load
+    fn check_ready(&self) -> Result<(), GenerationIssue> {
+        #[cfg(feature = "asset")]
+        if self.pending.is_some() {
+            return Err(GenerationIssue::NotReady);
+        }
+        Ok(())
+    }
+
+    /// Expands a stream using the generator's state, and keeps any variables the shadowing policy allows - optionally recording a trace.
+    /// If the shadowing policy rejects the expansion, the result is returned alongside the issue.
+    fn expand<R: GrammarRandomNumberGenerator>(
+        &mut self,
+        initial: &String,
+        rng: &mut R,
+        root: Option<(&str, Option<usize>)>,
+        trace: Option<&mut ExpansionTrace<String>>,
+        context: Option<&mut ExpansionContext<TraceryGrammar>>,
+    ) -> Result<String, (String, GenerationIssue)> {
+        self.check_ready().map_err(|issue| (String::new(), issue))?;
+        // Hooks and assertions need a trace of the expansion, even if the caller didn't ask for one
+        let needs_trace =
+            !self.hooks.is_empty() || self.grammar.assertions != AssertionPolicy::Ignore;
+        let mut hook_trace = needs_trace.then(ExpansionTrace::default);
+        let mut trace = trace.or(hook_trace.as_mut());
+        let tmp = context.unwrap_or(&mut self.context).prepare();
+        self.runtime.usage = Default::default();
+        self.runtime.stats = Default::default();
+        #[cfg(feature = "bevy")]
+        let start = bevy::utils::Instant::now();
+        std::mem::swap(&mut tmp.runtime, &mut self.runtime);
+        let result = match trace.as_deref_mut() {
+            Some(trace) => self.grammar.process_stream_traced(initial, rng, tmp, trace),
+            None => self.grammar.process_stream(initial, rng, tmp),
+        };
+        let result = self.grammar.typography().apply(&result);
+        std::mem::swap(&mut tmp.runtime, &mut self.runtime);
+        if root.is_some() {
+            // The root rule was expanded before processing, so everything processed was nested within it
+            let stats = &mut self.runtime.stats;
+            stats.expansions = stats.expansions.saturating_add(1);
+            stats.depth = stats.depth.saturating_add(1);
+        }
+        #[cfg(feature = "bevy")]
+        {
+            self.runtime.stats.duration = start.elapsed();
+        }
+        self.unreported.add(&self.runtime.stats);
+        self.runtime.selection.complete_generation();
+        self.emitted = self
+            .grammar
+            .take_emitted(tmp, root.map(|(key, _)| key), &result);
+        if let Some(trace) = trace.as_deref_mut() {
+            self.grammar.strip_emitted_lookups(trace);
+        }
+        self.issues.clear();
+        let checked = self
+            .grammar
+            .check_budget(&self.runtime, &result)
+            .and_then(|_| self.grammar.check_output_length(&result));
+        if let Err(issue) = checked {
+            self.issues.push(issue.clone());
+            return Err((result, issue));
+        }
+        match self
+            .grammar
+            .check_assertions(trace.as_deref(), root.map(|(key, _)| key))
+        {
+            Ok(issue) => self.issues.extend(issue),
+            Err(issue) => {
+                self.issues.push(issue.clone());
+                return Err((result, issue));
+            }
+        }
+        match self
+            .grammar
+            .apply_shadowing_policy(tmp, &self.runtime.variables)
+        {
+            Ok(issue) => self.issues.extend(issue),
+            Err(issue) => {
+                self.issues.push(issue.clone());
+                return Err((result, issue));
+            }
+        }
+        let global_writes = self
+            .grammar
+            .sandbox
+            .as_ref()
+            .is_none_or(|sandbox| sandbox.allow_global_writes);
+        for key in tmp.keys.iter() {
+            let Some(values) = tmp.rules.get(key) else {
+                continue;
+            };
+            match key.strip_prefix(GLOBAL_PREFIX) {
+                // Sandboxed grammars are checked for global writes, but rules added to them since then aren't
+                Some(_) if !global_writes => {}
+                Some(global) => {
+                    self.runtime.shared.set_var(global, values.iter().cloned());
+                    self.global_writes.set_var(global, values.iter().cloned());
+                }
+                None if self.variable_lifetime == VariableLifetime::Generation => {}
+                None => self
+                    .runtime
+                    .variables
+                    .set_var(key.clone(), values.iter().cloned()),
+            }
+        }
+        if let Some(trace) = trace {
+            self.hooks.notify(root, trace);
+        }
+        Ok(result)
+    }
+}
+
+impl StatefulGenerator<String, String, String, TraceryGrammar> for StatefulStringGenerator {
+    fn generate<R: GrammarRandomNumberGenerator>(&mut self, rng: &mut R) -> Option<String> {
+        let key = self.get_grammar().default_starting_point().clone();
+        self.generate_at(&key, rng)
+    }
+
+    fn generate_at<R: GrammarRandomNumberGenerator>(
+        &mut self,
+        key: &String,
+        rng: &mut R,
+    ) -> Option<String> {
+        self.try_generate_at(key, rng).ok()
+    }
+
+    fn generate_traced<R: GrammarRandomNumberGenerator>(
+        &mut self,
+        rng: &mut R,
+    ) -> Option<(String, ExpansionTrace<String>)> {
+        self.trace = Some(ExpansionTrace::default());
+        let result = self.try_generate(rng);
+        let trace = self.trace.take()?;
+        Some((result.ok()?, trace))
+    }
+
+    fn expand_from<R: GrammarRandomNumberGenerator>(
+        &mut self,
+        initial: &String,
+        rng: &mut R,
+    ) -> String {
+        self.expand(initial, rng, None, None, None)
+            .unwrap_or_else(|(result, _)| result)
+    }
+
+    fn expand_from_with_context<R: GrammarRandomNumberGenerator>(
+        &mut self,
+        initial: &String,
+        rng: &mut R,
+        context: &mut ExpansionContext<TraceryGrammar>,
+    ) -> String {
+        self.expand(initial, rng, None, None, Some(context))
+            .unwrap_or_else(|(result, _)| result)
+    }
+
+    fn set_grammar(&mut self, grammar: &TraceryGrammar) {
+        self.grammar = grammar.clone();
+        self.runtime.selection.clear();
+        self.runtime.variables.clear_vars();
+    }
+
+    fn get_grammar(&self) -> &TraceryGrammar {
+        &self.grammar
+    }
+
+    fn get_grammar_mut(&mut self) -> &mut TraceryGrammar {
+        &mut self.grammar
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::SequenceRng;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    pub fn can_choose_a_single_element_from_a_list() {
+        let rule = TraceryGrammar::new(&[("default", &["One", "Two"])], Some("default"));
+
+        assert_eq!(StringGenerator::generate(&rule, &mut 0).unwrap(), "One");
+        assert_eq!(StringGenerator::generate(&rule, &mut 1).unwrap(), "Two");
+        assert_eq!(StringGenerator::generate(&rule, &mut 2).unwrap(), "Two");
+    }
+
+    #[test]
+    pub fn element_replacer_can_replace_elements_with_other_valid_elements() {
+        let rule = TraceryGrammar::new(
+            &[("default", &["One", "#Two#"]), ("Two", &["Three", "Four"])],
+            Some("default"),
+        );
+        let selection = StringGenerator::generate(&rule, &mut 1);
+        assert_eq!(selection.unwrap(), "Four");
+    }
+
+    #[test]
+    pub fn element_replacer_can_replace_elements_with_other_valid_elements_at_depth() {
+        let rule = TraceryGrammar::new(
+            &[
+                ("default", &["One", "#Two#"]),
+                ("Two", &["Three", "#Four# is up"]),
+                ("Four", &["What"]),
+            ],
+            Some("default"),
+        );
+        let selection = StringGenerator::generate(&rule, &mut 1);
+        assert_eq!(selection.unwrap(), "What is up");
+    }
+
+    #[test]
+    pub fn stateful_can_choose_a_single_element_from_a_list() {
+        let rule = TraceryGrammar::new(&[("default", &["One", "Two"])], Some("default"));
+        let mut generator = StatefulStringGenerator::from_grammar(rule);
+
+        assert_eq!(generator.generate(&mut 0).unwrap(), "One");
+        assert_eq!(generator.generate(&mut 1).unwrap(), "Two");
+        assert_eq!(generator.generate(&mut 2).unwrap(), "Two");
+    }
+
+    #[test]
+    pub fn stateful_element_replacer_can_replace_elements_with_other_valid_elements() {
+        let rule = TraceryGrammar::new(
+            &[("default", &["One", "#Two#"]), ("Two", &["Three", "Four"])],
+            Some("default"),
+        );
+        let selection = StatefulStringGenerator::from_grammar(rule).generate(&mut 1);
+        assert_eq!(selection.unwrap(), "Four");
+    }
+
+    #[test]
+    pub fn stateful_element_replacer_can_replace_elements_with_other_valid_elements_at_depth() {
+        let rule = TraceryGrammar::new(
+            &[
+                ("default", &["One", "#Two#"]),
+                ("Two", &["Three", "#Four#"]),
+                ("Four", &["What"]),
+            ],
+            Some("default"),
+        );
+        let selection = StatefulStringGenerator::from_grammar(rule).generate(&mut 1);
+        assert_eq!(selection.unwrap(), "What");
+    }
+
+    #[test]
+    pub fn stateful_generator_can_set_value_and_use_it_later() {
+        let rule = TraceryGrammar::new(
+            &[
+                ("default", &["One", "[val:#Two#]Hi #val#"]),
+                ("Two", &["Three", "#Four#"]),
+                ("Four", &["What is going on?"]),
+                ("Five", &["Hey there"]),
+            ],
+            Some("default"),
+        );
+        let mut stateful_string_generator = StatefulStringGenerator::from_grammar(rule);
+        let selection = stateful_string_generator.generate(&mut 1);
+        assert_eq!(selection.unwrap(), "Hi What is going on?");
+    }
+
+    #[test]
+    pub fn stateful_generator_can_set_value_at_depth_and_use_it_later() {
+        let rule = TraceryGrammar::new(
+            &[
+                ("default", &["One", "#set#Hi #val#"]),
+                ("set", &["[val:#Two#]"]),
+                ("Two", &["Three", "#Four#"]),
+                ("Four", &["What is going on?"]),
+            ],
+            Some("default"),
+        );
+        let mut stateful_string_generator = StatefulStringGenerator::from_grammar(rule);
+        let selection = stateful_string_generator.generate(&mut 1);
+        assert_eq!(selection.unwrap(), "Hi What is going on?");
+    }
+
+    #[test]
+    pub fn stateful_generator_can_reset_a_value_at_depth_and_use_the_new_value() {
+        let rule = TraceryGrammar::new(
+            &[
+                ("default", &["One", "#set#Hi #val#"]),
+                ("set", &["[val:#Two#]"]),
+                ("set_2", &["[val:#Five#]"]),
+                ("Two", &["Three", "#Four# here"]),
+                ("Four", &["What is going on"]),
+                ("Five", &["Hey there"]),
+            ],
+            Some("default"),
+        );
+        let mut stateful_string_generator = StatefulStringGenerator::from_grammar(rule);
+        let selection = stateful_string_generator.generate(&mut 1);
+        assert_eq!(selection.unwrap(), "Hi What is going on here");
+        let selection =
+            stateful_string_generator.expand_from(&"#set_2#Oh #val#".to_string(), &mut 1);
+        assert_eq!(selection, "Oh Hey there");
+    }
+
+    #[test]
+    pub fn generators_capture_the_options_rules_chose() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["#mood# #mood._name.capitalize# #mood._index#"]),
+                ("mood", &["{name:calm}relaxed", "angry"]),
+                ("reply", &["#mood._name#"]),
+            ],
+            None,
+        );
+        assert_eq!(
+            StringGenerator::generate(&grammar, &mut 0).unwrap(),
+            "relaxed Calm 0"
+        );
+        assert_eq!(
+            StringGenerator::generate(&grammar, &mut 1).unwrap(),
+            "angry 1 1"
+        );
+
+        let mut generator = StatefulStringGenerator::clone_grammar(&grammar);
+        assert_eq!(generator.chosen_index("mood"), None);
+        generator.generate(&mut 0);
+        assert_eq!(generator.chosen_index("mood"), Some(0));
+        assert_eq!(generator.chosen_name("mood"), Some("calm".to_string()));
+        assert_eq!(generator.chosen_index("origin"), Some(0));
+        assert_eq!(
+            generator.generate_at(&"reply".to_string(), &mut 1).unwrap(),
+            "calm"
+        );
+    }
+
+    #[test]
+    pub fn batches_are_reproducible_from_their_seed() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["#name# the #title#"]),
+                ("name", &["Mia", "Arjun", "Noor", "Tomas"]),
+                ("title", &["Bold", "Wise", "Quiet"]),
+            ],
+            None,
+        );
+        let batch = StringGenerator::generate_batch(&grammar, 20, 42);
+        assert_eq!(batch.len(), 20);
+        assert_eq!(batch, StringGenerator::generate_batch(&grammar, 20, 42));
+        assert_ne!(batch, StringGenerator::generate_batch(&grammar, 20, 43));
+        assert!(StringGenerator::generate_batch(&TraceryGrammar::empty(), 5, 42).is_empty());
+    }
+
+    #[test]
+    pub fn unique_outputs_are_rerolled_until_enough_are_found() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["The #quest#"]),
+                ("quest", &["Lost Crown", "Iron Oath", "Last Ember"]),
+            ],
+            None,
+        );
+        let mut rng = SeededRng::new(7);
+        let quests = StringGenerator::generate_unique("origin", &grammar, 2, 50, &mut rng);
+        assert_eq!(quests.len(), 2);
+        assert_ne!(quests[0], quests[1]);
+
+        let mut all = StringGenerator::generate_unique("origin", &grammar, 5, 100, &mut rng);
+        assert_eq!(all.len(), 3);
+        all.sort();
+        assert_eq!(
+            all,
+            vec!["The Iron Oath", "The Last Ember", "The Lost Crown"]
+        );
+        assert!(StringGenerator::generate_unique("missing", &grammar, 2, 10, &mut rng).is_empty());
+    }
+
+    #[test]
+    pub fn weighted_starting_points_choose_where_generation_starts() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["A common day"]),
+                ("rare_origin", &["A rare day"]),
+            ],
+            None,
+        )
+        .with_starting_points([("origin", 4), ("rare_origin", 1)]);
+        let mut last = |len: usize| len - 1;
+        assert_eq!(
+            StringGenerator::generate_weighted_start(&grammar, &mut last),
+            Some("A rare day".to_string())
+        );
+        assert_eq!(
+            StringGenerator::generate_weighted_start(&grammar, &mut 3),
+            Some("A common day".to_string())
+        );
+        let mut generator = StatefulStringGenerator::from_grammar(grammar);
+        assert_eq!(
+            generator.generate_weighted_start(&mut last),
+            Some("A rare day".to_string())
+        );
+        generator.get_grammar_mut().set_starting_points::<&str>([]);
+        assert_eq!(
+            generator.generate_weighted_start(&mut last),
+            Some("A common day".to_string())
+        );
+    }
+
+    #[test]
+    pub fn separators_are_placed_between_the_pieces_of_expansions() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["#entry##entry##entry#"]),
+                ("entry", &["#time#"]),
+                ("time", &["dawn", "noon", "dusk"]),
+            ],
+            None,
+        )
+        .with_separator("\n");
+        assert_eq!(
+            StringGenerator::generate(&grammar, &mut SequenceRng::new([0, 1, 2])),
+            Some("dusk\nnoon\ndawn".to_string())
+        );
+        let grammar = grammar.with_separator(",");
+        assert_eq!(
+            StringGenerator::generate(&grammar, &mut SequenceRng::new([0, 1, 2])),
+            Some("dusk,noon,dawn".to_string())
+        );
+    }
+
+    #[test]
+    pub fn linked_rules_choose_matching_options() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["#capital# is the capital of #country#"]),
+                ("country", &["France", "Japan", "Peru"]),
+                ("capital", &["Paris", "Tokyo", "Lima"]),
+            ],
+            None,
+        )
+        .with_link("country", "countries")
+        .with_link("capital", "countries");
+        for seed in 0..6 {
+            let mut rng = SeededRng::new(seed);
+            let result = StringGenerator::generate(&grammar, &mut rng).unwrap();
+            assert!(
+                [
+                    "Paris is the capital of France",
+                    "Tokyo is the capital of Japan",
+                    "Lima is the capital of Peru"
+                ]
+                .contains(&result.as_str()),
+                "{result}"
+            );
+        }
+
+        let mut generator = StatefulStringGenerator::from_grammar(grammar);
+        assert_eq!(
+            generator.generate(&mut 2).unwrap(),
+            "Lima is the capital of Peru"
+        );
+        assert_eq!(
+            generator.generate(&mut 0).unwrap(),
+            "Paris is the capital of France"
+        );
+    }
+
+    #[test]
+    pub fn distinct_rules_avoid_repeats_within_a_generation() {
+        let grammar = TraceryGrammar::new(
+            &[("origin", &["#adj# and #adj#"]), ("adj", &["tall", "dark"])],
+            None,
+        );
+        assert_eq!(
+            StringGenerator::generate(&grammar, &mut 0).unwrap(),
+            "tall and tall"
+        );
+        let grammar = grammar.with_distinct("adj");
+        assert_eq!(
+            StringGenerator::generate(&grammar, &mut 0).unwrap(),
+            "tall and dark"
+        );
+        assert_eq!(
+            StringGenerator::expand_from(&"#adj# #adj# #adj#".to_string(), &grammar, &mut 1),
+            "dark tall dark"
+        );
+
+        let mut generator = StatefulStringGenerator::from_grammar(grammar);
+        assert_eq!(generator.generate(&mut 0).unwrap(), "tall and dark");
+        assert_eq!(generator.generate(&mut 0).unwrap(), "tall and dark");
+    }
+
+    #[test]
+    pub fn excluded_options_are_not_chosen_together() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["#creature# faces #obstacle#"]),
+                ("reversed", &["#obstacle# face #creature#"]),
+                ("creature", &["lion", "tiger"]),
+                ("obstacle", &["{name:monsters}the monsters", "a river"]),
+            ],
+            None,
+        )
+        .with_exclusion(Exclusion::new("creature", "lion", "obstacle", "monsters"));
+        assert_eq!(
+            StringGenerator::generate(&grammar, &mut 0).unwrap(),
+            "lion faces a river"
+        );
+        assert_eq!(
+            StringGenerator::generate_at(&"reversed".to_string(), &grammar, &mut 0).unwrap(),
+            "the monsters face tiger"
+        );
+    }
+
+    #[test]
+    pub fn options_can_be_filtered_by_their_tags() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["A #monster# blocks the #path#"]),
+                (
+                    "monster",
+                    &[
+                        "{tags:fantasy,dangerous}dragon",
+                        "{tags:scifi}robot",
+                        "{tags:fantasy}pixie",
+                    ],
+                ),
+                ("path", &["road", "{tags:scifi}corridor"]),
+            ],
+            None,
+        );
+        assert_eq!(
+            StringGenerator::generate_with_filter("origin", &grammar, &mut 1, |tags| {
+                !tags.contains(&"scifi")
+            })
+            .unwrap(),
+            "A pixie blocks the road"
+        );
+        let mut generator = StatefulStringGenerator::from_grammar(grammar);
+        assert_eq!(
+            generator
+                .generate_with_filter("origin", &mut 0, |tags| !tags.contains(&"fantasy"))
+                .unwrap(),
+            "A robot blocks the road"
+        );
+        assert_eq!(
+            generator.generate(&mut 0).unwrap(),
+            "A dragon blocks the road"
+        );
+    }
+
+    #[cfg(feature = "tracery_compat")]
+    #[test]
+    pub fn tagged_options_can_be_written_as_objects() {
+        let grammar: TraceryGrammar = serde_json::from_str(
+            r#"{"rules": {"origin": [{"text": "dragon", "tags": ["fantasy", "dangerous"]}, "robot"]}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            grammar.get_rule_options(&"origin".to_string()),
+            Some(&vec![
+                "{tags:fantasy,dangerous}dragon".to_string(),
+                "robot".to_string()
+            ])
+        );
+    }
+
+    #[cfg(feature = "tracery_compat")]
+    #[test]
+    pub fn plain_tracery_grammars_can_be_deserialized() {
+        let grammar: TraceryGrammar = serde_json::from_str(
+            r##"{
+                "origin": "#animal.capitalize# says hi",
+                "animal": ["cat", {"text": "dragon", "tags": ["fantasy"]}],
+                "modifiers": {"shout": {"suffix": "!"}}
+            }"##,
+        )
+        .unwrap();
+        assert_eq!(
+            StringGenerator::generate(&grammar, &mut 0).unwrap(),
+            "Cat says hi"
+        );
+        assert_eq!(grammar.rule_keys().len(), 2);
+        assert!(!grammar.has_rule(&"modifiers".to_string()));
+
+        let grammar: TraceryGrammar = serde_json::from_str(
+            r##"{"rules": {"start": "#name#", "name": ["Mia", "Sam"]}, "starting_point": "start"}"##,
+        )
+        .unwrap();
+        assert_eq!(StringGenerator::generate(&grammar, &mut 1).unwrap(), "Sam");
+    }
+
+    #[cfg(feature = "tracery_compat")]
+    #[test]
+    pub fn grammars_with_rules_report_invalid_settings() {
+        // A grammar holding it's rules under `rules` is never read as a plain grammar, so the invalid setting is reported
+        let error = serde_json::from_str::<TraceryGrammar>(
+            r##"{"rules": {"origin": ["hi"]}, "max_depth": "deep"}"##,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("invalid type"), "{error}");
+        assert!(serde_json::from_str::<TraceryGrammar>(r##"{"rules": {"origin": 5}}"##).is_err());
+    }
+
+    #[cfg(feature = "tracery_compat")]
+    #[test]
+    pub fn rules_that_arent_options_fail_to_load() {
+        let error = serde_json::from_str::<TraceryGrammar>(
+            r##"{"origin": "#animal#", "animal": {"name": "cat"}}"##,
+        )
+        .unwrap_err();
+        assert!(
+            error.to_string().contains("invalid rule `animal`"),
+            "{error}"
+        );
+        assert!(serde_json::from_str::<TraceryGrammar>(r##"{"origin": ["hi", 5]}"##).is_err());
+        assert!(serde_json::from_str::<TraceryGrammar>(r##"{"origin": true}"##).is_err());
+    }
+
+    #[test]
+    pub fn inline_alternatives_act_like_anonymous_rules() {
+        let grammar = TraceryGrammar::new(
+            &[
+                (
+                    "origin",
+                    &["there was a (tiny|huge|#odd#) #creature# (in the woods)"],
+                ),
+                ("odd", &["strange"]),
+                ("creature", &["frog"]),
+            ],
+            None,
+        );
+        assert_eq!(
+            StringGenerator::generate(&grammar, &mut 0).unwrap(),
+            "there was a tiny frog (in the woods)"
+        );
+        assert_eq!(
+            StringGenerator::generate(&grammar, &mut 2).unwrap(),
+            "there was a strange frog (in the woods)"
+        );
+        assert_eq!(
+            StringGenerator::expand_from(
+                &"([a:one]#a#|x) and (b|[c|d]y)".to_string(),
+                &grammar,
+                &mut 0
+            ),
+            "one and b"
+        );
+        assert_eq!(
+            StringGenerator::expand_from(&"(unclosed|".to_string(), &grammar, &mut 0),
+            "(unclosed|"
+        );
+    }
+
+    #[test]
+    pub fn aliases_expand_the_rule_they_refer_to() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["#colour# and #hue#"]),
+                ("color", &["red", "blue"]),
+            ],
+            None,
+        )
+        .with_alias("colour", "color")
+        .with_alias("hue", "colour")
+        .with_alias("loop", "cycle")
+        .with_alias("cycle", "loop");
+        assert_eq!(
+            StringGenerator::generate(&grammar, &mut 1).unwrap(),
+            "blue and blue"
+        );
+        assert_eq!(
+            StringGenerator::generate_at(&"colour".to_string(), &grammar, &mut 0).unwrap(),
+            "red"
+        );
+        assert!(grammar.has_rule(&"hue".to_string()));
+        assert!(!grammar.has_rule(&"loop".to_string()));
+    }
+
+    #[test]
+    pub fn previews_list_missing_rules_as_placeholders() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["#greeting.capitalize#, #name#!"]),
+                ("name", &["Sam"]),
+            ],
+            None,
+        );
+        let preview = StringGenerator::preview("#origin#", &grammar, &mut 0);
+        assert_eq!(preview.text, "greeting, Sam!");
+        assert_eq!(
+            preview.placeholders,
+            vec![Placeholder {
+                key: "greeting".to_string(),
+                span: 0..8
+            }]
+        );
+    }
+
+    #[test]
+    pub fn hooks_are_called_for_each_expanded_rule() {
+        let mut generator = StatefulStringGenerator::new(
+            &[
+                ("origin", &["A #monster.capitalize# appears"]),
+                ("monster", &["goblin", "#size# troll"]),
+                ("size", &["huge"]),
+            ],
+            None,
+        );
+        let expanded = Arc::new(Mutex::new(vec![]));
+        let recorded = expanded.clone();
+        generator.on_rule_expanded(move |rule| {
+            recorded
+                .lock()
+                .unwrap()
+                .push((rule.key.to_string(), rule.chosen, rule.depth));
+        });
+        assert_eq!(generator.generate(&mut 1).unwrap(), "A Huge troll appears");
+        assert_eq!(
+            *expanded.lock().unwrap(),
+            vec![
+                ("origin".to_string(), Some(0), 0),
+                ("monster".to_string(), Some(1), 1),
+                ("size".to_string(), Some(0), 2),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn rules_can_emit_their_expansions_as_data() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["#hero.capitalize# went to #place#"]),
+                ("hero", &["#title# sam"]),
+                ("title", &["sir"]),
+                ("place", &["the [tmp:old]#tmp# mill"]),
+            ],
+            None,
+        )
+        .with_emitted("hero")
+        .with_emitted("place");
+        let (result, data) = StringGenerator::generate_with_data(&grammar, &mut 0).unwrap();
+        assert_eq!(result, "Sir sam went to the old mill");
+        assert_eq!(data.get("hero").map(String::as_str), Some("sir sam"));
+        assert_eq!(data.get("place").map(String::as_str), Some("the old mill"));
+        assert_eq!(data.len(), 2);
+
+        let mut generator = StatefulStringGenerator::from_grammar(grammar.with_emitted("origin"));
+        let (_, trace) = generator.generate_traced(&mut 0).unwrap();
+        assert_eq!(
+            generator.last_emitted().get("origin").map(String::as_str),
+            Some("Sir sam went to the old mill")
+        );
+        assert!(trace
+            .nodes()
+            .all(|(_, node)| !node.rule.contains(EMITTED_CAPTURE)));
+        assert!(!generator.variables().contains("hero._emitted"));
+    }
+
+    #[test]
+    pub fn stateful_generator_lists_its_variables() {
+        let mut generator = StatefulStringGenerator::new(
+            &[("origin", &["[name:#names#]#name#"]), ("names", &["Mia"])],
+            None,
+        );
+        assert!(generator.variables().is_empty());
+        generator.generate(&mut 0);
+        assert_eq!(
+            generator.variables().iter().collect::<Vec<_>>(),
+            vec![(&"name".to_string(), &vec!["Mia".to_string()])]
+        );
+    }
+
+    #[test]
+    pub fn generation_scoped_variables_never_change_later_generations() {
+        let rules: &[(&str, &[&str])] = &[
+            ("origin", &["[animal:#pet#]I have a #animal#"]),
+            ("animal", &["wolf", "bear"]),
+            ("pet", &["cat", "dog"]),
+            ("wild", &["a #animal# roams"]),
+        ];
+        let grammar = TraceryGrammar::new(rules, None);
+        let mut generator = StatefulStringGenerator::from_grammar(grammar.clone())
+            .with_variable_lifetime(VariableLifetime::Generation);
+        generator.variables_mut().set_var("owner", ["Mia"]);
+        assert_eq!(generator.generate(&mut 1).unwrap(), "I have a dog");
+        assert_eq!(
+            generator.generate_at(&"wild".to_string(), &mut 1).unwrap(),
+            "a bear roams"
+        );
+        assert!(!generator.variables().contains("animal"));
+        assert!(generator.variables().contains("owner"));
+        assert_eq!(
+            generator
+                .get_grammar()
+                .get_rule_options(&"animal".to_string()),
+            grammar.get_rule_options(&"animal".to_string())
+        );
+
+        generator.set_variable_lifetime(VariableLifetime::Persistent);
+        generator.generate(&mut 1);
+        assert_eq!(
+            generator.generate_at(&"wild".to_string(), &mut 1).unwrap(),
+            "a dog roams"
+        );
+    }
+
+    #[test]
+    pub fn frozen_generators_keep_their_variables_as_rules() {
+        let mut generator = StatefulStringGenerator::new(
+            &[
+                ("origin", &["#name# waves"]),
+                ("setup", &["[name:#names#]"]),
+                ("names", &["Ash", "Birch"]),
+                ("name", &["nobody"]),
+            ],
+            None,
+        );
+        generator.get_grammar_mut().set_weights("name", [1]);
+        generator.generate_at(&"setup".to_string(), &mut 1).unwrap();
+        let grammar = generator.freeze();
+        assert_eq!(
+            grammar.get_rule_options(&"name".to_string()),
+            Some(&vec!["Birch".to_string()])
+        );
+        assert!(grammar.rule_settings("name").is_none());
+        assert_eq!(
+            StringGenerator::generate(&grammar, &mut 0).unwrap(),
+            "Birch waves"
+        );
+    }
+
+    #[test]
+    pub fn expansion_contexts_are_cleared_between_expansions() {
+        let mut generator = StatefulStringGenerator::new(
+            &[("origin", &["#name#"]), ("names", &["Mia", "Sam"])],
+            None,
+        );
+        let mut context = ExpansionContext::new(TraceryGrammar::empty());
+        let result = generator.expand_from_with_context(
+            &"[name:#names#]#name#".to_string(),
+            &mut 0,
+            &mut context,
+        );
+        assert_eq!(result, "Mia");
+        assert!(context.temporary_grammar().has_rule(&"name".to_string()));
+        let result = generator.expand_from_with_context(
+            &"[pet:#names#]#name#".to_string(),
+            &mut 1,
+            &mut context,
+        );
+        assert_eq!(result, "Mia");
+        assert!(!context.temporary_grammar().has_rule(&"name".to_string()));
+        assert!(context.temporary_grammar().has_rule(&"pet".to_string()));
+        assert_eq!(generator.generate(&mut 0).unwrap(), "Mia");
+    }
+
+    #[test]
+    pub fn shadowing_policies_control_variables_assigned_over_base_rules() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["[animal:#pet#]I have a #animal#"]),
+                ("animal", &["wolf"]),
+                ("pet", &["cat"]),
+            ],
+            None,
+        );
+
+        let mut generator = StatefulStringGenerator::clone_grammar(&grammar);
+        assert_eq!(generator.generate(&mut 0).unwrap(), "I have a cat");
+        assert!(generator.last_issues().is_empty());
+        assert_eq!(
+            generator.expand_from(&"#animal#".to_string(), &mut 0),
+            "cat"
+        );
+
+        let mut generator = StatefulStringGenerator::from_grammar(
+            grammar.clone().with_shadowing_policy(ShadowingPolicy::Warn),
+        );
+        assert_eq!(generator.generate(&mut 0).unwrap(), "I have a cat");
+        assert_eq!(
+            generator.last_issues(),
+            &[GenerationIssue::ShadowedRules(vec!["animal".to_string()])]
+        );
+        generator.generate(&mut 0);
+        assert!(generator.last_issues().is_empty());
+
+        let mut generator = StatefulStringGenerator::from_grammar(
+            grammar
+                .clone()
+                .with_shadowing_policy(ShadowingPolicy::Scoped),
+        );
+        assert_eq!(generator.generate(&mut 0).unwrap(), "I have a cat");
+        assert_eq!(
+            generator.expand_from(&"#animal#".to_string(), &mut 0),
+            "wolf"
+        );
+
+        let grammar = grammar.with_shadowing_policy(ShadowingPolicy::Error);
+        let mut generator = StatefulStringGenerator::clone_grammar(&grammar);
+        assert_eq!(
+            generator.try_generate(&mut 0),
+            Err(GenerationIssue::ShadowedRules(vec!["animal".to_string()]))
+        );
+        assert_eq!(
+            generator.expand_from(&"#animal#".to_string(), &mut 0),
+            "wolf"
+        );
+        assert!(StringGenerator::generate(&grammar, &mut 0).is_none());
+        assert_eq!(
+            StringGenerator::try_generate_at("missing", &grammar, &mut 0),
+            Err(GenerationIssue::UnknownRule("missing".to_string()))
+        );
+    }
+
+    #[test]
+    pub fn stateful_generator_honours_rule_selection_modes() {
         let rule = TraceryGrammar::new(
             &[
-                ("default", &["One", "#set#Hi #val#"]),
-                ("set", &["[val:#Two#]"]),
-                ("Two", &["Three", "#Four#"]),
-                ("Four", &["What is going on?"]),
+                ("default", &["#greeting#, #greeting#"]),
+                ("greeting", &["Hi", "Hello", "Hey"]),
             ],
             Some("default"),
+        )
+        .with_selection_mode("greeting", SelectionMode::Cycle);
+        let mut generator = StatefulStringGenerator::from_grammar(rule);
+        assert_eq!(generator.generate(&mut 0).unwrap(), "Hi, Hello");
+        assert_eq!(generator.generate(&mut 0).unwrap(), "Hey, Hi");
+    }
+
+    #[test]
+    pub fn stateful_generator_avoids_immediate_repeats_across_generations() {
+        let rule = TraceryGrammar::new(&[("default", &["One", "Two"])], Some("default"))
+            .with_selection_mode("default", SelectionMode::NoImmediateRepeat);
+        let mut generator = StatefulStringGenerator::from_grammar(rule);
+        assert_eq!(generator.generate(&mut 0).unwrap(), "One");
+        assert_eq!(generator.generate(&mut 0).unwrap(), "Two");
+        assert_eq!(generator.generate(&mut 0).unwrap(), "One");
+    }
+
+    #[cfg(feature = "json_source")]
+    #[test]
+    pub fn stateful_generator_plays_sequences_once() {
+        let grammar = TraceryGrammar::from_json_str(
+            r#"{
+                "rules": {"hint": ["Press A to jump", "Hold A to jump higher", "Good luck!"]},
+                "starting_point": "hint",
+                "settings": {"hint": {"selection": "Sequence"}}
+            }"#,
+        )
+        .unwrap();
+        let mut generator = StatefulStringGenerator::from_grammar(grammar);
+        let hints = (0..4)
+            .map(|_| generator.generate(&mut 0).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            hints,
+            vec![
+                "Press A to jump",
+                "Hold A to jump higher",
+                "Good luck!",
+                "Good luck!"
+            ]
         );
-        let mut stateful_string_generator = StatefulStringGenerator(rule);
-        let selection = stateful_string_generator.generate(&mut 1);
-        assert_eq!(selection.unwrap(), "Hi What is going on?");
     }
 
     #[test]
-    pub fn stateful_generator_can_reset_a_value_at_depth_and_use_the_new_value() {
+    pub fn stateful_generator_guards_options_by_generation_count() {
         let rule = TraceryGrammar::new(
             &[
-                ("default", &["One", "#set#Hi #val#"]),
-                ("set", &["[val:#Two#]"]),
-                ("set_2", &["[val:#Five#]"]),
-                ("Two", &["Three", "#Four# here"]),
-                ("Four", &["What is going on"]),
-                ("Five", &["Hey there"]),
+                ("greeting", &["#welcome# (visit #gen_count#)"]),
+                (
+                    "welcome",
+                    &[
+                        "{if:gen_count<2}What do you want?",
+                        "{if:gen_count>=2}Welcome back, friend!",
+                    ],
+                ),
+            ],
+            Some("greeting"),
+        );
+        let mut generator = StatefulStringGenerator::from_grammar(rule);
+        assert_eq!(
+            generator.generate(&mut 1).unwrap(),
+            "What do you want? (visit 0)"
+        );
+        assert_eq!(
+            generator.generate(&mut 1).unwrap(),
+            "What do you want? (visit 1)"
+        );
+        assert_eq!(
+            generator.generate(&mut 0).unwrap(),
+            "Welcome back, friend! (visit 2)"
+        );
+        assert_eq!(generator.generation_count(), 3);
+    }
+
+    #[test]
+    pub fn stateful_generator_skips_options_on_cooldown() {
+        let rule = TraceryGrammar::new(
+            &[
+                ("default", &["{cooldown:1}Hello", "Hi", "{cooldown:5s}Hey"]),
+                ("twice", &["#default#, #default#"]),
             ],
             Some("default"),
         );
-        let mut stateful_string_generator = StatefulStringGenerator(rule);
-        let selection = stateful_string_generator.generate(&mut 1);
-        assert_eq!(selection.unwrap(), "Hi What is going on here");
-        let selection =
-            stateful_string_generator.expand_from(&"#set_2#Oh #val#".to_string(), &mut 1);
-        assert_eq!(selection, "Oh Hey there");
+        let mut generator = StatefulStringGenerator::from_grammar(rule);
+        generator.set_elapsed_seconds(0.);
+        assert_eq!(generator.generate(&mut 0).unwrap(), "Hello");
+        assert_eq!(generator.generate(&mut 0).unwrap(), "Hi");
+        assert_eq!(generator.generate(&mut 2).unwrap(), "Hey");
+        assert_eq!(generator.generate(&mut 2).unwrap(), "Hi");
+        generator.set_elapsed_seconds(5.);
+        assert_eq!(generator.generate(&mut 2).unwrap(), "Hey");
+        assert_eq!(
+            generator.generate_at(&"twice".to_string(), &mut 0).unwrap(),
+            "Hello, Hi"
+        );
+    }
+
+    #[test]
+    pub fn stateful_generator_falls_back_on_tokens() {
+        let rule = TraceryGrammar::new(
+            &[
+                ("default", &["It is #time_of_day#, on #day#"]),
+                ("day", &["Monday"]),
+            ],
+            Some("default"),
+        );
+        let mut generator = StatefulStringGenerator::from_grammar(rule);
+        generator.set_token("time_of_day", "dusk");
+        generator.set_token("day", "Sunday");
+        assert_eq!(generator.generate(&mut 0).unwrap(), "It is dusk, on Monday");
+        assert_eq!(generator.get_token("time_of_day").unwrap(), "dusk");
+        generator.set_token("time_of_day", "dawn");
+        assert_eq!(generator.generate(&mut 0).unwrap(), "It is dawn, on Monday");
+        assert_eq!(generator.remove_token("time_of_day").unwrap(), "dawn");
+    }
+
+    #[test]
+    pub fn in_tag_actions_only_apply_while_the_tag_is_expanded() {
+        let rule = TraceryGrammar::new(
+            &[
+                (
+                    "default",
+                    &["[hero:Mia]#[hero:#name#][pet|#animal#]story# Then #hero# was alone."],
+                ),
+                ("name", &["Arjun"]),
+                ("animal", &["an owl", "a fox"]),
+                ("story", &["#hero# left with #pet#."]),
+            ],
+            Some("default"),
+        );
+        assert_eq!(
+            StringGenerator::generate(&rule, &mut 1).unwrap(),
+            "Arjun left with a fox. Then Mia was alone."
+        );
+        let mut generator = StatefulStringGenerator::from_grammar(rule);
+        assert_eq!(
+            generator.generate(&mut 0).unwrap(),
+            "Arjun left with an owl. Then Mia was alone."
+        );
+        assert_eq!(
+            generator.expand_from(&"#hero##pet#".to_string(), &mut 0),
+            "Mia#pet#"
+        );
+    }
+
+    #[test]
+    pub fn generators_trace_the_options_chosen_for_each_rule() {
+        let rule = TraceryGrammar::new(
+            &[
+                ("default", &["One", "#[pet:#animal#]story#"]),
+                ("animal", &["owl", "fox"]),
+                ("story", &["A #pet.capitalize# met #someone#"]),
+            ],
+            Some("default"),
+        );
+        let (result, trace) = StringGenerator::generate_traced(&rule, &mut 1).unwrap();
+        assert_eq!(result, "A Fox met #someone#");
+        assert_eq!(
+            trace.to_string(),
+            "default -> 1\n  animal -> 1\n  story -> 0\n    pet.capitalize -> 0\n    someone -> unresolved\n"
+        );
+
+        let mut generator = StatefulStringGenerator::from_grammar(rule);
+        let (_, stateful_trace) = generator.generate_traced(&mut 1).unwrap();
+        assert_eq!(stateful_trace, trace);
+        assert_eq!(generator.generation_count(), 1);
+    }
+
+    #[test]
+    pub fn stateful_traces_are_recorded_while_generating_normally() {
+        let grammar = TraceryGrammar::new(
+            &[("origin", &["Dr. #animal#"]), ("animal", &["rat", "owl"])],
+            None,
+        );
+        let mut generator = StatefulStringGenerator::from_grammar(grammar)
+            .with_output_profile(OutputProfile::new().with_abbreviations([("Dr.", "Doctor")]))
+            .with_content_filter(
+                ContentFilter::new()
+                    .with_banned_words(["rat"])
+                    .with_policy(FilterPolicy::Reroll(100)),
+            );
+        for seed in 0..5 {
+            let (result, trace) = generator
+                .generate_traced(&mut SeededRng::new(seed))
+                .unwrap();
+            assert_eq!(result, "Doctor owl");
+            assert_eq!(trace.to_string(), "origin -> 0\n  animal -> 1\n");
+        }
+    }
+
+    #[test]
+    pub fn exponential_expansions_stop_once_the_queue_is_full() {
+        let grammar = TraceryGrammar::new(&[("origin", &["#a#"]), ("a", &["#a##a#"])], None);
+        let result = grammar.breadth_first_processing(
+            &"#a#".to_string(),
+            &mut TraceryGrammar::empty(),
+            &mut 0,
+        );
+        let tokens = result.matches("#a#").count();
+        assert!(tokens > grammar.max_queue_size());
+        assert!(tokens <= 2 * grammar.max_queue_size());
+        let result = StringGenerator::generate(&grammar, &mut 0).unwrap();
+        assert_eq!(result.matches("#a#").count(), grammar.max_expansions() + 1);
     }
 
     const RULES: &[(&str, &[&str])] = &[
@@ -0,0 +1,232 @@
+#[cfg(feature = "bevy")]
+use bevy::prelude::Resource;
+
+use super::layout::strip_icons;
+
+/// A transform applied to generated text by an `OutputProfile`, such as a plain language simplification
+pub type OutputTransform = fn(&str) -> String;
+
+/// The abbreviations expanded by the screen reader profile
+pub const COMMON_ABBREVIATIONS: &[(&str, &str)] = &[
+    ("e.g.", "for example"),
+    ("i.e.", "that is"),
+    ("etc.", "and so on"),
+    ("approx.", "approximately"),
+    ("vs.", "versus"),
+    ("Dr.", "Doctor"),
+    ("Mr.", "Mister"),
+    ("Mrs.", "Missus"),
+    ("Capt.", "Captain"),
+    ("Sgt.", "Sergeant"),
+];
+
+/// This is a profile of transforms applied to generated text once it is complete, so the same grammars can serve different
+/// variants of their output - such as screen reader friendly text, with abbreviations expanded and decorative symbols removed.
+/// It is selected at generation time, using `StringGenerator::try_generate_for_profile` or `StatefulStringGenerator::set_output_profile`.
+/// With the `bevy` feature, it can be inserted as a resource holding the profile the player chose.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "bevy", derive(Resource))]
+pub struct OutputProfile {
+    strip_decorative: bool,
+    abbreviations: Vec<(String, String)>,
+    transforms: Vec<OutputTransform>,
+}
+
+impl OutputProfile {
+    /// Creates a profile that leaves the text unchanged
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a profile for screen readers, which removes decorative unicode and expands the `COMMON_ABBREVIATIONS`
+    pub fn screen_reader() -> Self {
+        Self::new()
+            .with_decorative_unicode_removed()
+            .with_abbreviations(COMMON_ABBREVIATIONS.iter().copied())
+    }
+
+    /// Removes decorative unicode - such as emoji, dingbats and box drawing characters - and inline icons like `[icon:sword]`,
+    /// returning the updated profile. Spaces left doubled by the removal are collapsed.
+    pub fn with_decorative_unicode_removed(mut self) -> Self {
+        self.strip_decorative = true;
+        self
+    }
+
+    /// Expands abbreviations into the words they stand for, returning the updated profile. Abbreviations are only expanded as whole words,
+    /// and one starting with a capital letter - like at the start of a sentence - has it's expansion capitalized.
+    pub fn with_abbreviations<K: Into<String>, V: Into<String>>(
+        mut self,
+        abbreviations: impl IntoIterator<Item = (K, V)>,
+    ) -> Self {
+        self.abbreviations.extend(
+            abbreviations
+                .into_iter()
+                .map(|(abbreviation, expansion)| (abbreviation.into(), expansion.into())),
+        );
+        self
+    }
+
+    /// Adds a transform run after the built in ones, such as a plain language simplification, returning the updated profile.
+    /// Transforms run in the order they were added.
+    pub fn with_transform(mut self, transform: OutputTransform) -> Self {
+        self.transforms.push(transform);
+        self
+    }
+
+    /// Applies the profile to generated text
+    pub fn apply(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        if self.strip_decorative {
+            text = strip_decorative_unicode(&strip_icons(&text));
+        }
+        if !self.abbreviations.is_empty() {
+            text = self.expand_abbreviations(&text);
+        }
+        for transform in self.transforms.iter() {
+            text = transform(&text);
+        }
+        text
+    }
+
+    fn expand_abbreviations(&self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut index = 0;
+        'text: while index < text.len() {
+            let rest = &text[index..];
+            let at_word_start = !text[..index]
+                .chars()
+                .next_back()
+                .is_some_and(char::is_alphanumeric);
+            if at_word_start {
+                for (abbreviation, expansion) in self.abbreviations.iter() {
+                    let Some(expansion) = match_abbreviation(rest, abbreviation, expansion) else {
+                        continue;
+                    };
+                    result.push_str(&expansion);
+                    index += abbreviation.len();
+                    // A trailing period also ends the sentence when nothing follows it
+                    if abbreviation.ends_with('.') && index == text.len() {
+                        result.push('.');
+                    }
+                    continue 'text;
+                }
+            }
+            let Some(character) = rest.chars().next() else {
+                break;
+            };
+            result.push(character);
+            index += character.len_utf8();
+        }
+        result
+    }
+}
+
+/// Matches an abbreviation at the start of the text, as it's written or with a leading capital letter, returning it's expansion
+fn match_abbreviation(text: &str, abbreviation: &str, expansion: &str) -> Option<String> {
+    let candidate = text.get(..abbreviation.len())?;
+    let ends_word = !text[abbreviation.len()..]
+        .chars()
+        .next()
+        .is_some_and(char::is_alphanumeric);
+    if !ends_word {
+        return None;
+    }
+    if candidate == abbreviation {
+        return Some(expansion.to_string());
+    }
+    (candidate == capitalize(abbreviation)).then(|| capitalize(expansion))
+}
+
+fn capitalize(text: &str) -> String {
+    let mut characters = text.chars();
+    match characters.next() {
+        Some(first) => first.to_uppercase().chain(characters).collect(),
+        None => String::new(),
+    }
+}
+
+/// Checks if a character is only decorative - emoji, pictographs, dingbats, box drawing, or the joiners and selectors used to compose emoji
+pub fn is_decorative(character: char) -> bool {
+    matches!(
+        character as u32,
+        0x2500..=0x25FF // Box drawing, block elements and geometric shapes
+            | 0x2600..=0x27BF // Miscellaneous symbols and dingbats
+            | 0x2B00..=0x2BFF // Miscellaneous symbols and arrows
+            | 0x1F000..=0x1FAFF // Emoji and pictographs
+            | 0x200D // Zero width joiner
+            | 0xFE0E..=0xFE0F // Variation selectors
+            | 0xE0020..=0xE007F // Tags used by flag emoji
+    )
+}
+
+/// Removes decorative unicode from the text, collapsing the spaces left doubled by the removal and trimming them from the ends of lines
+pub fn strip_decorative_unicode(text: &str) -> String {
+    if !text.chars().any(is_decorative) {
+        return text.to_string();
+    }
+    text.split('\n')
+        .map(|line| {
+            let line = line.chars().filter(|character| !is_decorative(*character));
+            let mut stripped = String::new();
+            for character in line {
+                if character == ' ' && (stripped.is_empty() || stripped.ends_with(' ')) {
+                    continue;
+                }
+                stripped.push(character);
+            }
+            stripped.trim_end().to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracery::{StatefulStringGenerator, StringGenerator, TraceryGrammar};
+
+    #[test]
+    pub fn screen_reader_profile_expands_and_strips() {
+        let profile = OutputProfile::screen_reader();
+        assert_eq!(
+            profile.apply("⚔️ Dr. Vex attacks! ⚔️ Bring potions, herbs, etc."),
+            "Doctor Vex attacks! Bring potions, herbs, and so on."
+        );
+        assert_eq!(
+            profile.apply("E.g. a sword\n╔══ a shield ══╗ [icon:shield]"),
+            "For example a sword\na shield"
+        );
+        assert_eq!(
+            profile.apply("Drive to the etcetera"),
+            "Drive to the etcetera"
+        );
+        assert_eq!(OutputProfile::new().apply("⚔️ Dr. Vex"), "⚔️ Dr. Vex");
+    }
+
+    #[test]
+    pub fn profiles_are_selected_at_generation_time() {
+        fn plain_language(text: &str) -> String {
+            text.replace("commence", "start")
+        }
+        let grammar = TraceryGrammar::new(
+            &[("origin", &["✨ The festivities commence, i.e. now"])],
+            None,
+        );
+        let profile = OutputProfile::screen_reader().with_transform(plain_language);
+        assert_eq!(
+            StringGenerator::try_generate_for_profile(&grammar, &mut 0, &profile).unwrap(),
+            "The festivities start, that is now"
+        );
+
+        let mut generator = StatefulStringGenerator::from_grammar(grammar);
+        assert_eq!(
+            generator.try_generate(&mut 0).unwrap(),
+            "✨ The festivities commence, i.e. now"
+        );
+        generator.set_output_profile(Some(profile));
+        assert_eq!(
+            generator.try_generate(&mut 0).unwrap(),
+            "The festivities start, that is now"
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use super::annotations::split_annotations;
+
+/// The name of the annotation declaring an option's grammatical gender - `{gender:feminine}`
+pub const GENDER_ANNOTATION: &str = "gender";
+
+/// The name of the annotation declaring an option's grammatical number - `{number:plural}`
+pub const NUMBER_ANNOTATION: &str = "number";
+
+/// The name of the modifier conjugating a verb to agree with a variable - `#verb.agree(hero)#`
+pub const AGREE_MODIFIER: &str = "agree";
+
+/// The names of the modifiers replacing text with the pronoun that agrees with it - `#hero.they#` becomes `she` for a feminine hero
+pub const PRONOUN_MODIFIERS: &[&str] = &["they", "them", "their", "theirs", "themselves"];
+
+/// This is the grammatical gender of an option, declared using the `{gender:...}` annotation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gender {
+    /// Uses she and her - `{gender:feminine}`
+    Feminine,
+    /// Uses he and him - `{gender:masculine}`
+    Masculine,
+    /// Uses it - `{gender:neuter}`, for objects and animals
+    Neuter,
+    /// Uses the singular they, along with plural verbs - `{gender:neutral}`
+    Neutral,
+}
+
+/// This is the grammatical number of an option, declared using the `{number:...}` annotation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GrammaticalNumber {
+    /// A single thing - the default
+    #[default]
+    Singular,
+    /// Several things - `{number:plural}`
+    Plural,
+}
+
+/// This is the agreement metadata of some text, used to choose it's pronouns and conjugate the verbs referring to it.
+/// Text without a declared gender uses they as it's pronoun, while still taking singular verbs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Agreement {
+    /// The declared gender, if there is one
+    pub gender: Option<Gender>,
+    /// The declared number
+    pub number: GrammaticalNumber,
+}
+
+impl Agreement {
+    /// Reads the agreement annotations on an option, returning `None` if it doesn't have any
+    pub fn from_option(option: &str) -> Option<Self> {
+        if !option.starts_with('{') {
+            return None;
+        }
+        let mut agreement = None;
+        for annotation in split_annotations(option).0 {
+            match annotation.name {
+                GENDER_ANNOTATION => {
+                    agreement.get_or_insert(Self::default()).gender = match annotation.value {
+                        "feminine" => Some(Gender::Feminine),
+                        "masculine" => Some(Gender::Masculine),
+                        "neuter" => Some(Gender::Neuter),
+                        "neutral" => Some(Gender::Neutral),
+                        _ => None,
+                    };
+                }
+                NUMBER_ANNOTATION => {
+                    agreement.get_or_insert(Self::default()).number = match annotation.value {
+                        "plural" => GrammaticalNumber::Plural,
+                        _ => GrammaticalNumber::Singular,
+                    };
+                }
+                _ => {}
+            }
+        }
+        agreement
+    }
+
+    /// Checks whether verbs referring to the text take their plural form
+    fn takes_plural_verbs(&self) -> bool {
+        self.number == GrammaticalNumber::Plural || self.gender == Some(Gender::Neutral)
+    }
+
+    /// Gets the pronoun agreeing with the text, in the form named by one of the `PRONOUN_MODIFIERS`
+    pub fn pronoun(&self, form: &str) -> Option<&'static str> {
+        let forms = match (self.number, self.gender) {
+            (GrammaticalNumber::Singular, Some(Gender::Feminine)) => {
+                ["she", "her", "her", "hers", "herself"]
+            }
+            (GrammaticalNumber::Singular, Some(Gender::Masculine)) => {
+                ["he", "him", "his", "his", "himself"]
+            }
+            (GrammaticalNumber::Singular, Some(Gender::Neuter)) => {
+                ["it", "it", "its", "its", "itself"]
+            }
+            (GrammaticalNumber::Singular, Some(Gender::Neutral)) => {
+                ["they", "them", "their", "theirs", "themself"]
+            }
+            _ => ["they", "them", "their", "theirs", "themselves"],
+        };
+        let index = PRONOUN_MODIFIERS.iter().position(|name| *name == form)?;
+        Some(forms[index])
+    }
+
+    /// Conjugates a verb in the present tense, so it agrees with the text - `run` becomes `runs` for a singular subject.
+    /// Only the first word is conjugated, so `look around` becomes `looks around`.
+    pub fn conjugate(&self, verb: &str) -> String {
+        let (word, rest) = match verb.find(' ') {
+            Some(index) => verb.split_at(index),
+            None => (verb, ""),
+        };
+        let conjugated = match (word, self.takes_plural_verbs()) {
+            ("be" | "is" | "are", true) => "are".to_string(),
+            ("be" | "is" | "are", false) => "is".to_string(),
+            ("have" | "has", true) => "have".to_string(),
+            ("have" | "has", false) => "has".to_string(),
+            (_, true) => word.to_string(),
+            (_, false) => third_person(word),
+        };
+        format!("{conjugated}{rest}")
+    }
+}
+
+/// Gets the third person singular of a verb - `watch` becomes `watches`, and `carry` becomes `carries`
+fn third_person(verb: &str) -> String {
+    let mut characters = verb.chars().rev();
+    match (characters.next(), characters.next()) {
+        (None, _) => String::new(),
+        (Some('s' | 'x' | 'z' | 'o'), _) | (Some('h'), Some('c' | 's')) => format!("{verb}es"),
+        (Some('y'), Some(before)) if !matches!(before, 'a' | 'e' | 'i' | 'o' | 'u') => {
+            format!("{}ies", &verb[..verb.len() - 1])
+        }
+        _ => format!("{verb}s"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generator::{Generator, StatefulGenerator},
+        tracery::{StatefulStringGenerator, StringGenerator, TraceryGrammar},
+    };
+
+    #[test]
+    pub fn agreement_is_read_from_annotations() {
+        let hero = Agreement::from_option("{gender:feminine}Ana").unwrap();
+        assert_eq!(hero.pronoun("they"), Some("she"));
+        assert_eq!(hero.pronoun("their"), Some("her"));
+        assert_eq!(hero.conjugate("watch the sea"), "watches the sea");
+        let crowd = Agreement::from_option("{number:plural}the crowd").unwrap();
+        assert_eq!(crowd.pronoun("them"), Some("them"));
+        assert_eq!(crowd.conjugate("be"), "are");
+        let friend = Agreement::from_option("{gender:neutral}Sam").unwrap();
+        assert_eq!(friend.pronoun("themselves"), Some("themself"));
+        assert_eq!(friend.conjugate("go"), "go");
+        assert_eq!(Agreement::default().conjugate("carry"), "carries");
+        assert_eq!(Agreement::default().conjugate("go"), "goes");
+        assert_eq!(Agreement::from_option("Bo"), None);
+    }
+
+    #[test]
+    pub fn pronouns_and_verbs_agree_with_variables() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["#[hero:#name#]story#"]),
+                (
+                    "story",
+                    &["#hero# #verb.agree(hero)#. #hero.their.capitalize# dog #verb.agree(dog)#."],
+                ),
+                (
+                    "name",
+                    &[
+                        "{gender:feminine}Ana",
+                        "{gender:masculine}Bo",
+                        "{number:plural}The twins",
+                    ],
+                ),
+                ("verb", &["watch the sea"]),
+            ],
+            None,
+        );
+        assert_eq!(
+            StringGenerator::generate(&grammar, &mut 0).unwrap(),
+            "Ana watches the sea. Her dog watches the sea."
+        );
+        assert_eq!(
+            StringGenerator::generate(&grammar, &mut 1).unwrap(),
+            "Bo watches the sea. His dog watches the sea."
+        );
+        let mut generator = StatefulStringGenerator::from_grammar(grammar);
+        assert_eq!(
+            generator.generate(&mut 2).unwrap(),
+            "The twins watch the sea. Their dog watches the sea."
+        );
+    }
+}
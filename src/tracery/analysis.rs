@@ -0,0 +1,482 @@
+use std::fmt::Display;
+
+#[cfg(feature = "bevy")]
+use bevy::utils::{HashMap, HashSet};
+#[cfg(not(feature = "bevy"))]
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    annotations::option_text, validation::referenced_keys, StringGenerator, TraceryGrammar,
+};
+use crate::generator::{ExpansionTrace, GrammarRandomNumberGenerator};
+
+/// This limits how much of a grammar is explored when looking for duplicate outputs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DuplicateSearch {
+    /// The number of choices explored along each path. Any further choices always take their first option
+    pub max_depth: usize,
+    /// The maximum number of paths explored before the search stops
+    pub max_paths: usize,
+}
+
+impl Default for DuplicateSearch {
+    fn default() -> Self {
+        Self {
+            max_depth: 8,
+            max_paths: 10_000,
+        }
+    }
+}
+
+/// This is the sequence of options chosen while expanding a rule, in the order the rules were expanded
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChoicePath(pub Vec<(String, usize)>);
+
+impl Display for ChoicePath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, (rule, option)) in self.0.iter().enumerate() {
+            if index > 0 {
+                write!(f, " > ")?;
+            }
+            write!(f, "{rule}[{option}]")?;
+        }
+        Ok(())
+    }
+}
+
+/// This is an output that can be generated by several different paths through a grammar
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateOutput {
+    /// The generated text
+    pub output: String,
+    /// The distinct paths that generate it
+    pub paths: Vec<ChoicePath>,
+}
+
+/// This replays a set of choices, recording the number of options available at each of them
+#[derive(Default)]
+struct ReplayRng {
+    choices: Vec<usize>,
+    options: Vec<usize>,
+}
+
+impl ReplayRng {
+    /// Finds the next combination of choices to explore, by advancing the last of the first `explored` choices that has options left
+    fn next_combination(&self, explored: usize) -> Option<Vec<usize>> {
+        let explored = self.options.len().min(explored);
+        let next = (0..explored).rev().find(|index| {
+            let choice = self.choices.get(*index).copied().unwrap_or(0);
+            choice + 1 < self.options[*index]
+        })?;
+        let mut choices = self.choices.clone();
+        let choice = choices.get(next).copied().unwrap_or(0);
+        choices.resize(next, 0);
+        choices.push(choice + 1);
+        Some(choices)
+    }
+}
+
+impl GrammarRandomNumberGenerator for ReplayRng {
+    fn get_number(&mut self, len: usize) -> usize {
+        let choice = self.choices.get(self.options.len()).copied().unwrap_or(0);
+        self.options.push(len);
+        choice
+    }
+}
+
+/// Explores every combination of choices a rule can make, yielding each distinct output the first time it is generated
+struct Expansions {
+    grammar: TraceryGrammar,
+    key: String,
+    next: Option<Vec<usize>>,
+    seen: HashSet<String>,
+}
+
+impl Iterator for Expansions {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        loop {
+            let mut rng = ReplayRng {
+                choices: self.next.take()?,
+                options: vec![],
+            };
+            let generated = StringGenerator::try_generate_with(
+                &self.key,
+                &self.grammar,
+                &mut rng,
+                None,
+                Default::default(),
+            );
+            self.next = rng.next_combination(usize::MAX);
+            if let Ok((_, output, _)) = generated {
+                // Expansions cut off at the maximum depth still hold the rules they didn't expand, so they aren't outputs
+                let truncated = referenced_keys(&output).iter().any(|key| {
+                    self.grammar
+                        .rules
+                        .contains_key(self.grammar.resolve_alias(key))
+                });
+                if !truncated && self.seen.insert(output.clone()) {
+                    return Some(output);
+                }
+            }
+        }
+    }
+}
+
+impl TraceryGrammar {
+    /// This finds outputs that can be generated from a rule by more than one path - usually a sign that options could be
+    /// consolidated, or that their weighting isn't doing what was intended. Every combination of choices is explored, up to the limits of the search.
+    /// The results are sorted by output.
+    pub fn find_duplicate_outputs(
+        &self,
+        rule: &str,
+        search: DuplicateSearch,
+    ) -> Vec<DuplicateOutput> {
+        let mut outputs: HashMap<String, Vec<ChoicePath>> = HashMap::default();
+        let mut choices = vec![];
+        for _ in 0..search.max_paths {
+            let mut rng = ReplayRng {
+                choices: choices.clone(),
+                options: vec![],
+            };
+            let mut trace = ExpansionTrace::default();
+            if let Ok((index, output, _)) = StringGenerator::try_generate_with(
+                rule,
+                self,
+                &mut rng,
+                Some(&mut trace),
+                Default::default(),
+            ) {
+                let trace = trace.into_rooted_at(rule.to_string(), index);
+                let path = ChoicePath(
+                    trace
+                        .nodes()
+                        .filter_map(|(_, node)| Some((node.rule.clone(), node.option?)))
+                        .collect(),
+                );
+                let paths = outputs.entry(output).or_default();
+                if !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+            // Move on to the next combination, by advancing the last choice that has options left
+            let Some(next) = rng.next_combination(search.max_depth) else {
+                break;
+            };
+            choices = next;
+        }
+        let mut duplicates = outputs
+            .into_iter()
+            .filter(|(_, paths)| paths.len() > 1)
+            .map(|(output, paths)| DuplicateOutput { output, paths })
+            .collect::<Vec<_>>();
+        duplicates.sort_by(|a, b| a.output.cmp(&b.output));
+        duplicates
+    }
+
+    /// This enumerates every distinct output a rule can generate, by exploring every combination of choices - so it is only
+    /// practical for small grammars. It is useful for unit tests, exporting every line for localization, or screening every
+    /// possible output. Expansions that would nest rules more than `max_depth` levels within the starting option are skipped,
+    /// which keeps recursive rules finite. The outputs are produced lazily, in the order they are first found.
+    pub fn enumerate(&self, key: &str, max_depth: usize) -> impl Iterator<Item = String> {
+        Expansions {
+            grammar: self.clone().with_max_depth(max_depth),
+            key: key.to_string(),
+            next: Some(vec![]),
+            seen: HashSet::default(),
+        }
+    }
+}
+
+/// This is the number of distinct outputs a grammar can generate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputCount {
+    /// The grammar generates at most this many outputs. Counts too large to hold are saturated at `u128::MAX`
+    Finite(u128),
+    /// The grammar's rules refer back to themselves, so there is no limit to the outputs it can generate
+    Unbounded,
+}
+
+impl Display for OutputCount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputCount::Finite(count) => write!(f, "{count}"),
+            OutputCount::Unbounded => write!(f, "unbounded"),
+        }
+    }
+}
+
+/// This describes how much variation a grammar has, when generating from it's starting point. It is calculated from the
+/// grammar's rules alone - inline alternatives, variables and guards aren't taken into account, and different choices
+/// generating the same text are counted separately - so the numbers are estimates, rather than exact values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrammarStatistics {
+    /// The number of rules that can be reached from the starting point, including the starting point itself
+    pub reachable_rules: usize,
+    /// The number of distinct combinations of choices that can be made while generating
+    pub distinct_outputs: OutputCount,
+    /// The fewest levels of rules that need to be expanded in a generation
+    pub min_depth: usize,
+    /// The most levels of rules that can be expanded in a generation, or `None` if the rules refer back to themselves
+    pub max_depth: Option<usize>,
+    /// The largest number of options of any reachable rule
+    pub max_branching: usize,
+    /// The average number of options of the reachable rules
+    pub average_branching: f64,
+    /// The entropy of a generation in bits, taking the rules' weights into account - or `None` if the rules refer back to themselves.
+    /// A grammar with an entropy of 10 bits is about as varied as choosing from 1024 equally likely outputs.
+    pub entropy: Option<f64>,
+}
+
+/// The statistics of a rule that doesn't refer back to itself - it's output count, maximum depth and entropy
+type RuleStatistics = (u128, usize, f64);
+
+impl TraceryGrammar {
+    /// This calculates statistics describing how much variation the grammar has - such as the number of distinct outputs
+    /// it can generate from it's starting point - so writers know whether it generates 20 or 2 million variations.
+    pub fn analyze(&self) -> GrammarStatistics {
+        // The rules referenced by each of the options of every reachable rule
+        let mut references: HashMap<&str, Vec<Vec<&str>>> = HashMap::default();
+        let mut pending = vec![self.resolve_alias(&self.starting_point)];
+        while let Some(rule) = pending.pop() {
+            let Some((key, options)) = self.rules.get_key_value(rule) else {
+                continue;
+            };
+            if references.contains_key(key.as_str()) {
+                continue;
+            }
+            let options = options
+                .iter()
+                .map(|option| {
+                    referenced_keys(option_text(option))
+                        .iter()
+                        .filter_map(|key| self.rules.get_key_value(self.resolve_alias(key)))
+                        .map(|(key, _)| key.as_str())
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            pending.extend(options.iter().flatten().copied());
+            references.insert(key, options);
+        }
+
+        // The fewest levels needed to finish each rule - found by relaxing them until nothing changes, so recursion is handled
+        let mut min_depths: HashMap<&str, usize> = HashMap::default();
+        loop {
+            let mut changed = false;
+            for (rule, options) in references.iter() {
+                let depth = options
+                    .iter()
+                    .filter_map(|option| {
+                        option.iter().try_fold(0, |depth: usize, reference| {
+                            min_depths.get(reference).map(|inner| depth.max(*inner))
+                        })
+                    })
+                    .min()
+                    .map(|depth| depth + 1);
+                if let Some(depth) = depth {
+                    if min_depths.get(rule).is_none_or(|current| depth < *current) {
+                        min_depths.insert(rule, depth);
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        let start = self.resolve_alias(&self.starting_point);
+        let mut memo = HashMap::default();
+        let statistics = match references.contains_key(start) {
+            true => self.rule_statistics(start, &references, &mut vec![], &mut memo),
+            false => None,
+        };
+        let branching = references
+            .keys()
+            .map(|rule| self.rules.get(*rule).map_or(0, Vec::len))
+            .collect::<Vec<_>>();
+        GrammarStatistics {
+            reachable_rules: references.len(),
+            distinct_outputs: match (references.is_empty(), statistics) {
+                (true, _) => OutputCount::Finite(0),
+                (false, Some((count, _, _))) => OutputCount::Finite(count),
+                (false, None) => OutputCount::Unbounded,
+            },
+            min_depth: min_depths.get(start).copied().unwrap_or(0),
+            max_depth: statistics.map(|(_, depth, _)| depth),
+            max_branching: branching.iter().copied().max().unwrap_or(0),
+            average_branching: match branching.len() {
+                0 => 0.,
+                len => branching.iter().sum::<usize>() as f64 / len as f64,
+            },
+            entropy: statistics.map(|(_, _, entropy)| entropy),
+        }
+    }
+
+    /// Calculates the statistics of a rule, or `None` if it refers back to itself - directly or through other rules
+    fn rule_statistics<'a>(
+        &self,
+        rule: &'a str,
+        references: &HashMap<&'a str, Vec<Vec<&'a str>>>,
+        stack: &mut Vec<&'a str>,
+        memo: &mut HashMap<&'a str, Option<RuleStatistics>>,
+    ) -> Option<RuleStatistics> {
+        if let Some(statistics) = memo.get(rule) {
+            return *statistics;
+        }
+        if stack.contains(&rule) {
+            return None;
+        }
+        stack.push(rule);
+        let weights = self
+            .settings
+            .get(rule)
+            .map(|settings| settings.weights.as_slice())
+            .unwrap_or_default();
+        let options = references.get(rule).map(Vec::as_slice).unwrap_or_default();
+        let weight = |index: usize| weights.get(index).copied().unwrap_or(1) as f64;
+        let total = (0..options.len()).map(weight).sum::<f64>();
+        let mut statistics = Some((0u128, 0usize, 0f64));
+        for (index, option) in options.iter().enumerate() {
+            let mut option_statistics = Some((1u128, 0usize, 0f64));
+            for reference in option {
+                let inner = self.rule_statistics(reference, references, stack, memo);
+                option_statistics = option_statistics.zip(inner).map(
+                    |((count, depth, entropy), (inner_count, inner_depth, inner_entropy))| {
+                        (
+                            count.saturating_mul(inner_count),
+                            depth.max(inner_depth),
+                            entropy + inner_entropy,
+                        )
+                    },
+                );
+            }
+            let probability = match total > 0. {
+                true => weight(index) / total,
+                false => 0.,
+            };
+            statistics = statistics.zip(option_statistics).map(
+                |((count, depth, entropy), (option_count, option_depth, option_entropy))| {
+                    let choice = match probability > 0. {
+                        true => -probability * probability.log2(),
+                        false => 0.,
+                    };
+                    (
+                        match probability > 0. {
+                            true => count.saturating_add(option_count),
+                            false => count,
+                        },
+                        depth.max(option_depth),
+                        entropy + choice + probability * option_entropy,
+                    )
+                },
+            );
+        }
+        stack.pop();
+        let statistics = statistics.map(|(count, depth, entropy)| (count, depth + 1, entropy));
+        memo.insert(rule, statistics);
+        statistics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn finds_paths_generating_the_same_output() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["#animal#", "a #pet#"]),
+                ("animal", &["a cat", "a dog", "an owl"]),
+                ("pet", &["cat", "fish"]),
+            ],
+            None,
+        );
+        let duplicates = grammar.find_duplicate_outputs("origin", DuplicateSearch::default());
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].output, "a cat");
+        assert_eq!(
+            duplicates[0]
+                .paths
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec!["origin[0] > animal[0]", "origin[1] > pet[0]"]
+        );
+    }
+
+    #[test]
+    pub fn every_expansion_of_a_rule_is_enumerated() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["#animal#", "a #pet#"]),
+                ("animal", &["a cat", "a dog"]),
+                ("pet", &["cat", "(gold|cat)fish"]),
+                ("chain", &["link #chain#", "end"]),
+            ],
+            None,
+        );
+        assert_eq!(
+            grammar.enumerate("origin", 10).collect::<Vec<_>>(),
+            vec!["a cat", "a dog", "a goldfish", "a catfish"]
+        );
+        // Recursive rules stop expanding at the maximum depth
+        assert_eq!(
+            grammar.enumerate("chain", 2).collect::<Vec<_>>(),
+            vec!["link link end", "link end", "end"]
+        );
+        assert_eq!(grammar.enumerate("missing", 10).count(), 0);
+    }
+
+    #[test]
+    pub fn truncated_expansions_are_not_enumerated() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("list", &["#item#, #list#", "#item#"]),
+                ("item", &["a", "b"]),
+                ("forever", &["and #forever#"]),
+            ],
+            None,
+        );
+        let outputs = grammar.enumerate("list", 2).collect::<Vec<_>>();
+        assert_eq!(outputs, vec!["a, a", "a, b", "b, a", "b, b", "a", "b"]);
+        assert!(outputs.iter().all(|output| !output.contains('#')));
+        // A rule that never finishes has no outputs at all
+        assert_eq!(grammar.enumerate("forever", 5).count(), 0);
+    }
+
+    #[test]
+    pub fn statistics_describe_the_variation_of_a_grammar() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["#hero# met #villain#", "#hero# rested"]),
+                ("hero", &["Ana", "Bo", "#title# Cy"]),
+                ("villain", &["Dax", "Eve"]),
+                ("title", &["Sir", "Dame"]),
+                ("unused", &["never"]),
+            ],
+            None,
+        );
+        let statistics = grammar.analyze();
+        assert_eq!(statistics.reachable_rules, 4);
+        // Each hero can meet either villain, or rest
+        assert_eq!(statistics.distinct_outputs, OutputCount::Finite(12));
+        assert_eq!(statistics.min_depth, 2);
+        assert_eq!(statistics.max_depth, Some(3));
+        assert_eq!(statistics.max_branching, 3);
+        assert_eq!(statistics.average_branching, 2.25);
+        assert!(statistics.entropy.unwrap() > 3.);
+
+        let recursive = TraceryGrammar::new(
+            &[("origin", &["#more#"]), ("more", &["end", "and #more#"])],
+            None,
+        )
+        .analyze();
+        assert_eq!(recursive.distinct_outputs, OutputCount::Unbounded);
+        assert_eq!(recursive.min_depth, 2);
+        assert_eq!(recursive.max_depth, None);
+        assert_eq!(recursive.entropy, None);
+    }
+}
@@ -0,0 +1,262 @@
+use super::{captures::NAME_ANNOTATION, selection::Cooldown};
+
+/// This is a single annotation attached to the start of an option, written as `{name:value}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OptionAnnotation<'a> {
+    /// The annotation's name - the text before the first `:`
+    pub name: &'a str,
+    /// The annotation's value - the text after the first `:`, which can be empty
+    pub value: &'a str,
+}
+
+/// Splits the annotations prefixing an option (any number of `{name:value}` blocks) from its text.
+/// Only blocks starting with a single word followed by a `:` are annotations, so options starting with other braces - like
+/// `{braces} are fun` - keep them as part of their text. A brace escaped as `\{` is always text, and is returned without the `\`.
+/// If the option has no annotations, the full option is returned as the text.
+pub fn split_annotations(option: &str) -> (Vec<OptionAnnotation<'_>>, &str) {
+    let mut annotations = vec![];
+    let mut rest = option;
+    while let Some(inner) = rest.strip_prefix('{') {
+        let Some((name, value)) = inner
+            .find('}')
+            .and_then(|end| inner[..end].split_once(':'))
+            .filter(|(name, _)| is_annotation_name(name.trim()))
+        else {
+            break;
+        };
+        annotations.push(OptionAnnotation {
+            name: name.trim(),
+            value: value.trim(),
+        });
+        rest = &inner[name.len() + value.len() + 2..];
+    }
+    if rest.starts_with("\\{") {
+        rest = &rest[1..];
+    }
+    (annotations, rest)
+}
+
+fn is_annotation_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || character == '_')
+}
+
+/// Returns the text of an option, without any annotations
+pub fn option_text(option: &str) -> &str {
+    split_annotations(option).1
+}
+
+/// The name of the annotation used for cooldowns - `{cooldown:3}` for generations, or `{cooldown:10s}` for seconds
+pub const COOLDOWN_ANNOTATION: &str = "cooldown";
+
+/// Gets the cooldown declared on an option, if it has a valid one
+pub fn option_cooldown(option: &str) -> Option<Cooldown> {
+    if !option.starts_with('{') {
+        return None;
+    }
+    let annotations = split_annotations(option).0;
+    let value = annotations
+        .iter()
+        .find(|annotation| annotation.name == COOLDOWN_ANNOTATION)?
+        .value;
+    match value.strip_suffix('s') {
+        Some(seconds) => seconds
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|seconds| seconds.is_finite() && *seconds > 0.)
+            .map(Cooldown::Seconds),
+        None => value.parse::<usize>().ok().map(Cooldown::Generations),
+    }
+}
+
+/// Gets the name declared on an option using the `{name:value}` annotation
+pub fn option_name(option: &str) -> Option<&str> {
+    if !option.starts_with('{') {
+        return None;
+    }
+    split_annotations(option)
+        .0
+        .into_iter()
+        .find(|annotation| annotation.name == NAME_ANNOTATION)
+        .map(|annotation| annotation.value)
+        .filter(|name| !name.is_empty())
+}
+
+/// The name of the annotation used to tag an option - `{tags:fantasy,dangerous}`
+pub const TAGS_ANNOTATION: &str = "tags";
+
+/// Adds a `tags` annotation holding the provided tags to an option's text, unless there are no tags
+#[cfg(feature = "serde")]
+pub(crate) fn tagged_option(text: String, tags: &[String]) -> String {
+    match tags.is_empty() {
+        true => text,
+        false => format!("{{{TAGS_ANNOTATION}:{}}}{text}", tags.join(",")),
+    }
+}
+
+/// Gets the tags declared on an option. Tags are separated by commas, and an option can have more than one `tags` annotation.
+pub fn option_tags(option: &str) -> Vec<&str> {
+    if !option.starts_with('{') {
+        return vec![];
+    }
+    split_annotations(option)
+        .0
+        .into_iter()
+        .filter(|annotation| annotation.name == TAGS_ANNOTATION)
+        .flat_map(|annotation| annotation.value.split(','))
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+/// The name of the annotation used for guards - `{if:gen_count>=3}`
+pub const GUARD_ANNOTATION: &str = "if";
+
+/// The name of the built in value holding the number of generations a stateful generator completed.
+/// It can be referenced in guards, or expanded directly as `#gen_count#`.
+pub const GENERATION_COUNT_KEY: &str = "gen_count";
+
+/// This is the information guards are evaluated against
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GuardContext {
+    /// The number of generations completed so far
+    pub generation: usize,
+}
+
+impl GuardContext {
+    fn value(&self, name: &str) -> Option<i64> {
+        match name {
+            GENERATION_COUNT_KEY => i64::try_from(self.generation).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Checks whether all the guards on an option pass. Options without guards always pass,
+/// while guards that can't be parsed or reference unknown values always fail.
+pub fn guards_pass(option: &str, context: &GuardContext) -> bool {
+    if !option.starts_with('{') {
+        return true;
+    }
+    split_annotations(option)
+        .0
+        .iter()
+        .filter(|annotation| annotation.name == GUARD_ANNOTATION)
+        .all(|annotation| evaluate_guard(annotation.value, context).unwrap_or(false))
+}
+
+fn evaluate_guard(guard: &str, context: &GuardContext) -> Option<bool> {
+    const OPERATORS: [&str; 6] = [">=", "<=", "==", "!=", ">", "<"];
+    let (operator, (left, right)) = OPERATORS
+        .iter()
+        .find_map(|operator| guard.split_once(operator).map(|split| (*operator, split)))?;
+    let left = context.value(left.trim())?;
+    let right = right.trim().parse::<i64>().ok()?;
+    Some(match operator {
+        ">=" => left >= right,
+        "<=" => left <= right,
+        "==" => left == right,
+        "!=" => left != right,
+        ">" => left > right,
+        _ => left < right,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn leading_braces_that_arent_annotations_are_text() {
+        assert_eq!(
+            split_annotations("{braces} are fun"),
+            (vec![], "{braces} are fun")
+        );
+        assert_eq!(
+            split_annotations("{a map: of things} and more"),
+            (vec![], "{a map: of things} and more")
+        );
+        assert_eq!(
+            split_annotations("\\{tags:scifi} is text"),
+            (vec![], "{tags:scifi} is text")
+        );
+        assert_eq!(
+            split_annotations("{tags:scifi}\\{name:x}"),
+            (
+                vec![OptionAnnotation {
+                    name: "tags",
+                    value: "scifi"
+                }],
+                "{name:x}"
+            )
+        );
+        assert_eq!(option_tags("\\{tags:scifi}robot"), Vec::<&str>::new());
+
+        let grammar = crate::tracery::TraceryGrammar::new(
+            &[
+                ("origin", &["{braces} are #kept#"]),
+                ("kept", &["\\{kept}"]),
+            ],
+            None,
+        );
+        assert_eq!(
+            crate::tracery::StringGenerator::try_generate(&grammar, &mut 0).unwrap(),
+            "{braces} are {kept}"
+        );
+    }
+
+    #[test]
+    pub fn parses_cooldowns_in_generations_and_seconds() {
+        assert_eq!(
+            option_cooldown("{cooldown:3}Hi"),
+            Some(Cooldown::Generations(3))
+        );
+        assert_eq!(
+            option_cooldown("{if:gen_count>1}{cooldown: 2.5s}Hi"),
+            Some(Cooldown::Seconds(2.5))
+        );
+        assert_eq!(option_cooldown("{cooldown:soon}Hi"), None);
+        assert_eq!(option_cooldown("Hi"), None);
+    }
+
+    #[test]
+    pub fn splits_annotations_from_option_text() {
+        let (annotations, text) = split_annotations("{if: gen_count > 2}{tag:}Hello {there}");
+        assert_eq!(
+            annotations,
+            vec![
+                OptionAnnotation {
+                    name: "if",
+                    value: "gen_count > 2"
+                },
+                OptionAnnotation {
+                    name: "tag",
+                    value: ""
+                }
+            ]
+        );
+        assert_eq!(text, "Hello {there}");
+        assert_eq!(option_text("{unclosed"), "{unclosed");
+        assert_eq!(
+            option_tags("{tags:fantasy, dangerous}{name:x}{tags:big}Dragon"),
+            vec!["fantasy", "dangerous", "big"]
+        );
+    }
+
+    #[test]
+    pub fn guards_compare_against_the_generation_count() {
+        let context = GuardContext { generation: 3 };
+        assert!(guards_pass("No guard", &context));
+        assert!(guards_pass("{if:gen_count>=3}Friend", &context));
+        assert!(!guards_pass("{if:gen_count<3}Stranger", &context));
+        assert!(guards_pass(
+            "{if:gen_count>1}{if:gen_count!=4}Both",
+            &context
+        ));
+        assert!(!guards_pass("{if:unknown>1}Unknown", &context));
+        assert!(!guards_pass("{if:gen_count>lots}Malformed", &context));
+    }
+}
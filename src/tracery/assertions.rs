@@ -0,0 +1,181 @@
+use std::fmt::Display;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::generator::ExpansionTrace;
+
+/// The name of the directive asserting that a variable has been set by the time it is reached - `[assert:hero]`
+pub const ASSERT_DIRECTIVE: &str = "assert";
+
+/// This determines what happens when an assertion like `[assert:hero]` is reached before it's variable was set.
+/// Assertions are only checked when the policy isn't `Ignore` - so they can be enabled in debug builds, and cost nothing otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AssertionPolicy {
+    /// Assertions are skipped. This is the default behaviour
+    #[default]
+    Ignore,
+    /// Failed assertions are reported as a `GenerationIssue` - and logged, when using bevy - but the generation still succeeds
+    Warn,
+    /// The generation fails with a `GenerationIssue` listing every failed assertion
+    Error,
+}
+
+#[cfg(feature = "serde")]
+impl AssertionPolicy {
+    pub(crate) fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// This is an assertion that was reached before it's variable was set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssertionFailure {
+    /// The variable that should have been set
+    pub variable: String,
+    /// The rules expanded on the way to the assertion, starting from the rule generation started from. The last one is the rule
+    /// whose option holds the assertion, and it is empty if the assertion was in the text being expanded.
+    pub path: Vec<String>,
+}
+
+impl AssertionFailure {
+    /// Gets the rule whose option holds the assertion, if there is one
+    pub fn rule(&self) -> Option<&str> {
+        self.path.last().map(String::as_str)
+    }
+}
+
+impl Display for AssertionFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.rule() {
+            Some(rule) => write!(
+                f,
+                "`{}` was used in `{rule}` before it was set ({})",
+                self.variable,
+                self.path.join(" > ")
+            ),
+            None => write!(f, "`{}` was used before it was set", self.variable),
+        }
+    }
+}
+
+/// Gets the key an assertion is expanded as, or `None` if the action isn't one. The key can't clash with a rule's key,
+/// since rules can't be referenced with a `:` in their key.
+pub(crate) fn assertion_key(action: &str) -> Option<String> {
+    action
+        .strip_prefix(ASSERT_DIRECTIVE)
+        .and_then(|variable| variable.strip_prefix(':'))
+        .map(|variable| format!("{ASSERT_DIRECTIVE}:{}", variable.trim()))
+}
+
+/// Gets the variable an assertion's key refers to, or `None` if the key isn't an assertion
+pub(crate) fn asserted_variable(key: &str) -> Option<&str> {
+    key.strip_prefix(ASSERT_DIRECTIVE)?.strip_prefix(':')
+}
+
+/// Finds the assertions that failed during an expansion. Assertions are recorded in the trace like rules, and the ones that held
+/// are recorded with an option - so failed assertions are the ones without one. The root is the rule generation started from.
+pub(crate) fn failed_assertions(
+    trace: &ExpansionTrace<String>,
+    root: Option<&str>,
+) -> Vec<AssertionFailure> {
+    let mut failures = vec![];
+    let mut path = vec![];
+    for (depth, node) in trace.nodes() {
+        path.truncate(depth);
+        match asserted_variable(&node.rule) {
+            Some(variable) if node.option.is_none() => failures.push(AssertionFailure {
+                variable: variable.to_string(),
+                path: root
+                    .into_iter()
+                    .chain(path.iter().copied())
+                    .map(str::to_string)
+                    .collect(),
+            }),
+            _ => {}
+        }
+        path.push(node.rule.as_str());
+    }
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracery::{
+        GenerationIssue, StatefulStringGenerator, StringGenerator, TraceryGrammar,
+    };
+
+    fn grammar() -> TraceryGrammar {
+        TraceryGrammar::new(
+            &[
+                ("origin", &["#intro# #ending#"]),
+                (
+                    "intro",
+                    &["[hero:#name#]#hero# set out.", "A storm rolled in."],
+                ),
+                ("ending", &["#farewell#"]),
+                ("farewell", &["[assert:hero]#hero# came home."]),
+                ("name", &["Ana"]),
+            ],
+            None,
+        )
+    }
+
+    #[test]
+    pub fn failed_assertions_identify_the_rule_and_path() {
+        let grammar = grammar().with_assertion_policy(AssertionPolicy::Error);
+        assert_eq!(
+            StringGenerator::try_generate(&grammar, &mut 0).unwrap(),
+            "Ana set out. Ana came home."
+        );
+        let failure = AssertionFailure {
+            variable: "hero".to_string(),
+            path: vec![
+                "origin".to_string(),
+                "ending".to_string(),
+                "farewell".to_string(),
+            ],
+        };
+        assert_eq!(failure.rule(), Some("farewell"));
+        assert_eq!(
+            StringGenerator::try_generate(&grammar, &mut 1),
+            Err(GenerationIssue::FailedAssertions(vec![failure.clone()]))
+        );
+        assert_eq!(
+            failure.to_string(),
+            "`hero` was used in `farewell` before it was set (origin > ending > farewell)"
+        );
+
+        let mut generator = StatefulStringGenerator::from_grammar(
+            grammar.with_assertion_policy(AssertionPolicy::Warn),
+        );
+        assert_eq!(
+            generator.try_generate(&mut 1).unwrap(),
+            "A storm rolled in. #hero# came home."
+        );
+        assert_eq!(
+            generator.last_issues(),
+            &[GenerationIssue::FailedAssertions(vec![failure])]
+        );
+    }
+
+    #[test]
+    pub fn assertions_hold_once_their_variable_is_set() {
+        let grammar = grammar();
+        assert_eq!(
+            StringGenerator::try_generate(&grammar, &mut 1).unwrap(),
+            "A storm rolled in. #hero# came home."
+        );
+        let mut generator = StatefulStringGenerator::from_grammar(
+            grammar.with_assertion_policy(AssertionPolicy::Error),
+        );
+        generator.set_token("hero", "Bo");
+        assert_eq!(
+            generator.try_generate(&mut 1).unwrap(),
+            "A storm rolled in. Bo came home."
+        );
+        assert!(generator.last_issues().is_empty());
+    }
+}
@@ -0,0 +1,180 @@
+use bevy::prelude::*;
+
+use super::{GlobalStoryGenerator, StatefulStringGenerator, VariableStore};
+
+/// This holds variables shared by every `StatefulStringGenerator` in the world - facts like the current season, or whether a war is on.
+/// Generators use them when a key doesn't match any of their own variables, so they reflect the state of the world without it
+/// being copied into each of them by hand. Grammars can write to it using assignments like `[global.season:winter]`.
+/// It requires the `GrammarBlackboardPlugin`.
+#[derive(Resource, Debug, Clone, Default, Deref, DerefMut)]
+pub struct GrammarBlackboard(pub VariableStore);
+
+/// This event is sent whenever a grammar writes a new value to the `GrammarBlackboard`, so other systems can react to it
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct BlackboardChanged {
+    /// The key of the variable that was written, without the `global.` prefix
+    pub key: String,
+    /// The variable's new options
+    pub values: Vec<String>,
+}
+
+/// This plugin writes the shared variables assigned by grammars to the `GrammarBlackboard`, sending a `BlackboardChanged` event for each of them.
+/// It then shares the blackboard with the stateful generators in the world, whenever it changes or a generator is added.
+#[derive(Default)]
+pub struct GrammarBlackboardPlugin;
+
+impl Plugin for GrammarBlackboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GrammarBlackboard>()
+            .add_event::<BlackboardChanged>()
+            .add_systems(
+                PreUpdate,
+                (collect_global_writes, apply_grammar_blackboard).chain(),
+            );
+    }
+}
+
+fn collect_global_writes(
+    mut blackboard: ResMut<GrammarBlackboard>,
+    mut changes: EventWriter<BlackboardChanged>,
+    mut generators: Query<&mut StatefulStringGenerator>,
+    generator_resource: Option<ResMut<StatefulStringGenerator>>,
+    story: Option<ResMut<GlobalStoryGenerator>>,
+) {
+    let writes = generators
+        .iter_mut()
+        .chain(generator_resource.map(Mut::from))
+        .chain(story.map(|story| Mut::from(story).map_unchanged(|story| &mut story.0)))
+        .filter(|generator| !generator.global_writes().is_empty())
+        .map(|mut generator| generator.bypass_change_detection().take_global_writes())
+        .collect::<Vec<_>>();
+    for (key, values) in writes.iter().flat_map(VariableStore::iter) {
+        if blackboard.get_var(key) != Some(values) {
+            blackboard.set_var(key.clone(), values.iter().cloned());
+            changes.send(BlackboardChanged {
+                key: key.clone(),
+                values: values.clone(),
+            });
+        }
+    }
+}
+
+fn apply_grammar_blackboard(
+    blackboard: Res<GrammarBlackboard>,
+    mut generators: Query<&mut StatefulStringGenerator>,
+    generator_resource: Option<ResMut<StatefulStringGenerator>>,
+    story: Option<ResMut<GlobalStoryGenerator>>,
+) {
+    let changed = blackboard.is_changed();
+    for mut generator in generators.iter_mut() {
+        if changed || generator.is_added() {
+            generator.set_shared_variables(blackboard.0.clone());
+        }
+    }
+    if let Some(mut generator) = generator_resource {
+        if changed || generator.is_added() {
+            generator.set_shared_variables(blackboard.0.clone());
+        }
+    }
+    if let Some(mut story) = story {
+        if changed || story.is_added() {
+            story.set_shared_variables(blackboard.0.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::StatefulGenerator;
+
+    #[test]
+    pub fn generators_use_the_blackboard_after_their_own_variables() {
+        let mut app = App::new();
+        app.add_plugins(GrammarBlackboardPlugin);
+        app.world
+            .resource_mut::<GrammarBlackboard>()
+            .set_var("season", ["winter"]);
+        let generator = StatefulStringGenerator::new(
+            &[
+                ("origin", &["It is #season# in #place#"]),
+                ("season", &["spring"]),
+                ("place", &["town"]),
+            ],
+            None,
+        );
+        let entity = app.world.spawn(generator).id();
+        app.update();
+
+        let mut generator = app
+            .world
+            .get_mut::<StatefulStringGenerator>(entity)
+            .unwrap();
+        assert_eq!(generator.generate(&mut 0).unwrap(), "It is winter in town");
+        generator.variables_mut().set_var("season", ["summer"]);
+        assert_eq!(generator.generate(&mut 0).unwrap(), "It is summer in town");
+
+        app.world
+            .resource_mut::<GrammarBlackboard>()
+            .set_var("place", ["the capital"]);
+        app.update();
+        let mut generator = app
+            .world
+            .get_mut::<StatefulStringGenerator>(entity)
+            .unwrap();
+        assert_eq!(
+            generator.generate(&mut 0).unwrap(),
+            "It is summer in the capital"
+        );
+    }
+
+    #[test]
+    pub fn grammars_can_write_to_the_blackboard() {
+        let mut app = App::new();
+        app.add_plugins(GrammarBlackboardPlugin);
+        let writer = app
+            .world
+            .spawn(StatefulStringGenerator::new(
+                &[("origin", &["[global.season:winter]It got cold"])],
+                None,
+            ))
+            .id();
+        let reader = app
+            .world
+            .spawn(StatefulStringGenerator::new(
+                &[("origin", &["It is #season#"]), ("season", &["spring"])],
+                None,
+            ))
+            .id();
+        app.update();
+        let mut generator = app
+            .world
+            .get_mut::<StatefulStringGenerator>(writer)
+            .unwrap();
+        assert_eq!(generator.generate(&mut 0).unwrap(), "It got cold");
+        assert!(generator.variables().is_empty());
+
+        app.update();
+        assert_eq!(
+            app.world
+                .resource::<GrammarBlackboard>()
+                .get_var("season")
+                .cloned(),
+            Some(vec!["winter".to_string()])
+        );
+        let changes = app.world.resource::<Events<BlackboardChanged>>();
+        assert_eq!(
+            changes
+                .get_reader()
+                .read(changes)
+                .map(|change| change.key.as_str())
+                .collect::<Vec<_>>(),
+            vec!["season"]
+        );
+        let mut generator = app
+            .world
+            .get_mut::<StatefulStringGenerator>(reader)
+            .unwrap();
+        assert_eq!(generator.generate(&mut 0).unwrap(), "It is winter");
+    }
+}
@@ -0,0 +1,191 @@
+use std::fmt::Display;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{runtime::RuntimeState, GenerationIssue, TraceryGrammar};
+
+/// This limits how much work a single generation can do, so malformed grammars - like one where `origin` expands to
+/// `#origin##origin#` - fail with `GenerationIssue::BudgetExceeded` before they balloon memory, rather than relying on the depth limit.
+/// It is set on a grammar using `TraceryGrammar::set_budget`, and unlike a `SandboxPolicy` it doesn't restrict what the grammar can access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct GenerationBudget {
+    /// The maximum length, in bytes, of a generation's output. While generating, the options expanded so far are counted - which
+    /// never undercounts the output - so runaway generations stop early
+    pub max_output_length: usize,
+    /// The maximum number of replacements - rules, variables and tokens expanded - made by a single generation
+    pub max_replacements: usize,
+}
+
+impl Default for GenerationBudget {
+    fn default() -> Self {
+        Self {
+            max_output_length: 100_000,
+            max_replacements: 10_000,
+        }
+    }
+}
+
+impl GenerationBudget {
+    /// Creates the default budget, of 100,000 bytes of output and 10,000 replacements
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum length of a generation's output
+    pub fn with_max_output_length(mut self, max_output_length: usize) -> Self {
+        self.max_output_length = max_output_length;
+        self
+    }
+
+    /// Sets the maximum number of replacements made by a single generation
+    pub fn with_max_replacements(mut self, max_replacements: usize) -> Self {
+        self.max_replacements = max_replacements;
+        self
+    }
+}
+
+/// This is the limit of a `GenerationBudget` a generation went over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetLimit {
+    /// The output grew longer than the budget's maximum output length - holding the limit
+    OutputLength(usize),
+    /// The generation made more replacements than the budget allows - holding the limit
+    Replacements(usize),
+}
+
+impl Display for BudgetLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BudgetLimit::OutputLength(limit) => write!(f, "{limit} bytes of output"),
+            BudgetLimit::Replacements(limit) => write!(f, "{limit} replacements"),
+        }
+    }
+}
+
+/// This tracks how much of it's budget a generation has used
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct BudgetUsage {
+    replacements: usize,
+    output_length: usize,
+    pub(crate) exceeded: Option<BudgetLimit>,
+}
+
+impl TraceryGrammar {
+    /// This limits how much work a single generation can do, returning the updated grammar. See `set_budget`.
+    pub fn with_budget(mut self, budget: GenerationBudget) -> Self {
+        self.set_budget(Some(budget));
+        self
+    }
+
+    /// This sets or clears the grammar's generation budget. Once a generation goes over it, no further rules are expanded and
+    /// it fails with `GenerationIssue::BudgetExceeded`. Grammars without a budget leave rules unexpanded once they reach
+    /// `Grammar::max_expansions`, without failing.
+    pub fn set_budget(&mut self, budget: Option<GenerationBudget>) {
+        self.budget = budget;
+    }
+
+    /// This gets the grammar's generation budget, if it has one
+    pub fn budget(&self) -> Option<&GenerationBudget> {
+        self.budget.as_ref()
+    }
+
+    /// Charges a replacement to the generation's budget before it is made, returning whether it can go ahead
+    pub(crate) fn charge_replacement(&self, runtime: &mut RuntimeState) -> bool {
+        let Some(budget) = self.budget.as_ref() else {
+            return true;
+        };
+        let usage = &mut runtime.usage;
+        if usage.exceeded.is_some() {
+            return false;
+        }
+        usage.replacements += 1;
+        if usage.replacements > budget.max_replacements {
+            usage.exceeded = Some(BudgetLimit::Replacements(budget.max_replacements));
+            return false;
+        }
+        true
+    }
+
+    /// Charges the value a replacement selected to the generation's budget, returning whether it can be used
+    pub(crate) fn charge_output(&self, runtime: &mut RuntimeState, value: &str) -> bool {
+        let Some(budget) = self.budget.as_ref() else {
+            return true;
+        };
+        let usage = &mut runtime.usage;
+        usage.output_length = usage.output_length.saturating_add(value.len());
+        if usage.output_length > budget.max_output_length {
+            usage.exceeded = Some(BudgetLimit::OutputLength(budget.max_output_length));
+            return false;
+        }
+        true
+    }
+
+    /// Checks a generation against the budget, once it is complete
+    pub(crate) fn check_budget(
+        &self,
+        runtime: &RuntimeState,
+        result: &str,
+    ) -> Result<(), GenerationIssue> {
+        if let Some(limit) = runtime.usage.exceeded {
+            return Err(GenerationIssue::BudgetExceeded(limit));
+        }
+        match self.budget.as_ref() {
+            Some(budget) if result.len() > budget.max_output_length => {
+                Err(GenerationIssue::BudgetExceeded(BudgetLimit::OutputLength(
+                    budget.max_output_length,
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generator::SeededRng,
+        tracery::{StatefulStringGenerator, StringGenerator},
+    };
+
+    #[test]
+    pub fn runaway_generations_fail_once_they_go_over_budget() {
+        let grammar = TraceryGrammar::new(&[("origin", &["#origin##origin#"])], None)
+            .with_max_depth(40)
+            .with_budget(GenerationBudget::new().with_max_replacements(500));
+        assert_eq!(
+            StringGenerator::try_generate(&grammar, &mut 0),
+            Err(GenerationIssue::BudgetExceeded(BudgetLimit::Replacements(
+                500
+            )))
+        );
+
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["#word# #word# #word#"]),
+                ("word", &["lorem ipsum"]),
+            ],
+            None,
+        )
+        .with_budget(GenerationBudget::new().with_max_output_length(20));
+        let issue = Err(GenerationIssue::BudgetExceeded(BudgetLimit::OutputLength(
+            20,
+        )));
+        assert_eq!(StringGenerator::try_generate(&grammar, &mut 0), issue);
+        let mut generator = StatefulStringGenerator::from_grammar(grammar.clone());
+        assert_eq!(generator.try_generate(&mut SeededRng::new(1)), issue);
+
+        // The usage is reset between generations
+        let mut generator = StatefulStringGenerator::from_grammar(
+            grammar.with_budget(GenerationBudget::new().with_max_replacements(4)),
+        );
+        for _ in 0..3 {
+            assert_eq!(
+                generator.try_generate(&mut 0).unwrap(),
+                "lorem ipsum lorem ipsum lorem ipsum"
+            );
+        }
+    }
+}
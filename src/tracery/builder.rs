@@ -0,0 +1,237 @@
+use super::{GrammarError, JoinMode, TraceryGrammar};
+
+/// This builds a `TraceryGrammar` one rule at a time, which is more convenient than `TraceryGrammar::new` for content
+/// assembled at runtime. Problems are caught as each rule is added, and reported by `build`.
+#[derive(Debug, Clone)]
+pub struct TraceryGrammarBuilder {
+    grammar: TraceryGrammar,
+    error: Option<GrammarError>,
+}
+
+/// This adds the options of a single rule to a `TraceryGrammarBuilder`. The rule is added to the grammar once another rule is started,
+/// or the grammar is built.
+#[derive(Debug, Clone)]
+pub struct RuleBuilder {
+    builder: TraceryGrammarBuilder,
+    key: String,
+    options: Vec<String>,
+    weights: Vec<u32>,
+}
+
+impl TraceryGrammar {
+    /// This starts building a grammar using a `TraceryGrammarBuilder`
+    pub fn builder() -> TraceryGrammarBuilder {
+        TraceryGrammarBuilder::new()
+    }
+}
+
+impl Default for TraceryGrammarBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TraceryGrammarBuilder {
+    /// Creates a builder for an empty grammar, starting at "origin"
+    pub fn new() -> Self {
+        Self {
+            grammar: TraceryGrammar::empty(),
+            error: None,
+        }
+    }
+
+    /// Starts adding a new rule
+    pub fn rule(mut self, key: impl Into<String>) -> RuleBuilder {
+        let key = key.into();
+        if key.is_empty() {
+            self.fail(GrammarError::EmptyKey);
+        } else if self.grammar.rules.contains_key(&key) {
+            self.fail(GrammarError::DuplicateRule(key.clone()));
+        }
+        RuleBuilder {
+            builder: self,
+            key,
+            options: vec![],
+            weights: vec![],
+        }
+    }
+
+    /// Sets the rule generation starts at. If not set, it starts at "origin".
+    pub fn starting_point(mut self, key: impl Into<String>) -> Self {
+        self.grammar.starting_point = key.into();
+        self
+    }
+
+    /// Sets the separator placed between the pieces of each expansion - see `TraceryGrammar::set_separator`
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.grammar.separator = separator.into();
+        self
+    }
+
+    /// Sets how the pieces of each expansion are joined together - see `TraceryGrammar::set_join_mode`
+    pub fn join_mode(mut self, join: JoinMode) -> Self {
+        self.grammar.join = join;
+        self
+    }
+
+    /// Sets the locale the grammar is written in - see `TraceryGrammar::set_locale`
+    pub fn locale(mut self, locale: impl Into<String>) -> Self {
+        self.grammar.locale = Some(locale.into());
+        self
+    }
+
+    /// Finishes building the grammar, or provides the first problem found while building it
+    pub fn build(mut self) -> Result<TraceryGrammar, GrammarError> {
+        if !self
+            .grammar
+            .rules
+            .contains_key(&self.grammar.starting_point)
+        {
+            let starting_point = self.grammar.starting_point.clone();
+            self.fail(GrammarError::MissingStartingPoint(starting_point));
+        }
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.grammar),
+        }
+    }
+
+    fn fail(&mut self, error: GrammarError) {
+        self.error.get_or_insert(error);
+    }
+}
+
+impl RuleBuilder {
+    /// Adds a single option to the rule
+    pub fn option(mut self, option: impl Into<String>) -> Self {
+        self.options.push(option.into());
+        self
+    }
+
+    /// Adds several options to the rule
+    pub fn options<T: Into<String>>(mut self, options: impl IntoIterator<Item = T>) -> Self {
+        self.options.extend(options.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets the weight of the most recently added option. Options have a weight of 1 unless one is set.
+    pub fn weight(mut self, weight: u32) -> Self {
+        if self.options.is_empty() {
+            self.builder
+                .fail(GrammarError::WeightWithoutOption(self.key.clone()));
+            return self;
+        }
+        self.weights.resize(self.options.len(), 1);
+        self.weights[self.options.len() - 1] = weight;
+        self
+    }
+
+    /// Finishes this rule, and starts adding a new one
+    pub fn rule(self, key: impl Into<String>) -> RuleBuilder {
+        self.finish().rule(key)
+    }
+
+    /// Finishes this rule, and sets the rule generation starts at
+    pub fn starting_point(self, key: impl Into<String>) -> TraceryGrammarBuilder {
+        self.finish().starting_point(key)
+    }
+
+    /// Finishes this rule, and sets the separator placed between the pieces of each expansion
+    pub fn separator(self, separator: impl Into<String>) -> TraceryGrammarBuilder {
+        self.finish().separator(separator)
+    }
+
+    /// Finishes this rule, and sets the locale the grammar is written in
+    pub fn locale(self, locale: impl Into<String>) -> TraceryGrammarBuilder {
+        self.finish().locale(locale)
+    }
+
+    /// Finishes this rule, then finishes building the grammar
+    pub fn build(self) -> Result<TraceryGrammar, GrammarError> {
+        self.finish().build()
+    }
+
+    /// Finishes this rule, returning to the grammar builder
+    pub fn finish(self) -> TraceryGrammarBuilder {
+        let Self {
+            mut builder,
+            key,
+            options,
+            mut weights,
+        } = self;
+        if options.is_empty() {
+            builder.fail(GrammarError::NoOptions(key.clone()));
+        }
+        if key.is_empty() || builder.grammar.rules.contains_key(&key) {
+            return builder;
+        }
+        if weights.iter().any(|weight| *weight != 1) {
+            weights.resize(options.len(), 1);
+            builder.grammar.set_weights(key.clone(), weights);
+        }
+        builder.grammar.keys.push(key.clone());
+        builder.grammar.rules.insert(key, options);
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generator::Generator, tracery::StringGenerator};
+
+    #[test]
+    pub fn builds_grammars_with_weighted_options() {
+        let grammar = TraceryGrammar::builder()
+            .rule("greeting")
+            .options(["Hi #animal#"])
+            .rule("animal")
+            .option("cat")
+            .weight(3)
+            .option("dog")
+            .starting_point("greeting")
+            .build()
+            .unwrap();
+        assert_eq!(grammar.rule_settings("animal").unwrap().weights, vec![3, 1]);
+        assert_eq!(
+            StringGenerator::generate(&grammar, &mut 2).unwrap(),
+            "Hi cat"
+        );
+        assert_eq!(
+            StringGenerator::generate(&grammar, &mut 3).unwrap(),
+            "Hi dog"
+        );
+    }
+
+    #[test]
+    pub fn reports_the_first_problem_found() {
+        let duplicate = TraceryGrammar::builder()
+            .rule("origin")
+            .option("a")
+            .rule("origin")
+            .option("b")
+            .build();
+        assert_eq!(
+            duplicate.unwrap_err(),
+            GrammarError::DuplicateRule("origin".to_string())
+        );
+        let weight = TraceryGrammar::builder()
+            .rule("origin")
+            .weight(2)
+            .option("a")
+            .build();
+        assert_eq!(
+            weight.unwrap_err(),
+            GrammarError::WeightWithoutOption("origin".to_string())
+        );
+        let empty = TraceryGrammar::builder().rule("origin").build();
+        assert_eq!(
+            empty.unwrap_err(),
+            GrammarError::NoOptions("origin".to_string())
+        );
+        assert_eq!(
+            TraceryGrammar::builder().build().unwrap_err(),
+            GrammarError::MissingStartingPoint("origin".to_string())
+        );
+    }
+}
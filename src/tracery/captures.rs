@@ -0,0 +1,58 @@
+/// The capture holding the index of the option a rule last chose - `#noun._index#`
+pub const INDEX_CAPTURE: &str = "_index";
+
+/// The capture holding the name of the option a rule last chose - `#noun._name#`.
+/// Options are named using the `{name:value}` annotation, and options without a name use their index instead.
+pub const NAME_CAPTURE: &str = "_name";
+
+/// The capture holding the full expansion of a rule that emits data - used internally while the rule is expanded
+pub const EMITTED_CAPTURE: &str = "_emitted";
+
+/// The name of the annotation used to name an option - `{name:friendly}`
+pub const NAME_ANNOTATION: &str = "name";
+
+/// Gets the key a rule's capture is stored under - such as `noun._index`
+pub fn capture_key(rule: &str, capture: &str) -> String {
+    format!("{rule}.{capture}")
+}
+
+/// Checks whether a key refers to a capture rather than a rule
+pub fn is_capture_key(key: &str) -> bool {
+    key.rsplit_once('.')
+        .is_some_and(|(_, capture)| capture.starts_with('_'))
+}
+
+/// Splits a key from its modifiers. Segments starting with `_` are captures, and are kept as part of the key -
+/// so `noun._name.capitalize` refers to the `noun._name` capture, with the `capitalize` modifier.
+pub fn split_key_and_modifiers(rule: &str) -> Option<(&str, &str)> {
+    let mut start = 0;
+    while let Some(offset) = rule[start..].find('.') {
+        let split = start + offset;
+        if !rule[split + 1..].starts_with('_') {
+            return Some((&rule[..split], &rule[split + 1..]));
+        }
+        start = split + 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn captures_are_kept_as_part_of_the_key() {
+        assert_eq!(split_key_and_modifiers("noun"), None);
+        assert_eq!(split_key_and_modifiers("noun._index"), None);
+        assert_eq!(
+            split_key_and_modifiers("noun.s.capitalize"),
+            Some(("noun", "s.capitalize"))
+        );
+        assert_eq!(
+            split_key_and_modifiers("noun._name.capitalize"),
+            Some(("noun._name", "capitalize"))
+        );
+        assert!(is_capture_key("noun._name"));
+        assert!(!is_capture_key("noun"));
+    }
+}
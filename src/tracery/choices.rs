@@ -0,0 +1,314 @@
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{
+    annotations::{option_name, option_text},
+    runtime::RuntimeState,
+    GenerationIssue, StringGenerator, TraceryGrammar,
+};
+use crate::generator::{Grammar, SeededRng};
+
+/// This is an option the player can choose at a choice point
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChoiceOption {
+    /// The index of the option within it's rule, which is passed to `StringGenerator::resume_interactive` to choose it
+    pub index: usize,
+    /// The option's name, set using the `{name:...}` annotation - handy as a short label, like `"threaten"`
+    pub name: Option<String>,
+    /// The option's text, without it's annotations. It isn't expanded, so choice options are best written as plain text
+    pub text: String,
+}
+
+/// This is a point in an interactive generation where the caller chooses how it continues, such as a line of dialogue
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChoicePoint {
+    /// The rule being chosen from
+    pub rule: String,
+    /// The options to choose between
+    pub options: Vec<ChoiceOption>,
+    /// The token resuming the generation once an option was chosen
+    pub token: ChoiceToken,
+}
+
+/// This is the result of a step of an interactive generation - either a choice that needs to be made, or the finished text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InteractiveStep {
+    /// Generation reached a choice point, and continues once one of it's options is chosen
+    Choice(ChoicePoint),
+    /// Generation is complete
+    Complete(String),
+}
+
+/// This is the continuation of an interactive generation, passed to `StringGenerator::resume_interactive` along with the chosen option.
+/// It holds the random number generator the generation started with and the choices made so far - so each step replays the generation
+/// with those choices, and choosing the same options always generates the same text. With the `serde` feature, it can be saved
+/// to continue a conversation later.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ChoiceToken {
+    key: String,
+    rng: SeededRng,
+    choices: Vec<usize>,
+    rule: String,
+    options: usize,
+}
+
+impl ChoiceToken {
+    /// Gets the options chosen so far, in the order their choice points were reached
+    pub fn choices(&self) -> &[usize] {
+        &self.choices
+    }
+
+    /// Gets the rule the next choice is made from
+    pub fn rule(&self) -> &str {
+        &self.rule
+    }
+}
+
+#[derive(Debug, Default)]
+struct Choices {
+    made: Vec<usize>,
+    next: usize,
+    pending: Option<(String, Vec<String>)>,
+}
+
+/// This provides the choices made so far to an interactive generation, and records the first choice point left to be made.
+/// It is shared with the generation through it's runtime state, so the result can be read once it's done.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ChoiceState(Arc<Mutex<Choices>>);
+
+impl PartialEq for ChoiceState {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl ChoiceState {
+    fn new(made: Vec<usize>) -> Self {
+        Self(Arc::new(Mutex::new(Choices {
+            made,
+            ..Default::default()
+        })))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Choices> {
+        self.0.lock().unwrap_or_else(|error| error.into_inner())
+    }
+
+    fn take_pending(&self) -> Option<(String, Vec<String>)> {
+        self.lock().pending.take()
+    }
+}
+
+impl TraceryGrammar {
+    /// This makes a rule a choice point, returning the updated grammar. See `set_choice_point`.
+    pub fn with_choice_point(mut self, rule: impl Into<String>) -> Self {
+        self.set_choice_point(rule, true);
+        self
+    }
+
+    /// This sets whether a rule is a choice point. When generating interactively, reaching a choice point pauses the generation
+    /// so the caller can choose one of it's options - like the player picking a line of dialogue. Otherwise, the rule chooses it's
+    /// options as usual, so the same grammar can also generate whole conversations on it's own.
+    pub fn set_choice_point(&mut self, rule: impl Into<String>, choice: bool) {
+        self.settings.entry(rule.into()).or_default().choice = choice;
+    }
+
+    /// This checks whether a rule is a choice point
+    pub fn is_choice_point(&self, rule: &str) -> bool {
+        self.settings
+            .get(self.resolve_alias(rule))
+            .is_some_and(|settings| settings.choice)
+    }
+
+    /// Selects the option of a choice point during an interactive generation, using the next choice that was made.
+    /// Once the choices run out, the first choice point reached is recorded and expands to nothing.
+    pub(crate) fn select_choice(
+        &self,
+        rule: &str,
+        runtime: &mut RuntimeState,
+    ) -> Option<(String, Option<usize>)> {
+        let choices = runtime.choices.as_ref()?;
+        let rule = self.resolve_alias(rule);
+        let options = self.rules.get(rule)?;
+        let mut choices = choices.lock();
+        let index = choices.made.get(choices.next).copied();
+        choices.next += 1;
+        match index.filter(|index| *index < options.len()) {
+            Some(index) => {
+                runtime.selection.record_choice(rule, index);
+                let value = option_text(&options[index]).to_string();
+                Some(match self.emits(rule) {
+                    true => (self.wrap_emitted(rule, value), Some(index)),
+                    false => (value, Some(index)),
+                })
+            }
+            None => {
+                if choices.pending.is_none() {
+                    choices.pending = Some((rule.to_string(), options.clone()));
+                }
+                Some((String::new(), None))
+            }
+        }
+    }
+}
+
+impl StringGenerator {
+    /// This generates interactively from the grammar's starting point. Rather than choosing the options of choice points at random,
+    /// generation pauses at the first one it reaches and returns it's options - so the game can present them to the player, and continue
+    /// using `resume_interactive` with the option they chose. Every other rule is expanded using the random number generator, as usual.
+    /// The starting point itself isn't treated as a choice point.
+    pub fn generate_interactive(
+        grammar: &TraceryGrammar,
+        rng: SeededRng,
+    ) -> Result<InteractiveStep, GenerationIssue> {
+        let key = grammar.default_starting_point().clone();
+        Self::generate_interactive_at(&key, grammar, rng)
+    }
+
+    /// This generates interactively from the provided key. See `generate_interactive`.
+    pub fn generate_interactive_at(
+        key: &str,
+        grammar: &TraceryGrammar,
+        rng: SeededRng,
+    ) -> Result<InteractiveStep, GenerationIssue> {
+        Self::step_interactive(key.to_string(), grammar, rng, vec![])
+    }
+
+    /// This continues an interactive generation with the index of the chosen option, until it reaches the next choice point or completes.
+    /// The grammar should be the one the generation started with.
+    pub fn resume_interactive(
+        grammar: &TraceryGrammar,
+        token: ChoiceToken,
+        choice: usize,
+    ) -> Result<InteractiveStep, GenerationIssue> {
+        if choice >= token.options {
+            return Err(GenerationIssue::InvalidChoice(token.rule, choice));
+        }
+        let ChoiceToken {
+            key,
+            rng,
+            mut choices,
+            ..
+        } = token;
+        choices.push(choice);
+        Self::step_interactive(key, grammar, rng, choices)
+    }
+
+    fn step_interactive(
+        key: String,
+        grammar: &TraceryGrammar,
+        rng: SeededRng,
+        choices: Vec<usize>,
+    ) -> Result<InteractiveStep, GenerationIssue> {
+        let state = ChoiceState::new(choices.clone());
+        let runtime = RuntimeState {
+            choices: Some(state.clone()),
+            ..Default::default()
+        };
+        // The generation is replayed from the start, so the random number generator is restored first
+        let mut replay = rng.clone();
+        let (_, result, _) = Self::try_generate_with(&key, grammar, &mut replay, None, runtime)?;
+        Ok(match state.take_pending() {
+            Some((rule, options)) => InteractiveStep::Choice(ChoicePoint {
+                options: options
+                    .iter()
+                    .enumerate()
+                    .map(|(index, option)| ChoiceOption {
+                        index,
+                        name: option_name(option).map(str::to_string),
+                        text: option_text(option).to_string(),
+                    })
+                    .collect(),
+                token: ChoiceToken {
+                    key,
+                    rng,
+                    choices,
+                    rule: rule.clone(),
+                    options: options.len(),
+                },
+                rule,
+            }),
+            None => InteractiveStep::Complete(result),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dialogue() -> TraceryGrammar {
+        TraceryGrammar::new(
+            &[
+                ("origin", &["#greeting# #reply# #farewell#"]),
+                ("greeting", &["Halt, traveller!", "Well met!"]),
+                (
+                    "reply",
+                    &[
+                        "{name:friendly}I come in peace.",
+                        "{name:threaten}Step aside.",
+                    ],
+                ),
+                ("farewell", &["#mood#"]),
+                ("mood", &["Safe travels.", "Be gone!"]),
+            ],
+            None,
+        )
+        .with_choice_point("reply")
+        .with_choice_point("mood")
+    }
+
+    fn choice(step: InteractiveStep) -> ChoicePoint {
+        match step {
+            InteractiveStep::Choice(choice) => choice,
+            InteractiveStep::Complete(text) => panic!("expected a choice, got `{text}`"),
+        }
+    }
+
+    #[test]
+    pub fn generation_pauses_at_choice_points() {
+        let grammar = dialogue();
+        let first =
+            choice(StringGenerator::generate_interactive(&grammar, SeededRng::new(3)).unwrap());
+        assert_eq!(first.rule, "reply");
+        assert_eq!(
+            first.options[1],
+            ChoiceOption {
+                index: 1,
+                name: Some("threaten".to_string()),
+                text: "Step aside.".to_string(),
+            }
+        );
+
+        let second =
+            choice(StringGenerator::resume_interactive(&grammar, first.token.clone(), 1).unwrap());
+        assert_eq!(second.rule, "mood");
+        assert_eq!(second.token.choices(), &[1]);
+        let InteractiveStep::Complete(text) =
+            StringGenerator::resume_interactive(&grammar, second.token.clone(), 1).unwrap()
+        else {
+            panic!("the generation should be complete");
+        };
+        assert!(text.ends_with("Step aside. Be gone!"));
+        // Replaying the same choices generates the same text
+        assert_eq!(
+            StringGenerator::resume_interactive(&grammar, second.token, 1).unwrap(),
+            InteractiveStep::Complete(text)
+        );
+
+        assert_eq!(
+            StringGenerator::resume_interactive(&grammar, first.token, 2),
+            Err(GenerationIssue::InvalidChoice("reply".to_string(), 2))
+        );
+    }
+
+    #[test]
+    pub fn choice_points_are_chosen_at_random_otherwise() {
+        use crate::generator::Generator;
+        let text = StringGenerator::generate(&dialogue(), &mut SeededRng::new(3)).unwrap();
+        assert!(!text.is_empty() && !text.contains('#'));
+    }
+}
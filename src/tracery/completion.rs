@@ -0,0 +1,157 @@
+use super::{
+    agreement::{AGREE_MODIFIER, PRONOUN_MODIFIERS},
+    find_closing,
+    modifiers::BUILT_IN_MODIFIERS,
+    StatefulStringGenerator, TraceryGrammar,
+};
+
+/// What a completion candidate refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CompletionKind {
+    /// One of the grammar's rules
+    Rule,
+    /// An alias for one of the grammar's rules
+    Alias,
+    /// A variable - either assigned by an action within the grammar, or remembered by a stateful generator
+    Variable,
+    /// A built in or custom modifier
+    Modifier,
+}
+
+/// This is a possible completion for a key being typed in an editor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionCandidate {
+    /// The completed key or modifier
+    pub text: String,
+    /// What the completion refers to
+    pub kind: CompletionKind,
+}
+
+impl TraceryGrammar {
+    /// This lists the keys that could complete the text typed so far within a `#key#`, sorted alphabetically.
+    /// If the prefix contains a `.`, the text after the last `.` is completed using the built in and custom modifiers instead.
+    pub fn completion_candidates(&self, prefix: &str) -> Vec<CompletionCandidate> {
+        let mut candidates = vec![];
+        self.add_completion_candidates(prefix, &mut candidates);
+        finish_candidates(candidates)
+    }
+
+    fn add_completion_candidates(&self, prefix: &str, candidates: &mut Vec<CompletionCandidate>) {
+        let mut add = |text: &str, kind: CompletionKind| {
+            candidates.push(CompletionCandidate {
+                text: text.to_string(),
+                kind,
+            });
+        };
+        if let Some((_, modifier)) = prefix.rsplit_once('.') {
+            BUILT_IN_MODIFIERS
+                .iter()
+                .chain(PRONOUN_MODIFIERS)
+                .copied()
+                .chain([AGREE_MODIFIER])
+                .chain(self.modifiers.keys().map(String::as_str))
+                .filter(|name| name.starts_with(modifier))
+                .for_each(|name| add(name, CompletionKind::Modifier));
+            return;
+        }
+        self.keys
+            .iter()
+            .filter(|key| key.starts_with(prefix))
+            .for_each(|key| add(key, CompletionKind::Rule));
+        self.aliases
+            .keys()
+            .filter(|alias| alias.starts_with(prefix))
+            .for_each(|alias| add(alias, CompletionKind::Alias));
+        self.rules
+            .values()
+            .flatten()
+            .flat_map(|option| assigned_variables(option))
+            .filter(|variable| variable.starts_with(prefix))
+            .for_each(|variable| add(variable, CompletionKind::Variable));
+    }
+}
+
+impl StatefulStringGenerator {
+    /// This lists the keys that could complete the text typed so far within a `#key#`, like `TraceryGrammar::completion_candidates`,
+    /// including the variables the generator remembered.
+    pub fn completion_candidates(&self, prefix: &str) -> Vec<CompletionCandidate> {
+        let mut candidates = vec![];
+        self.grammar
+            .add_completion_candidates(prefix, &mut candidates);
+        if !prefix.contains('.') {
+            candidates.extend(
+                self.variables()
+                    .iter()
+                    .filter(|(key, _)| key.starts_with(prefix))
+                    .map(|(key, _)| CompletionCandidate {
+                        text: key.clone(),
+                        kind: CompletionKind::Variable,
+                    }),
+            );
+        }
+        finish_candidates(candidates)
+    }
+}
+
+/// Sorts the candidates, only keeping the first kind found for each text
+fn finish_candidates(mut candidates: Vec<CompletionCandidate>) -> Vec<CompletionCandidate> {
+    candidates.sort_by(|a, b| a.text.cmp(&b.text).then(a.kind.cmp(&b.kind)));
+    candidates.dedup_by(|a, b| a.text == b.text);
+    candidates
+}
+
+/// Finds the keys of the variables assigned by actions within an option, like `name` in `[name:#names#]`
+pub(crate) fn assigned_variables(option: &str) -> impl Iterator<Item = &str> {
+    let mut rest = option;
+    std::iter::from_fn(move || loop {
+        let start = rest.find('[')?;
+        let inner = &rest[start + 1..];
+        let end = find_closing(inner, ']');
+        let action = &inner[..end];
+        rest = inner.get(end + 1..).unwrap_or_default();
+        if let Some(split) = action.find([':', '|']).filter(|split| *split > 0) {
+            return Some(&action[..split]);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn completes_rules_variables_and_modifiers() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["[name:#names#][nickname|#names#]#name#"]),
+                ("names", &["Mia"]),
+                ("noun", &["cat"]),
+            ],
+            None,
+        )
+        .with_alias("nom", "names");
+        let texts = |candidates: Vec<CompletionCandidate>| {
+            candidates
+                .into_iter()
+                .map(|candidate| (candidate.text, candidate.kind))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            texts(grammar.completion_candidates("n")),
+            vec![
+                ("name".to_string(), CompletionKind::Variable),
+                ("names".to_string(), CompletionKind::Rule),
+                ("nickname".to_string(), CompletionKind::Variable),
+                ("nom".to_string(), CompletionKind::Alias),
+                ("noun".to_string(), CompletionKind::Rule),
+            ]
+        );
+        assert_eq!(
+            texts(grammar.completion_candidates("name.cap")),
+            vec![
+                ("capitalize".to_string(), CompletionKind::Modifier),
+                ("capitalizeAll".to_string(), CompletionKind::Modifier),
+            ]
+        );
+    }
+}
@@ -0,0 +1,77 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::annotations::{option_name, option_text};
+
+/// This stops two options from being chosen within the same generation - so if `creature` chose `lion`, `obstacle` can't choose
+/// `monsters`, and the other way around. Options are referred to by their name, declared using the `{name:value}` annotation, or by their text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Exclusion {
+    /// The rule on the first side of the exclusion
+    pub rule: String,
+    /// The option on the first side of the exclusion
+    pub option: String,
+    /// The rule on the other side of the exclusion
+    pub other_rule: String,
+    /// The option on the other side of the exclusion
+    pub other_option: String,
+}
+
+impl Exclusion {
+    /// Creates an exclusion between an option of one rule, and an option of another
+    pub fn new(
+        rule: impl Into<String>,
+        option: impl Into<String>,
+        other_rule: impl Into<String>,
+        other_option: impl Into<String>,
+    ) -> Self {
+        Self {
+            rule: rule.into(),
+            option: option.into(),
+            other_rule: other_rule.into(),
+            other_option: other_option.into(),
+        }
+    }
+
+    /// Checks whether the exclusion involves a rule
+    pub fn involves(&self, rule: &str) -> bool {
+        self.rule == rule || self.other_rule == rule
+    }
+
+    /// Gets the rule and option on the other side of the exclusion, if one side of it refers to the provided option
+    pub(crate) fn other_side(&self, rule: &str, option: &str) -> Option<(&str, &str)> {
+        if self.rule == rule && refers_to(option, &self.option) {
+            Some((&self.other_rule, &self.other_option))
+        } else if self.other_rule == rule && refers_to(option, &self.other_option) {
+            Some((&self.rule, &self.option))
+        } else {
+            None
+        }
+    }
+}
+
+/// Checks whether an option has the provided name or text
+pub(crate) fn refers_to(option: &str, reference: &str) -> bool {
+    option_name(option) == Some(reference) || option_text(option) == reference
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn exclusions_apply_in_both_directions() {
+        let exclusion = Exclusion::new("creature", "lion", "obstacle", "monsters");
+        assert_eq!(
+            exclusion.other_side("creature", "lion"),
+            Some(("obstacle", "monsters"))
+        );
+        assert_eq!(
+            exclusion.other_side("obstacle", "{name:monsters}a horde"),
+            Some(("creature", "lion"))
+        );
+        assert_eq!(exclusion.other_side("creature", "tiger"), None);
+        assert!(exclusion.involves("obstacle"));
+    }
+}
@@ -0,0 +1,258 @@
+use super::{GenerationIssue, StatefulStringGenerator, StringGenerator, TraceryGrammar};
+use crate::generator::GrammarRandomNumberGenerator;
+
+/// A check accepting or rejecting generated text, returning `true` if the text is acceptable
+pub type OutputCheck = fn(&str) -> bool;
+
+/// This determines what happens when the content filter rejects a generated output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterPolicy {
+    /// The output is generated again, up to the provided number of attempts in total - failing once they are used up
+    Reroll(usize),
+    /// The generation fails straight away
+    Fail,
+}
+
+impl Default for FilterPolicy {
+    fn default() -> Self {
+        Self::Reroll(10)
+    }
+}
+
+/// This is a guard against unfortunate random combinations in generated text - rejecting outputs containing a banned word or
+/// substring, or failing a custom check. Words are matched whole and ignoring case, so banning `ass` doesn't reject `class`,
+/// while substrings are matched anywhere in the text. Rejected outputs are re-rolled or fail, depending on the `FilterPolicy`.
+/// It is set on a stateful generator using `StatefulStringGenerator::set_content_filter`, or used with `StringGenerator::try_generate_filtered`.
+#[derive(Debug, Clone, Default)]
+pub struct ContentFilter {
+    words: Vec<String>,
+    substrings: Vec<String>,
+    checks: Vec<OutputCheck>,
+    policy: FilterPolicy,
+}
+
+impl ContentFilter {
+    /// Creates a filter accepting every output, re-rolling up to 10 times by default
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// This bans a list of words, returning the updated filter. See `ban_word`.
+    pub fn with_banned_words<T: AsRef<str>>(mut self, words: impl IntoIterator<Item = T>) -> Self {
+        for word in words {
+            self.ban_word(word.as_ref());
+        }
+        self
+    }
+
+    /// This bans a word, rejecting outputs where it appears as a whole word - ignoring case
+    pub fn ban_word(&mut self, word: &str) {
+        let word = word.trim().to_lowercase();
+        if !word.is_empty() && !self.words.contains(&word) {
+            self.words.push(word);
+        }
+    }
+
+    /// This bans a list of substrings, returning the updated filter. See `ban_substring`.
+    pub fn with_banned_substrings<T: AsRef<str>>(
+        mut self,
+        substrings: impl IntoIterator<Item = T>,
+    ) -> Self {
+        for substring in substrings {
+            self.ban_substring(substring.as_ref());
+        }
+        self
+    }
+
+    /// This bans a substring, rejecting outputs that contain it anywhere - ignoring case
+    pub fn ban_substring(&mut self, substring: &str) {
+        let substring = substring.to_lowercase();
+        if !substring.is_empty() && !self.substrings.contains(&substring) {
+            self.substrings.push(substring);
+        }
+    }
+
+    /// This adds a custom check, returning the updated filter. Outputs the check returns `false` for are rejected.
+    pub fn with_check(mut self, check: OutputCheck) -> Self {
+        self.checks.push(check);
+        self
+    }
+
+    /// This sets what happens when an output is rejected, returning the updated filter
+    pub fn with_policy(mut self, policy: FilterPolicy) -> Self {
+        self.set_policy(policy);
+        self
+    }
+
+    /// This sets what happens when an output is rejected
+    pub fn set_policy(&mut self, policy: FilterPolicy) {
+        self.policy = policy;
+    }
+
+    /// This gets what happens when an output is rejected
+    pub fn policy(&self) -> FilterPolicy {
+        self.policy
+    }
+
+    /// This gets the banned words, in lower case
+    pub fn banned_words(&self) -> &[String] {
+        &self.words
+    }
+
+    /// This gets the banned substrings, in lower case
+    pub fn banned_substrings(&self) -> &[String] {
+        &self.substrings
+    }
+
+    /// This checks whether some text passes the filter
+    pub fn accepts(&self, text: &str) -> bool {
+        let lower = text.to_lowercase();
+        let banned_word = lower
+            .split(|character: char| !character.is_alphanumeric() && character != '\'')
+            .any(|word| self.words.iter().any(|banned| banned == word));
+        !banned_word
+            && !self.substrings.iter().any(|banned| lower.contains(banned))
+            && self.checks.iter().all(|check| check(text))
+    }
+
+    /// The number of generations the filter allows in total
+    fn attempts(&self) -> usize {
+        match self.policy {
+            FilterPolicy::Reroll(attempts) => attempts.max(1),
+            FilterPolicy::Fail => 1,
+        }
+    }
+}
+
+impl StringGenerator {
+    /// This generates from the grammar's starting point, re-rolling outputs the content filter rejects as it's policy describes.
+    /// If every attempt is rejected, this returns `GenerationIssue::Filtered`.
+    pub fn try_generate_filtered<R: GrammarRandomNumberGenerator>(
+        grammar: &TraceryGrammar,
+        filter: &ContentFilter,
+        rng: &mut R,
+    ) -> Result<String, GenerationIssue> {
+        let attempts = filter.attempts();
+        for _ in 0..attempts {
+            let output = Self::try_generate(grammar, rng)?;
+            if filter.accepts(&output) {
+                return Ok(output);
+            }
+        }
+        Err(GenerationIssue::Filtered(attempts))
+    }
+}
+
+impl StatefulStringGenerator {
+    /// This sets the content filter checking the generator's outputs, returning the updated generator. See `set_content_filter`.
+    pub fn with_content_filter(mut self, filter: ContentFilter) -> Self {
+        self.set_content_filter(Some(filter));
+        self
+    }
+
+    /// This sets the content filter checking the generator's final outputs - or removes it. Rejected outputs are re-rolled or fail,
+    /// as the filter's policy describes, and none of their variables are kept.
+    pub fn set_content_filter(&mut self, filter: Option<ContentFilter>) {
+        self.content_filter = filter;
+    }
+
+    /// This gets the content filter checking the generator's outputs, if there is one
+    pub fn content_filter(&self) -> Option<&ContentFilter> {
+        self.content_filter.as_ref()
+    }
+
+    /// Generates from the provided key, re-rolling outputs the content filter rejects
+    pub(crate) fn generate_filtered<R: GrammarRandomNumberGenerator>(
+        &mut self,
+        key: &str,
+        rng: &mut R,
+    ) -> Result<String, GenerationIssue> {
+        self.filtered(rng, |generator, rng| generator.generate_retained(key, rng))
+    }
+
+    /// Runs a generation, re-running it while the content filter rejects it's output
+    pub(crate) fn filtered<R: GrammarRandomNumberGenerator>(
+        &mut self,
+        rng: &mut R,
+        mut generate: impl FnMut(&mut Self, &mut R) -> Result<String, GenerationIssue>,
+    ) -> Result<String, GenerationIssue> {
+        let Some(filter) = self.content_filter.clone() else {
+            return generate(self, rng);
+        };
+        let attempts = filter.attempts();
+        for _ in 0..attempts {
+            // A rejected output is rolled back, so it's variables don't leak into the next attempt
+            let runtime = self.runtime.clone();
+            let global_writes = self.global_writes.clone();
+            let emitted = self.emitted.clone();
+            let last_generation = self.last_generation.clone();
+            let output = generate(self, rng)?;
+            if filter.accepts(&output) {
+                return Ok(output);
+            }
+            self.runtime = runtime;
+            self.global_writes = global_writes;
+            self.emitted = emitted;
+            self.last_generation = last_generation;
+        }
+        Err(GenerationIssue::Filtered(attempts))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::SeededRng;
+
+    #[test]
+    pub fn banned_words_and_substrings_are_matched_ignoring_case() {
+        let filter = ContentFilter::new()
+            .with_banned_words(["Ass"])
+            .with_banned_substrings(["darn"])
+            .with_check(|text| text.len() < 40);
+        assert!(filter.accepts("A class of mages"));
+        assert!(!filter.accepts("The stubborn ASS, of course"));
+        assert!(!filter.accepts("Those Darned goblins"));
+        assert!(!filter.accepts("A story that goes on for far too long to tell"));
+    }
+
+    #[test]
+    pub fn rejected_outputs_are_rerolled_or_fail() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["[pet:#animal#]a #pet#"]),
+                ("animal", &["rat", "cat", "bat", "owl"]),
+            ],
+            None,
+        );
+        let filter = ContentFilter::new()
+            .with_banned_words(["rat", "cat", "bat"])
+            .with_policy(FilterPolicy::Reroll(100));
+        let mut generator = StatefulStringGenerator::from_grammar(grammar.clone())
+            .with_content_filter(filter.clone());
+        let mut rng = SeededRng::new(2);
+        for _ in 0..5 {
+            assert_eq!(generator.try_generate(&mut rng).unwrap(), "a owl");
+            assert_eq!(
+                generator.variables().get_var("pet"),
+                Some(&vec!["owl".to_string()])
+            );
+        }
+        assert_eq!(
+            StringGenerator::try_generate_filtered(&grammar, &filter, &mut rng),
+            Ok("a owl".to_string())
+        );
+
+        let everything = ContentFilter::new()
+            .with_check(|_| false)
+            .with_policy(FilterPolicy::Fail);
+        let mut generator =
+            StatefulStringGenerator::from_grammar(grammar).with_content_filter(everything);
+        assert_eq!(
+            generator.try_generate(&mut rng),
+            Err(GenerationIssue::Filtered(1))
+        );
+        // None of the rejected output's variables are kept
+        assert_eq!(generator.variables().get_var("pet"), None);
+    }
+}
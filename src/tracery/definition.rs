@@ -0,0 +1,242 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    annotations::tagged_option, AssertionPolicy, Exclusion, GenerationBudget, JoinMode,
+    RuleSettings, SelectionMode, ShadowingPolicy, TraceryGrammar,
+};
+use crate::generator::Grammar;
+#[cfg(feature = "bevy")]
+use bevy::utils::HashMap;
+#[cfg(not(feature = "bevy"))]
+use std::collections::HashMap;
+
+/// This is the full featured format for authoring grammars, with each rule's settings written alongside it's options.
+/// It is usually written in RON - which allows comments - and is converted into a `TraceryGrammar` when it is loaded.
+/// The JSON format used by `TraceryGrammar` itself is still supported, but this format is easier to maintain by hand.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GrammarDefinition {
+    /// The rule generation starts from by default
+    #[serde(default = "default_starting_point")]
+    pub starting_point: String,
+    /// Alternative starting points along with their relative weights - see `TraceryGrammar::set_starting_points`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub starting_points: Vec<(String, u32)>,
+    /// The separator placed between the pieces of each expansion - see `TraceryGrammar::set_separator`
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub separator: String,
+    /// How the pieces of each expansion are joined together - see `TraceryGrammar::set_join_mode`
+    #[serde(default, skip_serializing_if = "JoinMode::is_default")]
+    pub join: JoinMode,
+    /// The locale the grammar is written in, picking the conventions it's output follows - see `TraceryGrammar::set_locale`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// How deeply rules can be nested within each other while generating
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<usize>,
+    /// The limits on how much work a single generation can do - see `TraceryGrammar::set_budget`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget: Option<GenerationBudget>,
+    /// The path of the grammar this one extends, relative to it - see `TraceryGrammar::set_extends`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    /// Other definitions written inline, whose rules, aliases and exclusions are included in this one.
+    /// Rules defined in this definition replace included rules that share their key.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub includes: Vec<GrammarDefinition>,
+    /// The grammar's rules
+    #[serde(default)]
+    pub rules: HashMap<String, RuleDefinition>,
+    /// Aliases for the grammar's rules, mapping each alias to the rule it refers to
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub aliases: HashMap<String, String>,
+    /// The policy for variables assigned over the grammar's rules
+    #[serde(default)]
+    pub shadowing: ShadowingPolicy,
+    /// The policy for assertions like `[assert:hero]` - see `TraceryGrammar::set_assertion_policy`
+    #[serde(default, skip_serializing_if = "AssertionPolicy::is_default")]
+    pub assertions: AssertionPolicy,
+    /// The exclusions between the options of different rules
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclusions: Vec<Exclusion>,
+}
+
+fn default_starting_point() -> String {
+    "origin".to_string()
+}
+
+/// This is a single rule in a `GrammarDefinition` - it's options, along with the settings controlling how they are chosen
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleDefinition {
+    /// The rule's options
+    pub options: Vec<OptionDefinition>,
+    /// The selection mode used when choosing between the options
+    pub selection: SelectionMode,
+    /// The link group the rule belongs to - see `TraceryGrammar::set_link`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+    /// Whether the rule avoids choosing the same option twice within a single generation
+    pub distinct: bool,
+    /// Whether the rule emits data alongside the generated text - see `TraceryGrammar::set_emitted`
+    pub emit: bool,
+    /// Whether the rule is a choice point when generating interactively - see `TraceryGrammar::set_choice_point`
+    pub choice: bool,
+}
+
+/// This is a single option in a `RuleDefinition` - either plain text, or text along with a weight and tags
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum OptionDefinition {
+    /// An option with a weight of 1, and no tags
+    Text(String),
+    /// An option with a weight and tags
+    Detailed {
+        /// The option's text
+        text: String,
+        /// The option's weight, relative to the rule's other options
+        #[serde(default = "default_weight")]
+        weight: u32,
+        /// The option's tags, which can be used to filter options while generating
+        #[serde(default)]
+        tags: Vec<String>,
+    },
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+impl OptionDefinition {
+    fn weight(&self) -> u32 {
+        match self {
+            OptionDefinition::Text(_) => 1,
+            OptionDefinition::Detailed { weight, .. } => *weight,
+        }
+    }
+
+    fn into_option(self) -> String {
+        match self {
+            OptionDefinition::Text(text) => text,
+            OptionDefinition::Detailed { text, tags, .. } => tagged_option(text, &tags),
+        }
+    }
+}
+
+#[cfg(feature = "ron_source")]
+impl GrammarDefinition {
+    /// This parses a definition written in RON
+    pub fn from_ron(ron: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::from_str(ron)
+    }
+}
+
+impl GrammarDefinition {
+    /// Adds the rules, aliases and exclusions of this definition and the ones it includes to a grammar
+    fn add_to(self, grammar: &mut TraceryGrammar) {
+        for include in self.includes {
+            include.add_to(grammar);
+        }
+        for (key, rule) in self.rules {
+            let weights = rule
+                .options
+                .iter()
+                .map(OptionDefinition::weight)
+                .collect::<Vec<_>>();
+            let settings = RuleSettings {
+                selection: rule.selection,
+                link: rule.link,
+                distinct: rule.distinct,
+                weights: match weights.iter().any(|weight| *weight != 1) {
+                    true => weights,
+                    false => vec![],
+                },
+                emit: rule.emit,
+                choice: rule.choice,
+            };
+            let options = rule
+                .options
+                .into_iter()
+                .map(OptionDefinition::into_option)
+                .collect::<Vec<_>>();
+            match settings == RuleSettings::default() {
+                true => grammar.settings.remove(&key),
+                false => grammar.settings.insert(key.clone(), settings),
+            };
+            grammar.set_additional_rules(key, &options);
+        }
+        grammar.aliases.extend(self.aliases);
+        grammar.exclusions.extend(self.exclusions);
+    }
+}
+
+impl From<GrammarDefinition> for TraceryGrammar {
+    fn from(definition: GrammarDefinition) -> Self {
+        let mut grammar = TraceryGrammar::empty();
+        grammar.starting_point = definition.starting_point.clone();
+        grammar.starting_points = definition.starting_points.clone();
+        grammar.max_depth = definition.max_depth;
+        grammar.budget = definition.budget;
+        grammar.separator = definition.separator.clone();
+        grammar.join = definition.join;
+        grammar.extends = definition.extends.clone();
+        grammar.locale = definition.locale.clone();
+        grammar.shadowing = definition.shadowing;
+        grammar.assertions = definition.assertions;
+        definition.add_to(&mut grammar);
+        grammar
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generator::Generator, tracery::StringGenerator};
+
+    const DEFINITION: &str = r##"
+        (
+            starting_point: "greeting",
+            max_depth: Some(10),
+            // Shared word lists can be written inline
+            includes: [
+                (rules: {
+                    "name": (options: ["Mia", "Sam"]),
+                    "hello": (options: ["Hi"]),
+                }),
+            ],
+            rules: {
+                "greeting": (options: ["#hello#, #name#!"]),
+                "hello": (
+                    selection: Cycle,
+                    options: [
+                        "Hello",
+                        (text: "Hey", weight: 3, tags: ["casual"]),
+                    ],
+                ),
+            },
+        )
+    "##;
+
+    #[test]
+    pub fn definitions_are_converted_into_grammars() {
+        let definition: GrammarDefinition = ron::from_str(DEFINITION).unwrap();
+        let grammar = TraceryGrammar::from(definition);
+        assert_eq!(grammar.default_starting_point(), "greeting");
+        assert_eq!(grammar.max_depth(), 10);
+        assert_eq!(
+            grammar.get_rule_options(&"hello".to_string()),
+            Some(&vec!["Hello".to_string(), "{tags:casual}Hey".to_string()])
+        );
+        assert_eq!(grammar.selection_mode("hello"), SelectionMode::Cycle);
+        assert_eq!(
+            grammar
+                .rule_settings("hello")
+                .map(|settings| &settings.weights),
+            Some(&vec![1, 3])
+        );
+        assert!(grammar.rule_settings("name").is_none());
+        assert_eq!(
+            StringGenerator::generate(&grammar, &mut 1).unwrap(),
+            "Hello, Sam!"
+        );
+    }
+}
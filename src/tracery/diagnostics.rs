@@ -0,0 +1,173 @@
+use std::time::Duration;
+
+#[cfg(feature = "bevy")]
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+};
+
+#[cfg(feature = "bevy")]
+use super::StatefulStringGenerator;
+
+/// This describes the work done by a single generation of a `StatefulStringGenerator`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GenerationStats {
+    /// The number of rules expanded
+    pub expansions: usize,
+    /// How deeply rules were nested within each other, at the deepest point
+    pub depth: usize,
+    /// How long the generation took. It is only measured with the `bevy` feature, and is zero otherwise.
+    pub duration: Duration,
+}
+
+impl GenerationStats {
+    /// Records a rule expanded at the provided depth
+    pub(crate) fn record_expansion(&mut self, depth: usize) {
+        self.expansions = self.expansions.saturating_add(1);
+        self.depth = self.depth.max(depth);
+    }
+}
+
+/// The totals of the generations a stateful generator made since they were last reported as diagnostics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct UnreportedStats {
+    generations: u32,
+    duration: Duration,
+    depth: usize,
+}
+
+impl UnreportedStats {
+    pub(crate) fn add(&mut self, stats: &GenerationStats) {
+        self.generations = self.generations.saturating_add(1);
+        self.duration = self.duration.saturating_add(stats.duration);
+        self.depth = self.depth.saturating_add(stats.depth);
+    }
+}
+
+/// This plugin reports diagnostics for the generations made by every `StatefulStringGenerator` in the world - as components or
+/// as a resource - so the cost of narrative systems shows up alongside the rest of the game's, such as in the output of the
+/// `LogDiagnosticsPlugin`. Generations are collected at the end of each frame.
+#[cfg(feature = "bevy")]
+#[derive(Default)]
+pub struct GrammarDiagnosticsPlugin;
+
+#[cfg(feature = "bevy")]
+impl GrammarDiagnosticsPlugin {
+    /// The number of generations made each second
+    pub const GENERATIONS_PER_SECOND: DiagnosticId =
+        DiagnosticId::from_u128(270544157390917284761963155218603712054);
+    /// The average time, in milliseconds, each generation took
+    pub const EXPANSION_TIME: DiagnosticId =
+        DiagnosticId::from_u128(96318004812757349961402271358112093427);
+    /// The average depth each generation reached - how deeply rules were nested within each other
+    pub const EXPANSION_DEPTH: DiagnosticId =
+        DiagnosticId::from_u128(181760255930384817306637416043961028715);
+}
+
+#[cfg(feature = "bevy")]
+impl Plugin for GrammarDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(
+            Self::GENERATIONS_PER_SECOND,
+            "grammar_generations_per_second",
+            20,
+        ))
+        .register_diagnostic(
+            Diagnostic::new(Self::EXPANSION_TIME, "grammar_expansion_time", 20).with_suffix("ms"),
+        )
+        .register_diagnostic(Diagnostic::new(
+            Self::EXPANSION_DEPTH,
+            "grammar_expansion_depth",
+            20,
+        ))
+        .add_systems(Last, grammar_diagnostics);
+    }
+}
+
+#[cfg(feature = "bevy")]
+fn grammar_diagnostics(
+    mut diagnostics: Diagnostics,
+    time: Option<Res<Time>>,
+    mut generators: Query<&mut StatefulStringGenerator>,
+    generator_resource: Option<ResMut<StatefulStringGenerator>>,
+) {
+    let mut totals = UnreportedStats::default();
+    for mut generator in generators
+        .iter_mut()
+        .chain(generator_resource.map(Mut::from))
+    {
+        let unreported = std::mem::take(&mut generator.bypass_change_detection().unreported);
+        totals.generations = totals.generations.saturating_add(unreported.generations);
+        totals.duration = totals.duration.saturating_add(unreported.duration);
+        totals.depth = totals.depth.saturating_add(unreported.depth);
+    }
+    if let Some(delta) = time.map(|time| time.delta_seconds_f64()) {
+        if delta > 0. {
+            diagnostics.add_measurement(GrammarDiagnosticsPlugin::GENERATIONS_PER_SECOND, || {
+                totals.generations as f64 / delta
+            });
+        }
+    }
+    if totals.generations == 0 {
+        return;
+    }
+    let generations = totals.generations as f64;
+    diagnostics.add_measurement(GrammarDiagnosticsPlugin::EXPANSION_TIME, || {
+        totals.duration.as_secs_f64() * 1000. / generations
+    });
+    diagnostics.add_measurement(GrammarDiagnosticsPlugin::EXPANSION_DEPTH, || {
+        totals.depth as f64 / generations
+    });
+}
+
+#[cfg(all(test, feature = "bevy"))]
+mod tests {
+    use super::*;
+    use crate::{generator::StatefulGenerator, tracery::TraceryGrammar};
+    use bevy::diagnostic::DiagnosticsStore;
+
+    #[test]
+    pub fn generations_are_reported_as_diagnostics() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["#greeting#, #name#"]),
+                ("greeting", &["Hello #title#"]),
+                ("title", &["dear"]),
+                ("name", &["Sam"]),
+            ],
+            None,
+        );
+        let mut generator = StatefulStringGenerator::from_grammar(grammar);
+        generator.generate(&mut 0).unwrap();
+        let stats = generator.last_generation_stats();
+        assert_eq!((stats.expansions, stats.depth), (4, 3));
+
+        let mut app = App::new();
+        app.add_plugins(GrammarDiagnosticsPlugin)
+            .init_resource::<Time>();
+        app.world.spawn(generator.clone());
+        app.world.spawn(generator);
+        app.add_systems(
+            Update,
+            |mut generators: Query<&mut StatefulStringGenerator>| {
+                for mut generator in generators.iter_mut() {
+                    generator.generate(&mut 0).unwrap();
+                }
+            },
+        );
+        app.world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_millis(500));
+        app.update();
+
+        let store = app.world.resource::<DiagnosticsStore>();
+        let measurement = |id| store.get_measurement(id).unwrap().value;
+        // The generation made before spawning is reported too
+        assert_eq!(
+            measurement(GrammarDiagnosticsPlugin::GENERATIONS_PER_SECOND),
+            8.
+        );
+        assert_eq!(measurement(GrammarDiagnosticsPlugin::EXPANSION_DEPTH), 3.);
+        assert!(measurement(GrammarDiagnosticsPlugin::EXPANSION_TIME) >= 0.);
+    }
+}
@@ -0,0 +1,342 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{
+    annotations::{option_name, option_text},
+    assertions::asserted_variable,
+    captures::split_key_and_modifiers,
+    TraceryGrammar,
+};
+use crate::generator::{Grammar, Replacable};
+
+/// This is a static tree of every expansion a grammar can generate from one of it's rules, compiled using `TraceryGrammar::compile_tree`.
+/// With the `serde` feature, it can be serialized for tools importing it into dialogue systems like Yarn or ink,
+/// or for designers auditing every line the grammar can output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DialogueTree {
+    /// The rule the tree was compiled from
+    pub root: String,
+    /// The maximum number of nested rules the tree expands
+    pub max_depth: usize,
+    /// The tree itself, starting with the root rule
+    pub node: TreeNode,
+}
+
+/// This is a node of a `DialogueTree`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TreeNode {
+    /// Plain text
+    Text(String),
+    /// Pieces of text and rules, expanded one after another
+    Sequence(Vec<TreeNode>),
+    /// A rule, which expands to one of it's options
+    Rule {
+        /// The rule's key
+        rule: String,
+        /// The modifiers applied to the rule's expansion, like `capitalize`
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "Option::is_none")
+        )]
+        modifiers: Option<String>,
+        /// Whether the rule is a choice point, so the player chooses the option when generating interactively
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "std::ops::Not::not")
+        )]
+        choice: bool,
+        /// The rule's options
+        options: Vec<TreeOption>,
+    },
+    /// One of a set of inline alternatives, like `(tiny|huge)`
+    Alternatives(Vec<TreeNode>),
+    /// A variable assigned by an action, like `[hero:#name#]`
+    Assign {
+        /// The variable being assigned
+        variable: String,
+        /// The value assigned to it
+        value: Box<TreeNode>,
+    },
+    /// A reference that isn't one of the grammar's rules - like a variable, a token or a capture - so it's only known while generating
+    Dynamic {
+        /// The referenced key
+        key: String,
+        /// The modifiers applied to the value, like `capitalize`
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "Option::is_none")
+        )]
+        modifiers: Option<String>,
+    },
+    /// A rule that wasn't expanded, since the tree reached it's maximum depth
+    Truncated(String),
+}
+
+/// This is one of the options of a rule in a `DialogueTree`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TreeOption {
+    /// The index of the option within it's rule
+    pub index: usize,
+    /// The option's name, set using the `{name:...}` annotation
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub name: Option<String>,
+    /// The option's weight, relative to the rule's other options
+    pub weight: u32,
+    /// The option's expansion
+    pub node: TreeNode,
+}
+
+impl TreeNode {
+    /// Checks whether any part of the tree was truncated by the maximum depth
+    pub fn is_truncated(&self) -> bool {
+        match self {
+            TreeNode::Text(_) | TreeNode::Dynamic { .. } => false,
+            TreeNode::Truncated(_) => true,
+            TreeNode::Sequence(nodes) | TreeNode::Alternatives(nodes) => {
+                nodes.iter().any(TreeNode::is_truncated)
+            }
+            TreeNode::Rule { options, .. } => {
+                options.iter().any(|option| option.node.is_truncated())
+            }
+            TreeNode::Assign { value, .. } => value.is_truncated(),
+        }
+    }
+
+    /// Collects the choice points within the tree, in the order they appear - each one once
+    pub fn choice_points(&self) -> Vec<&str> {
+        let mut points = vec![];
+        self.collect_choice_points(&mut points);
+        points
+    }
+
+    fn collect_choice_points<'a>(&'a self, points: &mut Vec<&'a str>) {
+        match self {
+            TreeNode::Text(_) | TreeNode::Dynamic { .. } | TreeNode::Truncated(_) => {}
+            TreeNode::Sequence(nodes) | TreeNode::Alternatives(nodes) => {
+                for node in nodes {
+                    node.collect_choice_points(points);
+                }
+            }
+            TreeNode::Rule {
+                rule,
+                choice,
+                options,
+                ..
+            } => {
+                if *choice && !points.contains(&rule.as_str()) {
+                    points.push(rule);
+                }
+                for option in options {
+                    option.node.collect_choice_points(points);
+                }
+            }
+            TreeNode::Assign { value, .. } => value.collect_choice_points(points),
+        }
+    }
+}
+
+impl TraceryGrammar {
+    /// This compiles the grammar's starting point into a static tree of every expansion it can generate, including it's choice points.
+    /// Rules nested deeper than `max_depth` are left as `TreeNode::Truncated` - so recursive rules end, and the tree's size stays bounded.
+    /// Rules are expanded wherever they are referenced, so the tree grows quickly with the depth.
+    pub fn compile_tree(&self, max_depth: usize) -> DialogueTree {
+        self.compile_tree_at(self.default_starting_point(), max_depth)
+    }
+
+    /// This compiles the provided rule into a static tree of every expansion it can generate. See `compile_tree`.
+    pub fn compile_tree_at(&self, rule: &str, max_depth: usize) -> DialogueTree {
+        DialogueTree {
+            root: rule.to_string(),
+            max_depth,
+            node: self.compile_reference(rule, max_depth),
+        }
+    }
+
+    /// Compiles a `#...#` reference, with any actions that precede it's key
+    fn compile_reference(&self, reference: &str, depth: usize) -> TreeNode {
+        let reference = reference.to_string();
+        let (mut nodes, key) = match self.split_actions(&reference) {
+            Some((actions, key)) => (self.compile_tokens(actions, depth), key.unwrap_or_default()),
+            None => (vec![], reference),
+        };
+        if !key.is_empty() {
+            nodes.push(self.compile_rule(&key, depth));
+        }
+        match nodes.len() {
+            1 => nodes.remove(0),
+            _ => TreeNode::Sequence(nodes),
+        }
+    }
+
+    fn compile_rule(&self, key: &str, depth: usize) -> TreeNode {
+        // Assertions never generate any text
+        if asserted_variable(key).is_some() {
+            return TreeNode::Text(String::new());
+        }
+        let (rule, modifiers) = match split_key_and_modifiers(key) {
+            Some((rule, modifiers)) => (rule, Some(modifiers.to_string())),
+            None => (key, None),
+        };
+        let Some(options) = self.rules.get(self.resolve_alias(rule)) else {
+            return TreeNode::Dynamic {
+                key: rule.to_string(),
+                modifiers,
+            };
+        };
+        if depth == 0 {
+            return TreeNode::Truncated(rule.to_string());
+        }
+        let weights = self
+            .settings
+            .get(self.resolve_alias(rule))
+            .map(|settings| settings.weights.as_slice())
+            .unwrap_or_default();
+        TreeNode::Rule {
+            rule: rule.to_string(),
+            modifiers,
+            choice: self.is_choice_point(rule),
+            options: options
+                .iter()
+                .enumerate()
+                .map(|(index, option)| TreeOption {
+                    index,
+                    name: option_name(option).map(str::to_string),
+                    weight: weights.get(index).copied().unwrap_or(1),
+                    node: self.compile_text(option_text(option), depth - 1),
+                })
+                .collect(),
+        }
+    }
+
+    fn compile_text(&self, text: &str, depth: usize) -> TreeNode {
+        let (_, tokens) = self.check_token_stream(&text.to_string());
+        let mut nodes = self.compile_tokens(tokens, depth);
+        match nodes.len() {
+            0 => TreeNode::Text(String::new()),
+            1 => nodes.remove(0),
+            _ => TreeNode::Sequence(nodes),
+        }
+    }
+
+    fn compile_tokens(
+        &self,
+        tokens: Vec<Replacable<String, String>>,
+        depth: usize,
+    ) -> Vec<TreeNode> {
+        tokens
+            .into_iter()
+            .map(|token| match token {
+                Replacable::Ready(text) => TreeNode::Text(text),
+                Replacable::Replace(reference) => self.compile_reference(&reference, depth),
+                Replacable::ImmediateMeta(variable, value)
+                | Replacable::DelayedMeta(variable, value) => TreeNode::Assign {
+                    variable,
+                    value: Box::new(self.compile_text(&value, depth)),
+                },
+                Replacable::Alternatives(alternatives) => TreeNode::Alternatives(
+                    alternatives
+                        .iter()
+                        .map(|alternative| self.compile_text(alternative, depth))
+                        .collect(),
+                ),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str) -> TreeNode {
+        TreeNode::Text(text.to_string())
+    }
+
+    #[test]
+    pub fn trees_hold_every_expansion() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["[hero:#name#]#greeting# #hero#"]),
+                ("greeting", &["{name:polite}Hello", "Hey (you|there)"]),
+                ("name", &["Ana"]),
+            ],
+            None,
+        )
+        .with_weights("greeting", [3, 1])
+        .with_choice_point("greeting");
+        let tree = grammar.compile_tree(4);
+        assert_eq!(tree.root, "origin");
+        let TreeNode::Rule { options, .. } = &tree.node else {
+            panic!("the root should be a rule");
+        };
+        let TreeNode::Sequence(pieces) = &options[0].node else {
+            panic!("the option should be a sequence");
+        };
+        assert!(matches!(&pieces[0], TreeNode::Assign { variable, .. } if variable == "hero"));
+        let TreeNode::Rule {
+            rule,
+            choice,
+            options,
+            ..
+        } = &pieces[1]
+        else {
+            panic!("the greeting should be a rule");
+        };
+        assert_eq!((rule.as_str(), *choice), ("greeting", true));
+        assert_eq!(
+            options[0],
+            TreeOption {
+                index: 0,
+                name: Some("polite".to_string()),
+                weight: 3,
+                node: text("Hello"),
+            }
+        );
+        assert_eq!(
+            options[1].node,
+            TreeNode::Sequence(vec![
+                text("Hey "),
+                TreeNode::Alternatives(vec![text("you"), text("there")])
+            ])
+        );
+        assert_eq!(
+            pieces[3],
+            TreeNode::Dynamic {
+                key: "hero".to_string(),
+                modifiers: None
+            }
+        );
+        assert_eq!(tree.node.choice_points(), vec!["greeting"]);
+        assert!(!tree.node.is_truncated());
+    }
+
+    #[test]
+    pub fn recursive_rules_are_truncated() {
+        let grammar = TraceryGrammar::new(&[("origin", &["a", "a #origin.capitalize#"])], None);
+        let tree = grammar.compile_tree(2);
+        assert!(tree.node.is_truncated());
+        let TreeNode::Rule { options, .. } = &tree.node else {
+            panic!("the root should be a rule");
+        };
+        let TreeNode::Sequence(pieces) = &options[1].node else {
+            panic!("the option should be a sequence");
+        };
+        let TreeNode::Rule {
+            modifiers, options, ..
+        } = &pieces[1]
+        else {
+            panic!("the nested origin should be a rule");
+        };
+        assert_eq!(modifiers.as_deref(), Some("capitalize"));
+        assert_eq!(
+            options[1].node,
+            TreeNode::Sequence(vec![text("a "), TreeNode::Truncated("origin".to_string())])
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use std::fmt::Display;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{RuleSettings, TraceryGrammar};
+
+/// This is a structured comparison of two versions of a grammar, made using `TraceryGrammar::diff` - so tools can review changes
+/// to grammar assets rule by rule, rather than as a diff of their text. Rules are listed in alphabetical order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GrammarDiff {
+    /// The rules only the new version has
+    pub added_rules: Vec<String>,
+    /// The rules only the old version has
+    pub removed_rules: Vec<String>,
+    /// The rules both versions have, but with different options or settings
+    pub changed_rules: Vec<RuleDiff>,
+    /// The old and new default starting points, if they differ
+    pub starting_point: Option<(String, String)>,
+}
+
+/// This is the change to a rule both versions of a grammar have
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RuleDiff {
+    /// The rule's key
+    pub rule: String,
+    /// The options only the new version has, in the order they appear
+    pub added_options: Vec<String>,
+    /// The options only the old version has, in the order they appeared
+    pub removed_options: Vec<String>,
+    /// Whether the rule has the same options, but in a different order - which changes rules that cycle through their options
+    pub reordered: bool,
+    /// Whether the rule's settings, like it's selection mode or weights, changed
+    pub settings_changed: bool,
+}
+
+impl GrammarDiff {
+    /// Checks whether the versions are the same
+    pub fn is_empty(&self) -> bool {
+        self.added_rules.is_empty()
+            && self.removed_rules.is_empty()
+            && self.changed_rules.is_empty()
+            && self.starting_point.is_none()
+    }
+}
+
+impl Display for GrammarDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some((old, new)) = &self.starting_point {
+            writeln!(f, "~ starting point: {old} -> {new}")?;
+        }
+        for rule in self.added_rules.iter() {
+            writeln!(f, "+ {rule}")?;
+        }
+        for rule in self.removed_rules.iter() {
+            writeln!(f, "- {rule}")?;
+        }
+        for rule in self.changed_rules.iter() {
+            writeln!(f, "~ {}", rule.rule)?;
+            for option in rule.added_options.iter() {
+                writeln!(f, "    + {option}")?;
+            }
+            for option in rule.removed_options.iter() {
+                writeln!(f, "    - {option}")?;
+            }
+            if rule.reordered {
+                writeln!(f, "    options reordered")?;
+            }
+            if rule.settings_changed {
+                writeln!(f, "    settings changed")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TraceryGrammar {
+    /// This compares the grammar with a newer version of it, listing the rules and options that were added, removed or changed.
+    /// Options are compared by their text, annotations included - so an option whose tags changed is listed as removed and added.
+    pub fn diff(&self, other: &TraceryGrammar) -> GrammarDiff {
+        let mut diff = GrammarDiff::default();
+        if self.starting_point != other.starting_point {
+            diff.starting_point = Some((self.starting_point.clone(), other.starting_point.clone()));
+        }
+        let default_settings = RuleSettings::default();
+        for (rule, options) in other.rules.iter() {
+            let Some(old_options) = self.rules.get(rule) else {
+                diff.added_rules.push(rule.clone());
+                continue;
+            };
+            // Options are matched one for one, so duplicated options are counted
+            let mut added_options = options.clone();
+            let mut removed_options = vec![];
+            for option in old_options {
+                match added_options.iter().position(|added| added == option) {
+                    Some(index) => {
+                        added_options.remove(index);
+                    }
+                    None => removed_options.push(option.clone()),
+                }
+            }
+            let reordered =
+                added_options.is_empty() && removed_options.is_empty() && old_options != options;
+            let settings_changed = self.settings.get(rule).unwrap_or(&default_settings)
+                != other.settings.get(rule).unwrap_or(&default_settings);
+            if !added_options.is_empty()
+                || !removed_options.is_empty()
+                || reordered
+                || settings_changed
+            {
+                diff.changed_rules.push(RuleDiff {
+                    rule: rule.clone(),
+                    added_options,
+                    removed_options,
+                    reordered,
+                    settings_changed,
+                });
+            }
+        }
+        diff.removed_rules = self
+            .rules
+            .keys()
+            .filter(|rule| !other.rules.contains_key(*rule))
+            .cloned()
+            .collect();
+        diff.added_rules.sort();
+        diff.removed_rules.sort();
+        diff.changed_rules.sort_by(|a, b| a.rule.cmp(&b.rule));
+        diff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracery::SelectionMode;
+
+    #[test]
+    pub fn diffs_list_the_changed_rules_and_options() {
+        let old = TraceryGrammar::new(
+            &[
+                ("origin", &["#hero# meets #villain#"]),
+                ("hero", &["Ana", "Bo", "Cleo"]),
+                ("villain", &["Vex", "Mordra"]),
+                ("weather", &["rain"]),
+            ],
+            None,
+        );
+        let new = TraceryGrammar::new(
+            &[
+                ("origin", &["#hero# meets #villain#"]),
+                ("hero", &["Ana", "Dov", "Cleo", "Ana"]),
+                ("villain", &["Mordra", "Vex"]),
+                ("place", &["the keep"]),
+            ],
+            Some("origin"),
+        )
+        .with_selection_mode("origin", SelectionMode::Cycle);
+        let diff = old.diff(&new);
+        assert_eq!(diff.added_rules, vec!["place".to_string()]);
+        assert_eq!(diff.removed_rules, vec!["weather".to_string()]);
+        assert_eq!(diff.starting_point, None);
+        assert_eq!(
+            diff.changed_rules,
+            vec![
+                RuleDiff {
+                    rule: "hero".to_string(),
+                    added_options: vec!["Dov".to_string(), "Ana".to_string()],
+                    removed_options: vec!["Bo".to_string()],
+                    ..Default::default()
+                },
+                RuleDiff {
+                    rule: "origin".to_string(),
+                    settings_changed: true,
+                    ..Default::default()
+                },
+                RuleDiff {
+                    rule: "villain".to_string(),
+                    reordered: true,
+                    ..Default::default()
+                },
+            ]
+        );
+        assert!(diff
+            .to_string()
+            .starts_with("+ place\n- weather\n~ hero\n    + Dov\n"));
+        assert!(new.diff(&new).is_empty());
+    }
+}
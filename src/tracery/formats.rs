@@ -0,0 +1,177 @@
+use serde::{de::IgnoredAny, Deserialize};
+
+#[cfg(feature = "bevy")]
+use bevy::utils::HashMap;
+#[cfg(not(feature = "bevy"))]
+use std::collections::HashMap;
+
+use super::TraceryGrammar;
+
+/// Just enough of a grammar file to tell a `GrammarDefinition` apart from a `TraceryGrammar` - definitions write each rule as
+/// an object holding it's options, while grammars write rules as options, and can include other definitions
+#[derive(Deserialize)]
+struct GrammarShape {
+    #[serde(default)]
+    includes: Option<IgnoredAny>,
+    #[serde(default)]
+    rules: HashMap<String, RuleShape>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RuleShape {
+    Object(HashMap<String, IgnoredAny>),
+    Options(IgnoredAny),
+}
+
+impl GrammarShape {
+    fn is_definition(&self) -> bool {
+        // A single option written with it's tags is an object too, but always holds it's text
+        self.includes.is_some()
+            || self.rules.values().any(
+                |rule| matches!(rule, RuleShape::Object(fields) if !fields.contains_key("text")),
+            )
+    }
+}
+
+#[cfg(feature = "json_source")]
+impl TraceryGrammar {
+    /// This parses a grammar written in JSON - either an object holding it's rules and settings, or the plain object of rules
+    /// used by the original Tracery. Unlike the asset plugin, it doesn't need bevy, so grammars can be authored as files in any app.
+    pub fn from_json_str(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// This parses a grammar written in JSON from raw bytes, such as a file's contents. See `from_json_str`.
+    pub fn from_json_slice(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+
+    /// This parses a grammar file written in JSON, which can hold either a grammar or a `GrammarDefinition` - telling them
+    /// apart by how their rules are written.
+    pub fn from_json_file_str(json: &str) -> Result<Self, serde_json::Error> {
+        match serde_json::from_str::<GrammarShape>(json).is_ok_and(|shape| shape.is_definition()) {
+            true => serde_json::from_str::<super::GrammarDefinition>(json).map(Self::from),
+            false => Self::from_json_str(json),
+        }
+    }
+}
+
+#[cfg(feature = "ron_source")]
+impl TraceryGrammar {
+    /// This parses a grammar written in RON, without needing bevy. Grammars written as a `GrammarDefinition` are parsed
+    /// using `GrammarDefinition::from_ron` instead.
+    pub fn from_ron_str(ron: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::from_str(ron)
+    }
+
+    /// This parses a grammar written in RON from raw bytes, such as a file's contents. See `from_ron_str`.
+    pub fn from_ron_bytes(bytes: &[u8]) -> Result<Self, ron::error::SpannedError> {
+        ron::de::from_bytes(bytes)
+    }
+
+    /// This parses a grammar file written in RON, which can hold either a grammar or a `GrammarDefinition` - telling them
+    /// apart by how their rules are written.
+    pub fn from_ron_file_str(ron: &str) -> Result<Self, ron::error::SpannedError> {
+        match ron::from_str::<GrammarShape>(ron).is_ok_and(|shape| shape.is_definition()) {
+            true => super::GrammarDefinition::from_ron(ron).map(Self::from),
+            false => Self::from_ron_str(ron),
+        }
+    }
+}
+
+#[cfg(feature = "toml_source")]
+impl TraceryGrammar {
+    /// This parses a grammar written in TOML, without needing bevy
+    pub fn from_toml_str(toml: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(toml)
+    }
+}
+
+#[cfg(all(test, any(feature = "json_source", feature = "toml_source")))]
+mod tests {
+    use super::*;
+    use crate::tracery::StringGenerator;
+
+    #[test]
+    #[cfg(feature = "json_source")]
+    pub fn grammars_are_parsed_from_json() {
+        let grammar = TraceryGrammar::from_json_str(
+            r##"{"rules": {"origin": ["#animal# sleeps"], "animal": ["the cat"]}}"##,
+        )
+        .unwrap();
+        assert_eq!(
+            StringGenerator::try_generate(&grammar, &mut 0).unwrap(),
+            "the cat sleeps"
+        );
+        let plain = TraceryGrammar::from_json_slice(
+            br##"{"origin": "#animal# sleeps", "animal": "the cat"}"##,
+        )
+        .unwrap();
+        assert_eq!(
+            StringGenerator::try_generate(&plain, &mut 0).unwrap(),
+            "the cat sleeps"
+        );
+        assert!(TraceryGrammar::from_json_str("[]").is_err());
+    }
+
+    #[test]
+    #[cfg(all(feature = "json_source", feature = "ron_source"))]
+    pub fn grammar_files_are_parsed_as_grammars_or_definitions() {
+        let expected = "the cat sleeps";
+        let files = [
+            TraceryGrammar::from_json_file_str(
+                r##"{"rules": {"origin": ["#animal# sleeps"], "animal": "the cat"}}"##,
+            )
+            .map_err(|error| error.to_string()),
+            TraceryGrammar::from_json_file_str(
+                r##"{"rules": {"origin": {"options": ["#animal# sleeps"]}, "animal": {"options": ["the cat"]}}}"##,
+            )
+            .map_err(|error| error.to_string()),
+            TraceryGrammar::from_json_file_str(
+                r##"{"origin": "#animal# sleeps", "animal": {"text": "the cat", "tags": ["pet"]}}"##,
+            )
+            .map_err(|error| error.to_string()),
+            TraceryGrammar::from_ron_file_str(
+                r##"(rules: {"origin": ["#animal# sleeps"], "animal": ["the cat"]})"##,
+            )
+            .map_err(|error| error.to_string()),
+            TraceryGrammar::from_ron_file_str(
+                r##"(rules: {"origin": (options: ["#animal# sleeps"]), "animal": (options: ["the cat"])})"##,
+            )
+            .map_err(|error| error.to_string()),
+        ];
+        for grammar in files {
+            assert_eq!(
+                StringGenerator::try_generate(&grammar.unwrap(), &mut 0).unwrap(),
+                expected
+            );
+        }
+        // Definitions report their own errors
+        let error = TraceryGrammar::from_ron_file_str(r##"(rules: {"origin": (options: 5)})"##)
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("Expected opening `[`"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "toml_source")]
+    pub fn grammars_are_parsed_from_toml() {
+        let grammar = TraceryGrammar::from_toml_str(
+            r##"
+            starting_point = "origin"
+
+            [rules]
+            origin = ["#animal# sleeps"]
+            animal = ["the cat"]
+            "##,
+        )
+        .unwrap();
+        assert_eq!(
+            StringGenerator::try_generate(&grammar, &mut 0).unwrap(),
+            "the cat sleeps"
+        );
+    }
+}
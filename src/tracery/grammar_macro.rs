@@ -0,0 +1,128 @@
+use super::{LintKind, TraceryGrammar};
+
+/// This defines a `TraceryGrammar` in code, with each rule's key written as an identifier followed by a list of it's options.
+/// Defining the same rule twice is a compile error.
+/// Starting the macro with `strict;` also checks that every `#key#` referenced in the options is either one of the rules,
+/// or a variable assigned within the grammar - panicking when the grammar is created otherwise. Keys provided from outside
+/// the grammar, such as tokens, can't be used in strict grammars.
+#[macro_export]
+macro_rules! grammar {
+    (strict; $($key:ident => [$($option:literal),* $(,)?]),* $(,)?) => {{
+        let grammar = $crate::grammar! { $($key => [$($option),*]),* };
+        $crate::tracery::grammar_macro::check_references(&grammar);
+        grammar
+    }};
+    ($($key:ident => [$($option:expr),* $(,)?]),* $(,)?) => {{
+        #[allow(non_camel_case_types, dead_code)]
+        enum DefinedRules {
+            $($key),*
+        }
+        $crate::tracery::TraceryGrammar::new(
+            &[$((stringify!($key), &[$($option),*] as &[&str])),*],
+            None,
+        )
+    }};
+}
+
+/// Checks that every key referenced by the grammar's options is one of it's rules, or a variable assigned within the grammar -
+/// reading the options with the grammar's tokenizer, like `TraceryGrammar::validate`. This is used by `grammar!` in strict mode,
+/// and panics if a reference is missing.
+pub fn check_references(grammar: &TraceryGrammar) {
+    let undefined = grammar
+        .validate()
+        .into_iter()
+        .filter_map(|finding| match finding.kind {
+            LintKind::UndefinedRule(key) => Some(format!("`{key}` in `{}`", finding.rule)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if !undefined.is_empty() {
+        panic!(
+            "grammar! references rules that aren't defined: {}",
+            undefined.join(", ")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{generator::Generator, tracery::StringGenerator};
+
+    #[test]
+    pub fn defines_grammars_from_rules() {
+        let grammar = crate::grammar! {
+            strict;
+            origin => ["[name:#names#]#greeting# #name#"],
+            greeting => ["hello", "hi"],
+            names => ["Mia"],
+        };
+        assert_eq!(
+            StringGenerator::generate(&grammar, &mut 1).unwrap(),
+            "hi Mia"
+        );
+    }
+
+    #[test]
+    pub fn strict_grammars_allow_references_within_actions() {
+        let grammar = crate::grammar! {
+            strict;
+            origin => ["#[hero:#name#]story#", "[place:(#town#|#city#)]#place#"],
+            story => ["#hero# set out"],
+            name => ["Mia"],
+            town => ["Ash"],
+            city => ["Ore"],
+        };
+        assert_eq!(
+            StringGenerator::generate(&grammar, &mut 0).unwrap(),
+            "Mia set out"
+        );
+    }
+
+    #[test]
+    pub fn strict_grammars_allow_parameterized_calls() {
+        let grammar = crate::grammar! {
+            strict;
+            origin => ["#describe(hero, mood='grim')#", "#[line:#describe('The cat', mood=moods)#]line#"],
+            describe => ["#$1# looks #mood#"],
+            hero => ["Mia"],
+            moods => ["calm"],
+        };
+        assert_eq!(
+            StringGenerator::generate(&grammar, &mut 0).unwrap(),
+            "Mia looks grim"
+        );
+        assert_eq!(
+            StringGenerator::generate(&grammar, &mut 1).unwrap(),
+            "The cat looks calm"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "`villain` in `origin`")]
+    pub fn strict_grammars_reject_undefined_arguments() {
+        crate::grammar! {
+            strict;
+            origin => ["#describe(villain)#"],
+            describe => ["#$1# looks grim"],
+        };
+    }
+
+    #[test]
+    #[should_panic(expected = "`greeting` in `origin`")]
+    pub fn strict_grammars_reject_undefined_references() {
+        crate::grammar! {
+            strict;
+            origin => ["#greeting.capitalize# world"],
+        };
+    }
+
+    #[test]
+    #[should_panic(expected = "`name` in `origin`")]
+    pub fn strict_grammars_reject_undefined_references_within_actions() {
+        crate::grammar! {
+            strict;
+            origin => ["#[hero:#name#]story#"],
+            story => ["#hero#"],
+        };
+    }
+}
@@ -0,0 +1,68 @@
+use std::{fmt::Debug, sync::Arc};
+
+use super::captures::split_key_and_modifiers;
+use crate::generator::ExpansionTrace;
+
+/// This describes a rule that was expanded during a generation, and is passed to the hooks registered using
+/// `StatefulStringGenerator::on_rule_expanded`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuleExpanded<'a> {
+    /// The key of the rule, without any modifiers
+    pub key: &'a str,
+    /// The index of the option that was chosen, if the value came from one of the rule's options
+    pub chosen: Option<usize>,
+    /// How deeply the rule was nested - rules generation started from have a depth of 0
+    pub depth: usize,
+}
+
+type Hook = Arc<dyn Fn(&RuleExpanded) + Send + Sync>;
+
+/// The hooks registered with a stateful generator
+#[derive(Clone, Default)]
+pub(crate) struct ExpansionHooks {
+    hooks: Vec<Hook>,
+}
+
+impl Debug for ExpansionHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ExpansionHooks({})", self.hooks.len())
+    }
+}
+
+impl ExpansionHooks {
+    pub(crate) fn add(&mut self, hook: impl Fn(&RuleExpanded) + Send + Sync + 'static) {
+        self.hooks.push(Arc::new(hook));
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.hooks.clear();
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Calls every hook for each rule in the trace, in the order they were expanded. If the expansion started from a rule,
+    /// it is reported first and the rules in the trace are nested within it.
+    pub(crate) fn notify(
+        &self,
+        root: Option<(&str, Option<usize>)>,
+        trace: &ExpansionTrace<String>,
+    ) {
+        let offset = usize::from(root.is_some());
+        let root = root.map(|(key, chosen)| (0, key, chosen));
+        let nodes = trace
+            .nodes()
+            .map(|(depth, node)| (depth + offset, node.rule.as_str(), node.option));
+        for (depth, rule, chosen) in root.into_iter().chain(nodes) {
+            let expanded = RuleExpanded {
+                key: split_key_and_modifiers(rule).map_or(rule, |(key, _)| key),
+                chosen,
+                depth,
+            };
+            for hook in self.hooks.iter() {
+                hook(&expanded);
+            }
+        }
+    }
+}
@@ -0,0 +1,149 @@
+#[cfg(feature = "bevy")]
+use bevy::{prelude::Resource, utils::HashMap};
+#[cfg(not(feature = "bevy"))]
+use std::collections::HashMap;
+
+use super::layout::{rich_spans, RichSpan};
+
+/// This is how an icon is shown in the UI - as a glyph from an icon font, or an inline image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Icon<I> {
+    /// Text shown in place of the icon, usually a glyph from an icon font
+    Glyph(String),
+    /// An image shown inline with the text - in bevy, usually a `Handle<Image>`
+    Image(I),
+}
+
+/// This is a piece of generated text, with it's icons mapped by an `IconMap`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IconSpan<'a, I> {
+    /// Plain text
+    Text(&'a str),
+    /// An icon shown as a glyph, along with the icon's name
+    Glyph {
+        /// The name the icon was placed with
+        name: &'a str,
+        /// The glyph to show
+        glyph: &'a str,
+    },
+    /// An icon shown as an inline image, along with the icon's name
+    Image {
+        /// The name the icon was placed with
+        name: &'a str,
+        /// The image to show
+        image: &'a I,
+    },
+    /// An icon that isn't in the map. UIs usually skip these, or show a placeholder.
+    Unknown(&'a str),
+}
+
+/// This maps the icons placed using `[icon:...]` to the glyphs or images the UI shows for them - so item descriptions can
+/// be generated with inline icons. The image type is up to the UI - in bevy, usually `Handle<Image>` - and with the `bevy`
+/// feature the map can be inserted as a resource, so the systems building text and image nodes can read it.
+#[derive(Debug, Clone)]
+pub struct IconMap<I> {
+    icons: HashMap<String, Icon<I>>,
+}
+
+#[cfg(feature = "bevy")]
+impl<I: Send + Sync + 'static> Resource for IconMap<I> {}
+
+impl<I> Default for IconMap<I> {
+    fn default() -> Self {
+        Self {
+            icons: HashMap::default(),
+        }
+    }
+}
+
+impl<I> IconMap<I> {
+    /// Creates an empty icon map
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shows an icon as a glyph, returning the updated map
+    pub fn with_glyph(mut self, name: impl Into<String>, glyph: impl Into<String>) -> Self {
+        self.insert(name, Icon::Glyph(glyph.into()));
+        self
+    }
+
+    /// Shows an icon as an inline image, returning the updated map
+    pub fn with_image(mut self, name: impl Into<String>, image: I) -> Self {
+        self.insert(name, Icon::Image(image));
+        self
+    }
+
+    /// Sets how an icon is shown, replacing any previous mapping
+    pub fn insert(&mut self, name: impl Into<String>, icon: Icon<I>) {
+        self.icons.insert(name.into(), icon);
+    }
+
+    /// Gets how an icon is shown, if it is mapped
+    pub fn get(&self, name: &str) -> Option<&Icon<I>> {
+        self.icons.get(name)
+    }
+
+    /// Splits generated text into plain text and mapped icons, ready to be laid out as text sections and image nodes
+    pub fn map<'a>(&'a self, text: &'a str) -> Vec<IconSpan<'a, I>> {
+        rich_spans(text)
+            .into_iter()
+            .map(|span| match span {
+                RichSpan::Text(text) => IconSpan::Text(text),
+                RichSpan::Icon(name) => match self.icons.get(name) {
+                    Some(Icon::Glyph(glyph)) => IconSpan::Glyph { name, glyph },
+                    Some(Icon::Image(image)) => IconSpan::Image { name, image },
+                    None => IconSpan::Unknown(name),
+                },
+            })
+            .collect()
+    }
+
+    /// Replaces the icons that are shown as glyphs with their glyph, for UIs that only show text.
+    /// Other icons are kept, so they can still be found using `rich_spans`.
+    pub fn apply_glyphs(&self, text: &str) -> String {
+        rich_spans(text)
+            .into_iter()
+            .map(|span| match span {
+                RichSpan::Text(text) => text.to_string(),
+                RichSpan::Icon(name) => match self.icons.get(name) {
+                    Some(Icon::Glyph(glyph)) => glyph.clone(),
+                    _ => format!("[icon:{name}]"),
+                },
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn icons_are_mapped_to_glyphs_and_images() {
+        let icons = IconMap::new()
+            .with_glyph("sword", "\u{E001}")
+            .with_image("fire", 7u32);
+        let text = "[icon:sword] Ember Blade - [icon:fire] [icon:frost]";
+        assert_eq!(
+            icons.map(text),
+            vec![
+                IconSpan::Glyph {
+                    name: "sword",
+                    glyph: "\u{E001}"
+                },
+                IconSpan::Text(" Ember Blade - "),
+                IconSpan::Image {
+                    name: "fire",
+                    image: &7
+                },
+                IconSpan::Text(" "),
+                IconSpan::Unknown("frost"),
+            ]
+        );
+        assert_eq!(
+            icons.apply_glyphs(text),
+            "\u{E001} Ember Blade - [icon:fire] [icon:frost]"
+        );
+    }
+}
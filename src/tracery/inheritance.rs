@@ -0,0 +1,131 @@
+use super::TraceryGrammar;
+
+impl TraceryGrammar {
+    /// This layers a grammar of overrides over a base grammar, returning the combined grammar - rules the overrides define
+    /// replace the base's rules, and every other rule falls back to the base. This allows themes, like spooky or cheerful vocabulary,
+    /// to share a single base without duplicating it's full rule set. See `apply_overrides`.
+    pub fn with_overrides(base: &TraceryGrammar, overrides: &TraceryGrammar) -> TraceryGrammar {
+        let mut grammar = base.clone();
+        grammar.apply_overrides(overrides);
+        grammar
+    }
+
+    /// This layers a grammar of overrides over this one. Each rule the overrides define replaces this grammar's rule along with
+    /// it's settings, while aliases, exclusions and custom modifiers are added. The overrides' starting point is used if they
+    /// define it's rule, and their separator, join mode, locale, budget and maximum depth are used where they were set.
+    pub fn apply_overrides(&mut self, overrides: &TraceryGrammar) {
+        for key in overrides.keys.iter() {
+            let Some(options) = overrides.rules.get(key) else {
+                continue;
+            };
+            // Settings like weights refer to the base's options, so they are replaced along with them
+            self.settings.remove(key);
+            if self.rules.insert(key.clone(), options.clone()).is_none() {
+                self.keys.push(key.clone());
+            }
+        }
+        self.settings.extend(
+            overrides
+                .settings
+                .iter()
+                .map(|(key, settings)| (key.clone(), settings.clone())),
+        );
+        self.aliases.extend(
+            overrides
+                .aliases
+                .iter()
+                .map(|(alias, rule)| (alias.clone(), rule.clone())),
+        );
+        self.exclusions.extend(overrides.exclusions.iter().cloned());
+        self.modifiers.extend(
+            overrides
+                .modifiers
+                .iter()
+                .map(|(name, modifier)| (name.clone(), *modifier)),
+        );
+        if overrides.rules.contains_key(&overrides.starting_point) {
+            self.starting_point = overrides.starting_point.clone();
+        }
+        if !overrides.starting_points.is_empty() {
+            self.starting_points = overrides.starting_points.clone();
+        }
+        if !overrides.separator.is_empty() {
+            self.separator = overrides.separator.clone();
+        }
+        if overrides.join != Default::default() {
+            self.join = overrides.join;
+        }
+        if overrides.locale.is_some() {
+            self.locale = overrides.locale.clone();
+        }
+        if overrides.budget.is_some() {
+            self.budget = overrides.budget;
+        }
+        if overrides.max_depth.is_some() {
+            self.max_depth = overrides.max_depth;
+        }
+    }
+
+    /// This sets the path of the grammar this one extends, returning the updated grammar. See `set_extends`.
+    pub fn with_extends(mut self, parent: impl Into<String>) -> Self {
+        self.set_extends(Some(parent.into()));
+        self
+    }
+
+    /// This sets the path of the grammar this one extends, relative to it. When loaded as an asset, the parent grammar is loaded
+    /// too, and this one is layered over it using `with_overrides` - so a theme's file only needs the rules it changes.
+    pub fn set_extends(&mut self, parent: Option<String>) {
+        self.extends = parent;
+    }
+
+    /// This gets the path of the grammar this one extends, if it extends one
+    pub fn extends(&self) -> Option<&str> {
+        self.extends.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generator::{Generator, Grammar},
+        tracery::{SelectionMode, StringGenerator},
+    };
+
+    #[test]
+    pub fn overrides_replace_rules_and_fall_back_to_the_base() {
+        let base = TraceryGrammar::new(
+            &[
+                ("origin", &["The #adjective# #creature# #action#"]),
+                ("adjective", &["sunny", "bright"]),
+                ("creature", &["bunny"]),
+                ("action", &["hops by"]),
+            ],
+            None,
+        )
+        .with_weights("adjective", vec![1, 5]);
+        let spooky = TraceryGrammar::new(
+            &[
+                ("adjective", &["ghostly"]),
+                ("creature", &["#bat#"]),
+                ("bat", &["bat"]),
+            ],
+            None,
+        )
+        .with_selection_mode("creature", SelectionMode::Cycle);
+
+        let themed = TraceryGrammar::with_overrides(&base, &spooky);
+        assert_eq!(
+            StringGenerator::generate(&themed, &mut 0).unwrap(),
+            "The ghostly bat hops by"
+        );
+        assert_eq!(themed.default_starting_point(), "origin");
+        assert!(!themed.settings.contains_key("adjective"));
+        assert!(themed.settings.contains_key("creature"));
+        // The base is left unchanged
+        assert_eq!(
+            StringGenerator::generate(&base, &mut 0).unwrap(),
+            "The sunny bunny hops by"
+        );
+    }
+}
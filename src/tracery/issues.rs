@@ -0,0 +1,120 @@
+use std::fmt::Display;
+
+use super::{AssertionFailure, BudgetLimit};
+
+/// This is a problem encountered while generating - returned as an error by the `try_` generation functions,
+/// or reported as a warning by `StatefulStringGenerator::issues`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GenerationIssue {
+    /// The rule generation started from doesn't exist
+    UnknownRule(String),
+    /// None of the options of the rule generation started from were available - for example, because of their guards
+    NoAvailableOption(String),
+    /// Variables were assigned using the keys of base rules
+    ShadowedRules(Vec<String>),
+    /// The output of a sandboxed grammar was longer than it's `SandboxPolicy` allows - holding the output's length
+    OutputTooLong(usize),
+    /// Assertions like `[assert:hero]` were reached before their variables were set
+    FailedAssertions(Vec<AssertionFailure>),
+    /// An interactive generation was resumed with an option the choice point doesn't have - holding the rule and the choice
+    InvalidChoice(String, usize),
+    /// The generation went over the grammar's `GenerationBudget`, and was stopped
+    BudgetExceeded(BudgetLimit),
+    /// The generator is waiting for it's grammar asset to load - see `StatefulStringGenerator::from_handle`
+    NotReady,
+    /// A variable couldn't be re-rolled, since the last generation didn't assign it - or the generator isn't rerollable
+    NotRerollable(String),
+    /// Every output generated was rejected by the content filter, with the number of attempts made
+    Filtered(usize),
+}
+
+impl Display for GenerationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GenerationIssue::UnknownRule(rule) => write!(f, "the rule `{rule}` doesn't exist"),
+            GenerationIssue::NoAvailableOption(rule) => {
+                write!(f, "none of the options of `{rule}` are available")
+            }
+            GenerationIssue::ShadowedRules(rules) => write!(
+                f,
+                "variables were assigned over the base rules: {}",
+                rules.join(", ")
+            ),
+            GenerationIssue::OutputTooLong(length) => {
+                write!(f, "the output is longer than the sandbox allows ({length})")
+            }
+            GenerationIssue::FailedAssertions(failures) => {
+                write!(f, "assertions failed: ")?;
+                for (index, failure) in failures.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{failure}")?;
+                }
+                Ok(())
+            }
+            GenerationIssue::InvalidChoice(rule, choice) => {
+                write!(f, "`{rule}` doesn't have an option {choice} to choose")
+            }
+            GenerationIssue::BudgetExceeded(limit) => {
+                write!(f, "the generation went over it's budget of {limit}")
+            }
+            GenerationIssue::NotReady => write!(f, "the grammar hasn't loaded yet"),
+            GenerationIssue::NotRerollable(variable) => {
+                write!(f, "the variable `{variable}` can't be re-rolled")
+            }
+            GenerationIssue::Filtered(attempts) => write!(
+                f,
+                "every output was rejected by the content filter, after {attempts} attempts"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GenerationIssue {}
+
+/// This is a problem with a grammar's definition, found while building it using a `TraceryGrammarBuilder` or while changing it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GrammarError {
+    /// A rule was given an empty key
+    EmptyKey,
+    /// The same rule was defined more than once
+    DuplicateRule(String),
+    /// A rule was defined without any options
+    NoOptions(String),
+    /// A weight was set for a rule before any of it's options were added
+    WeightWithoutOption(String),
+    /// The grammar's starting point isn't one of it's rules
+    MissingStartingPoint(String),
+    /// A rule that doesn't exist was changed
+    UnknownRule(String),
+    /// A word list referenced using `@file(...)` couldn't be loaded
+    MissingWordList(String),
+    /// An option source referenced using `@source(...)` provided no options, and the rule had no other options to fall back on
+    MissingOptionSource(String),
+}
+
+impl Display for GrammarError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GrammarError::EmptyKey => write!(f, "a rule has an empty key"),
+            GrammarError::DuplicateRule(rule) => write!(f, "the rule `{rule}` is defined twice"),
+            GrammarError::NoOptions(rule) => write!(f, "the rule `{rule}` has no options"),
+            GrammarError::WeightWithoutOption(rule) => {
+                write!(f, "a weight was set for `{rule}` before any options")
+            }
+            GrammarError::MissingStartingPoint(rule) => {
+                write!(f, "the starting point `{rule}` isn't a rule")
+            }
+            GrammarError::UnknownRule(rule) => write!(f, "the rule `{rule}` doesn't exist"),
+            GrammarError::MissingWordList(path) => {
+                write!(f, "the word list `{path}` couldn't be loaded")
+            }
+            GrammarError::MissingOptionSource(name) => {
+                write!(f, "the option source `{name}` provided no options")
+            }
+        }
+    }
+}
+
+impl std::error::Error for GrammarError {}
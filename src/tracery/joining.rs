@@ -0,0 +1,85 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// This determines how the pieces of each expansion - the text written directly in an option, and the expansions of the rules
+/// it references - are joined together
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum JoinMode {
+    /// The pieces are joined using the grammar's separator, exactly as written. This is the default behaviour
+    #[default]
+    Separator,
+    /// The pieces keep the spacing written around them in the option, while cleaning up where they meet - rules that expanded
+    /// to nothing are skipped, a space starting a piece is dropped if the text before it already ends in whitespace, and the
+    /// separator is only placed between pieces that would otherwise run two words together. So with a separator of `" "`,
+    /// `a #adjective# #mountain#` with an empty adjective becomes `a mountain` rather than `a  mountain`, and `a` followed
+    /// directly by `mountain` doesn't become `amountain`.
+    Smart,
+}
+
+impl JoinMode {
+    /// Joins the pieces of an expansion, using the separator as this mode describes
+    pub fn join(&self, pieces: &[String], separator: &str) -> String {
+        match self {
+            JoinMode::Separator => pieces.join(separator),
+            JoinMode::Smart => smart_join(pieces, separator),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    pub(crate) fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+fn smart_join(pieces: &[String], separator: &str) -> String {
+    let mut output = String::new();
+    for piece in pieces.iter().filter(|piece| !piece.is_empty()) {
+        let mut piece = piece.as_str();
+        match (output.chars().last(), piece.chars().next()) {
+            (Some(previous), _) if previous.is_whitespace() => {
+                piece = piece.trim_start_matches([' ', '\t']);
+            }
+            (Some(previous), Some(next))
+                if previous.is_alphanumeric() && next.is_alphanumeric() =>
+            {
+                output.push_str(separator);
+            }
+            _ => {}
+        }
+        output.push_str(piece);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracery::{StringGenerator, TraceryGrammar};
+
+    #[test]
+    pub fn smart_joining_keeps_the_written_spacing() {
+        let pieces = ["a ", "", " mountain", ",", "a", "cave"].map(str::to_string);
+        assert_eq!(
+            JoinMode::Separator.join(&pieces, " "),
+            "a    mountain , a cave"
+        );
+        assert_eq!(JoinMode::Smart.join(&pieces, " "), "a mountain,a cave");
+        assert_eq!(JoinMode::Smart.join(&pieces, ""), "a mountain,acave");
+
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["[hero:Ana]#hero# saw a #adjective# #place#."]),
+                ("adjective", &[""]),
+                ("place", &["mountain"]),
+            ],
+            None,
+        )
+        .with_separator(" ")
+        .with_join_mode(JoinMode::Smart);
+        assert_eq!(
+            StringGenerator::try_generate(&grammar, &mut 0).unwrap(),
+            "Ana saw a mountain."
+        );
+    }
+}
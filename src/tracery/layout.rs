@@ -0,0 +1,175 @@
+/// The directive placing a line break in the output - `[br]`
+pub const LINE_BREAK: &str = "br";
+
+/// The directive starting a new paragraph in the output - `[p]`
+pub const PARAGRAPH_BREAK: &str = "p";
+
+/// The directive placing an inline icon in the output - `[icon:sword]`
+pub const ICON_DIRECTIVE: &str = "icon";
+
+/// Gets the text a layout directive is replaced with, or `None` if the action isn't one.
+/// Icons are kept in the output as they are written, so `rich_spans` can find them.
+pub(crate) fn directive_text(action: &str) -> Option<String> {
+    match action {
+        LINE_BREAK => Some("\n".to_string()),
+        PARAGRAPH_BREAK => Some("\n\n".to_string()),
+        _ => icon_name(action).map(|icon| format!("[{ICON_DIRECTIVE}:{icon}]")),
+    }
+}
+
+/// Gets the name of the icon an action places, or `None` if the action isn't an icon directive
+fn icon_name(action: &str) -> Option<&str> {
+    let icon = action
+        .strip_prefix(ICON_DIRECTIVE)?
+        .strip_prefix(':')?
+        .trim();
+    let valid = !icon.is_empty()
+        && icon
+            .chars()
+            .all(|character| character.is_alphanumeric() || "_-".contains(character));
+    valid.then_some(icon)
+}
+
+/// This is a piece of generated text, split into plain text and the icons placed using `[icon:...]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RichSpan<'a> {
+    /// Plain text
+    Text(&'a str),
+    /// An inline icon, with it's name
+    Icon(&'a str),
+}
+
+/// Splits generated text into plain text and icons - so UIs can show the icons as inline images or font glyphs,
+/// such as with an `IconMap`. Empty pieces of text are skipped.
+pub fn rich_spans(text: &str) -> Vec<RichSpan<'_>> {
+    let marker = format!("[{ICON_DIRECTIVE}:");
+    let mut spans = vec![];
+    let mut text_start = 0;
+    let mut search = 0;
+    while let Some(start) = text[search..].find(&marker).map(|start| search + start) {
+        search = start + marker.len();
+        let Some(end) = text[start..].find(']').map(|end| start + end) else {
+            break;
+        };
+        // Brackets that don't hold a valid icon name are kept as text
+        let Some(icon) = icon_name(&text[start + 1..end]) else {
+            continue;
+        };
+        if start > text_start {
+            spans.push(RichSpan::Text(&text[text_start..start]));
+        }
+        spans.push(RichSpan::Icon(icon));
+        text_start = end + 1;
+        search = text_start;
+    }
+    if text_start < text.len() {
+        spans.push(RichSpan::Text(&text[text_start..]));
+    }
+    spans
+}
+
+/// Removes the icons from generated text, for outputs that can't show them - like logs or screen readers.
+/// A space left doubled by a removed icon is dropped.
+pub fn strip_icons(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut skip_space = false;
+    for span in rich_spans(text) {
+        match span {
+            RichSpan::Text(text) => {
+                let text = match skip_space {
+                    true => text.strip_prefix(' ').unwrap_or(text),
+                    false => text,
+                };
+                result.push_str(text);
+                skip_space = false;
+            }
+            RichSpan::Icon(_) => skip_space = result.is_empty() || result.ends_with([' ', '\n']),
+        }
+    }
+    result
+}
+
+/// Splits generated text into paragraphs, each holding it's lines - so UIs can lay out each paragraph as a separate
+/// rich text section. Paragraphs are separated by blank lines, like the ones `[p]` produces, and empty paragraphs are skipped.
+pub fn paragraphs(text: &str) -> Vec<Vec<&str>> {
+    let mut paragraphs = vec![];
+    let mut lines = vec![];
+    for line in text.split('\n') {
+        if line.trim().is_empty() {
+            if !lines.is_empty() {
+                paragraphs.push(std::mem::take(&mut lines));
+            }
+            continue;
+        }
+        lines.push(line);
+    }
+    if !lines.is_empty() {
+        paragraphs.push(lines);
+    }
+    paragraphs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generator::Generator,
+        tracery::{StringGenerator, TraceryGrammar},
+    };
+
+    #[test]
+    pub fn directives_break_lines_and_paragraphs() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["#title#[p]#verse#[br]#verse#[p]The end."]),
+                ("title", &["#[br]name.capitalize#"]),
+                ("name", &["the sea"]),
+                ("verse", &["Waves roll"]),
+            ],
+            None,
+        );
+        let text = StringGenerator::generate(&grammar, &mut 0).unwrap();
+        assert_eq!(text, "\nThe sea\n\nWaves roll\nWaves roll\n\nThe end.");
+        assert_eq!(
+            paragraphs(&text),
+            vec![
+                vec!["The sea"],
+                vec!["Waves roll", "Waves roll"],
+                vec!["The end."]
+            ]
+        );
+    }
+
+    #[test]
+    pub fn icons_pass_through_as_rich_spans() {
+        let grammar = TraceryGrammar::new(
+            &[
+                (
+                    "origin",
+                    &["[icon:sword] #blade# deals 4 [icon:fire-damage] damage"],
+                ),
+                ("blade", &["Ember Blade"]),
+            ],
+            None,
+        );
+        let text = StringGenerator::generate(&grammar, &mut 0).unwrap();
+        assert_eq!(
+            text,
+            "[icon:sword] Ember Blade deals 4 [icon:fire-damage] damage"
+        );
+        assert_eq!(
+            rich_spans(&text),
+            vec![
+                RichSpan::Icon("sword"),
+                RichSpan::Text(" Ember Blade deals 4 "),
+                RichSpan::Icon("fire-damage"),
+                RichSpan::Text(" damage"),
+            ]
+        );
+        assert_eq!(strip_icons(&text), "Ember Blade deals 4 damage");
+        assert_eq!(
+            rich_spans("a [icon:two words] b"),
+            vec![RichSpan::Text("a [icon:two words] b")]
+        );
+    }
+}
@@ -0,0 +1,294 @@
+#[cfg(feature = "bevy")]
+use bevy::prelude::Resource;
+#[cfg(feature = "bevy")]
+use bevy::utils::HashMap;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(not(feature = "bevy"))]
+use std::collections::HashMap;
+
+use super::{GenerationIssue, StringGenerator, TraceryGrammar};
+use crate::generator::{Grammar, GrammarRandomNumberGenerator};
+
+/// This holds the translations of a grammar - one rule set for each locale, like `en` or `fr`, sharing the same rule keys.
+/// Each locale falls back on other locales for the rules it doesn't translate - first the ones set using `with_fallbacks`,
+/// then it's language (`fr` for `fr-CA`), and finally the default locale - so a translation can start out partial.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "bevy", derive(Resource))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "LocalizedContent"))]
+#[cfg_attr(
+    feature = "asset",
+    derive(bevy::reflect::TypeUuid, bevy::reflect::TypePath, bevy::asset::Asset,)
+)]
+#[cfg_attr(feature = "asset", uuid = "9b0f6a52-7c1e-4d38-a0e5-3f1b8c6d2e47")]
+pub struct LocalizedTraceryGrammar {
+    default_locale: String,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "HashMap::is_empty"))]
+    fallbacks: HashMap<String, Vec<String>>,
+    locales: HashMap<String, TraceryGrammar>,
+    /// Each locale's rules merged with the rules of the locales it falls back on
+    #[cfg_attr(feature = "serde", serde(skip_serializing))]
+    resolved: HashMap<String, TraceryGrammar>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct LocalizedContent {
+    default_locale: String,
+    #[serde(default)]
+    fallbacks: HashMap<String, Vec<String>>,
+    locales: HashMap<String, TraceryGrammar>,
+}
+
+#[cfg(feature = "serde")]
+impl From<LocalizedContent> for LocalizedTraceryGrammar {
+    fn from(content: LocalizedContent) -> Self {
+        let mut grammar = Self {
+            default_locale: content.default_locale,
+            fallbacks: content.fallbacks,
+            locales: content.locales,
+            resolved: HashMap::default(),
+        };
+        grammar.resolve();
+        grammar
+    }
+}
+
+impl LocalizedTraceryGrammar {
+    /// Creates a localized grammar holding the rules for it's default locale
+    pub fn new(default_locale: impl Into<String>, grammar: TraceryGrammar) -> Self {
+        let default_locale = default_locale.into();
+        let mut locales = HashMap::default();
+        locales.insert(default_locale.clone(), grammar);
+        let mut grammar = Self {
+            default_locale,
+            fallbacks: HashMap::default(),
+            locales,
+            resolved: HashMap::default(),
+        };
+        grammar.resolve();
+        grammar
+    }
+
+    /// Adds the rules for a locale, returning the updated grammar. See `add_locale`.
+    pub fn with_locale(mut self, locale: impl Into<String>, grammar: TraceryGrammar) -> Self {
+        self.add_locale(locale, grammar);
+        self
+    }
+
+    /// Adds the rules for a locale, replacing any it had before. Only the rules that differ from it's fallbacks need to be provided.
+    pub fn add_locale(&mut self, locale: impl Into<String>, grammar: TraceryGrammar) {
+        self.locales.insert(locale.into(), grammar);
+        self.resolve();
+    }
+
+    /// Sets the locales a locale falls back on, in order, returning the updated grammar. See `set_fallbacks`.
+    pub fn with_fallbacks<T: Into<String>>(
+        mut self,
+        locale: impl Into<String>,
+        fallbacks: impl IntoIterator<Item = T>,
+    ) -> Self {
+        self.set_fallbacks(locale, fallbacks);
+        self
+    }
+
+    /// Sets the locales a locale falls back on, in order - so `pt-BR` can fall back on `pt-PT` before the default locale.
+    /// These are tried before the locale's language and the default locale.
+    pub fn set_fallbacks<T: Into<String>>(
+        &mut self,
+        locale: impl Into<String>,
+        fallbacks: impl IntoIterator<Item = T>,
+    ) {
+        self.fallbacks.insert(
+            locale.into(),
+            fallbacks.into_iter().map(Into::into).collect(),
+        );
+        self.resolve();
+    }
+
+    /// Gets the locale used when no other locale matches
+    pub fn default_locale(&self) -> &str {
+        &self.default_locale
+    }
+
+    /// Gets the locales that have rules, in no particular order
+    pub fn locales(&self) -> impl Iterator<Item = &str> {
+        self.locales.keys().map(String::as_str)
+    }
+
+    /// Gets the locales tried when generating for a locale, in order. Locales without rules are included, and skipped while generating.
+    pub fn fallback_chain(&self, locale: &str) -> Vec<String> {
+        let mut chain = vec![locale.to_string()];
+        if let Some(fallbacks) = self.fallbacks.get(locale) {
+            chain.extend(fallbacks.iter().cloned());
+        }
+        if let Some((language, _)) = locale.split_once(['-', '_']) {
+            chain.push(language.to_string());
+        }
+        chain.push(self.default_locale.clone());
+        let mut seen = vec![];
+        chain.retain(|locale| {
+            let new = !seen.contains(locale);
+            seen.push(locale.clone());
+            new
+        });
+        chain
+    }
+
+    /// Gets the grammar used for a locale - the first locale in it's fallback chain that has rules, merged with the rules of the
+    /// locales it falls back on
+    pub fn grammar(&self, locale: &str) -> Option<&TraceryGrammar> {
+        self.fallback_chain(locale)
+            .iter()
+            .find_map(|locale| self.resolved.get(locale))
+    }
+
+    /// Generates from the starting point of a locale's grammar
+    pub fn generate<R: GrammarRandomNumberGenerator>(
+        &self,
+        locale: &str,
+        rng: &mut R,
+    ) -> Option<String> {
+        let grammar = self.grammar(locale)?;
+        StringGenerator::try_generate(grammar, rng).ok()
+    }
+
+    /// Generates from a rule of a locale's grammar, explaining why if it couldn't
+    pub fn try_generate_at<R: GrammarRandomNumberGenerator>(
+        &self,
+        locale: &str,
+        key: &str,
+        rng: &mut R,
+    ) -> Result<String, GenerationIssue> {
+        let grammar = self
+            .grammar(locale)
+            .ok_or_else(|| GenerationIssue::UnknownRule(key.to_string()))?;
+        StringGenerator::try_generate_at(key, grammar, rng)
+    }
+
+    /// Merges the rules of each locale with the locales it falls back on. The most specific locale's rules and settings win,
+    /// while the starting point comes from the last locale in the chain - usually the default locale. The typography
+    /// locale also comes from the most specific locale that sets one.
+    fn resolve(&mut self) {
+        let mut resolved = HashMap::default();
+        for locale in self.locales.keys() {
+            let mut chain = self
+                .fallback_chain(locale)
+                .into_iter()
+                .filter_map(|locale| self.locales.get(&locale))
+                .rev();
+            let Some(mut grammar) = chain.next().cloned() else {
+                continue;
+            };
+            for overrides in chain {
+                for key in overrides.keys.iter() {
+                    if let Some(options) = overrides.rules.get(key) {
+                        grammar.set_additional_rules(key.clone(), options);
+                    }
+                    match overrides.settings.get(key) {
+                        Some(settings) => {
+                            grammar.settings.insert(key.clone(), settings.clone());
+                        }
+                        None => {
+                            grammar.settings.remove(key);
+                        }
+                    }
+                }
+                grammar.aliases.extend(overrides.aliases.clone());
+                if overrides.locale.is_some() {
+                    grammar.locale = overrides.locale.clone();
+                }
+            }
+            resolved.insert(locale.clone(), grammar);
+        }
+        self.resolved = resolved;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grammar() -> LocalizedTraceryGrammar {
+        LocalizedTraceryGrammar::new(
+            "en",
+            TraceryGrammar::new(
+                &[
+                    ("origin", &["#greeting#, #name#!"]),
+                    ("greeting", &["Hello"]),
+                    ("name", &["Ana"]),
+                ],
+                None,
+            ),
+        )
+        .with_locale(
+            "fr",
+            TraceryGrammar::new(&[("greeting", &["Bonjour"])], None),
+        )
+        .with_locale(
+            "fr-CA",
+            TraceryGrammar::new(&[("greeting", &["Allô"])], None),
+        )
+        .with_locale("es", TraceryGrammar::new(&[("name", &["Ana María"])], None))
+        .with_fallbacks("ca", ["es"])
+    }
+
+    #[test]
+    pub fn locales_can_set_their_typography() {
+        let grammar = grammar().with_locale(
+            "fr",
+            TraceryGrammar::new(&[("greeting", &["Bonjour"])], None).with_locale("fr"),
+        );
+        let mut rng = 0;
+        assert_eq!(
+            grammar.generate("fr-CA", &mut rng).unwrap(),
+            "Allô, Ana\u{202F}!"
+        );
+        assert_eq!(grammar.generate("en", &mut rng).unwrap(), "Hello, Ana!");
+    }
+
+    #[test]
+    pub fn locales_fall_back_on_the_rules_they_dont_translate() {
+        let grammar = grammar();
+        let mut rng = 0;
+        assert_eq!(grammar.generate("en", &mut rng).unwrap(), "Hello, Ana!");
+        assert_eq!(grammar.generate("fr", &mut rng).unwrap(), "Bonjour, Ana!");
+        assert_eq!(grammar.generate("fr-CA", &mut rng).unwrap(), "Allô, Ana!");
+        assert_eq!(
+            grammar.generate("fr-BE", &mut rng).unwrap(),
+            "Bonjour, Ana!"
+        );
+        assert_eq!(
+            grammar.generate("ca", &mut rng).unwrap(),
+            "Hello, Ana María!"
+        );
+        assert_eq!(grammar.generate("de", &mut rng).unwrap(), "Hello, Ana!");
+        assert_eq!(
+            grammar.fallback_chain("fr-CA"),
+            vec!["fr-CA".to_string(), "fr".to_string(), "en".to_string()]
+        );
+        assert_eq!(
+            grammar.try_generate_at("fr", "missing", &mut rng),
+            Err(GenerationIssue::UnknownRule("missing".to_string()))
+        );
+    }
+
+    #[cfg(feature = "tracery_compat")]
+    #[test]
+    pub fn localized_grammars_can_be_deserialized_from_one_file() {
+        let grammar: LocalizedTraceryGrammar = serde_json::from_str(
+            r##"{
+                "default_locale": "en",
+                "fallbacks": { "ca": ["es"] },
+                "locales": {
+                    "en": { "origin": "#greeting#!", "greeting": "Hello" },
+                    "es": { "greeting": "Hola" }
+                }
+            }"##,
+        )
+        .unwrap();
+        assert_eq!(grammar.generate("ca", &mut 0).unwrap(), "Hola!");
+        assert_eq!(grammar.generate("en", &mut 0).unwrap(), "Hello!");
+    }
+}
@@ -0,0 +1,209 @@
+/// A modifier transforms the fully expanded text of a rule. It receives the text, and any parameters provided
+/// in the key - so `#name.replace(a,b)#` calls the `replace` modifier with `["a", "b"]`.
+pub type Modifier = fn(&str, &[&str]) -> String;
+
+/// The names of all the built in modifiers, matching the ones provided by the original Tracery
+pub const BUILT_IN_MODIFIERS: &[&str] = &[
+    "capitalize",
+    "capitalizeAll",
+    "a",
+    "s",
+    "firstS",
+    "ed",
+    "replace",
+    "inQuotes",
+    "comma",
+    "beeSpeak",
+];
+
+/// Gets one of the built in modifiers by name
+pub fn built_in_modifier(name: &str) -> Option<Modifier> {
+    let modifier: Modifier = match name {
+        "capitalize" => |text, _| capitalize(text),
+        "capitalizeAll" => |text, _| capitalize_all(text),
+        "a" => |text, _| a(text),
+        "s" => |text, _| s(text),
+        "firstS" => |text, _| first_s(text),
+        "ed" => |text, _| ed(text),
+        "replace" => |text, params| match params {
+            [from, to, ..] if !from.is_empty() => text.replace(from, to),
+            _ => text.to_string(),
+        },
+        "inQuotes" => |text, _| format!("\"{text}\""),
+        "comma" => |text, _| match text.chars().last() {
+            Some(',' | '.' | '?' | '!') => text.to_string(),
+            _ => format!("{text},"),
+        },
+        "beeSpeak" => |text, _| text.replace('s', "zzz"),
+        _ => return None,
+    };
+    Some(modifier)
+}
+
+/// Parses the modifier portion of a key (everything after the first `.`) into a list of modifier names and parameters.
+/// Modifiers are separated by `.`, and parameters are provided in parentheses, separated by `,`.
+pub fn parse_modifiers(modifiers: &str) -> Vec<(&str, Vec<&str>)> {
+    let mut result = vec![];
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (index, character) in modifiers.char_indices() {
+        match character {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            '.' if depth == 0 => {
+                result.push(parse_modifier(&modifiers[start..index]));
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    result.push(parse_modifier(&modifiers[start..]));
+    result.retain(|(name, _)| !name.is_empty());
+    result
+}
+
+fn parse_modifier(modifier: &str) -> (&str, Vec<&str>) {
+    match modifier.split_once('(') {
+        Some((name, params)) => {
+            let params = params.strip_suffix(')').unwrap_or(params);
+            (name, params.split(',').collect())
+        }
+        None => (modifier, vec![]),
+    }
+}
+
+fn is_vowel(character: char) -> bool {
+    matches!(character.to_ascii_lowercase(), 'a' | 'e' | 'i' | 'o' | 'u')
+}
+
+fn capitalize(text: &str) -> String {
+    capitalize_with(text, |character| character.to_uppercase().collect())
+}
+
+fn capitalize_all(text: &str) -> String {
+    capitalize_all_with(text, |character| character.to_uppercase().collect())
+}
+
+/// Capitalizes the first character of the text, using the provided conversion to upper case
+pub(crate) fn capitalize_with(text: &str, uppercase: fn(char) -> String) -> String {
+    let mut characters = text.chars();
+    match characters.next() {
+        Some(first) => uppercase(first) + characters.as_str(),
+        None => String::new(),
+    }
+}
+
+/// Capitalizes the first character of each word, using the provided conversion to upper case
+pub(crate) fn capitalize_all_with(text: &str, uppercase: fn(char) -> String) -> String {
+    let mut capitalize_next = true;
+    let mut result = String::with_capacity(text.len());
+    for character in text.chars() {
+        match capitalize_next {
+            true => result.push_str(&uppercase(character)),
+            false => result.push(character),
+        }
+        capitalize_next = !character.is_alphanumeric();
+    }
+    result
+}
+
+fn a(text: &str) -> String {
+    match takes_an(text) {
+        true => format!("an {text}"),
+        false => format!("a {text}"),
+    }
+}
+
+/// Checks whether a word takes `an` rather than `a` as it's indefinite article
+pub(crate) fn takes_an(word: &str) -> bool {
+    let mut characters = word.chars();
+    let first = characters.next();
+    let third = characters.nth(1);
+    match (first, third) {
+        (Some('u' | 'U'), Some('i' | 'I')) => false,
+        (Some(first), _) => is_vowel(first),
+        _ => false,
+    }
+}
+
+fn s(text: &str) -> String {
+    let mut characters = text.chars().rev();
+    match (characters.next(), characters.next()) {
+        (Some('s' | 'h' | 'x'), _) => format!("{text}es"),
+        (Some('y'), Some(before)) if !is_vowel(before) => {
+            format!("{}ies", &text[..text.len() - 1])
+        }
+        _ => format!("{text}s"),
+    }
+}
+
+fn first_s(text: &str) -> String {
+    match text.split_once(' ') {
+        Some((first, rest)) => format!("{} {rest}", s(first)),
+        None => s(text),
+    }
+}
+
+fn ed(text: &str) -> String {
+    let (word, rest) = match text.find(' ') {
+        Some(index) if index > 0 => text.split_at(index),
+        _ => (text, ""),
+    };
+    let mut characters = word.chars().rev();
+    match (characters.next(), characters.next()) {
+        (Some('e'), _) => format!("{word}d{rest}"),
+        (Some('y'), Some(before)) if !is_vowel(before) => {
+            format!("{}ied{rest}", &word[..word.len() - 1])
+        }
+        (Some(_), _) => format!("{word}ed{rest}"),
+        (None, _) => rest.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(name: &str, text: &str) -> String {
+        built_in_modifier(name).unwrap()(text, &[])
+    }
+
+    #[test]
+    pub fn built_in_modifiers_match_tracery() {
+        assert_eq!(apply("capitalize", "unicorn horn"), "Unicorn horn");
+        assert_eq!(
+            apply("capitalizeAll", "unicorn-horn tea"),
+            "Unicorn-Horn Tea"
+        );
+        assert_eq!(apply("a", "owl"), "an owl");
+        assert_eq!(apply("a", "unicorn"), "a unicorn");
+        assert_eq!(apply("a", "dragon"), "a dragon");
+        assert_eq!(apply("s", "fox"), "foxes");
+        assert_eq!(apply("s", "pony"), "ponies");
+        assert_eq!(apply("s", "day"), "days");
+        assert_eq!(apply("firstS", "cat in a hat"), "cats in a hat");
+        assert_eq!(apply("ed", "bake bread"), "baked bread");
+        assert_eq!(apply("ed", "cry"), "cried");
+        assert_eq!(apply("ed", "jump"), "jumped");
+        assert_eq!(apply("comma", "hello"), "hello,");
+        assert_eq!(apply("comma", "hello!"), "hello!");
+        assert_eq!(apply("inQuotes", "hi"), "\"hi\"");
+        assert_eq!(apply("beeSpeak", "sass"), "zzzazzzzzz");
+        assert_eq!(
+            built_in_modifier("replace").unwrap()("a cat", &["cat", "dog"]),
+            "a dog"
+        );
+    }
+
+    #[test]
+    pub fn parses_modifier_chains_with_parameters() {
+        assert_eq!(
+            parse_modifiers("replace(a,b).capitalize"),
+            vec![("replace", vec!["a", "b"]), ("capitalize", vec![])]
+        );
+        assert_eq!(
+            parse_modifiers("replace(.,!).s."),
+            vec![("replace", vec![".", "!"]), ("s", vec![])]
+        );
+    }
+}
@@ -0,0 +1,342 @@
+use super::{
+    captures::{is_capture_key, split_key_and_modifiers},
+    find_closing, GrammarError, TraceryGrammar,
+};
+use crate::generator::Grammar;
+
+impl TraceryGrammar {
+    /// Adds an option to the end of a rule, creating the rule if it doesn't exist yet
+    pub fn add_option(&mut self, rule: impl Into<String>, option: impl Into<String>) {
+        let rule = rule.into();
+        match self.rules.get_mut(&rule) {
+            Some(options) => options.push(option.into()),
+            None => self.set_additional_rules(rule, &[option.into()]),
+        }
+    }
+
+    /// Removes one of a rule's options, returning it. The weights of the remaining options are kept, and the rule is kept
+    /// even once it has no options left - so references to it expand to nothing.
+    pub fn remove_option(&mut self, rule: &str, index: usize) -> Option<String> {
+        let options = self
+            .rules
+            .get_mut(rule)
+            .filter(|options| index < options.len())?;
+        let option = options.remove(index);
+        if let Some(settings) = self.settings.get_mut(rule) {
+            if index < settings.weights.len() {
+                settings.weights.remove(index);
+            }
+        }
+        Some(option)
+    }
+
+    /// Merges one of a rule's options into another, so the `merged` option is removed and the `kept` one takes over it's
+    /// weight - like two words of a language collapsing into one. Returns the removed option.
+    pub fn merge_options(&mut self, rule: &str, kept: usize, merged: usize) -> Option<String> {
+        let len = self.rules.get(rule)?.len();
+        if kept == merged || kept >= len || merged >= len {
+            return None;
+        }
+        if let Some(settings) = self
+            .settings
+            .get_mut(rule)
+            .filter(|settings| !settings.weights.is_empty())
+        {
+            let weight = |index: usize| settings.weights.get(index).copied().unwrap_or(1);
+            let total = weight(kept).saturating_add(weight(merged));
+            if settings.weights.len() <= kept {
+                settings.weights.resize(kept + 1, 1);
+            }
+            settings.weights[kept] = total;
+        } else {
+            let mut weights = vec![1; len];
+            weights[kept] = 2;
+            self.set_weights(rule, weights);
+        }
+        self.remove_option(rule, merged)
+    }
+
+    /// Fixes a rule to a single value, replacing it's options - so `hero` always expands to `dragon`. The value is expanded like
+    /// any other option, so it can reference other rules. Only the rule's `emit` setting is kept, since the others choose between
+    /// options - and the rule is created if it doesn't exist yet, so variables and tokens can be bound as well.
+    pub fn bind_rule(&mut self, rule: &str, value: impl Into<String>) {
+        let rule = self.resolve_alias(rule).to_string();
+        let emit = self
+            .settings
+            .remove(&rule)
+            .is_some_and(|settings| settings.emit);
+        if emit {
+            self.set_emitted(rule.clone(), true);
+        }
+        self.set_additional_rules(rule, &[value.into()]);
+    }
+
+    /// Derives a grammar with some of it's rules fixed to single values, leaving this one unchanged - such as variants of a master
+    /// grammar for each character, or a themed subset of it. See `bind_rule`.
+    pub fn with_bindings<T: Clone + Into<String>>(&self, bindings: &[(T, T)]) -> TraceryGrammar {
+        let mut grammar = self.clone();
+        for (rule, value) in bindings {
+            grammar.bind_rule(&rule.clone().into(), value.clone());
+        }
+        grammar
+    }
+
+    /// Renames a rule, rewriting every reference to it - in the options of every rule, as well as in actions, captures,
+    /// aliases, exclusions and starting points - so the grammar generates the same text as before.
+    pub fn rename_rule(&mut self, rule: &str, name: impl Into<String>) -> Result<(), GrammarError> {
+        let name = name.into();
+        if name.is_empty() {
+            return Err(GrammarError::EmptyKey);
+        }
+        let Some(options) = self.rules.remove(rule) else {
+            return Err(GrammarError::UnknownRule(rule.to_string()));
+        };
+        if name != rule && self.rules.contains_key(&name) {
+            self.rules.insert(rule.to_string(), options);
+            return Err(GrammarError::DuplicateRule(name));
+        }
+        self.rules.insert(name.clone(), options);
+        for key in self.keys.iter_mut().filter(|key| *key == rule) {
+            key.clone_from(&name);
+        }
+        for options in self.rules.values_mut() {
+            for option in options.iter_mut() {
+                *option = rename_references(option, rule, &name);
+            }
+        }
+        if let Some(settings) = self.settings.remove(rule) {
+            self.settings.insert(name.clone(), settings);
+        }
+        for target in self.aliases.values_mut().filter(|target| *target == rule) {
+            target.clone_from(&name);
+        }
+        for exclusion in self.exclusions.iter_mut() {
+            for side in [&mut exclusion.rule, &mut exclusion.other_rule] {
+                if side == rule {
+                    side.clone_from(&name);
+                }
+            }
+        }
+        for (key, _) in self
+            .starting_points
+            .iter_mut()
+            .filter(|(key, _)| key == rule)
+        {
+            key.clone_from(&name);
+        }
+        if self.starting_point == rule {
+            self.starting_point = name;
+        }
+        Ok(())
+    }
+}
+
+/// Rewrites the references to a rule within some text, leaving everything else untouched
+fn rename_references(text: &str, rule: &str, name: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(['#', '[']) {
+        result.push_str(&rest[..start]);
+        let closing = if rest[start..].starts_with('#') {
+            '#'
+        } else {
+            ']'
+        };
+        let inner = &rest[start + 1..];
+        let end = find_closing(inner, closing);
+        result.push_str(&rest[start..start + 1]);
+        result.push_str(&match closing {
+            '#' => rename_in_tag(&inner[..end], rule, name),
+            _ => rename_in_action(&inner[..end], rule, name),
+        });
+        if end < inner.len() {
+            result.push(closing);
+        }
+        rest = inner.get(end + 1..).unwrap_or_default();
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Rewrites the references within a tag - it's actions, followed by it's key and modifiers
+fn rename_in_tag(tag: &str, rule: &str, name: &str) -> String {
+    let mut result = String::new();
+    let mut rest = tag;
+    while let Some(inner) = rest.strip_prefix('[') {
+        let end = find_closing(inner, ']');
+        result.push('[');
+        result.push_str(&rename_in_action(&inner[..end], rule, name));
+        if end < inner.len() {
+            result.push(']');
+        }
+        rest = inner.get(end + 1..).unwrap_or_default();
+    }
+    match split_key_and_modifiers(rest) {
+        Some((key, modifiers)) => {
+            result.push_str(&rename_key(key, rule, name));
+            result.push('.');
+            result.push_str(modifiers);
+        }
+        None => result.push_str(&rename_key(rest, rule, name)),
+    }
+    result
+}
+
+/// Rewrites the references within an action - the key it assigns, as well as the value assigned to it
+fn rename_in_action(action: &str, rule: &str, name: &str) -> String {
+    match action.find([':', '|']) {
+        Some(split) => format!(
+            "{}{}{}",
+            rename_key(&action[..split], rule, name),
+            &action[split..split + 1],
+            rename_references(&action[split + 1..], rule, name)
+        ),
+        None => rename_references(action, rule, name),
+    }
+}
+
+/// Renames a key if it is the rule, or one of the rule's captures
+fn rename_key(key: &str, rule: &str, name: &str) -> String {
+    if key == rule {
+        return name.to_string();
+    }
+    match key.strip_prefix(rule).and_then(|key| key.strip_prefix('.')) {
+        Some(capture) if is_capture_key(key) => format!("{name}.{capture}"),
+        _ => key.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generator::{Generator, SeededRng},
+        tracery::{Exclusion, StringGenerator},
+    };
+
+    #[test]
+    pub fn renamed_rules_are_rewritten_wherever_they_are_referenced() {
+        let mut grammar = TraceryGrammar::new(
+            &[
+                (
+                    "origin",
+                    &[
+                        "#[hero:#name#]story#",
+                        "#name.capitalize# (#name#|#name._index#)",
+                    ],
+                ),
+                ("story", &["#hero# met [name:#name#]#name# at #place#"]),
+                ("name", &["ana", "bo"]),
+                ("place", &["the inn"]),
+            ],
+            None,
+        )
+        .with_alias("who", "name")
+        .with_exclusion(Exclusion::new("name", "ana", "place", "the inn"))
+        .with_weights("name", [1, 3]);
+        let before = StringGenerator::generate_batch(&grammar, 8, 3);
+
+        grammar.rename_rule("name", "person").unwrap();
+        let options = grammar.get_rule_options(&"origin".to_string()).unwrap();
+        assert_eq!(
+            options,
+            &vec![
+                "#[hero:#person#]story#".to_string(),
+                "#person.capitalize# (#person#|#person._index#)".to_string()
+            ]
+        );
+        assert_eq!(
+            grammar.get_rule_options(&"story".to_string()).unwrap()[0],
+            "#hero# met [person:#person#]#person# at #place#"
+        );
+        assert!(!grammar.has_rule(&"name".to_string()));
+        assert_eq!(grammar.resolve_alias("who"), "person");
+        assert!(grammar.exclusions()[0].involves("person"));
+        assert_eq!(grammar.rule_settings("person").unwrap().weights, vec![1, 3]);
+        assert_eq!(StringGenerator::generate_batch(&grammar, 8, 3), before);
+
+        assert_eq!(
+            grammar.rename_rule("person", "place"),
+            Err(GrammarError::DuplicateRule("place".to_string()))
+        );
+        assert_eq!(
+            grammar.rename_rule("name", "other"),
+            Err(GrammarError::UnknownRule("name".to_string()))
+        );
+        assert!(grammar.has_rule(&"person".to_string()));
+    }
+
+    #[test]
+    pub fn bindings_fix_rules_to_a_single_value() {
+        let master = TraceryGrammar::new(
+            &[
+                ("origin", &["The #who# guards the #place#."]),
+                ("hero", &["knight", "wizard"]),
+                ("place", &["tower", "bridge", "gate"]),
+            ],
+            None,
+        )
+        .with_alias("who", "hero")
+        .with_weights("hero", [0, 1])
+        .with_emitted("hero");
+        let dragon =
+            master.with_bindings(&[("who", "dragon"), ("place", "#hoard#"), ("hoard", "gold")]);
+        for seed in 0..4 {
+            let (text, data) =
+                StringGenerator::generate_with_data(&dragon, &mut SeededRng::new(seed)).unwrap();
+            assert_eq!(text, "The dragon guards the gold.");
+            assert_eq!(data.get("hero").map(String::as_str), Some("dragon"));
+        }
+        assert_eq!(
+            master.get_rule_options(&"hero".to_string()).unwrap().len(),
+            2
+        );
+        assert!(StringGenerator::generate_batch(&master, 4, 1)
+            .iter()
+            .all(|text| text.starts_with("The wizard")));
+    }
+
+    #[test]
+    pub fn options_can_be_added_removed_and_merged() {
+        let mut grammar = TraceryGrammar::new(&[("word", &["thee", "you"])], Some("word"));
+        grammar.add_option("word", "ye");
+        grammar.add_option("greeting", "hail");
+        assert_eq!(
+            grammar
+                .get_rule_options(&"greeting".to_string())
+                .unwrap()
+                .len(),
+            1
+        );
+
+        assert_eq!(
+            grammar.merge_options("word", 1, 0),
+            Some("thee".to_string())
+        );
+        assert_eq!(
+            grammar.get_rule_options(&"word".to_string()).unwrap(),
+            &vec!["you".to_string(), "ye".to_string()]
+        );
+        assert_eq!(grammar.rule_settings("word").unwrap().weights, vec![2, 1]);
+        assert_eq!(grammar.merge_options("word", 0, 0), None);
+
+        assert_eq!(grammar.remove_option("word", 1), Some("ye".to_string()));
+        assert_eq!(grammar.remove_option("word", 1), None);
+        assert_eq!(grammar.rule_settings("word").unwrap().weights, vec![2]);
+        assert_eq!(
+            StringGenerator::generate(&grammar, &mut SeededRng::new(1)),
+            Some("you".to_string())
+        );
+    }
+
+    #[test]
+    pub fn merged_weights_saturate_instead_of_overflowing() {
+        let mut grammar = TraceryGrammar::new(&[("word", &["thee", "you"])], Some("word"))
+            .with_weights("word", [u32::MAX, 5]);
+        assert_eq!(grammar.merge_options("word", 0, 1), Some("you".to_string()));
+        assert_eq!(
+            grammar.rule_settings("word").unwrap().weights,
+            vec![u32::MAX]
+        );
+    }
+}
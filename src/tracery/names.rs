@@ -0,0 +1,215 @@
+use super::{StringGenerator, TraceryGrammar};
+use crate::generator::{Generator, GrammarRandomNumberGenerator};
+
+/// This builds grammars for procedural names from lists of syllables and patterns - rather than writing out the rules by hand.
+/// Each character of a pattern that has syllables, like `C` for consonants or `V` for vowels, is replaced by one of them, and any
+/// other character is kept - so `CV-CVC` can generate `Ka-rum`. Names are capitalized. Constraints like the maximum length and
+/// banned substrings are applied by `generate`, which rerolls names that break them.
+#[derive(Debug, Clone)]
+pub struct NameGrammar {
+    syllables: Vec<(char, Vec<String>)>,
+    patterns: Vec<String>,
+    min_length: usize,
+    max_length: Option<usize>,
+    banned: Vec<String>,
+    max_attempts: usize,
+    grammar: TraceryGrammar,
+}
+
+impl Default for NameGrammar {
+    fn default() -> Self {
+        Self {
+            syllables: vec![],
+            patterns: vec![],
+            min_length: 0,
+            max_length: None,
+            banned: vec![],
+            max_attempts: 100,
+            grammar: TraceryGrammar::empty(),
+        }
+    }
+}
+
+impl NameGrammar {
+    /// The key of the rule names are generated from
+    pub const NAME_RULE: &'static str = "name";
+    /// The key of the rule holding the patterns, before they are capitalized
+    pub const PATTERN_RULE: &'static str = "name_pattern";
+
+    /// Creates a name grammar without any syllables or patterns
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A name grammar for fantasy names, like `Koram` or `Eli-vash`
+    pub fn fantasy() -> Self {
+        Self::new()
+            .with_syllables(
+                'C',
+                ["b", "d", "k", "l", "m", "n", "r", "s", "t", "v", "th", "sh"],
+            )
+            .with_syllables('V', ["a", "e", "i", "o", "u", "ae", "ia"])
+            .with_syllables('E', ["n", "r", "s", "th", "l", "sh", "m"])
+            .with_patterns(["CVE", "CVCV", "CVCVE", "VCVE", "CV-CVE"])
+            .with_min_length(3)
+            .with_max_length(9)
+    }
+
+    /// Sets the syllables a character stands for in patterns, replacing any it had before
+    pub fn with_syllables<T: Into<String>>(
+        mut self,
+        class: char,
+        syllables: impl IntoIterator<Item = T>,
+    ) -> Self {
+        let syllables = syllables.into_iter().map(Into::into).collect();
+        match self
+            .syllables
+            .iter_mut()
+            .find(|(existing, _)| *existing == class)
+        {
+            Some((_, existing)) => *existing = syllables,
+            None => self.syllables.push((class, syllables)),
+        }
+        self.rebuild();
+        self
+    }
+
+    /// Adds a pattern names can follow, like `CVC` or `CV-CV`
+    pub fn with_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.patterns.push(pattern.into());
+        self.rebuild();
+        self
+    }
+
+    /// Adds several patterns names can follow
+    pub fn with_patterns<T: Into<String>>(mut self, patterns: impl IntoIterator<Item = T>) -> Self {
+        self.patterns.extend(patterns.into_iter().map(Into::into));
+        self.rebuild();
+        self
+    }
+
+    /// Sets the minimum number of characters in a generated name
+    pub fn with_min_length(mut self, min_length: usize) -> Self {
+        self.min_length = min_length;
+        self
+    }
+
+    /// Sets the maximum number of characters in a generated name
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = Some(max_length);
+        self
+    }
+
+    /// Bans a substring from generated names - it's matched regardless of case, so `ass` also bans `Cassia`
+    pub fn with_banned(mut self, substring: impl Into<String>) -> Self {
+        self.banned.push(substring.into().to_lowercase());
+        self
+    }
+
+    /// Sets how many names `generate` tries before giving up. By default, this is 100.
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Gets the grammar names are generated from. Since constraints are only applied by `generate`, names generated from it directly
+    /// can break them - but it can be merged into other grammars, or saved as an asset.
+    pub fn grammar(&self) -> &TraceryGrammar {
+        &self.grammar
+    }
+
+    /// Checks whether a name meets the grammar's constraints
+    pub fn accepts(&self, name: &str) -> bool {
+        let length = name.chars().count();
+        let lowercase = name.to_lowercase();
+        length >= self.min_length
+            && self
+                .max_length
+                .is_none_or(|max_length| length <= max_length)
+            && !self.banned.iter().any(|banned| lowercase.contains(banned))
+    }
+
+    /// Generates a name meeting the grammar's constraints, or `None` if none was found within the maximum number of attempts
+    pub fn generate<R: GrammarRandomNumberGenerator>(&self, rng: &mut R) -> Option<String> {
+        (0..self.max_attempts)
+            .filter_map(|_| StringGenerator::generate(&self.grammar, rng))
+            .find(|name| self.accepts(name))
+    }
+
+    fn rebuild(&mut self) {
+        let pattern_options = self
+            .patterns
+            .iter()
+            .map(|pattern| {
+                pattern
+                    .chars()
+                    .map(|character| match self.has_class(character) {
+                        true => format!("#{character}#"),
+                        false => character.to_string(),
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        let name = format!("#{}.capitalize#", Self::PATTERN_RULE);
+        let mut grammar = TraceryGrammar::empty();
+        grammar.starting_point = Self::NAME_RULE.to_string();
+        let rules = [
+            (Self::NAME_RULE.to_string(), vec![name]),
+            (Self::PATTERN_RULE.to_string(), pattern_options),
+        ]
+        .into_iter()
+        .chain(
+            self.syllables
+                .iter()
+                .map(|(class, syllables)| (class.to_string(), syllables.clone())),
+        );
+        for (key, options) in rules {
+            grammar.keys.push(key.clone());
+            grammar.rules.insert(key, options);
+        }
+        self.grammar = grammar;
+    }
+
+    fn has_class(&self, character: char) -> bool {
+        self.syllables.iter().any(|(class, _)| *class == character)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{Grammar, SeededRng};
+
+    #[test]
+    pub fn names_follow_the_patterns() {
+        let names = NameGrammar::new()
+            .with_syllables('C', ["k", "r"])
+            .with_syllables('V', ["a", "o"])
+            .with_pattern("CV-CVC");
+        assert_eq!(names.generate(&mut 0).unwrap(), "Ka-kak");
+        assert_eq!(
+            names
+                .grammar()
+                .get_rule_options(&"name_pattern".to_string()),
+            Some(&vec!["#C##V#-#C##V##C#".to_string()])
+        );
+    }
+
+    #[test]
+    pub fn names_breaking_the_constraints_are_rerolled() {
+        let names = NameGrammar::fantasy().with_banned("ka");
+        let mut rng = SeededRng::new(3);
+        for _ in 0..50 {
+            let name = names.generate(&mut rng).unwrap();
+            assert!(names.accepts(&name), "{name}");
+            assert!((3..=9).contains(&name.chars().count()), "{name}");
+            assert!(!name.to_lowercase().contains("ka"), "{name}");
+        }
+
+        let impossible = NameGrammar::new()
+            .with_syllables('V', ["a"])
+            .with_pattern("VV")
+            .with_max_length(1);
+        assert_eq!(impossible.generate(&mut 0), None);
+    }
+}
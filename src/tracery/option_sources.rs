@@ -0,0 +1,291 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, RwLock},
+};
+
+#[cfg(feature = "bevy")]
+use bevy::{prelude::Resource, utils::HashMap};
+#[cfg(not(feature = "bevy"))]
+use std::collections::HashMap;
+
+use super::{GrammarError, TraceryGrammar};
+
+/// The directive sourcing a rule's options from a registered option source - `@source(saved_heroes)`
+pub const OPTION_SOURCE_DIRECTIVE: &str = "@source";
+
+/// Gets the name of the option source an option refers to, or `None` if the option isn't an option source reference
+pub fn option_source_name(option: &str) -> Option<&str> {
+    option
+        .trim()
+        .strip_prefix(OPTION_SOURCE_DIRECTIVE)?
+        .strip_prefix('(')?
+        .strip_suffix(')')
+        .map(str::trim)
+}
+
+/// The future returned by an option source's fetcher
+pub type OptionFetch = Pin<Box<dyn Future<Output = Option<Vec<String>>> + Send>>;
+
+type Fetcher = Arc<dyn Fn() -> OptionFetch + Send + Sync>;
+
+#[derive(Default)]
+struct Sources {
+    fetchers: HashMap<String, Fetcher>,
+    cache: HashMap<String, Vec<String>>,
+}
+
+/// This holds the option sources grammars can reference using `@source(name)`, such as names read from a save file or a remote config.
+/// Sources are fetched asynchronously once, while the grammar loads, and the options they provide replace the reference - so generation
+/// itself stays synchronous and deterministic. The last options each source provided are cached, and used whenever fetching fails.
+/// Clones share the same sources, and with the `bevy` feature it is a resource used by the grammar asset loaders.
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "bevy", derive(Resource))]
+pub struct OptionSources {
+    sources: Arc<RwLock<Sources>>,
+}
+
+impl std::fmt::Debug for OptionSources {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sources = self
+            .sources
+            .read()
+            .unwrap_or_else(|error| error.into_inner());
+        f.debug_struct("OptionSources")
+            .field("sources", &sources.fetchers.keys().collect::<Vec<_>>())
+            .field("cache", &sources.cache)
+            .finish()
+    }
+}
+
+impl OptionSources {
+    /// Creates an empty set of option sources
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the fetcher for an option source, replacing any previous one with the same name. The fetcher returns `None`
+    /// when the options aren't available, such as when there is no save file yet.
+    pub fn register<F, Fut>(&self, name: impl Into<String>, fetcher: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Option<Vec<String>>> + Send + 'static,
+    {
+        let fetcher: Fetcher = Arc::new(move || Box::pin(fetcher()) as OptionFetch);
+        self.write().fetchers.insert(name.into(), fetcher);
+    }
+
+    /// Removes an option source, keeping it's cached options
+    pub fn unregister(&self, name: &str) -> bool {
+        self.write().fetchers.remove(name).is_some()
+    }
+
+    /// Checks if an option source is registered
+    pub fn is_registered(&self, name: &str) -> bool {
+        self.read().fetchers.contains_key(name)
+    }
+
+    /// Gets the options cached for a source - the last ones it provided, or the ones set using `set_cached`
+    pub fn cached(&self, name: &str) -> Option<Vec<String>> {
+        self.read().cache.get(name).cloned()
+    }
+
+    /// Sets the options cached for a source, such as ones kept from a previous session
+    pub fn set_cached(&self, name: impl Into<String>, options: Vec<String>) {
+        self.write().cache.insert(name.into(), options);
+    }
+
+    /// Fetches the options of a source, caching them if it provides any. If it doesn't, or it isn't registered, the cached options are used.
+    pub async fn fetch(&self, name: &str) -> Option<Vec<String>> {
+        // The fetcher is cloned out, so the sources aren't locked while it runs
+        let fetcher = self.read().fetchers.get(name).cloned();
+        let fetched = match fetcher {
+            Some(fetcher) => fetcher().await,
+            None => None,
+        };
+        match fetched {
+            Some(options) => {
+                self.set_cached(name, options.clone());
+                Some(options)
+            }
+            None => self.cached(name),
+        }
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Sources> {
+        self.sources
+            .read()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, Sources> {
+        self.sources
+            .write()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+impl TraceryGrammar {
+    /// Gets the names of the option sources referenced by the grammar's options, like `@source(saved_heroes)`, sorted and without duplicates
+    pub fn option_source_names(&self) -> Vec<String> {
+        let mut names = self
+            .rules
+            .values()
+            .flatten()
+            .filter_map(|option| option_source_name(option))
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Resolves the rules referencing option sources, using the options `fetched` provides for each source.
+    /// When a source provides options, they replace all of the rule's options. Otherwise the rule's other options are kept as
+    /// it's fallback, and if it has none the source is reported as missing.
+    pub fn resolve_option_sources(
+        &mut self,
+        mut fetched: impl FnMut(&str) -> Option<Vec<String>>,
+    ) -> Result<(), GrammarError> {
+        let mut sources: HashMap<String, Option<Vec<String>>> = HashMap::default();
+        for name in self.option_source_names() {
+            let options = fetched(&name).filter(|options| !options.is_empty());
+            sources.insert(name, options);
+        }
+        for key in self.keys.iter() {
+            let Some(options) = self.rules.get_mut(key) else {
+                continue;
+            };
+            let Some(name) = options
+                .iter()
+                .find_map(|option| option_source_name(option))
+                .map(str::to_string)
+            else {
+                continue;
+            };
+            match sources.get(&name).cloned().flatten() {
+                Some(fetched) => {
+                    *options = fetched;
+                    if let Some(settings) = self.settings.get_mut(key) {
+                        settings.weights.clear();
+                    }
+                }
+                None => {
+                    let mut index = 0;
+                    let weights = self
+                        .settings
+                        .get_mut(key)
+                        .map(|settings| std::mem::take(&mut settings.weights))
+                        .unwrap_or_default();
+                    let mut kept_weights = vec![];
+                    options.retain(|option| {
+                        let keep = option_source_name(option).is_none();
+                        if keep {
+                            kept_weights.extend(weights.get(index).copied());
+                        }
+                        index += 1;
+                        keep
+                    });
+                    if options.is_empty() {
+                        return Err(GrammarError::MissingOptionSource(name));
+                    }
+                    if let Some(settings) = self.settings.get_mut(key) {
+                        settings.weights = kept_weights;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Fetches every option source the grammar references, and resolves them as `resolve_option_sources` does.
+    /// The grammar asset loaders call this as grammars load, using the `OptionSources` resource.
+    pub async fn load_option_sources(
+        &mut self,
+        sources: &OptionSources,
+    ) -> Result<(), GrammarError> {
+        let mut fetched: HashMap<String, Vec<String>> = HashMap::default();
+        for name in self.option_source_names() {
+            if let Some(options) = sources.fetch(&name).await {
+                fetched.insert(name, options);
+            }
+        }
+        self.resolve_option_sources(|name| fetched.remove(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::{Context, Poll, Waker};
+
+    use super::*;
+    use crate::generator::Grammar;
+
+    /// Runs a future that never waits, which is all the sources in these tests do
+    fn block_on<T>(future: impl Future<Output = T>) -> T {
+        let mut future = std::pin::pin!(future);
+        match future
+            .as_mut()
+            .poll(&mut Context::from_waker(Waker::noop()))
+        {
+            Poll::Ready(value) => value,
+            Poll::Pending => panic!("the future should be ready"),
+        }
+    }
+
+    fn heroes() -> TraceryGrammar {
+        TraceryGrammar::new(
+            &[
+                ("origin", &["#hero# meets #rival#"]),
+                ("hero", &["@source(saved_heroes)", "Ana", "Bo"]),
+                ("rival", &["@source(rivals)"]),
+            ],
+            None,
+        )
+        .with_weights("hero", [1, 3, 1])
+    }
+
+    #[test]
+    pub fn fetched_options_replace_the_rule() {
+        let sources = OptionSources::new();
+        sources.register("saved_heroes", || async {
+            Some(vec!["Cleo".to_string(), "Dov".to_string()])
+        });
+        sources.register("rivals", || async { Some(vec!["Esme".to_string()]) });
+        let mut grammar = heroes();
+        assert_eq!(
+            grammar.option_source_names(),
+            vec!["rivals".to_string(), "saved_heroes".to_string()]
+        );
+        block_on(grammar.load_option_sources(&sources)).unwrap();
+        assert_eq!(
+            grammar.get_rule_options(&"hero".to_string()).unwrap(),
+            &vec!["Cleo".to_string(), "Dov".to_string()]
+        );
+        assert!(grammar.rule_settings("hero").unwrap().weights.is_empty());
+        assert_eq!(sources.cached("rivals"), Some(vec!["Esme".to_string()]));
+    }
+
+    #[test]
+    pub fn failed_fetches_fall_back_on_the_cache_and_the_rule() {
+        let sources = OptionSources::new();
+        sources.register("saved_heroes", || async { None });
+        sources.set_cached("rivals", vec!["Fen".to_string()]);
+        let mut grammar = heroes();
+        block_on(grammar.load_option_sources(&sources)).unwrap();
+        assert_eq!(
+            grammar.get_rule_options(&"hero".to_string()).unwrap(),
+            &vec!["Ana".to_string(), "Bo".to_string()]
+        );
+        assert_eq!(grammar.rule_settings("hero").unwrap().weights, vec![3, 1]);
+        assert_eq!(
+            grammar.get_rule_options(&"rival".to_string()).unwrap(),
+            &vec!["Fen".to_string()]
+        );
+
+        assert_eq!(
+            block_on(heroes().load_option_sources(&OptionSources::new())),
+            Err(GrammarError::MissingOptionSource("rivals".to_string()))
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use bevy::{prelude::*, utils::HashSet};
+
+use super::{
+    packs::{GrammarPack, GrammarPackLoader, PackError},
+    StatefulStringGenerator, TraceryGrammar,
+};
+
+/// This holds the grammar packs available to the game, and which of them are enabled - so seasonal or DLC content can be switched
+/// on and off while the game is running. Packs are enabled when they are added, and a pack is only used while the packs it depends
+/// on are enabled as well. Generators marked with `UsesGrammarPacks` have their grammar replaced with the enabled packs whenever
+/// they change. It requires the `GrammarPacksPlugin`.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct GrammarPacks {
+    loader: GrammarPackLoader,
+    disabled: HashSet<String>,
+    toggled: Vec<PackToggled>,
+}
+
+/// This event is sent whenever a pack in `GrammarPacks` is enabled or disabled
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct PackToggled {
+    /// The pack's name
+    pub pack: String,
+    /// Whether the pack is now enabled
+    pub enabled: bool,
+}
+
+/// This marks a `StatefulStringGenerator` whose grammar is made of the enabled packs in `GrammarPacks`. The grammar is replaced
+/// whenever a pack is toggled, keeping the generator's variables - so the story so far carries over.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct UsesGrammarPacks;
+
+impl From<GrammarPackLoader> for GrammarPacks {
+    fn from(loader: GrammarPackLoader) -> Self {
+        Self {
+            loader,
+            ..Default::default()
+        }
+    }
+}
+
+impl GrammarPacks {
+    /// Creates a set of packs, with every pack enabled
+    pub fn new(loader: GrammarPackLoader) -> Self {
+        loader.into()
+    }
+
+    /// Adds a pack, enabled - failing if a pack with the same name was already added
+    pub fn add_pack(&mut self, pack: GrammarPack) -> Result<(), PackError> {
+        self.loader.add_pack(pack)
+    }
+
+    /// Gets the loader holding every pack, enabled or not
+    pub fn loader(&self) -> &GrammarPackLoader {
+        &self.loader
+    }
+
+    /// Enables a pack. See `set_enabled`.
+    pub fn enable(&mut self, pack: &str) -> bool {
+        self.set_enabled(pack, true)
+    }
+
+    /// Disables a pack. See `set_enabled`.
+    pub fn disable(&mut self, pack: &str) -> bool {
+        self.set_enabled(pack, false)
+    }
+
+    /// Switches a pack on if it is disabled, or off if it is enabled - returning whether it is now enabled
+    pub fn toggle(&mut self, pack: &str) -> bool {
+        let enabled = !self.is_enabled(pack);
+        self.set_enabled(pack, enabled);
+        enabled
+    }
+
+    /// Enables or disables a pack, returning whether anything changed. Packs that weren't added are left alone.
+    pub fn set_enabled(&mut self, pack: &str, enabled: bool) -> bool {
+        if self.loader.pack(pack).is_none() || self.is_enabled(pack) == enabled {
+            return false;
+        }
+        match enabled {
+            true => self.disabled.remove(pack),
+            false => self.disabled.insert(pack.to_string()),
+        };
+        self.toggled.push(PackToggled {
+            pack: pack.to_string(),
+            enabled,
+        });
+        true
+    }
+
+    /// Checks whether a pack was added and is enabled
+    pub fn is_enabled(&self, pack: &str) -> bool {
+        self.loader.pack(pack).is_some() && !self.disabled.contains(pack)
+    }
+
+    /// Checks whether a pack is used - that it is enabled, along with every pack it depends on
+    pub fn is_active(&self, pack: &str) -> bool {
+        self.is_active_within(pack, &mut vec![])
+    }
+
+    fn is_active_within(&self, pack: &str, visited: &mut Vec<String>) -> bool {
+        if visited.iter().any(|visited| visited == pack) {
+            return true;
+        }
+        let Some(manifest) = self.loader.pack(pack).map(|pack| &pack.manifest) else {
+            return false;
+        };
+        visited.push(pack.to_string());
+        !self.disabled.contains(pack)
+            && manifest
+                .dependencies
+                .iter()
+                .all(|dependency| self.is_active_within(dependency, visited))
+    }
+
+    /// Merges the active packs into a single grammar, in dependency order
+    pub fn grammar(&self) -> Result<TraceryGrammar, PackError> {
+        self.loader.merge_where(|pack| self.is_active(pack.name()))
+    }
+}
+
+/// This plugin sends a `PackToggled` event for each pack switched on or off in `GrammarPacks`, and replaces the grammars of
+/// generators marked with `UsesGrammarPacks` - whenever the packs change, or a marked generator is added.
+#[derive(Default)]
+pub struct GrammarPacksPlugin;
+
+impl Plugin for GrammarPacksPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GrammarPacks>()
+            .add_event::<PackToggled>()
+            .add_systems(PreUpdate, apply_grammar_packs);
+    }
+}
+
+fn apply_grammar_packs(
+    mut packs: ResMut<GrammarPacks>,
+    mut toggles: EventWriter<PackToggled>,
+    mut generators: Query<(Ref<UsesGrammarPacks>, &mut StatefulStringGenerator)>,
+) {
+    let changed = packs.is_changed();
+    let mut generators = generators
+        .iter_mut()
+        .filter(|(marker, generator)| changed || marker.is_added() || generator.is_added())
+        .map(|(_, generator)| generator)
+        .peekable();
+    if generators.peek().is_none() && !changed {
+        return;
+    }
+    let toggled = std::mem::take(&mut packs.bypass_change_detection().toggled);
+    toggles.send_batch(toggled);
+    let grammar = match packs.grammar() {
+        Ok(grammar) => grammar,
+        Err(error) => {
+            error!("Couldn't merge the enabled grammar packs: {error}");
+            return;
+        }
+    };
+    for mut generator in generators {
+        generator.grammar = grammar.clone();
+        generator.runtime.selection.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generator::StatefulGenerator,
+        tracery::packs::{PackGrammar, PackManifest},
+    };
+
+    fn pack(name: &str, dependencies: &[&str], rules: &[(&str, &[&str])]) -> GrammarPack {
+        GrammarPack::new(PackManifest {
+            name: name.to_string(),
+            dependencies: dependencies.iter().map(|name| name.to_string()).collect(),
+            grammars: vec![PackGrammar {
+                path: "grammar.json".to_string(),
+                namespace: None,
+            }],
+            ..Default::default()
+        })
+        .with_grammar("grammar.json", TraceryGrammar::new(rules, None))
+    }
+
+    #[test]
+    pub fn generators_only_use_the_enabled_packs() {
+        let mut app = App::new();
+        app.add_plugins(GrammarPacksPlugin);
+        let loader = GrammarPackLoader::new()
+            .with_pack(pack(
+                "base",
+                &[],
+                &[("origin", &["You find #item#"]), ("item", &["a sword"])],
+            ))
+            .unwrap()
+            .with_pack(pack("winter", &["base"], &[("item", &["a snow globe"])]))
+            .unwrap();
+        app.insert_resource(GrammarPacks::new(loader));
+        let entity = app
+            .world
+            .spawn((
+                StatefulStringGenerator::new::<&str>(&[], None),
+                UsesGrammarPacks,
+            ))
+            .id();
+        app.update();
+        let generate = |app: &mut App| {
+            app.world
+                .get_mut::<StatefulStringGenerator>(entity)
+                .unwrap()
+                .generate(&mut 0)
+                .unwrap()
+        };
+        assert_eq!(generate(&mut app), "You find a snow globe");
+
+        assert!(!app.world.resource_mut::<GrammarPacks>().toggle("winter"));
+        app.update();
+        assert_eq!(generate(&mut app), "You find a sword");
+        let events = app.world.resource::<Events<PackToggled>>();
+        assert_eq!(
+            events.get_reader().read(events).collect::<Vec<_>>(),
+            vec![&PackToggled {
+                pack: "winter".to_string(),
+                enabled: false
+            }]
+        );
+
+        // Packs are only used while the packs they depend on are enabled
+        let mut packs = app.world.resource_mut::<GrammarPacks>();
+        packs.enable("winter");
+        packs.disable("base");
+        assert!(packs.is_enabled("winter"));
+        assert!(!packs.is_active("winter"));
+        app.update();
+        assert_eq!(
+            app.world
+                .get_mut::<StatefulStringGenerator>(entity)
+                .unwrap()
+                .try_generate(&mut 0),
+            Err(crate::tracery::GenerationIssue::UnknownRule(
+                "origin".to_string()
+            ))
+        );
+    }
+}
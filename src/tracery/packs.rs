@@ -0,0 +1,553 @@
+use std::{
+    fmt::Display,
+    path::{Component, Path},
+};
+
+#[cfg(feature = "bevy")]
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+#[cfg(not(feature = "bevy"))]
+use std::collections::HashMap;
+
+use super::TraceryGrammar;
+use crate::generator::Grammar;
+
+/// The file name a pack's manifest is written in, at the root of the pack's directory
+pub const MANIFEST_FILE: &str = "grammar.toml";
+
+/// This is the manifest of a grammar pack - usually written as a `grammar.toml` at the root of the pack - declaring the grammars
+/// it contains, and the other packs it depends on:
+///
+/// ```toml
+/// name = "haunted-tavern"
+/// version = "1.0.0"
+/// dependencies = ["base-tavern"]
+///
+/// [[grammars]]
+/// path = "ghosts.json"
+/// namespace = "ghosts"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PackManifest {
+    /// The pack's name, which other packs use to depend on it
+    pub name: String,
+    /// The pack's version. It is informational, and isn't checked when resolving dependencies.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub version: String,
+    /// The grammars the pack contains, merged in the order they are listed
+    pub grammars: Vec<PackGrammar>,
+    /// The names of the packs this pack depends on, which are merged before it
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>,
+}
+
+/// This is a grammar within a pack
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PackGrammar {
+    /// The grammar's path, relative to the pack's directory
+    pub path: String,
+    /// The namespace the grammar's rules are placed in, if any. A rule `hero` in the namespace `ghosts` is merged as `ghosts/hero`,
+    /// and the grammar's own references to it are rewritten to match - other packs can reference it as `#ghosts/hero#`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+}
+
+/// This is a problem found while loading, resolving or merging grammar packs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PackError {
+    /// A manifest couldn't be parsed - along with the reason
+    InvalidManifest(String),
+    /// A file couldn't be read - along with it's path and the reason
+    ReadFailed(String, String),
+    /// A grammar couldn't be parsed - along with it's path and the reason
+    InvalidGrammar(String, String),
+    /// A grammar's path leaves the pack's directory - by being absolute, or going through `..`
+    OutsidePack(String),
+    /// More than one pack has the same name
+    DuplicatePack(String),
+    /// A pack depends on a pack that wasn't added - holding the pack, and the missing dependency
+    MissingDependency(String, String),
+    /// Packs depend on each other in a cycle - holding the packs in the cycle, starting and ending with the same pack
+    DependencyCycle(Vec<String>),
+    /// A grammar listed in a pack's manifest wasn't added to the pack - holding the pack, and the grammar's path
+    MissingGrammar(String, String),
+}
+
+impl Display for PackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PackError::InvalidManifest(reason) => write!(f, "the manifest is invalid: {reason}"),
+            PackError::ReadFailed(path, reason) => write!(f, "couldn't read `{path}`: {reason}"),
+            PackError::InvalidGrammar(path, reason) => {
+                write!(f, "the grammar `{path}` is invalid: {reason}")
+            }
+            PackError::OutsidePack(path) => {
+                write!(f, "the grammar `{path}` isn't within the pack's directory")
+            }
+            PackError::DuplicatePack(pack) => write!(f, "the pack `{pack}` was added twice"),
+            PackError::MissingDependency(pack, dependency) => {
+                write!(
+                    f,
+                    "the pack `{pack}` depends on `{dependency}`, which is missing"
+                )
+            }
+            PackError::DependencyCycle(cycle) => {
+                write!(f, "the packs depend on each other: {}", cycle.join(" -> "))
+            }
+            PackError::MissingGrammar(pack, path) => {
+                write!(f, "the pack `{pack}` is missing it's grammar `{path}`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PackError {}
+
+impl PackManifest {
+    /// Parses a manifest written in TOML
+    pub fn from_toml(content: &str) -> Result<Self, PackError> {
+        toml::from_str(content).map_err(|error| PackError::InvalidManifest(error.to_string()))
+    }
+}
+
+/// This is a grammar pack - it's manifest, along with the grammars it lists
+#[derive(Debug, Clone, Default)]
+pub struct GrammarPack {
+    /// The pack's manifest
+    pub manifest: PackManifest,
+    grammars: HashMap<String, TraceryGrammar>,
+}
+
+impl GrammarPack {
+    /// Creates a pack from it's manifest. The grammars it lists are added using `with_grammar`.
+    pub fn new(manifest: PackManifest) -> Self {
+        Self {
+            manifest,
+            grammars: HashMap::default(),
+        }
+    }
+
+    /// Adds one of the grammars the manifest lists, by it's path - such as a grammar loaded as an asset
+    pub fn with_grammar(mut self, path: impl Into<String>, grammar: TraceryGrammar) -> Self {
+        self.add_grammar(path, grammar);
+        self
+    }
+
+    /// Adds one of the grammars the manifest lists, by it's path - replacing any grammar previously added for that path
+    pub fn add_grammar(&mut self, path: impl Into<String>, grammar: TraceryGrammar) {
+        self.grammars.insert(path.into(), grammar);
+    }
+
+    /// Gets the pack's name
+    pub fn name(&self) -> &str {
+        &self.manifest.name
+    }
+
+    /// Gets one of the pack's grammars by it's path, if it was added
+    pub fn grammar(&self, path: &str) -> Option<&TraceryGrammar> {
+        self.grammars.get(path)
+    }
+
+    /// Loads a pack from a directory, reading it's `grammar.toml` and each grammar it lists. Grammars are read as JSON, or - with
+    /// the `ron_source` feature - as RON, using their extension. Either can hold a `TraceryGrammar` or a `GrammarDefinition`.
+    /// Grammars have to be within the pack's directory, so their paths can't be absolute or go through `..`.
+    pub fn load(directory: impl AsRef<Path>) -> Result<Self, PackError> {
+        let directory = directory.as_ref();
+        let manifest = PackManifest::from_toml(&read(&directory.join(MANIFEST_FILE))?)?;
+        let mut pack = GrammarPack::new(manifest);
+        for entry in pack.manifest.grammars.clone() {
+            let within_pack = Path::new(&entry.path)
+                .components()
+                .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+            if !within_pack {
+                return Err(PackError::OutsidePack(entry.path));
+            }
+            let path = directory.join(&entry.path);
+            let grammar = parse_grammar(&path, &read(&path)?)
+                .map_err(|reason| PackError::InvalidGrammar(entry.path.clone(), reason))?;
+            pack.add_grammar(entry.path, grammar);
+        }
+        Ok(pack)
+    }
+
+    /// Merges the pack's grammars into a grammar, in the order the manifest lists them
+    fn merge_into(&self, merged: &mut TraceryGrammar) -> Result<(), PackError> {
+        for entry in self.manifest.grammars.iter() {
+            let Some(grammar) = self.grammars.get(&entry.path) else {
+                return Err(PackError::MissingGrammar(
+                    self.manifest.name.clone(),
+                    entry.path.clone(),
+                ));
+            };
+            match entry.namespace.as_deref() {
+                Some(namespace) => merge_grammar(merged, &namespaced(grammar, namespace)),
+                None => {
+                    merge_grammar(merged, grammar);
+                    merged.starting_point.clone_from(&grammar.starting_point);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read(path: &Path) -> Result<String, PackError> {
+    std::fs::read_to_string(path)
+        .map_err(|error| PackError::ReadFailed(path.display().to_string(), error.to_string()))
+}
+
+fn parse_grammar(path: &Path, content: &str) -> Result<TraceryGrammar, String> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        #[cfg(feature = "ron_source")]
+        Some("ron") => {
+            TraceryGrammar::from_ron_file_str(content).map_err(|error| error.to_string())
+        }
+        _ => TraceryGrammar::from_json_file_str(content).map_err(|error| error.to_string()),
+    }
+}
+
+/// Places every rule of a grammar in a namespace, rewriting the grammar's references to them
+fn namespaced(grammar: &TraceryGrammar, namespace: &str) -> TraceryGrammar {
+    let mut grammar = grammar.clone();
+    let mut rules = grammar.rules.keys().cloned().collect::<Vec<_>>();
+    rules.sort();
+    for rule in rules {
+        let _ = grammar.rename_rule(&rule, format!("{namespace}/{rule}"));
+    }
+    grammar
+}
+
+/// Merges a grammar's rules, settings, aliases and exclusions into another, replacing any rules that share their key
+fn merge_grammar(merged: &mut TraceryGrammar, grammar: &TraceryGrammar) {
+    for (rule, options) in grammar.rules.iter() {
+        merged.set_additional_rules(rule.clone(), options);
+        match grammar.settings.get(rule) {
+            Some(settings) => merged.settings.insert(rule.clone(), settings.clone()),
+            None => merged.settings.remove(rule),
+        };
+    }
+    merged.aliases.extend(
+        grammar
+            .aliases
+            .iter()
+            .map(|(alias, rule)| (alias.clone(), rule.clone())),
+    );
+    merged.exclusions.extend(grammar.exclusions.iter().cloned());
+}
+
+/// This resolves grammar packs against each other, and merges them into a single grammar in dependency order - so a pack's
+/// rules replace those of the packs it depends on, letting mods extend and override the base content.
+#[derive(Debug, Clone, Default)]
+pub struct GrammarPackLoader {
+    packs: Vec<GrammarPack>,
+}
+
+impl GrammarPackLoader {
+    /// Creates a loader without any packs
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a pack, returning the updated loader. See `add_pack`.
+    pub fn with_pack(mut self, pack: GrammarPack) -> Result<Self, PackError> {
+        self.add_pack(pack)?;
+        Ok(self)
+    }
+
+    /// Adds a pack, failing if a pack with the same name was already added
+    pub fn add_pack(&mut self, pack: GrammarPack) -> Result<(), PackError> {
+        if self.pack(pack.name()).is_some() {
+            return Err(PackError::DuplicatePack(pack.name().to_string()));
+        }
+        self.packs.push(pack);
+        Ok(())
+    }
+
+    /// Gets a pack by it's name
+    pub fn pack(&self, name: &str) -> Option<&GrammarPack> {
+        self.packs.iter().find(|pack| pack.name() == name)
+    }
+
+    /// Lists the packs, in the order they were added
+    pub fn packs(&self) -> &[GrammarPack] {
+        &self.packs
+    }
+
+    /// Orders the packs so each comes after the packs it depends on. Otherwise, packs keep the order they were added in.
+    pub fn resolve(&self) -> Result<Vec<&GrammarPack>, PackError> {
+        self.resolve_where(|_| true)
+    }
+
+    /// Merges the packs into a single grammar, in dependency order. Rules with the same key are replaced by the later pack,
+    /// and the grammar starts from the starting point of the last grammar merged without a namespace.
+    pub fn merge(&self) -> Result<TraceryGrammar, PackError> {
+        self.merge_where(|_| true)
+    }
+
+    /// Resolves the packs that are included, failing if an included pack depends on one that isn't
+    pub(crate) fn resolve_where(
+        &self,
+        included: impl Fn(&GrammarPack) -> bool,
+    ) -> Result<Vec<&GrammarPack>, PackError> {
+        let mut resolved: Vec<&GrammarPack> = vec![];
+        for pack in self.packs.iter().filter(|pack| included(pack)) {
+            self.visit(pack, &included, &mut vec![], &mut resolved)?;
+        }
+        Ok(resolved)
+    }
+
+    /// Merges the packs that are included into a single grammar, in dependency order
+    pub(crate) fn merge_where(
+        &self,
+        included: impl Fn(&GrammarPack) -> bool,
+    ) -> Result<TraceryGrammar, PackError> {
+        let mut merged = TraceryGrammar::empty();
+        merged.starting_point = "origin".to_string();
+        for pack in self.resolve_where(included)? {
+            pack.merge_into(&mut merged)?;
+        }
+        Ok(merged)
+    }
+
+    fn visit<'a>(
+        &'a self,
+        pack: &'a GrammarPack,
+        included: &impl Fn(&GrammarPack) -> bool,
+        path: &mut Vec<String>,
+        resolved: &mut Vec<&'a GrammarPack>,
+    ) -> Result<(), PackError> {
+        if resolved.iter().any(|done| done.name() == pack.name()) {
+            return Ok(());
+        }
+        if let Some(start) = path.iter().position(|name| name == pack.name()) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(pack.name().to_string());
+            return Err(PackError::DependencyCycle(cycle));
+        }
+        path.push(pack.name().to_string());
+        for dependency in pack.manifest.dependencies.iter() {
+            let Some(dependency_pack) = self.pack(dependency).filter(|pack| included(pack)) else {
+                return Err(PackError::MissingDependency(
+                    pack.name().to_string(),
+                    dependency.clone(),
+                ));
+            };
+            self.visit(dependency_pack, included, path, resolved)?;
+        }
+        path.pop();
+        resolved.push(pack);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracery::StringGenerator;
+
+    fn manifest(
+        name: &str,
+        dependencies: &[&str],
+        grammars: &[(&str, Option<&str>)],
+    ) -> PackManifest {
+        PackManifest {
+            name: name.to_string(),
+            dependencies: dependencies.iter().map(|name| name.to_string()).collect(),
+            grammars: grammars
+                .iter()
+                .map(|(path, namespace)| PackGrammar {
+                    path: path.to_string(),
+                    namespace: namespace.map(str::to_string),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    pub fn manifests_are_parsed_from_toml() {
+        let manifest = PackManifest::from_toml(
+            "name = \"haunted-tavern\"\nversion = \"1.0.0\"\ndependencies = [\"base-tavern\"]\n\n[[grammars]]\npath = \"ghosts.json\"\nnamespace = \"ghosts\"\n\n[[grammars]]\npath = \"patrons.json\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            manifest,
+            PackManifest {
+                version: "1.0.0".to_string(),
+                ..self::manifest(
+                    "haunted-tavern",
+                    &["base-tavern"],
+                    &[("ghosts.json", Some("ghosts")), ("patrons.json", None)]
+                )
+            }
+        );
+        assert!(matches!(
+            PackManifest::from_toml("name = ["),
+            Err(PackError::InvalidManifest(_))
+        ));
+    }
+
+    #[test]
+    pub fn packs_are_merged_in_dependency_order() {
+        let base = GrammarPack::new(manifest("base", &[], &[("base.json", None)])).with_grammar(
+            "base.json",
+            TraceryGrammar::new(
+                &[
+                    ("origin", &["#patron# walks in"]),
+                    ("patron", &["A farmer"]),
+                ],
+                None,
+            ),
+        );
+        let haunted = GrammarPack::new(manifest(
+            "haunted",
+            &["base"],
+            &[("patrons.json", None), ("ghosts.json", Some("ghosts"))],
+        ))
+        .with_grammar(
+            "patrons.json",
+            TraceryGrammar::new(&[("patron", &["#ghosts/origin#"])], None),
+        )
+        .with_grammar(
+            "ghosts.json",
+            TraceryGrammar::new(
+                &[("origin", &["A #ghost#"]), ("ghost", &["wailing spectre"])],
+                None,
+            ),
+        );
+        let loader = GrammarPackLoader::new()
+            .with_pack(haunted)
+            .unwrap()
+            .with_pack(base)
+            .unwrap();
+        let order = loader
+            .resolve()
+            .unwrap()
+            .into_iter()
+            .map(GrammarPack::name)
+            .collect::<Vec<_>>();
+        assert_eq!(order, vec!["base", "haunted"]);
+        let grammar = loader.merge().unwrap();
+        assert_eq!(
+            StringGenerator::try_generate(&grammar, &mut 0).unwrap(),
+            "A wailing spectre walks in"
+        );
+    }
+
+    /// Writes a pack's files to a fresh directory, returning it's path
+    fn write_pack(name: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+        let directory = std::env::temp_dir().join(format!(
+            "generative-grammars-pack-{name}-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        for (path, content) in files {
+            std::fs::write(directory.join(path), content).unwrap();
+        }
+        directory
+    }
+
+    #[test]
+    pub fn packs_load_grammars_and_definitions() {
+        let mut files = vec![
+            (
+                MANIFEST_FILE,
+                "name = \"tavern\"\n\n[[grammars]]\npath = \"patrons.json\"\n\n[[grammars]]\npath = \"drinks.json\"\n",
+            ),
+            ("patrons.json", r##"{"origin": "#patron# orders #drink#", "patron": "A farmer"}"##),
+            (
+                "drinks.json",
+                r##"{"starting_point": "origin", "rules": {"drink": {"options": ["ale"], "selection": "Cycle"}}}"##,
+            ),
+        ];
+        let directory = write_pack("json", &files);
+        let pack = GrammarPack::load(&directory).unwrap();
+        std::fs::remove_dir_all(directory).unwrap();
+        assert_eq!(
+            pack.grammar("drinks.json").unwrap().rule_keys(),
+            &vec!["drink".to_string()]
+        );
+        let grammar = GrammarPackLoader::new()
+            .with_pack(pack)
+            .unwrap()
+            .merge()
+            .unwrap();
+        assert_eq!(
+            StringGenerator::try_generate(&grammar, &mut 0).unwrap(),
+            "A farmer orders ale"
+        );
+
+        #[cfg(feature = "ron_source")]
+        {
+            files[0].1 = "name = \"tavern\"\n\n[[grammars]]\npath = \"drinks.ron\"\n";
+            files[2] = (
+                "drinks.ron",
+                r##"(rules: {"origin": (options: ["ale"]), "drink": (options: ["mead"])})"##,
+            );
+            let directory = write_pack("ron", &files);
+            let pack = GrammarPack::load(&directory).unwrap();
+            std::fs::remove_dir_all(directory).unwrap();
+            assert_eq!(pack.grammar("drinks.ron").unwrap().rule_keys().len(), 2);
+        }
+    }
+
+    #[test]
+    pub fn grammars_outside_the_pack_are_rejected() {
+        for path in [
+            "../secrets.json",
+            "/etc/secrets.json",
+            "nested/../../secrets.json",
+        ] {
+            let manifest = format!("name = \"sneaky\"\n\n[[grammars]]\npath = \"{path}\"\n");
+            let directory = write_pack("outside", &[(MANIFEST_FILE, &manifest)]);
+            let result = GrammarPack::load(&directory);
+            std::fs::remove_dir_all(directory).unwrap();
+            assert_eq!(result.err(), Some(PackError::OutsidePack(path.to_string())));
+        }
+    }
+
+    #[test]
+    pub fn missing_and_cyclic_dependencies_are_reported() {
+        let loader = GrammarPackLoader::new()
+            .with_pack(GrammarPack::new(manifest("a", &["b"], &[])))
+            .unwrap()
+            .with_pack(GrammarPack::new(manifest("b", &["c"], &[])))
+            .unwrap();
+        assert_eq!(
+            loader.resolve().err(),
+            Some(PackError::MissingDependency(
+                "b".to_string(),
+                "c".to_string()
+            ))
+        );
+        let loader = loader
+            .with_pack(GrammarPack::new(manifest("c", &["a"], &[])))
+            .unwrap();
+        assert_eq!(
+            loader.resolve().err(),
+            Some(PackError::DependencyCycle(
+                ["a", "b", "c", "a"].map(str::to_string).to_vec()
+            ))
+        );
+        assert_eq!(
+            loader
+                .clone()
+                .with_pack(GrammarPack::new(manifest("a", &[], &[])))
+                .err(),
+            Some(PackError::DuplicatePack("a".to_string()))
+        );
+        let loader = GrammarPackLoader::new()
+            .with_pack(GrammarPack::new(manifest("a", &[], &[("a.json", None)])))
+            .unwrap();
+        assert_eq!(
+            loader.merge().err(),
+            Some(PackError::MissingGrammar(
+                "a".to_string(),
+                "a.json".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use super::validation::visit_tags;
+use crate::generator::Replacable;
+
+/// The prefix of the keys positional arguments are bound to - the first argument is `#$1#`, the second `#$2#`, and so on
+pub const POSITIONAL_PREFIX: char = '$';
+
+/// This is a reference to a rule that passes it arguments - like `describe(hero, mood='grim')`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RuleCall<'a> {
+    /// The rule's key
+    pub(crate) rule: &'a str,
+    /// The modifiers applied to the rule's expansion, if any - without their leading `.`
+    pub(crate) modifiers: Option<&'a str>,
+    /// The arguments, in the order they were written
+    pub(crate) arguments: Vec<Argument<'a>>,
+}
+
+/// This is an argument passed into a rule, along with it's name if it was passed by name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Argument<'a> {
+    pub(crate) name: Option<&'a str>,
+    pub(crate) value: ArgumentValue<'a>,
+}
+
+/// This is the value of an argument - a key expanded at the call site, or text written in single quotes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ArgumentValue<'a> {
+    Key(&'a str),
+    Text(&'a str),
+}
+
+impl<'a> RuleCall<'a> {
+    /// Parses a reference that passes arguments to it's rule, like `describe(hero).capitalize`.
+    /// Returns `None` if the reference doesn't pass any arguments.
+    pub(crate) fn parse(key: &'a str) -> Option<Self> {
+        let open = key.find('(')?;
+        let rule = &key[..open];
+        if rule.is_empty() || rule.contains(['.', '#', '[', ' ']) {
+            return None;
+        }
+        let inner = &key[open + 1..];
+        let close = outside_quotes(inner)
+            .find(|(_, character)| *character == ')')?
+            .0;
+        let modifiers = match &inner[close + 1..] {
+            "" => None,
+            rest => Some(rest.strip_prefix('.')?),
+        };
+        let mut arguments = vec![];
+        let mut start = 0;
+        let splits = outside_quotes(&inner[..close])
+            .filter(|(_, character)| *character == ',')
+            .map(|(index, _)| index)
+            .chain([close]);
+        for end in splits {
+            let argument = inner[start..end].trim();
+            start = end + 1;
+            if argument.is_empty() {
+                continue;
+            }
+            let (name, value) = match argument.split_once('=') {
+                Some((name, value)) if !name.contains('\'') => (Some(name.trim()), value.trim()),
+                _ => (None, argument),
+            };
+            let value = match value
+                .strip_prefix('\'')
+                .and_then(|value| value.strip_suffix('\''))
+            {
+                Some(text) => ArgumentValue::Text(text),
+                None => ArgumentValue::Key(value),
+            };
+            arguments.push(Argument { name, value });
+        }
+        Some(Self {
+            rule,
+            modifiers,
+            arguments,
+        })
+    }
+
+    /// The key the rule is expanded with, including it's modifiers
+    pub(crate) fn rule_key(&self) -> String {
+        match self.modifiers {
+            Some(modifiers) => format!("{}.{modifiers}", self.rule),
+            None => self.rule.to_string(),
+        }
+    }
+
+    /// The actions binding each argument while the rule is expanded - positional arguments are bound to `$1`, `$2` and so on,
+    /// and named arguments to their name
+    pub(crate) fn bindings(&self) -> Vec<Replacable<String, String>> {
+        let mut position = 0;
+        self.arguments
+            .iter()
+            .map(|argument| {
+                let key = match argument.name {
+                    Some(name) => name.to_string(),
+                    None => {
+                        position += 1;
+                        format!("{POSITIONAL_PREFIX}{position}")
+                    }
+                };
+                let value = match argument.value {
+                    ArgumentValue::Key(key) => format!("#{key}#"),
+                    ArgumentValue::Text(text) => text.to_string(),
+                };
+                Replacable::ImmediateMeta(key, value)
+            })
+            .collect()
+    }
+}
+
+/// Lists the names of the arguments passed by name within an option, which are bound like variables
+pub(crate) fn named_arguments(option: &str) -> Vec<String> {
+    let mut names = vec![];
+    visit_tags(option, &mut |tag| {
+        if let Some(call) = RuleCall::parse(tag) {
+            names.extend(
+                call.arguments
+                    .into_iter()
+                    .filter_map(|argument| argument.name.map(str::to_string)),
+            );
+        }
+    });
+    names
+}
+
+/// Lists the characters of some text that aren't within single quotes, along with their index
+fn outside_quotes(text: &str) -> impl Iterator<Item = (usize, char)> + '_ {
+    let mut quoted = false;
+    text.char_indices().filter(move |(_, character)| {
+        if *character == '\'' {
+            quoted = !quoted;
+        }
+        !quoted && *character != '\''
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracery::{GrammarValidator, StringGenerator, TraceryGrammar};
+
+    #[test]
+    pub fn calls_are_parsed_with_their_arguments() {
+        let call = RuleCall::parse("describe(hero, mood='grim, (very)').capitalize").unwrap();
+        assert_eq!(call.rule_key(), "describe.capitalize");
+        assert_eq!(
+            call.arguments,
+            vec![
+                Argument {
+                    name: None,
+                    value: ArgumentValue::Key("hero")
+                },
+                Argument {
+                    name: Some("mood"),
+                    value: ArgumentValue::Text("grim, (very)")
+                }
+            ]
+        );
+        assert_eq!(RuleCall::parse("describe()").unwrap().arguments, vec![]);
+        assert_eq!(RuleCall::parse("describe"), None);
+        assert_eq!(RuleCall::parse("describe(hero"), None);
+    }
+
+    #[test]
+    pub fn arguments_are_bound_while_the_rule_is_expanded() {
+        let grammar = TraceryGrammar::new(
+            &[
+                (
+                    "origin",
+                    &["#describe(hero)# meets #describe(villain, mood='grim').capitalize#, and #mood# #$1# again"],
+                ),
+                ("describe", &["#$1#, looking #mood#"]),
+                ("mood", &["calm"]),
+                ("hero", &["the knight"]),
+                ("villain", &["the witch"]),
+            ],
+            None,
+        );
+        assert_eq!(
+            StringGenerator::try_generate(&grammar, &mut 0).unwrap(),
+            "the knight, looking calm meets The witch, looking grim, and calm #$1# again"
+        );
+        // Arguments, and the rules they are passed to, aren't reported as undefined
+        assert_eq!(GrammarValidator::new().validate(&grammar), vec![]);
+    }
+}
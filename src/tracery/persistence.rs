@@ -0,0 +1,222 @@
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::StatefulStringGenerator;
+
+/// The version of the save format written by this version of the crate. Saves from older versions are migrated when loaded,
+/// while saves from newer versions are rejected.
+pub const GENERATOR_SAVE_VERSION: u32 = 2;
+
+/// This is an error that occured while saving or loading a generator
+#[derive(Debug)]
+pub enum PersistenceError {
+    /// Reading or writing the save failed
+    Io(std::io::Error),
+    /// The save couldn't be parsed or written as JSON
+    Format(serde_json::Error),
+    /// The save is missing it's version, or was written by a newer version of the crate
+    UnsupportedVersion(Option<u64>),
+}
+
+impl Display for PersistenceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PersistenceError::Io(err) => write!(f, "couldn't access the saved generator: {err}"),
+            PersistenceError::Format(err) => write!(f, "the saved generator is invalid: {err}"),
+            PersistenceError::UnsupportedVersion(Some(version)) => write!(
+                f,
+                "the saved generator uses version {version}, but only versions up to {GENERATOR_SAVE_VERSION} are supported"
+            ),
+            PersistenceError::UnsupportedVersion(None) => {
+                write!(f, "the saved generator doesn't have a version")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PersistenceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PersistenceError::Io(err) => Some(err),
+            PersistenceError::Format(err) => Some(err),
+            PersistenceError::UnsupportedVersion(_) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for PersistenceError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<serde_json::Error> for PersistenceError {
+    fn from(value: serde_json::Error) -> Self {
+        Self::Format(value)
+    }
+}
+
+#[derive(Serialize)]
+struct SavedGeneratorRef<'a> {
+    version: u32,
+    #[serde(flatten)]
+    generator: &'a StatefulStringGenerator,
+}
+
+impl StatefulStringGenerator {
+    /// This writes the generator's full state - it's grammar, the variables it remembered, and it's selection memory and tokens - as versioned JSON.
+    /// Custom modifiers can't be saved, and need to be registered again after loading.
+    pub fn save_to_writer(&self, writer: impl Write) -> Result<(), PersistenceError> {
+        let saved = SavedGeneratorRef {
+            version: GENERATOR_SAVE_VERSION,
+            generator: self,
+        };
+        serde_json::to_writer(writer, &saved)?;
+        Ok(())
+    }
+
+    /// This reads a generator saved using `save_to_writer`, migrating saves from older versions.
+    pub fn load_from_reader(reader: impl Read) -> Result<Self, PersistenceError> {
+        let mut saved: Value = serde_json::from_reader(reader)?;
+        let version = saved.get("version").and_then(Value::as_u64);
+        match version {
+            Some(1) => migrate_variables_from_rules(&mut saved),
+            Some(version) if version <= u64::from(GENERATOR_SAVE_VERSION) => {}
+            _ => return Err(PersistenceError::UnsupportedVersion(version)),
+        }
+        Ok(serde_json::from_value(saved)?)
+    }
+
+    /// This saves the generator to a file, replacing it if it already exists
+    pub fn save_to_file(&self, path: impl AsRef<Path>) -> Result<(), PersistenceError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.save_to_writer(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// This loads a generator saved using `save_to_file`
+    pub fn load_from_file(path: impl AsRef<Path>) -> Result<Self, PersistenceError> {
+        Self::load_from_reader(BufReader::new(File::open(path)?))
+    }
+}
+
+/// Version 1 saves kept variables in the grammar's rules, with the runtime only listing their keys.
+/// This moves them into the runtime's variable store.
+fn migrate_variables_from_rules(saved: &mut Value) {
+    let keys = match saved.pointer_mut("/runtime/variables").map(Value::take) {
+        Some(Value::Array(keys)) => keys,
+        _ => vec![],
+    };
+    let mut variables = serde_json::Map::new();
+    if let Some(Value::Object(rules)) = saved.pointer_mut("/grammar/rules") {
+        for key in keys.iter().filter_map(Value::as_str) {
+            if let Some(values) = rules.remove(key) {
+                variables.insert(key.to_string(), values);
+            }
+        }
+    }
+    if let Some(Value::Object(runtime)) = saved.get_mut("runtime") {
+        runtime.insert("variables".to_string(), Value::Object(variables));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generator::{Grammar, SeededRng, StatefulGenerator},
+        tracery::{SelectionMode, TraceryGrammar},
+    };
+
+    #[test]
+    pub fn loaded_generators_continue_where_they_left_off() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["[name:#names#]#greeting#"]),
+                ("greeting", &["Hi #name#", "Hello #name#", "Hey #name#"]),
+                ("names", &["Mia", "Arjun"]),
+            ],
+            None,
+        )
+        .with_selection_mode("greeting", SelectionMode::Cycle);
+        let mut generator = StatefulStringGenerator::from_grammar(grammar);
+        generator.set_token("weather", "rain");
+        assert_eq!(generator.generate(&mut 1).unwrap(), "Hi Arjun");
+
+        let mut saved = vec![];
+        generator.save_to_writer(&mut saved).unwrap();
+        let mut loaded = StatefulStringGenerator::load_from_reader(saved.as_slice()).unwrap();
+
+        assert_eq!(loaded.generation_count(), 1);
+        assert_eq!(loaded.get_token("weather").unwrap(), "rain");
+        assert_eq!(
+            loaded.expand_from(&"#greeting#".to_string(), &mut 0),
+            "Hello Arjun"
+        );
+    }
+
+    #[test]
+    pub fn rejects_saves_from_newer_versions() {
+        let saved = r#"{"version": 99, "grammar": {"rules": {}}}"#;
+        assert!(matches!(
+            StatefulStringGenerator::load_from_reader(saved.as_bytes()),
+            Err(PersistenceError::UnsupportedVersion(Some(99)))
+        ));
+        let saved = r#"{"grammar": {"rules": {}}}"#;
+        assert!(matches!(
+            StatefulStringGenerator::load_from_reader(saved.as_bytes()),
+            Err(PersistenceError::UnsupportedVersion(None))
+        ));
+    }
+
+    #[test]
+    pub fn migrates_variables_out_of_version_one_grammars() {
+        let saved = r##"{
+            "version": 1,
+            "grammar": {"rules": {"origin": ["#name#"], "name": ["Mia"]}},
+            "runtime": {"variables": ["name"]}
+        }"##;
+        let mut loaded = StatefulStringGenerator::load_from_reader(saved.as_bytes()).unwrap();
+        assert!(!loaded.get_grammar().has_rule(&"name".to_string()));
+        assert_eq!(
+            loaded.variables().get_var("name").unwrap(),
+            &vec!["Mia".to_string()]
+        );
+        assert_eq!(loaded.generate(&mut 0).unwrap(), "Mia");
+    }
+
+    #[test]
+    pub fn serialized_generators_and_rngs_continue_identically() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["[hero:#names#]#hero# met #names#"]),
+                ("names", &["Mia", "Arjun", "Sam", "Noor"]),
+            ],
+            None,
+        )
+        .with_selection_mode("names", SelectionMode::Shuffle);
+        let mut generator = StatefulStringGenerator::from_grammar(grammar);
+        let mut rng = SeededRng::new(7);
+        generator.generate(&mut rng);
+
+        let saved = serde_json::to_string(&(&generator, &rng)).unwrap();
+        let (mut loaded, mut loaded_rng): (StatefulStringGenerator, SeededRng) =
+            serde_json::from_str(&saved).unwrap();
+
+        assert_eq!(loaded.variables(), generator.variables());
+        for _ in 0..3 {
+            assert_eq!(
+                loaded.generate(&mut loaded_rng),
+                generator.generate(&mut rng)
+            );
+        }
+    }
+}
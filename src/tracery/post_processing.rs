@@ -0,0 +1,199 @@
+use super::{accessibility::OutputTransform, modifiers::takes_an};
+
+/// This is a single step of a `PostProcessing` chain, cleaning up generated text once it is complete
+#[derive(Debug, Clone, Copy)]
+pub enum PostProcessor {
+    /// Removes whitespace from the start and end of the text, and from the end of each line
+    TrimWhitespace,
+    /// Collapses runs of spaces into a single space, and removes spaces before punctuation like `,` and `.` - so options that
+    /// expanded to nothing don't leave gaps behind
+    CollapseSpaces,
+    /// Makes each `a` or `an` agree with the word after it - so `a apple` becomes `an apple`, and `an dog` becomes `a dog`
+    FixArticles,
+    /// Replaces straight quotes with curly ones, `...` with an ellipsis and `--` with an em dash
+    SmartPunctuation,
+    /// Runs a custom transform
+    Custom(OutputTransform),
+}
+
+impl PostProcessor {
+    /// Applies this step to some text
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            PostProcessor::TrimWhitespace => trim_whitespace(text),
+            PostProcessor::CollapseSpaces => collapse_spaces(text),
+            PostProcessor::FixArticles => fix_articles(text),
+            PostProcessor::SmartPunctuation => smart_punctuation(text),
+            PostProcessor::Custom(transform) => transform(text),
+        }
+    }
+}
+
+/// This is a chain of post-processors applied to generated text once it is complete, so whitespace and articles don't need to be
+/// handled by every grammar's author. It is set on a stateful generator using `StatefulStringGenerator::set_post_processing`, and
+/// runs before the generator's `OutputProfile`. Only the final output is processed, so variables keep the text the grammar wrote.
+#[derive(Debug, Clone, Default)]
+pub struct PostProcessing {
+    steps: Vec<PostProcessor>,
+}
+
+impl PostProcessing {
+    /// Creates an empty chain, which leaves text unchanged
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates the usual chain for English text - collapsing spaces, fixing articles and trimming whitespace
+    pub fn standard() -> Self {
+        Self::new()
+            .with(PostProcessor::CollapseSpaces)
+            .with(PostProcessor::FixArticles)
+            .with(PostProcessor::TrimWhitespace)
+    }
+
+    /// Adds a step to the end of the chain, returning the updated chain
+    pub fn with(mut self, step: PostProcessor) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Adds a custom transform to the end of the chain, returning the updated chain
+    pub fn with_transform(self, transform: OutputTransform) -> Self {
+        self.with(PostProcessor::Custom(transform))
+    }
+
+    /// Gets the steps of the chain, in the order they run
+    pub fn steps(&self) -> &[PostProcessor] {
+        &self.steps
+    }
+
+    /// Applies each step of the chain to the text, in order
+    pub fn apply(&self, text: &str) -> String {
+        self.steps
+            .iter()
+            .fold(text.to_string(), |text, step| step.apply(&text))
+    }
+}
+
+fn trim_whitespace(text: &str) -> String {
+    text.split('\n')
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+fn collapse_spaces(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut characters = text.chars().peekable();
+    while let Some(character) = characters.next() {
+        if character != ' ' {
+            output.push(character);
+            continue;
+        }
+        while characters.peek() == Some(&' ') {
+            characters.next();
+        }
+        match characters.peek() {
+            Some(',' | '.' | ';' | ':' | '!' | '?') => {}
+            _ => output.push(' '),
+        }
+    }
+    output
+}
+
+fn fix_articles(text: &str) -> String {
+    let words = text
+        .split(char::is_whitespace)
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>();
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    for (word, next) in words.iter().zip(words.iter().skip(1)) {
+        let start = rest.find(word).unwrap_or_default();
+        output.push_str(&rest[..start]);
+        rest = &rest[start + word.len()..];
+        let starts_with_letter = next.chars().next().is_some_and(char::is_alphabetic);
+        if !starts_with_letter || !matches!(word.to_lowercase().as_str(), "a" | "an") {
+            output.push_str(word);
+            continue;
+        }
+        let article = match takes_an(next) {
+            true => "an",
+            false => "a",
+        };
+        match word.starts_with('A') {
+            true => output.push_str(&article.replacen('a', "A", 1)),
+            false => output.push_str(article),
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+fn smart_punctuation(text: &str) -> String {
+    let text = text.replace("...", "\u{2026}").replace("--", "\u{2014}");
+    let mut output = String::with_capacity(text.len());
+    let mut previous: Option<char> = None;
+    for character in text.chars() {
+        let opening = previous.is_none_or(|previous| {
+            previous.is_whitespace() || matches!(previous, '(' | '[' | '{' | '\u{2014}')
+        });
+        output.push(match (character, opening) {
+            ('"', true) => '\u{201C}',
+            ('"', false) => '\u{201D}',
+            ('\'', true) => '\u{2018}',
+            ('\'', false) => '\u{2019}',
+            (character, _) => character,
+        });
+        previous = Some(character);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generator::StatefulGenerator,
+        tracery::{StatefulStringGenerator, TraceryGrammar},
+    };
+
+    #[test]
+    pub fn post_processors_clean_up_generated_text() {
+        assert_eq!(
+            PostProcessing::standard().apply("  There was a  owl , and an  dog .  \nAn cat! "),
+            "There was an owl, and a dog.\nA cat!"
+        );
+        assert_eq!(
+            PostProcessor::SmartPunctuation.apply(r#""Wait..." she said -- it's 'fine'"#),
+            "\u{201C}Wait\u{2026}\u{201D} she said \u{2014} it\u{2019}s \u{2018}fine\u{2019}"
+        );
+        assert_eq!(
+            PostProcessor::FixArticles.apply("a unicorn and a 8 and a"),
+            "a unicorn and a 8 and a"
+        );
+    }
+
+    #[test]
+    pub fn post_processing_runs_on_the_generators_output() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["[pet:#animal#]a #pet# #extra# ."]),
+                ("animal", &["owl"]),
+                ("extra", &[""]),
+            ],
+            None,
+        );
+        let mut generator = StatefulStringGenerator::from_grammar(grammar).with_post_processing(
+            PostProcessing::standard().with_transform(|text| text.to_uppercase()),
+        );
+        assert_eq!(generator.generate(&mut 0).unwrap(), "AN OWL.");
+        // Variables keep the text the grammar wrote
+        assert_eq!(
+            generator.variables().get_var("pet"),
+            Some(&vec!["owl".to_string()])
+        );
+    }
+}
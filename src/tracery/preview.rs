@@ -0,0 +1,83 @@
+use std::ops::Range;
+
+use super::captures::split_key_and_modifiers;
+
+/// This is an expansion meant for editors - any rules that couldn't be expanded are listed as placeholders, rather than
+/// being left in the text as `#key#`. This lets editors render them differently, and offer to define the missing rules.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExpansionPreview {
+    /// The expanded text. Each placeholder's key is written in place of the unexpanded rule, without the surrounding `#`s
+    pub text: String,
+    /// The rules that couldn't be expanded, in the order they appear in the text
+    pub placeholders: Vec<Placeholder>,
+}
+
+/// This is a rule that couldn't be expanded within an `ExpansionPreview`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placeholder {
+    /// The key of the missing rule, without any modifiers
+    pub key: String,
+    /// The byte range of the key within the preview's text
+    pub span: Range<usize>,
+}
+
+impl ExpansionPreview {
+    /// Creates a preview from an expanded string, treating any `#key#` left in it as a placeholder
+    pub fn from_expansion(expansion: &str) -> Self {
+        let mut preview = Self::default();
+        let mut rest = expansion;
+        while let Some(start) = rest.find('#') {
+            let inner = &rest[start + 1..];
+            let Some(end) = inner
+                .find('#')
+                .filter(|end| *end > 0 && !inner[..*end].contains(char::is_whitespace))
+            else {
+                preview.text.push_str(&rest[..=start]);
+                rest = inner;
+                continue;
+            };
+            preview.text.push_str(&rest[..start]);
+            let rule = &inner[..end];
+            let key = split_key_and_modifiers(rule).map_or(rule, |(key, _)| key);
+            let span = preview.text.len()..preview.text.len() + key.len();
+            preview.text.push_str(key);
+            preview.placeholders.push(Placeholder {
+                key: key.to_string(),
+                span,
+            });
+            rest = &inner[end + 1..];
+        }
+        preview.text.push_str(rest);
+        preview
+    }
+
+    /// Checks whether every rule was expanded
+    pub fn is_complete(&self) -> bool {
+        self.placeholders.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn unexpanded_rules_become_placeholders() {
+        let preview = ExpansionPreview::from_expansion("The #animal.capitalize# is #1 at #mood#");
+        assert_eq!(preview.text, "The animal is #1 at mood");
+        assert_eq!(
+            preview.placeholders,
+            vec![
+                Placeholder {
+                    key: "animal".to_string(),
+                    span: 4..10
+                },
+                Placeholder {
+                    key: "mood".to_string(),
+                    span: 20..24
+                }
+            ]
+        );
+        assert!(ExpansionPreview::from_expansion("All done").is_complete());
+    }
+}
@@ -0,0 +1,256 @@
+use std::{fmt::Display, ops::RangeInclusive};
+
+use super::{word_lists::csv_fields, TraceryGrammar};
+use crate::generator::Grammar;
+
+/// This is an entry of a random table - the rolls that select it, and it's result
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableEntry {
+    /// The rolls selecting the entry, like `1..=5`
+    pub rolls: RangeInclusive<u32>,
+    /// The entry's result
+    pub result: String,
+}
+
+/// This is a classic tabletop random table, like a d100 table of tavern rumours - parsed from the markdown or CSV lists
+/// GM tools use, so it can become a weighted rule. Each entry's weight is the number of rolls that select it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RandomTable {
+    /// The die the table is rolled with, if the table names one - like `100` for `d100`
+    pub die: Option<u32>,
+    /// The table's entries, in the order they were written
+    pub entries: Vec<TableEntry>,
+}
+
+/// This is a problem found while parsing a random table. Lines are counted from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RandomTableError {
+    /// The table has no entries
+    NoEntries,
+    /// An entry's rolls are out of order, or outside of the table's die
+    InvalidRolls(usize, String),
+    /// An entry's rolls overlap those of an earlier entry
+    OverlappingRolls(usize, u32),
+}
+
+impl Display for RandomTableError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RandomTableError::NoEntries => write!(f, "the table has no entries"),
+            RandomTableError::InvalidRolls(line, rolls) => {
+                write!(f, "line {line} has invalid rolls `{rolls}`")
+            }
+            RandomTableError::OverlappingRolls(line, roll) => {
+                write!(f, "line {line} reuses the roll {roll}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RandomTableError {}
+
+impl RandomTable {
+    /// Parses a random table. Each entry is a line starting with it's roll or range of rolls, followed by it's result - like
+    /// `01-05: A drunk dwarf`, `6. A bard`, `7,"A lost child, crying"` or the markdown table row `| 96–00 | A dragon |`.
+    /// Rolls of `00` and `0` stand for the highest roll of a d100 or d10. The die is read from the first other line naming one,
+    /// like `d100 Tavern Patrons` or a table header of `| d100 | Patron |`. Any other lines - like titles and markdown table
+    /// separators - are skipped.
+    pub fn parse(content: &str) -> Result<Self, RandomTableError> {
+        let mut table = RandomTable::default();
+        let mut used: Vec<RangeInclusive<u32>> = vec![];
+        for (index, line) in content.lines().enumerate() {
+            let line_number = index + 1;
+            let Some((start, end, result)) = parse_entry(line) else {
+                if table.die.is_none() {
+                    table.die = die_size(line);
+                }
+                continue;
+            };
+            let start = table.highest_roll(start);
+            let end = end.map(|end| table.highest_roll(end)).unwrap_or(start);
+            let within_die = table.die.is_none_or(|die| end <= die);
+            if start == 0 || start > end || !within_die {
+                let rolls = line.trim().trim_start_matches('|').trim();
+                let rolls = rolls
+                    .split([' ', ':', '|', ',', '\t'])
+                    .next()
+                    .unwrap_or_default();
+                return Err(RandomTableError::InvalidRolls(
+                    line_number,
+                    rolls.to_string(),
+                ));
+            }
+            if let Some(used) = used
+                .iter()
+                .find(|used| *used.start() <= end && start <= *used.end())
+            {
+                let roll = start.max(*used.start());
+                return Err(RandomTableError::OverlappingRolls(line_number, roll));
+            }
+            used.push(start..=end);
+            table.entries.push(TableEntry {
+                rolls: start..=end,
+                result,
+            });
+        }
+        match table.entries.is_empty() {
+            true => Err(RandomTableError::NoEntries),
+            false => Ok(table),
+        }
+    }
+
+    /// Gets the weight of each entry - the number of rolls that select it
+    pub fn weights(&self) -> Vec<u32> {
+        self.entries
+            .iter()
+            .map(|entry| entry.rolls.end() - entry.rolls.start() + 1)
+            .collect()
+    }
+
+    /// Gets the result of a roll, if an entry covers it
+    pub fn result(&self, roll: u32) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|entry| entry.rolls.contains(&roll))
+            .map(|entry| entry.result.as_str())
+    }
+
+    /// Converts a roll of zero into the highest roll of the table's die, if it is a d10 or d100
+    fn highest_roll(&self, roll: u32) -> u32 {
+        match (roll, self.die) {
+            (0, Some(die @ (10 | 100))) => die,
+            (0, None) => 100,
+            _ => roll,
+        }
+    }
+}
+
+/// Parses an entry's rolls and result, or returns `None` if the line isn't an entry.
+/// A roll written as `0` or `00` is returned as `0`, so it can be converted once the die is known.
+fn parse_entry(line: &str) -> Option<(u32, Option<u32>, String)> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line).trim_start();
+    let line = line
+        .strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))
+        .unwrap_or(line);
+    let (start, rest) = leading_number(line)?;
+    let (end, rest) = match rest.trim_start().strip_prefix(['-', '–', '—']) {
+        Some(rest) => {
+            let (end, rest) = leading_number(rest.trim_start())?;
+            (Some(end), rest)
+        }
+        None => (None, rest),
+    };
+    let trimmed = rest.trim_start();
+    let result = match trimmed.chars().next()? {
+        ',' => csv_fields(&trimmed[1..]).into_iter().next()?,
+        '|' => trimmed[1..].split('|').next()?.trim().to_string(),
+        ':' | '.' | ')' => trimmed[1..].trim().to_string(),
+        _ if trimmed.len() < rest.len() => trimmed.to_string(),
+        _ => return None,
+    };
+    (!result.is_empty()).then_some((start, end, result))
+}
+
+fn leading_number(text: &str) -> Option<(u32, &str)> {
+    let digits = text
+        .find(|character: char| !character.is_ascii_digit())
+        .unwrap_or(text.len());
+    Some((text[..digits].parse().ok()?, &text[digits..]))
+}
+
+/// Finds the die a line names, like the `100` of `d100` or `1d100`
+fn die_size(line: &str) -> Option<u32> {
+    line.split(|character: char| !character.is_alphanumeric())
+        .find_map(|word| {
+            let word = word.trim_start_matches(|character: char| character.is_ascii_digit());
+            word.strip_prefix(['d', 'D'])?.parse().ok()
+        })
+        .filter(|die| *die > 0)
+}
+
+impl TraceryGrammar {
+    /// This adds a random table as a rule, returning the updated grammar. See `add_random_table`.
+    pub fn with_random_table(mut self, rule: impl Into<String>, table: &RandomTable) -> Self {
+        self.add_random_table(rule, table);
+        self
+    }
+
+    /// This adds a random table as a rule - replacing any rule with the same key - with an option for each entry, weighted by the
+    /// number of rolls that select it. Results can use the grammar's syntax, so entries can reference other rules and tables.
+    pub fn add_random_table(&mut self, rule: impl Into<String>, table: &RandomTable) {
+        let rule = rule.into();
+        let options = table
+            .entries
+            .iter()
+            .map(|entry| entry.result.clone())
+            .collect::<Vec<_>>();
+        self.set_additional_rules(rule.clone(), &options);
+        self.set_weights(rule, table.weights());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn tables_are_parsed_from_markdown_and_csv() {
+        let table = RandomTable::parse(
+            "## Tavern Patrons\n\n| d100 | Patron |\n|---|---|\n| 01–60 | A drunk dwarf |\n| 61-95 | A bard | singing |\n| 96-00 | A dragon, in disguise |",
+        )
+        .unwrap();
+        assert_eq!(table.die, Some(100));
+        assert_eq!(table.weights(), vec![60, 35, 5]);
+        assert_eq!(table.result(61), Some("A bard"));
+        assert_eq!(table.result(100), Some("A dragon, in disguise"));
+
+        let table =
+            RandomTable::parse("1d6 Weather\n1-3,Rain\n4,\"Fog, thick\"\n5. Snow\n6 Clear skies")
+                .unwrap();
+        assert_eq!(table.die, Some(6));
+        assert_eq!(
+            table.entries[1],
+            TableEntry {
+                rolls: 4..=4,
+                result: "Fog, thick".to_string()
+            }
+        );
+        assert_eq!(table.weights(), vec![3, 1, 1, 1]);
+
+        assert_eq!(
+            RandomTable::parse("d6\n1-4: Rain\n4-6: Sun"),
+            Err(RandomTableError::OverlappingRolls(3, 4))
+        );
+        assert_eq!(
+            RandomTable::parse("d6\n5-7: Rain"),
+            Err(RandomTableError::InvalidRolls(2, "5-7".to_string()))
+        );
+        assert_eq!(
+            RandomTable::parse("Tavern Patrons"),
+            Err(RandomTableError::NoEntries)
+        );
+    }
+
+    #[test]
+    pub fn tables_become_weighted_rules() {
+        let table = RandomTable::parse("d20\n1-19: #monster#\n20: a #monster# boss").unwrap();
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["You meet #encounter#"]),
+                ("monster", &["goblin"]),
+            ],
+            None,
+        )
+        .with_random_table("encounter", &table);
+        assert_eq!(
+            grammar.get_rule_options(&"encounter".to_string()).unwrap(),
+            &vec!["#monster#".to_string(), "a #monster# boss".to_string()]
+        );
+        assert_eq!(
+            grammar.rule_settings("encounter").unwrap().weights,
+            vec![19, 1]
+        );
+    }
+}
@@ -0,0 +1,206 @@
+#[cfg(feature = "bevy")]
+use bevy::utils::{HashMap, HashSet};
+#[cfg(not(feature = "bevy"))]
+use std::collections::{HashMap, HashSet};
+
+use super::{annotations::option_text, captures::split_key_and_modifiers, TraceryGrammar};
+use crate::generator::{ExpansionTrace, Grammar, Replacable, TraceNode};
+
+impl TraceryGrammar {
+    /// This checks whether the text could have been generated from the grammar's starting point. See `recognize_at`.
+    pub fn recognize(&self, text: &str) -> Option<ExpansionTrace<String>> {
+        self.recognize_at(self.default_starting_point(), text)
+    }
+
+    /// This checks whether the text could have been generated from a rule, returning a trace of the options that generate it -
+    /// useful for testing grammars, or for working out what a player meant when they typed a phrase.
+    /// Rules using modifiers can't be recognized, and actions are skipped - so variables they assign can't be recognized either.
+    pub fn recognize_at(&self, rule: &str, text: &str) -> Option<ExpansionTrace<String>> {
+        let mut recognizer = Recognizer {
+            grammar: self,
+            text,
+            matches: HashMap::default(),
+            checked: HashSet::default(),
+            changed: false,
+        };
+        // Rules that refer back to each other are matched using the matches found for each other so far, so the text is
+        // matched again until a pass doesn't find any new matches
+        loop {
+            recognizer.checked.clear();
+            recognizer.changed = false;
+            let matches = recognizer.match_rule(rule, 0);
+            if let Some((_, node)) = matches.into_iter().find(|(end, _)| *end == text.len()) {
+                return Some(ExpansionTrace { roots: vec![node] });
+            }
+            if !recognizer.changed {
+                return None;
+            }
+        }
+    }
+}
+
+/// This finds the ways a grammar's rules can match parts of a text
+struct Recognizer<'a> {
+    grammar: &'a TraceryGrammar,
+    text: &'a str,
+    /// The ends of the matches found for a rule starting at a given position, each with the derivation that produced it
+    matches: HashMap<(String, usize), Vec<(usize, TraceNode<String>)>>,
+    /// The rules and positions matched during the current pass
+    checked: HashSet<(String, usize)>,
+    /// Whether the current pass found any new matches
+    changed: bool,
+}
+
+impl<'a> Recognizer<'a> {
+    /// Finds where the rule can stop matching, if it starts matching at `start`. Only one derivation is kept for each end.
+    /// Each rule is matched once per pass - rules reached again within the same pass, such as rules that refer back to
+    /// themselves, use the matches found so far.
+    fn match_rule(&mut self, key: &str, start: usize) -> Vec<(usize, TraceNode<String>)> {
+        let memo = (key.to_string(), start);
+        if !self.checked.insert(memo.clone()) {
+            return self.matches.get(&memo).cloned().unwrap_or_default();
+        }
+        let found = self.match_options(key, start);
+        let known = self.matches.entry(memo).or_default();
+        for (end, node) in found {
+            if known.iter().all(|(existing, _)| *existing != end) {
+                known.push((end, node));
+                self.changed = true;
+            }
+        }
+        known.clone()
+    }
+
+    /// Finds where the rule can stop matching, by matching each of it's options
+    fn match_options(&mut self, key: &str, start: usize) -> Vec<(usize, TraceNode<String>)> {
+        let mut found: Vec<(usize, TraceNode<String>)> = vec![];
+        let grammar = self.grammar;
+        let options = match split_key_and_modifiers(key) {
+            Some(_) => None,
+            None => grammar.rules.get(grammar.resolve_alias(key)),
+        };
+        for (index, option) in options.into_iter().flatten().enumerate() {
+            for (end, children) in self.match_stream(option_text(option), start) {
+                if found.iter().all(|(existing, _)| *existing != end) {
+                    found.push((
+                        end,
+                        TraceNode {
+                            rule: key.to_string(),
+                            option: Some(index),
+                            children,
+                        },
+                    ));
+                }
+            }
+        }
+        found
+    }
+
+    /// Finds where a stream can stop matching, if it starts matching at `start`, along with the rules matched within it
+    fn match_stream(&mut self, stream: &str, start: usize) -> Vec<(usize, Vec<TraceNode<String>>)> {
+        let (_, tokens) = self.grammar.check_token_stream(&stream.to_string());
+        let mut partial = vec![(start, vec![])];
+        for token in tokens {
+            let mut next: Vec<(usize, Vec<TraceNode<String>>)> = vec![];
+            for (position, nodes) in partial {
+                let mut matched = vec![];
+                match &token {
+                    Replacable::Ready(value) => {
+                        if self.text[position..].starts_with(value.as_str()) {
+                            matched.push((position + value.len(), vec![]));
+                        }
+                    }
+                    Replacable::Replace(key) => {
+                        let rule = match self.grammar.split_actions(key) {
+                            Some((_, rule)) => rule,
+                            None => Some(key.clone()),
+                        };
+                        match rule {
+                            Some(rule) => matched.extend(
+                                self.match_rule(&rule, position)
+                                    .into_iter()
+                                    .map(|(end, node)| (end, vec![node])),
+                            ),
+                            None => matched.push((position, vec![])),
+                        }
+                    }
+                    Replacable::Alternatives(alternatives) => {
+                        for alternative in alternatives {
+                            matched.append(&mut self.match_stream(alternative, position));
+                        }
+                    }
+                    Replacable::ImmediateMeta(..) | Replacable::DelayedMeta(..) => {
+                        matched.push((position, vec![]));
+                    }
+                }
+                for (end, mut children) in matched {
+                    if next.iter().all(|(existing, _)| *existing != end) {
+                        let mut nodes = nodes.clone();
+                        nodes.append(&mut children);
+                        next.push((end, nodes));
+                    }
+                }
+            }
+            partial = next;
+            if partial.is_empty() {
+                break;
+            }
+        }
+        partial
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn recognizes_text_the_grammar_can_generate() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["#greeting#, #name#!", "bye"]),
+                ("greeting", &["hi", "hello (there|friend)"]),
+                ("name", &["sam", "#name# and #name#"]),
+            ],
+            None,
+        );
+        let trace = grammar.recognize("hello there, sam and sam!").unwrap();
+        assert_eq!(
+            trace
+                .nodes()
+                .map(|(_, node)| (node.rule.as_str(), node.option))
+                .collect::<Vec<_>>(),
+            vec![
+                ("origin", Some(0)),
+                ("greeting", Some(1)),
+                ("name", Some(1)),
+                ("name", Some(0)),
+                ("name", Some(0)),
+            ]
+        );
+        assert!(grammar.recognize("bye").is_some());
+        assert!(grammar.recognize("hello, sam!").is_none());
+        assert!(grammar.recognize_at("greeting", "hi").is_some());
+    }
+
+    #[test]
+    pub fn recognizes_mutually_recursive_rules() {
+        let grammar = TraceryGrammar::new(&[("a", &["#b#x", "y"]), ("b", &["#a#y"])], Some("a"));
+        for text in ["y", "yyx", "yyxyx"] {
+            assert!(grammar.recognize(text).is_some(), "{text}");
+        }
+        for text in ["yy", "yyxy"] {
+            assert!(grammar.recognize_at("b", text).is_some(), "{text}");
+        }
+        assert!(grammar.recognize("yx").is_none());
+        assert!(grammar.recognize("yyxy").is_none());
+        let trace = grammar.recognize("yyx").unwrap();
+        assert_eq!(
+            trace
+                .nodes()
+                .map(|(_, node)| (node.rule.as_str(), node.option))
+                .collect::<Vec<_>>(),
+            vec![("a", Some(0)), ("b", Some(0)), ("a", Some(1))]
+        );
+    }
+}
@@ -0,0 +1,339 @@
+use std::sync::{Arc, Mutex};
+
+use super::{runtime::RuntimeState, GenerationIssue, StatefulStringGenerator};
+use crate::generator::GrammarRandomNumberGenerator;
+
+/// The random numbers drawn while processing the value of a single assignment
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AssignmentSpan {
+    key: String,
+    start: usize,
+    end: usize,
+}
+
+/// This is the structure retained from a stateful generator's last generation, so it can be replayed with a single variable re-rolled
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RerollRecord {
+    key: String,
+    runtime: RuntimeState,
+    draws: Vec<usize>,
+    spans: Vec<AssignmentSpan>,
+}
+
+/// A replay of a recorded generation, drawing fresh numbers only while the variable being re-rolled is processed
+#[derive(Debug)]
+struct Replay {
+    draws: Vec<usize>,
+    spans: Vec<AssignmentSpan>,
+    variable: String,
+    position: usize,
+    /// While the variable is being re-rolled, the number of assignments open around it's value and where the replay continues afterwards
+    rerolling: Option<(usize, usize)>,
+}
+
+#[derive(Debug, Default)]
+struct RerollLog {
+    draws: Vec<usize>,
+    spans: Vec<AssignmentSpan>,
+    open: Vec<(String, usize)>,
+    replay: Option<Replay>,
+}
+
+/// This records the random numbers drawn during a generation, along with the assignments they were drawn for - and replays a
+/// previous recording when re-rolling. It is shared with the generation through it's runtime state, like the `ChoiceState`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RerollRecorder(Arc<Mutex<RerollLog>>);
+
+impl PartialEq for RerollRecorder {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl RerollRecorder {
+    fn replaying(record: &RerollRecord, variable: &str) -> Self {
+        Self(Arc::new(Mutex::new(RerollLog {
+            replay: Some(Replay {
+                draws: record.draws.clone(),
+                spans: record.spans.clone(),
+                variable: variable.to_string(),
+                position: 0,
+                rerolling: None,
+            }),
+            ..Default::default()
+        })))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, RerollLog> {
+        self.0.lock().unwrap_or_else(|error| error.into_inner())
+    }
+
+    /// Records that the value of an assignment started being processed
+    pub(crate) fn begin_assignment(&self, key: &str) {
+        let log = &mut *self.lock();
+        log.open.push((key.to_string(), log.draws.len()));
+        let depth = log.open.len();
+        let Some(replay) = log.replay.as_mut() else {
+            return;
+        };
+        if replay.rerolling.is_some() || replay.variable != key {
+            return;
+        }
+        // Once the new value is processed, the replay skips the numbers drawn for the old one
+        let position = replay.position;
+        let resume = replay
+            .spans
+            .iter()
+            .find(|span| span.key == key && span.start == position)
+            .map_or(position, |span| span.end);
+        replay.rerolling = Some((depth, resume));
+    }
+
+    /// Records that the value of an assignment was processed
+    pub(crate) fn end_assignment(&self) {
+        let log = &mut *self.lock();
+        let depth = log.open.len();
+        let Some((key, start)) = log.open.pop() else {
+            return;
+        };
+        log.spans.push(AssignmentSpan {
+            key,
+            start,
+            end: log.draws.len(),
+        });
+        if let Some(replay) = log.replay.as_mut() {
+            if let Some((_, resume)) = replay.rerolling.filter(|(open, _)| *open == depth) {
+                replay.position = resume;
+                replay.rerolling = None;
+            }
+        }
+    }
+}
+
+/// Draws numbers for a recorded generation - replaying a previous recording where there is one
+struct RerollRng<'a, R> {
+    rng: &'a mut R,
+    recorder: RerollRecorder,
+}
+
+impl<R: GrammarRandomNumberGenerator> GrammarRandomNumberGenerator for RerollRng<'_, R> {
+    fn get_number(&mut self, len: usize) -> usize {
+        let log = &mut *self.recorder.lock();
+        let replayed = match log.replay.as_mut() {
+            Some(replay) if replay.rerolling.is_none() => {
+                let draw = replay.draws.get(replay.position).copied();
+                replay.position += 1;
+                draw
+            }
+            _ => None,
+        };
+        // If the generation went further than the recording, the rest of it's numbers are drawn fresh
+        let number = match replayed {
+            Some(number) => number.min(len.saturating_sub(1)),
+            None => self.rng.get_number(len),
+        };
+        log.draws.push(number);
+        number
+    }
+}
+
+impl StatefulStringGenerator {
+    /// This makes the generator keep the structure of it's generations, returning the updated generator. See `set_rerollable`.
+    pub fn with_rerolls(mut self) -> Self {
+        self.set_rerollable(true);
+        self
+    }
+
+    /// This sets whether the generator keeps the structure of it's last generation, so a single variable can be re-rolled using
+    /// `reroll`. It's off by default, since every random choice the generation makes gets recorded.
+    pub fn set_rerollable(&mut self, rerollable: bool) {
+        self.rerollable = rerollable;
+        if !rerollable {
+            self.last_generation = None;
+        }
+    }
+
+    /// This checks whether the generator keeps the structure of it's last generation
+    pub fn is_rerollable(&self) -> bool {
+        self.rerollable
+    }
+
+    /// This lists the variables assigned by the last generation, which can be re-rolled using `reroll`
+    pub fn rerollable_variables(&self) -> Vec<&str> {
+        let mut variables = vec![];
+        for span in self.last_generation.iter().flat_map(|record| &record.spans) {
+            if !variables.contains(&span.key.as_str()) {
+                variables.push(span.key.as_str());
+            }
+        }
+        variables
+    }
+
+    /// This re-rolls a single variable assigned by the last generation, and re-renders the text that referenced it. Every other
+    /// choice is replayed - so with `[hero:#name#][obstacle:#monster#]`, re-rolling `obstacle` keeps the hero and the rest of the
+    /// text, and replaces every mention of the obstacle. The re-rendered text replaces the last generation, along with it's variables.
+    /// This needs the generator to be rerollable, and only applies to generations from `try_generate` or `try_generate_at`.
+    pub fn reroll<R: GrammarRandomNumberGenerator>(
+        &mut self,
+        variable: &str,
+        rng: &mut R,
+    ) -> Result<String, GenerationIssue> {
+        let Some(record) = self
+            .last_generation
+            .clone()
+            .filter(|record| record.spans.iter().any(|span| span.key == variable))
+        else {
+            return Err(GenerationIssue::NotRerollable(variable.to_string()));
+        };
+        // Re-rolled outputs are checked by the content filter, just like new generations
+        self.filtered(rng, |generator, rng| {
+            // The generation is replayed from the state it started in
+            generator.runtime.selection = record.runtime.selection.clone();
+            generator.runtime.variables = record.runtime.variables.clone();
+            generator.runtime.shared = record.runtime.shared.clone();
+            let recorder = RerollRecorder::replaying(&record, variable);
+            generator.generate_recorded(&record.key, rng, recorder)
+        })
+    }
+
+    /// Generates from the provided key, keeping the structure of the generation if the generator is rerollable
+    pub(crate) fn generate_retained<R: GrammarRandomNumberGenerator>(
+        &mut self,
+        key: &str,
+        rng: &mut R,
+    ) -> Result<String, GenerationIssue> {
+        match self.rerollable {
+            true => self.generate_recorded(key, rng, RerollRecorder::default()),
+            false => self.generate_from(key, rng),
+        }
+    }
+
+    fn generate_recorded<R: GrammarRandomNumberGenerator>(
+        &mut self,
+        key: &str,
+        rng: &mut R,
+        recorder: RerollRecorder,
+    ) -> Result<String, GenerationIssue> {
+        let runtime = self.runtime.clone();
+        self.runtime.rerolls = Some(recorder.clone());
+        let mut rerolls = RerollRng {
+            rng,
+            recorder: recorder.clone(),
+        };
+        let result = self.generate_from(key, &mut rerolls);
+        self.runtime.rerolls = None;
+        if result.is_ok() {
+            let log = std::mem::take(&mut *recorder.lock());
+            self.last_generation = Some(RerollRecord {
+                key: key.to_string(),
+                runtime,
+                draws: log.draws,
+                spans: log.spans,
+            });
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        generator::{SeededRng, StatefulGenerator},
+        tracery::{
+            ContentFilter, FilterPolicy, GenerationIssue, StatefulStringGenerator, TraceryGrammar,
+        },
+    };
+
+    #[test]
+    pub fn rerolled_variables_are_replaced_everywhere_they_were_used() {
+        let grammar = TraceryGrammar::new(
+            &[
+                (
+                    "origin",
+                    &["[hero:#name#][obstacle:#monster#]#hero# meets a #obstacle#, and #hero# fights the #obstacle.capitalize# with #weapon#"],
+                ),
+                ("name", &["Ana", "Bo", "Cy", "Dee", "Eve"]),
+                ("monster", &["troll", "ogre", "wyrm", "imp", "golem", "hydra"]),
+                ("weapon", &["a sword", "an axe", "a spear", "a bow", "a staff"]),
+            ],
+            None,
+        );
+        let mut generator = StatefulStringGenerator::from_grammar(grammar).with_rerolls();
+        let mut rng = SeededRng::new(5);
+        let original = generator.try_generate(&mut rng).unwrap();
+        let hero = generator.variables().get_var("hero").unwrap()[0].clone();
+        let obstacle = generator.variables().get_var("obstacle").unwrap()[0].clone();
+        let weapon = original.rsplit(" with ").next().unwrap().to_string();
+        assert_eq!(generator.rerollable_variables(), vec!["hero", "obstacle"]);
+
+        let mut rerolled = original.clone();
+        let mut new_obstacle = obstacle.clone();
+        for _ in 0..20 {
+            rerolled = generator.reroll("obstacle", &mut rng).unwrap();
+            new_obstacle = generator.variables().get_var("obstacle").unwrap()[0].clone();
+            if new_obstacle != obstacle {
+                break;
+            }
+        }
+        assert_ne!(new_obstacle, obstacle);
+        let mut capitalized = new_obstacle.clone();
+        capitalized[..1].make_ascii_uppercase();
+        assert_eq!(
+            rerolled,
+            format!(
+                "{hero} meets a {new_obstacle}, and {hero} fights the {capitalized} with {weapon}"
+            )
+        );
+        assert_eq!(
+            generator.variables().get_var("hero"),
+            Some(&vec![hero.clone()])
+        );
+
+        assert_eq!(
+            generator.reroll("villain", &mut rng),
+            Err(GenerationIssue::NotRerollable("villain".to_string()))
+        );
+        generator.set_rerollable(false);
+        generator.generate(&mut rng).unwrap();
+        assert_eq!(
+            generator.reroll("hero", &mut rng),
+            Err(GenerationIssue::NotRerollable("hero".to_string()))
+        );
+    }
+
+    #[test]
+    pub fn rerolled_outputs_are_checked_by_the_content_filter() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["[pet:#animal#]a #pet#"]),
+                ("animal", &["owl", "rat", "cat"]),
+            ],
+            None,
+        );
+        let mut generator = StatefulStringGenerator::from_grammar(grammar)
+            .with_rerolls()
+            .with_content_filter(
+                ContentFilter::new()
+                    .with_banned_words(["rat"])
+                    .with_policy(FilterPolicy::Reroll(100)),
+            );
+        let mut rng = SeededRng::new(3);
+        generator.try_generate(&mut rng).unwrap();
+        for _ in 0..20 {
+            assert_ne!(generator.reroll("pet", &mut rng).unwrap(), "a rat");
+        }
+
+        let pet = generator.variables().get_var("pet").cloned();
+        generator.set_content_filter(Some(
+            ContentFilter::new()
+                .with_banned_words(["owl", "rat", "cat"])
+                .with_policy(FilterPolicy::Fail),
+        ));
+        assert_eq!(
+            generator.reroll("pet", &mut rng),
+            Err(GenerationIssue::Filtered(1))
+        );
+        assert_eq!(generator.variables().get_var("pet").cloned(), pet);
+        assert_eq!(generator.rerollable_variables(), vec!["pet"]);
+    }
+}
@@ -0,0 +1,111 @@
+use super::{runtime::RuntimeState, sync::Fingerprint, StatefulStringGenerator, VariableStore};
+
+/// This is a copy of everything a `StatefulStringGenerator` carries between generations - it's variables, tokens, shared
+/// variables and selection memory - without it's grammar, so it is cheap enough to take every frame. Restoring it rewinds
+/// the generator, which is what rollback networking needs when a late input arrives and frames are simulated again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratorSnapshot {
+    runtime: RuntimeState,
+    global_writes: VariableStore,
+}
+
+impl StatefulStringGenerator {
+    /// This copies the generator's current state, so it can be restored later
+    pub fn snapshot(&self) -> GeneratorSnapshot {
+        let mut runtime = self.runtime.clone();
+        runtime.filter = None;
+        GeneratorSnapshot {
+            runtime,
+            global_writes: self.global_writes.clone(),
+        }
+    }
+
+    /// This rewinds the generator to a snapshot, replacing it's current state. The issues and data from the last generation are cleared.
+    pub fn restore(&mut self, snapshot: &GeneratorSnapshot) {
+        self.runtime = snapshot.runtime.clone();
+        self.global_writes = snapshot.global_writes.clone();
+        self.issues.clear();
+        self.emitted.clear();
+    }
+
+    /// This hashes the generator's state, so peers in a lockstep game can compare it to detect desyncs. Generators with the
+    /// same state have the same hash on every platform - regardless of the order their variables were set in.
+    pub fn state_hash(&self) -> u64 {
+        let mut fingerprint = Fingerprint::new();
+        self.runtime.selection.write_fingerprint(&mut fingerprint);
+        let mut tokens = self.runtime.tokens.iter().collect::<Vec<_>>();
+        tokens.sort();
+        for (key, value) in tokens {
+            fingerprint.write(key);
+            fingerprint.write(value);
+        }
+        for variables in [
+            &self.runtime.variables,
+            &self.runtime.shared,
+            &self.global_writes,
+        ] {
+            variables.write_fingerprint(&mut fingerprint);
+            fingerprint.write("");
+        }
+        fingerprint.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generator::{SeededRng, StatefulGenerator},
+        tracery::{SelectionMode, TraceryGrammar},
+    };
+
+    fn generator() -> StatefulStringGenerator {
+        StatefulStringGenerator::from_grammar(
+            TraceryGrammar::new(
+                &[
+                    ("origin", &["[pet:#animal#]The #pet# #sound#"]),
+                    ("animal", &["cat", "dog", "owl"]),
+                    ("sound", &["purrs", "barks", "hoots", "sleeps"]),
+                ],
+                None,
+            )
+            .with_selection_mode("sound", SelectionMode::Shuffle),
+        )
+    }
+
+    #[test]
+    pub fn restoring_a_snapshot_replays_the_same_generations() {
+        let mut generator = generator();
+        let mut rng = SeededRng::new(4);
+        generator.generate(&mut rng);
+        let snapshot = generator.snapshot();
+        let (hash, saved_rng) = (generator.state_hash(), rng.clone());
+
+        let first = (0..3)
+            .map(|_| generator.generate(&mut rng).unwrap())
+            .collect::<Vec<_>>();
+        assert_ne!(generator.state_hash(), hash);
+
+        generator.restore(&snapshot);
+        assert_eq!(generator.state_hash(), hash);
+        let mut rng = saved_rng;
+        let replayed = (0..3)
+            .map(|_| generator.generate(&mut rng).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(first, replayed);
+    }
+
+    #[test]
+    pub fn state_hashes_detect_diverging_generators() {
+        let (mut a, mut b) = (generator(), generator());
+        a.set_token("weather", "rain");
+        a.set_token("time", "noon");
+        b.set_token("time", "noon");
+        b.set_token("weather", "rain");
+        assert_eq!(a.state_hash(), b.state_hash());
+
+        a.generate(&mut 0);
+        b.generate(&mut 1);
+        assert_ne!(a.state_hash(), b.state_hash());
+    }
+}
@@ -0,0 +1,62 @@
+use std::{fmt::Debug, sync::Arc};
+
+use super::{
+    annotations::option_tags, budget::BudgetUsage, choices::ChoiceState,
+    diagnostics::GenerationStats, rerolls::RerollRecorder, SelectionState, VariableStore,
+};
+#[cfg(feature = "bevy")]
+use bevy::utils::HashMap;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(not(feature = "bevy"))]
+use std::collections::HashMap;
+
+/// This holds the state used while a grammar is being expanded - the selection memory, the variables kept from previous generations,
+/// and any token values, shared variables or option filters provided from outside the grammar.
+/// Generators place it in the temporary grammar used for an expansion, and stateful generators keep it between generations.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub(crate) struct RuntimeState {
+    pub(crate) selection: SelectionState,
+    pub(crate) tokens: HashMap<String, String>,
+    pub(crate) variables: VariableStore,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) shared: VariableStore,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) filter: Option<OptionFilter>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) choices: Option<ChoiceState>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) rerolls: Option<RerollRecorder>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) usage: BudgetUsage,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) stats: GenerationStats,
+}
+
+/// This decides which options can be chosen during a generation, based on their tags
+#[derive(Clone)]
+pub(crate) struct OptionFilter(Arc<dyn Fn(&[&str]) -> bool + Send + Sync>);
+
+impl OptionFilter {
+    pub(crate) fn new(filter: impl Fn(&[&str]) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(filter))
+    }
+
+    /// Checks whether an option can be chosen
+    pub(crate) fn allows(&self, option: &str) -> bool {
+        (self.0)(&option_tags(option))
+    }
+}
+
+impl Debug for OptionFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("OptionFilter")
+    }
+}
+
+impl PartialEq for OptionFilter {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
@@ -0,0 +1,257 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{TraceryGrammar, GLOBAL_PREFIX};
+
+/// This limits what grammars from untrusted sources - like mods - can do, so they can't exhaust memory or tamper with the world.
+/// It is applied to a grammar using `TraceryGrammar::sandboxed`, which checks the grammar's definition straight away and limits
+/// it's generations from then on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct SandboxPolicy {
+    /// The maximum number of rules the grammar can define
+    pub max_rules: usize,
+    /// The maximum length, in bytes, of any of the grammar's options
+    pub max_option_length: usize,
+    /// The maximum length, in bytes, of a generation's output. Longer generations fail with `GenerationIssue::OutputTooLong`
+    pub max_output_length: usize,
+    /// The maximum number of rules expanded by a single generation, replacing `Grammar::max_expansions`
+    pub max_expansions: usize,
+    /// Whether the grammar can write to the shared variables using assignments like `[global.season:winter]`
+    pub allow_global_writes: bool,
+    /// Whether the grammar can read values provided from outside of it - tokens, and shared variables like those on a `GrammarBlackboard`
+    pub allow_external_values: bool,
+}
+
+impl Default for SandboxPolicy {
+    fn default() -> Self {
+        Self {
+            max_rules: 1_000,
+            max_option_length: 1_000,
+            max_output_length: 10_000,
+            max_expansions: 1_000,
+            allow_global_writes: false,
+            allow_external_values: false,
+        }
+    }
+}
+
+impl SandboxPolicy {
+    /// Creates the default policy, which keeps grammars to a moderate size and doesn't let them read or write anything outside of themselves
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of rules the grammar can define
+    pub fn with_max_rules(mut self, max_rules: usize) -> Self {
+        self.max_rules = max_rules;
+        self
+    }
+
+    /// Sets the maximum length of any of the grammar's options
+    pub fn with_max_option_length(mut self, max_option_length: usize) -> Self {
+        self.max_option_length = max_option_length;
+        self
+    }
+
+    /// Sets the maximum length of a generation's output
+    pub fn with_max_output_length(mut self, max_output_length: usize) -> Self {
+        self.max_output_length = max_output_length;
+        self
+    }
+
+    /// Sets the maximum number of rules expanded by a single generation
+    pub fn with_max_expansions(mut self, max_expansions: usize) -> Self {
+        self.max_expansions = max_expansions;
+        self
+    }
+
+    /// Sets whether the grammar can write to the shared variables
+    pub fn with_global_writes(mut self, allow: bool) -> Self {
+        self.allow_global_writes = allow;
+        self
+    }
+
+    /// Sets whether the grammar can read tokens and shared variables
+    pub fn with_external_values(mut self, allow: bool) -> Self {
+        self.allow_external_values = allow;
+        self
+    }
+
+    /// Checks a grammar's definition against the policy, returning the first problem found - checking rules in order of their keys.
+    pub fn check(&self, grammar: &TraceryGrammar) -> Result<(), SandboxViolation> {
+        if grammar.rules.len() > self.max_rules {
+            return Err(SandboxViolation::TooManyRules(grammar.rules.len()));
+        }
+        let mut rules = grammar.rules.iter().collect::<Vec<_>>();
+        rules.sort_by_key(|(rule, _)| *rule);
+        for (rule, options) in rules {
+            for (option, text) in options.iter().enumerate() {
+                if text.len() > self.max_option_length {
+                    return Err(SandboxViolation::OptionTooLong {
+                        rule: rule.clone(),
+                        option,
+                    });
+                }
+                if !self.allow_global_writes && writes_globals(text) {
+                    return Err(SandboxViolation::GlobalWrite {
+                        rule: rule.clone(),
+                        option,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks whether an option contains an assignment to a shared variable, including those nested within other actions
+pub(crate) fn writes_globals(option: &str) -> bool {
+    option
+        .match_indices('[')
+        .any(|(start, _)| option[start + 1..].starts_with(GLOBAL_PREFIX))
+}
+
+/// This is a part of a grammar that breaks a `SandboxPolicy`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SandboxViolation {
+    /// The grammar defines more rules than allowed - holding the number of rules it defines
+    TooManyRules(usize),
+    /// One of the grammar's options is longer than allowed
+    OptionTooLong {
+        /// The rule containing the option
+        rule: String,
+        /// The index of the option
+        option: usize,
+    },
+    /// One of the grammar's options writes to the shared variables, which isn't allowed
+    GlobalWrite {
+        /// The rule containing the option
+        rule: String,
+        /// The index of the option
+        option: usize,
+    },
+}
+
+impl std::fmt::Display for SandboxViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SandboxViolation::TooManyRules(count) => {
+                write!(f, "the grammar defines too many rules ({count})")
+            }
+            SandboxViolation::OptionTooLong { rule, option } => {
+                write!(f, "{rule} (option {option}) is too long")
+            }
+            SandboxViolation::GlobalWrite { rule, option } => {
+                write!(f, "{rule} (option {option}) writes to the shared variables")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SandboxViolation {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generator::StatefulGenerator,
+        tracery::{GenerationIssue, StatefulStringGenerator, StringGenerator},
+    };
+
+    #[test]
+    pub fn policies_reject_grammars_that_break_them() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["The #season# is long"]),
+                ("season", &["winter", "[a|[global.war:declared]]summer"]),
+            ],
+            None,
+        );
+        assert_eq!(
+            grammar.clone().sandboxed(SandboxPolicy::new()).unwrap_err(),
+            SandboxViolation::GlobalWrite {
+                rule: "season".to_string(),
+                option: 1
+            }
+        );
+        assert_eq!(
+            SandboxPolicy::new().with_max_rules(1).check(&grammar),
+            Err(SandboxViolation::TooManyRules(2))
+        );
+        assert_eq!(
+            SandboxPolicy::new()
+                .with_global_writes(true)
+                .with_max_option_length(16)
+                .check(&grammar),
+            Err(SandboxViolation::OptionTooLong {
+                rule: "origin".to_string(),
+                option: 0
+            })
+        );
+        assert!(SandboxPolicy::new()
+            .with_global_writes(true)
+            .check(&grammar)
+            .is_ok());
+    }
+
+    #[test]
+    pub fn sandboxed_grammars_are_limited_while_generating() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["It is #time_of_day#, and #more#"]),
+                ("more", &["more and #more#"]),
+            ],
+            None,
+        )
+        .sandboxed(
+            SandboxPolicy::new()
+                .with_max_expansions(2)
+                .with_max_output_length(32),
+        )
+        .unwrap();
+        assert_eq!(
+            StringGenerator::try_generate(&grammar, &mut 0),
+            Err(GenerationIssue::OutputTooLong(40))
+        );
+
+        let mut generator = StatefulStringGenerator::from_grammar(grammar.clone());
+        generator.set_token("time_of_day", "noon");
+        assert_eq!(
+            generator.try_generate(&mut 0),
+            Err(GenerationIssue::OutputTooLong(40))
+        );
+
+        let policy = grammar
+            .sandbox()
+            .cloned()
+            .unwrap()
+            .with_external_values(true);
+        let mut generator =
+            StatefulStringGenerator::from_grammar(grammar.sandboxed(policy).unwrap());
+        generator.set_token("time_of_day", "noon");
+        assert_eq!(
+            generator.generate(&mut 0).unwrap(),
+            "It is noon, and more and #more#"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    pub fn sandboxes_are_kept_when_grammars_are_serialized() {
+        let grammar = TraceryGrammar::new(&[("origin", &["#more# and #more#"])], None)
+            .sandboxed(SandboxPolicy::new().with_max_option_length(20))
+            .unwrap();
+        let saved = ron::to_string(&grammar).unwrap();
+        let loaded: TraceryGrammar = ron::from_str(&saved).unwrap();
+        assert_eq!(loaded.sandbox(), grammar.sandbox());
+        assert_eq!(loaded.fingerprint(), grammar.fingerprint());
+
+        // Grammars edited to break their sandbox fail to load
+        let edited = saved.replace("#more# and #more#", "#more# and #more# and #more#");
+        assert!(ron::from_str::<TraceryGrammar>(&edited)
+            .unwrap_err()
+            .to_string()
+            .contains("origin (option 0) is too long"));
+    }
+}
@@ -0,0 +1,463 @@
+use super::sync::Fingerprint;
+use crate::generator::GrammarRandomNumberGenerator;
+#[cfg(feature = "bevy")]
+use bevy::utils::HashMap;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(not(feature = "bevy"))]
+use std::collections::HashMap;
+
+/// This determines how a rule chooses between its options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SelectionMode {
+    /// Any option can be chosen at any time - this is the default behaviour
+    #[default]
+    Random,
+    /// Every option is used once, in a random order, before any of them can repeat
+    Shuffle,
+    /// The options are used in the order they were defined, looping back to the start after the last one
+    Cycle,
+    /// Options are chosen at random, but the same option is never chosen twice in a row
+    NoImmediateRepeat,
+    /// The options are used in the order they were defined, then the last one is used from then on - like the beats of a tutorial
+    Sequence,
+}
+
+/// This is how long an option stays unavailable after it was chosen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cooldown {
+    /// The option can't be chosen again for this many generations after the one it was chosen in
+    Generations(usize),
+    /// The option can't be chosen again until this many seconds have passed, as provided by `SelectionState::set_time`
+    Seconds(f64),
+}
+
+/// This holds the settings that control how a single rule is expanded.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct RuleSettings {
+    /// The selection mode used when choosing between the rule's options
+    pub selection: SelectionMode,
+    /// The link group this rule belongs to. Within a single generation, every rule in a link group chooses the same index as
+    /// the first one of them that was expanded - so a `capital` linked to a `country` always matches it.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub link: Option<String>,
+    /// Whether the rule avoids choosing the same option twice within a single generation - so `#adj# and #adj#` uses two different adjectives.
+    /// Once every available option was chosen, options can repeat again.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "std::ops::Not::not"))]
+    pub distinct: bool,
+    /// The relative weight of each of the rule's options, used when the selection mode is `Random`. Options without a weight
+    /// have a weight of 1, and options with a weight of 0 are never chosen. If empty, every option is equally likely.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub weights: Vec<u32>,
+    /// Whether the rule emits data - the full expansion of it's chosen option gets collected alongside the generated text,
+    /// so game code can use facts like a hero's name without parsing the text.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "std::ops::Not::not"))]
+    pub emit: bool,
+    /// Whether the rule is a choice point - when generating interactively, the caller chooses between it's options rather than
+    /// them being chosen at random. See `StringGenerator::generate_interactive`.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "std::ops::Not::not"))]
+    pub choice: bool,
+}
+
+/// This tracks which options each rule has used so far, allowing selection modes other than `Random` to avoid repeats.
+/// Stateful generators keep it between generations, while stateless generators start fresh on every call.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct SelectionState {
+    rules: HashMap<String, RuleSelectionState>,
+    generation: usize,
+    time: Option<f64>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    links: HashMap<String, usize>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    chosen: HashMap<String, Vec<usize>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+struct RuleSelectionState {
+    last: Option<usize>,
+    remaining: Vec<usize>,
+    uses: HashMap<usize, OptionUse>,
+}
+
+/// When an option with a cooldown was last chosen
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct OptionUse {
+    generation: usize,
+    time: Option<f64>,
+}
+
+impl SelectionState {
+    /// Selects the index of an option for a rule with `len` options, using the provided mode, and records the choice.
+    /// Returns `None` if the rule has no options.
+    pub fn select<R: GrammarRandomNumberGenerator>(
+        &mut self,
+        rule: &str,
+        mode: SelectionMode,
+        len: usize,
+        rng: &mut R,
+    ) -> Option<usize> {
+        self.select_where(rule, mode, len, |_| true, rng)
+    }
+
+    /// Selects the index of an option for a rule with `len` options, only considering the options for which `eligible` returns true.
+    /// Returns `None` if none of the options are eligible.
+    pub fn select_where<R: GrammarRandomNumberGenerator, F: Fn(usize) -> bool>(
+        &mut self,
+        rule: &str,
+        mode: SelectionMode,
+        len: usize,
+        eligible: F,
+        rng: &mut R,
+    ) -> Option<usize> {
+        let count = (0..len).filter(|index| eligible(*index)).count();
+        if count == 0 {
+            return None;
+        }
+        let state = match self.rules.get_mut(rule) {
+            Some(state) => state,
+            None => self.rules.entry(rule.to_string()).or_default(),
+        };
+        let pick = |rng: &mut R, count: usize| rng.get_number(count).min(count - 1);
+        let index = match mode {
+            SelectionMode::Random => (0..len).filter(|i| eligible(*i)).nth(pick(rng, count)),
+            SelectionMode::Shuffle => {
+                state.remaining.retain(|index| *index < len);
+                let mut available = state.remaining.iter().filter(|i| eligible(**i)).count();
+                if available == 0 {
+                    state.remaining = (0..len).collect();
+                    available = count;
+                }
+                let chosen = pick(rng, available);
+                let position = state
+                    .remaining
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, index)| eligible(**index))
+                    .nth(chosen)
+                    .map(|(position, _)| position);
+                position.map(|position| state.remaining.swap_remove(position))
+            }
+            SelectionMode::Cycle => {
+                let from = state.last.map_or(0, |last| last.saturating_add(1) % len);
+                (0..len)
+                    .map(|offset| (from + offset) % len)
+                    .find(|index| eligible(*index))
+            }
+            SelectionMode::Sequence => {
+                let from = state.last.map_or(0, |last| last.saturating_add(1));
+                (from..len)
+                    .find(|index| eligible(*index))
+                    .or_else(|| (0..len).rev().find(|index| eligible(*index)))
+            }
+            SelectionMode::NoImmediateRepeat => match state.last {
+                Some(last) if count > 1 && last < len && eligible(last) => (0..len)
+                    .filter(|index| *index != last && eligible(*index))
+                    .nth(pick(rng, count - 1)),
+                _ => (0..len).filter(|i| eligible(*i)).nth(pick(rng, count)),
+            },
+        };
+        state.last = index.or(state.last);
+        index
+    }
+
+    /// Selects the index of an option for a rule with `len` options at random, using the provided weights.
+    /// Only the options for which `eligible` returns true are considered, and options without a weight have a weight of 1.
+    /// Returns `None` if none of the eligible options have a weight.
+    pub fn select_weighted_where<R: GrammarRandomNumberGenerator, F: Fn(usize) -> bool>(
+        &mut self,
+        rule: &str,
+        weights: &[u32],
+        len: usize,
+        eligible: F,
+        rng: &mut R,
+    ) -> Option<usize> {
+        let weight = |index: usize| match eligible(index) {
+            true => weights.get(index).copied().unwrap_or(1) as usize,
+            false => 0,
+        };
+        // Saturating keeps huge weights from overflowing on platforms with a small usize, at the cost of skewing them
+        let total = (0..len).map(weight).fold(0, usize::saturating_add);
+        if total == 0 {
+            return None;
+        }
+        let mut pick = rng.get_number(total).min(total - 1);
+        let index = (0..len).find(|index| {
+            let weight = weight(*index);
+            if pick < weight {
+                return true;
+            }
+            pick = pick.saturating_sub(weight);
+            false
+        })?;
+        self.record_choice(rule, index);
+        Some(index)
+    }
+
+    /// Writes the state that carries over between generations into a fingerprint, in a stable order
+    pub(crate) fn write_fingerprint(&self, fingerprint: &mut Fingerprint) {
+        fingerprint.write_u64(self.generation as u64);
+        fingerprint.write_u64(self.time.map_or(u64::MAX, f64::to_bits));
+        let mut rules = self.rules.iter().collect::<Vec<_>>();
+        rules.sort_by_key(|(rule, _)| *rule);
+        for (rule, state) in rules {
+            fingerprint.write(rule);
+            fingerprint.write_u64(state.last.map_or(u64::MAX, |last| last as u64));
+            for index in state.remaining.iter() {
+                fingerprint.write_u64(*index as u64);
+            }
+            let mut uses = state.uses.iter().collect::<Vec<_>>();
+            uses.sort_by_key(|(index, _)| **index);
+            for (index, option_use) in uses {
+                fingerprint.write_u64(*index as u64);
+                fingerprint.write_u64(option_use.generation as u64);
+                fingerprint.write_u64(option_use.time.map_or(u64::MAX, f64::to_bits));
+            }
+        }
+    }
+
+    /// Gets the index of the option a rule chose most recently
+    pub fn last_choice(&self, rule: &str) -> Option<usize> {
+        self.rules.get(rule)?.last
+    }
+
+    /// Records the option a rule chose without using a selection mode - for example, when it followed a link group
+    pub fn record_choice(&mut self, rule: &str, index: usize) {
+        match self.rules.get_mut(rule) {
+            Some(state) => state.last = Some(index),
+            None => {
+                self.rules.entry(rule.to_string()).or_default().last = Some(index);
+            }
+        }
+    }
+
+    /// Records that an option was chosen at the current generation and time, so it's cooldown can be checked later
+    pub fn record_use(&mut self, rule: &str, index: usize) {
+        let state = match self.rules.get_mut(rule) {
+            Some(state) => state,
+            None => self.rules.entry(rule.to_string()).or_default(),
+        };
+        state.uses.insert(
+            index,
+            OptionUse {
+                generation: self.generation,
+                time: self.time,
+            },
+        );
+    }
+
+    /// Checks whether an option is still cooling down since the last time it was chosen.
+    /// If the time went backwards - for example, when loading a generator saved in a previous session - the cooldown is treated as expired.
+    pub fn is_cooling_down(&self, rule: &str, index: usize, cooldown: Cooldown) -> bool {
+        let Some(last_use) = self
+            .rules
+            .get(rule)
+            .and_then(|state| state.uses.get(&index))
+        else {
+            return false;
+        };
+        match cooldown {
+            Cooldown::Generations(generations) => {
+                self.generation <= last_use.generation.saturating_add(generations)
+            }
+            Cooldown::Seconds(seconds) => match (self.time, last_use.time) {
+                (Some(now), Some(used)) => now >= used && now < used + seconds,
+                _ => false,
+            },
+        }
+    }
+
+    /// Sets the current time in seconds, used for cooldowns measured in seconds
+    pub fn set_time(&mut self, seconds: f64) {
+        self.time = Some(seconds);
+    }
+
+    /// Gets the current time in seconds, if it was set
+    pub fn time(&self) -> Option<f64> {
+        self.time
+    }
+
+    /// Gets the index chosen for a link group during the current generation
+    pub fn linked_index(&self, link: &str) -> Option<usize> {
+        self.links.get(link).copied()
+    }
+
+    /// Records the index chosen for a link group, so the other rules in the group choose the same index until the generation completes
+    pub fn set_linked_index(&mut self, link: impl Into<String>, index: usize) {
+        self.links.insert(link.into(), index);
+    }
+
+    /// Checks whether an option was chosen during the current generation. Only choices recorded using `record_chosen` are tracked.
+    pub fn was_chosen(&self, rule: &str, index: usize) -> bool {
+        self.chosen
+            .get(rule)
+            .is_some_and(|chosen| chosen.contains(&index))
+    }
+
+    /// Records that an option was chosen during the current generation
+    pub fn record_chosen(&mut self, rule: &str, index: usize) {
+        match self.chosen.get_mut(rule) {
+            Some(chosen) => chosen.push(index),
+            None => {
+                self.chosen.insert(rule.to_string(), vec![index]);
+            }
+        }
+    }
+
+    /// Gets the number of generations that were completed using this state
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// Records that another generation was completed
+    pub fn complete_generation(&mut self) {
+        self.generation = self.generation.saturating_add(1);
+        self.links.clear();
+        self.chosen.clear();
+    }
+
+    /// Forgets all the previous selections, and resets the generation count
+    pub fn clear(&mut self) {
+        self.rules.clear();
+        self.links.clear();
+        self.chosen.clear();
+        self.generation = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn select_many(mode: SelectionMode, len: usize, count: usize, rng: &mut usize) -> Vec<usize> {
+        let mut state = SelectionState::default();
+        (0..count)
+            .filter_map(|_| state.select("rule", mode, len, rng))
+            .collect()
+    }
+
+    #[test]
+    pub fn random_selection_uses_the_rng_directly() {
+        assert_eq!(
+            select_many(SelectionMode::Random, 3, 3, &mut 1),
+            vec![1, 1, 1]
+        );
+    }
+
+    #[test]
+    pub fn weighted_selection_favours_heavier_options() {
+        let mut state = SelectionState::default();
+        let weights = [3, 0, 1];
+        let selected = (0..4)
+            .map(|mut number| {
+                state
+                    .select_weighted_where("rule", &weights, 3, |_| true, &mut number)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(selected, vec![0, 0, 0, 2]);
+        assert_eq!(
+            state.select_weighted_where("rule", &weights, 3, |index| index == 1, &mut 0),
+            None
+        );
+    }
+
+    #[test]
+    pub fn cycle_selection_loops_through_the_options_in_order() {
+        assert_eq!(
+            select_many(SelectionMode::Cycle, 3, 5, &mut 2),
+            vec![0, 1, 2, 0, 1]
+        );
+    }
+
+    #[test]
+    pub fn sequence_selection_stays_on_the_last_option() {
+        assert_eq!(
+            select_many(SelectionMode::Sequence, 3, 5, &mut 2),
+            vec![0, 1, 2, 2, 2]
+        );
+        let mut state = SelectionState::default();
+        let rng = &mut 0;
+        let early = |index: usize| index < 2;
+        assert_eq!(
+            state.select_where("a", SelectionMode::Sequence, 3, |index| index != 0, rng),
+            Some(1)
+        );
+        assert_eq!(
+            state.select_where("a", SelectionMode::Sequence, 3, early, rng),
+            Some(1)
+        );
+    }
+
+    #[test]
+    pub fn shuffle_selection_uses_every_option_before_repeating() {
+        let selected = select_many(SelectionMode::Shuffle, 4, 8, &mut 1);
+        let mut first_round = selected[0..4].to_vec();
+        let mut second_round = selected[4..8].to_vec();
+        first_round.sort();
+        second_round.sort();
+        assert_eq!(first_round, vec![0, 1, 2, 3]);
+        assert_eq!(second_round, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    pub fn no_immediate_repeat_never_selects_the_same_option_twice_in_a_row() {
+        let selected = select_many(SelectionMode::NoImmediateRepeat, 3, 6, &mut 1);
+        assert_eq!(selected, vec![1, 2, 1, 2, 1, 2]);
+    }
+
+    #[test]
+    pub fn selections_are_tracked_per_rule() {
+        let mut state = SelectionState::default();
+        let rng = &mut 0;
+        assert_eq!(state.select("a", SelectionMode::Cycle, 2, rng), Some(0));
+        assert_eq!(state.select("b", SelectionMode::Cycle, 2, rng), Some(0));
+        assert_eq!(state.select("a", SelectionMode::Cycle, 2, rng), Some(1));
+        assert_eq!(state.select("a", SelectionMode::Cycle, 0, rng), None);
+    }
+
+    #[test]
+    pub fn cooldowns_expire_after_generations_or_seconds() {
+        let mut state = SelectionState::default();
+        state.set_time(10.);
+        state.record_use("a", 1);
+        let generations = Cooldown::Generations(2);
+        let seconds = Cooldown::Seconds(5.);
+        assert!(state.is_cooling_down("a", 1, generations));
+        assert!(!state.is_cooling_down("a", 0, generations));
+        state.complete_generation();
+        state.complete_generation();
+        assert!(state.is_cooling_down("a", 1, generations));
+        state.complete_generation();
+        assert!(!state.is_cooling_down("a", 1, generations));
+
+        assert!(state.is_cooling_down("a", 1, seconds));
+        state.set_time(15.);
+        assert!(!state.is_cooling_down("a", 1, seconds));
+        state.set_time(2.);
+        assert!(!state.is_cooling_down("a", 1, seconds));
+    }
+
+    #[test]
+    pub fn selection_skips_ineligible_options() {
+        let mut state = SelectionState::default();
+        let rng = &mut 0;
+        let odd = |index: usize| index % 2 == 1;
+        assert_eq!(
+            state.select_where("a", SelectionMode::Cycle, 4, odd, rng),
+            Some(1)
+        );
+        assert_eq!(
+            state.select_where("a", SelectionMode::Cycle, 4, odd, rng),
+            Some(3)
+        );
+        assert_eq!(
+            state.select_where("a", SelectionMode::Random, 4, |_| false, rng),
+            None
+        );
+    }
+}
@@ -0,0 +1,25 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// This determines what happens when a variable assignment like `[name:#value#]` uses the same key as one of the grammar's base rules.
+/// Variables assigned by previous generations are not considered base rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ShadowingPolicy {
+    /// The variable replaces the base rule - permanently, in stateful generators. This is the default behaviour
+    #[default]
+    Allow,
+    /// The variable replaces the base rule, but a `GenerationIssue` is reported - and logged, when using bevy
+    Warn,
+    /// The variable only replaces the base rule until the end of the generation, after which the base rule is used again
+    Scoped,
+    /// The generation fails with a `GenerationIssue`, and none of it's variables are kept
+    Error,
+}
+
+#[cfg(feature = "serde")]
+impl ShadowingPolicy {
+    pub(crate) fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
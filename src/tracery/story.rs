@@ -0,0 +1,123 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use super::{GenerationIssue, StatefulStringGenerator, TraceryGrammar};
+use crate::generator::{GrammarRng, StatefulGenerator};
+
+/// This resource holds the stateful generator telling the game's overarching story - so one-off lines, like barks or journal
+/// entries, can be generated from any system using the `StoryGen` system param. It keeps it's variables between generations, like
+/// any other `StatefulStringGenerator`, and shares the `GrammarBlackboard` when the `GrammarBlackboardPlugin` is added.
+#[derive(Resource, Debug, Clone, Deref, DerefMut)]
+pub struct GlobalStoryGenerator(pub StatefulStringGenerator);
+
+impl GlobalStoryGenerator {
+    /// Creates the resource from a stateful generator
+    pub fn new(generator: StatefulStringGenerator) -> Self {
+        Self(generator)
+    }
+
+    /// Creates the resource from a grammar
+    pub fn from_grammar(grammar: TraceryGrammar) -> Self {
+        Self(StatefulStringGenerator::from_grammar(grammar))
+    }
+}
+
+/// This system param generates from the `GlobalStoryGenerator`, using a fork of the `GrammarRng` for each generation - so a one-off
+/// generation is a single line, and stays reproducible from the world seed. Both resources need to be inserted before it is used.
+///
+/// ```rust
+/// use bevy::prelude::*;
+/// use bevy_generative_grammars::{
+///     generator::GrammarRng,
+///     tracery::{GlobalStoryGenerator, StoryGen, TraceryGrammar},
+/// };
+///
+/// #[derive(Resource, Default)]
+/// struct Forecast(Option<String>);
+///
+/// fn announce_weather(mut story: StoryGen, mut forecast: ResMut<Forecast>) {
+///     forecast.0 = story.generate("weather");
+/// }
+///
+/// let grammar = TraceryGrammar::new(&[("weather", &["It's #sky#"]), ("sky", &["raining"])], None);
+/// let mut app = App::new();
+/// app.insert_resource(GlobalStoryGenerator::from_grammar(grammar))
+///     .insert_resource(GrammarRng::new(7))
+///     .init_resource::<Forecast>()
+///     .add_systems(Update, announce_weather);
+/// app.update();
+/// assert_eq!(
+///     app.world.resource::<Forecast>().0.as_deref(),
+///     Some("It's raining")
+/// );
+/// ```
+#[derive(SystemParam)]
+pub struct StoryGen<'w> {
+    generator: ResMut<'w, GlobalStoryGenerator>,
+    rng: ResMut<'w, GrammarRng>,
+}
+
+impl<'w> StoryGen<'w> {
+    /// Generates from the provided rule, or `None` if it couldn't be generated
+    pub fn generate(&mut self, rule: &str) -> Option<String> {
+        self.try_generate(rule).ok()
+    }
+
+    /// Generates from the provided rule, explaining why if it couldn't
+    pub fn try_generate(&mut self, rule: &str) -> Result<String, GenerationIssue> {
+        let mut rng = self.rng.fork();
+        self.generator.try_generate_at(rule, &mut rng)
+    }
+
+    /// Generates from the grammar's starting point, or `None` if it couldn't be generated
+    pub fn generate_story(&mut self) -> Option<String> {
+        let mut rng = self.rng.fork();
+        self.generator.generate(&mut rng)
+    }
+
+    /// Gets the global story generator
+    pub fn generator(&self) -> &StatefulStringGenerator {
+        &self.generator
+    }
+
+    /// Gets the global story generator, so it's variables and tokens can be changed
+    pub fn generator_mut(&mut self) -> &mut StatefulStringGenerator {
+        &mut self.generator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Resource, Default)]
+    struct Lines(Vec<String>);
+
+    fn tell(mut story: StoryGen, mut lines: ResMut<Lines>) {
+        lines.0.extend(story.generate("greeting"));
+        lines.0.extend(story.generate("missing"));
+        lines.0.extend(story.generate_story());
+    }
+
+    #[test]
+    pub fn story_gen_generates_from_the_global_generator() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["#hero# sets out"]),
+                ("greeting", &["[hero:Ana]Hello, #hero#"]),
+            ],
+            None,
+        );
+        let mut app = App::new();
+        app.insert_resource(GlobalStoryGenerator::from_grammar(grammar))
+            .insert_resource(GrammarRng::new(3))
+            .init_resource::<Lines>()
+            .add_systems(Update, tell);
+        app.update();
+
+        assert_eq!(
+            app.world.resource::<Lines>().0,
+            vec!["Hello, Ana".to_string(), "Ana sets out".to_string()]
+        );
+        assert_eq!(app.world.resource::<GrammarRng>().forks(), 3);
+    }
+}
@@ -0,0 +1,315 @@
+#[cfg(feature = "bevy")]
+use bevy::prelude::{Event, Resource};
+#[cfg(feature = "bevy")]
+use bevy::utils::HashMap;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(not(feature = "bevy"))]
+use std::collections::HashMap;
+
+use super::{GenerationIssue, StringGenerator, TraceryGrammar};
+use crate::generator::SeededRng;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// A hasher whose output only depends on the values written to it, so fingerprints match between builds and platforms
+pub(crate) struct Fingerprint(u64);
+
+impl Fingerprint {
+    pub(crate) fn new() -> Self {
+        Self(FNV_OFFSET)
+    }
+
+    fn write_bytes(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        for byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    /// Writes some text, followed by a separator so neighbouring values can't run into each other
+    pub(crate) fn write(&mut self, text: &str) {
+        self.write_bytes(text.bytes().chain([0xff]));
+    }
+
+    pub(crate) fn write_u64(&mut self, value: u64) {
+        self.write_bytes(value.to_le_bytes());
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl TraceryGrammar {
+    /// This identifies the grammar by it's content - it's rules, aliases, starting points and every setting that changes what it generates,
+    /// along with the names of it's custom modifiers - so peers can check they hold the same grammar before regenerating text from it.
+    /// The fingerprint is the same on every platform.
+    pub fn fingerprint(&self) -> u64 {
+        let mut fingerprint = Fingerprint::new();
+        fingerprint.write(&self.starting_point);
+        fingerprint.write(&self.separator);
+        if let Some(locale) = &self.locale {
+            fingerprint.write(locale);
+        }
+        for (key, weight) in self.starting_points.iter() {
+            fingerprint.write(key);
+            fingerprint.write_u64(*weight as u64);
+        }
+        let mut rules = self.rules.iter().collect::<Vec<_>>();
+        rules.sort_by_key(|(rule, _)| *rule);
+        for (rule, options) in rules {
+            fingerprint.write(rule);
+            for option in options {
+                fingerprint.write(option);
+            }
+            if let Some(settings) = self.settings.get(rule) {
+                fingerprint.write(&format!("{settings:?}"));
+            }
+        }
+        let mut aliases = self.aliases.iter().collect::<Vec<_>>();
+        aliases.sort();
+        for (alias, rule) in aliases {
+            fingerprint.write(alias);
+            fingerprint.write(rule);
+        }
+        // Every other setting that changes what the grammar generates is included as well
+        for exclusion in self.exclusions.iter() {
+            fingerprint.write(&format!("{exclusion:?}"));
+        }
+        fingerprint.write(&format!("{:?}", self.max_depth));
+        fingerprint.write(&format!("{:?}", self.join));
+        fingerprint.write(&format!("{:?}", self.budget));
+        fingerprint.write(&format!("{:?}", self.assertions));
+        fingerprint.write(&format!("{:?}", self.shadowing));
+        fingerprint.write(&format!("{:?}", self.sandbox));
+        let mut modifiers = self.modifiers.keys().collect::<Vec<_>>();
+        modifiers.sort();
+        for modifier in modifiers {
+            fingerprint.write(modifier);
+        }
+        fingerprint.finish()
+    }
+}
+
+/// This describes a single generation, so it can be replicated to other peers instead of the text it generates. Every peer
+/// holding the same grammar regenerates identical text from it, using a `SeededRng` created from the seed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "bevy", derive(Event))]
+pub struct GenerationRequest {
+    /// The fingerprint of the grammar to generate from
+    pub grammar: u64,
+    /// The rule to generate from
+    pub key: String,
+    /// The seed used for the generation
+    pub seed: u64,
+}
+
+impl GenerationRequest {
+    /// Creates a request to generate from the provided rule of a grammar
+    pub fn new(grammar: &TraceryGrammar, key: impl Into<String>, seed: u64) -> Self {
+        Self {
+            grammar: grammar.fingerprint(),
+            key: key.into(),
+            seed,
+        }
+    }
+
+    /// Generates the requested text, failing if the grammar doesn't match the one it was requested from
+    pub fn generate(&self, grammar: &TraceryGrammar) -> Result<String, SyncError> {
+        if grammar.fingerprint() != self.grammar {
+            return Err(SyncError::UnknownGrammar(self.grammar));
+        }
+        StringGenerator::try_generate_at(&self.key, grammar, &mut SeededRng::new(self.seed))
+            .map_err(SyncError::Generation)
+    }
+}
+
+/// This is a problem encountered while regenerating a `GenerationRequest`
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyncError {
+    /// No grammar with the requested fingerprint is available - usually because peers hold different versions of it
+    UnknownGrammar(u64),
+    /// The grammar couldn't generate from the requested rule
+    Generation(GenerationIssue),
+}
+
+impl std::fmt::Display for SyncError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncError::UnknownGrammar(fingerprint) => {
+                write!(f, "no grammar has the fingerprint {fingerprint:016x}")
+            }
+            SyncError::Generation(issue) => write!(f, "{issue}"),
+        }
+    }
+}
+
+impl std::error::Error for SyncError {}
+
+/// This sends generation requests between peers. Implement it on top of your networking library - such as sending the
+/// requests as messages through `bevy_replicon` - so the `SyncedGenerator` doesn't depend on any of them.
+pub trait GenerationTransport {
+    /// Sends a request to every other peer
+    fn send(&mut self, request: &GenerationRequest);
+    /// Takes the requests received from other peers since the last call
+    fn receive(&mut self) -> Vec<GenerationRequest>;
+}
+
+/// This keeps generation in sync between peers. Generations are made locally and sent to the other peers as a
+/// `GenerationRequest` - a grammar fingerprint, key and seed - which they regenerate identical text from, rather than
+/// the full text being sent over the wire.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "bevy", derive(Resource))]
+pub struct SyncedGenerator<T: GenerationTransport> {
+    grammars: HashMap<u64, TraceryGrammar>,
+    transport: T,
+}
+
+impl<T: GenerationTransport> SyncedGenerator<T> {
+    /// Creates a generator sending it's requests using the provided transport
+    pub fn new(transport: T) -> Self {
+        Self {
+            grammars: HashMap::default(),
+            transport,
+        }
+    }
+
+    /// Registers a grammar, so requests for it can be generated. Returns it's fingerprint.
+    pub fn register(&mut self, grammar: TraceryGrammar) -> u64 {
+        let fingerprint = grammar.fingerprint();
+        self.grammars.insert(fingerprint, grammar);
+        fingerprint
+    }
+
+    /// Gets the registered grammar with the provided fingerprint
+    pub fn grammar(&self, fingerprint: u64) -> Option<&TraceryGrammar> {
+        self.grammars.get(&fingerprint)
+    }
+
+    /// Gets the transport used to send requests
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Generates from a registered grammar, sending the request to the other peers once it succeeds
+    pub fn generate(&mut self, grammar: u64, key: &str, seed: u64) -> Result<String, SyncError> {
+        let request = GenerationRequest {
+            grammar,
+            key: key.to_string(),
+            seed,
+        };
+        let text = self.regenerate(&request)?;
+        self.transport.send(&request);
+        Ok(text)
+    }
+
+    /// Regenerates a request from one of the registered grammars, without sending it
+    pub fn regenerate(&self, request: &GenerationRequest) -> Result<String, SyncError> {
+        let grammar = self
+            .grammars
+            .get(&request.grammar)
+            .ok_or(SyncError::UnknownGrammar(request.grammar))?;
+        StringGenerator::try_generate_at(&request.key, grammar, &mut SeededRng::new(request.seed))
+            .map_err(SyncError::Generation)
+    }
+
+    /// Regenerates the requests received from the other peers, in the order they were received
+    pub fn receive(&mut self) -> Vec<(GenerationRequest, Result<String, SyncError>)> {
+        self.transport
+            .receive()
+            .into_iter()
+            .map(|request| {
+                let text = self.regenerate(&request);
+                (request, text)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::tracery::{Exclusion, GenerationBudget, JoinMode};
+
+    /// A transport connecting peers within the same process
+    #[derive(Default, Clone)]
+    struct SharedChannel(Arc<Mutex<Vec<GenerationRequest>>>);
+
+    impl GenerationTransport for SharedChannel {
+        fn send(&mut self, request: &GenerationRequest) {
+            self.0.lock().unwrap().push(request.clone());
+        }
+
+        fn receive(&mut self) -> Vec<GenerationRequest> {
+            std::mem::take(&mut *self.0.lock().unwrap())
+        }
+    }
+
+    fn grammar() -> TraceryGrammar {
+        TraceryGrammar::new(
+            &[
+                ("origin", &["The #loot# glows"]),
+                ("loot", &["sword", "shield", "crown", "ring", "amulet"]),
+            ],
+            None,
+        )
+    }
+
+    #[test]
+    pub fn peers_regenerate_identical_text_from_requests() {
+        let channel = SharedChannel::default();
+        let mut host = SyncedGenerator::new(channel.clone());
+        let mut client = SyncedGenerator::new(channel);
+        let fingerprint = host.register(grammar());
+        assert_eq!(client.register(grammar()), fingerprint);
+
+        let texts = (0..4)
+            .map(|seed| host.generate(fingerprint, "origin", seed).unwrap())
+            .collect::<Vec<_>>();
+        let received = client.receive();
+        assert_eq!(received.len(), 4);
+        for ((request, text), expected) in received.into_iter().zip(texts) {
+            assert_eq!(request.grammar, fingerprint);
+            assert_eq!(text.unwrap(), expected);
+        }
+    }
+
+    #[test]
+    pub fn requests_for_other_grammars_are_rejected() {
+        let request = GenerationRequest::new(&grammar(), "origin", 3);
+        let changed = grammar().with_weights("loot", [1, 2]);
+        assert_ne!(changed.fingerprint(), request.grammar);
+        assert_eq!(
+            request.generate(&changed),
+            Err(SyncError::UnknownGrammar(request.grammar))
+        );
+        assert!(request.generate(&grammar()).is_ok());
+    }
+
+    #[test]
+    pub fn settings_that_change_generation_change_the_fingerprint() {
+        let fingerprint = grammar().fingerprint();
+        assert_eq!(grammar().fingerprint(), fingerprint);
+        assert_ne!(grammar().with_max_depth(3).fingerprint(), fingerprint);
+        assert_ne!(
+            grammar().with_join_mode(JoinMode::Smart).fingerprint(),
+            fingerprint
+        );
+        assert_ne!(
+            grammar()
+                .with_budget(GenerationBudget::default())
+                .fingerprint(),
+            fingerprint
+        );
+        assert_ne!(
+            grammar()
+                .with_exclusion(Exclusion::new("loot", "sword", "loot", "ring"))
+                .fingerprint(),
+            fingerprint
+        );
+    }
+}
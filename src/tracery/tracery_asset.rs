@@ -1,14 +1,24 @@
 use bevy::prelude::*;
 
-use super::TraceryGrammar;
+use super::{
+    tracery_commands::{attach_loaded_generators, resolve_generator_handles},
+    LocalizedTraceryGrammar, OptionSources, TraceryGrammar,
+};
 
-/// The Tracery Asset
+/// The Tracery Asset. It also adds the generators requested using `spawn_generator` once their grammars load, and resolves
+/// the grammars of generators created using `StatefulStringGenerator::from_handle`.
 #[derive(Default)]
 pub struct TraceryAssetPlugin {
     #[cfg(feature = "json")]
     json: Option<&'static [&'static str]>,
+    #[cfg(feature = "json")]
+    localized_json: Option<&'static [&'static str]>,
     #[cfg(feature = "ron")]
     ron: Option<&'static [&'static str]>,
+    #[cfg(feature = "ron")]
+    ron_definitions: Option<&'static [&'static str]>,
+    #[cfg(feature = "ron")]
+    localized_ron: Option<&'static [&'static str]>,
     #[cfg(feature = "msgpack")]
     msgpack: Option<&'static [&'static str]>,
     #[cfg(feature = "toml")]
@@ -23,20 +33,45 @@ impl TraceryAssetPlugin {
         Self::default()
     }
 
-    /// Enables JSON support - with the provided extensions
+    /// Enables JSON support - with the provided extensions. Options written as `@file(creatures.txt)` are replaced with the
+    /// word list in that file, read relative to the grammar.
     #[cfg(feature = "json")]
     pub fn with_json(mut self, extensions: &'static [&'static str]) -> Self {
         self.json = Some(extensions);
         self
     }
 
-    /// Enables RON support - with the provided extensions
+    /// Enables loading a `LocalizedTraceryGrammar` from JSON - with the provided extensions, like `grammar.l10n.json`.
+    /// A single file holds every translation of the grammar.
+    #[cfg(feature = "json")]
+    pub fn with_localized_json(mut self, extensions: &'static [&'static str]) -> Self {
+        self.localized_json = Some(extensions);
+        self
+    }
+
+    /// Enables RON support - with the provided extensions. Like JSON grammars, these can reference word lists using `@file(...)`.
     #[cfg(feature = "ron")]
     pub fn with_ron(mut self, extensions: &'static [&'static str]) -> Self {
         self.ron = Some(extensions);
         self
     }
 
+    /// Enables loading grammars written as a RON `GrammarDefinition` - with the provided extensions, like `grammar.ron`.
+    /// These are converted into a `TraceryGrammar` as they load, and can reference word lists using `@file(...)`.
+    #[cfg(feature = "ron")]
+    pub fn with_ron_definitions(mut self, extensions: &'static [&'static str]) -> Self {
+        self.ron_definitions = Some(extensions);
+        self
+    }
+
+    /// Enables loading a `LocalizedTraceryGrammar` from RON - with the provided extensions, like `grammar.l10n.ron`.
+    /// A single file holds every translation of the grammar.
+    #[cfg(feature = "ron")]
+    pub fn with_localized_ron(mut self, extensions: &'static [&'static str]) -> Self {
+        self.localized_ron = Some(extensions);
+        self
+    }
+
     /// Enables `MessagePack` support - with the provided extensions
     #[cfg(feature = "msgpack")]
     pub fn with_msgpack(mut self, extensions: &'static [&'static str]) -> Self {
@@ -61,13 +96,55 @@ impl TraceryAssetPlugin {
 
 impl Plugin for TraceryAssetPlugin {
     fn build(&self, app: &mut App) {
+        app.add_systems(
+            PreUpdate,
+            (attach_loaded_generators, resolve_generator_handles),
+        )
+        .init_resource::<OptionSources>();
+        #[cfg(any(feature = "json", feature = "ron"))]
+        let sources = app.world.resource::<OptionSources>().clone();
+        // Every grammar loader can read the grammars it's grammar extends, whichever format they are written in
+        #[cfg(any(feature = "json", feature = "ron"))]
+        let mut parsers: Vec<grammar_loader::GrammarParser> = vec![];
+        #[cfg(feature = "json")]
+        parsers.extend(self.json.map(|extensions| grammar_loader::GrammarParser {
+            extensions,
+            parse: grammar_loader::parse_json,
+        }));
+        #[cfg(feature = "ron")]
+        parsers.extend(self.ron.map(|extensions| grammar_loader::GrammarParser {
+            extensions,
+            parse: grammar_loader::parse_ron,
+        }));
+        #[cfg(feature = "ron")]
+        parsers.extend(
+            self.ron_definitions
+                .map(|extensions| grammar_loader::GrammarParser {
+                    extensions,
+                    parse: grammar_loader::parse_ron_definition,
+                }),
+        );
+        #[cfg(any(feature = "json", feature = "ron"))]
+        for parser in parsers.iter() {
+            app.init_asset::<TraceryGrammar>().register_asset_loader(
+                grammar_loader::GrammarLoader {
+                    parser: *parser,
+                    parsers: parsers.clone(),
+                    sources: sources.clone(),
+                },
+            );
+        }
         #[cfg(feature = "json")]
-        if let Some(ext) = self.json {
-            app.add_plugins(bevy_common_assets::json::JsonAssetPlugin::<TraceryGrammar>::new(ext));
+        if let Some(ext) = self.localized_json {
+            app.add_plugins(bevy_common_assets::json::JsonAssetPlugin::<
+                LocalizedTraceryGrammar,
+            >::new(ext));
         }
         #[cfg(feature = "ron")]
-        if let Some(ext) = self.ron {
-            app.add_plugins(bevy_common_assets::ron::RonAssetPlugin::<TraceryGrammar>::new(ext));
+        if let Some(ext) = self.localized_ron {
+            app.add_plugins(bevy_common_assets::ron::RonAssetPlugin::<
+                LocalizedTraceryGrammar,
+            >::new(ext));
         }
         #[cfg(feature = "msgpack")]
         if let Some(ext) = self.msgpack {
@@ -85,3 +162,286 @@ impl Plugin for TraceryAssetPlugin {
         }
     }
 }
+
+#[cfg(any(feature = "json", feature = "ron"))]
+mod grammar_loader {
+    use std::path::{Component, Path, PathBuf};
+
+    use bevy::{
+        asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+        utils::{BoxedFuture, HashMap},
+    };
+
+    use crate::tracery::{GrammarError, OptionSources, TraceryGrammar};
+
+    /// Loads grammars using the provided parser, and then replaces their `@file(...)` options with the word lists they reference.
+    /// Word lists are read relative to the grammar, and changing one reloads the grammar when hot reloading is enabled.
+    /// Options written as `@source(...)` are then fetched from the `OptionSources` resource. Grammars that extend another
+    /// are then layered over it, once it is loaded as well.
+    pub(super) struct GrammarLoader {
+        pub(super) parser: GrammarParser,
+        pub(super) parsers: Vec<GrammarParser>,
+        pub(super) sources: OptionSources,
+    }
+
+    /// Parses grammars written in one of the supported formats, from files with the provided extensions
+    #[derive(Clone, Copy)]
+    pub(super) struct GrammarParser {
+        pub(super) extensions: &'static [&'static str],
+        pub(super) parse: fn(&[u8]) -> Result<TraceryGrammar, String>,
+    }
+
+    impl GrammarParser {
+        fn parses(&self, path: &Path) -> bool {
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy())
+                .unwrap_or_default();
+            self.extensions
+                .iter()
+                .any(|extension| name.ends_with(&format!(".{extension}")))
+        }
+    }
+
+    /// This is a problem encountered while loading a grammar
+    #[derive(Debug)]
+    pub enum GrammarLoadError {
+        /// The file couldn't be read
+        Io(std::io::Error),
+        /// The file isn't a valid grammar
+        Parse(String),
+        /// One of the grammar's word lists couldn't be loaded
+        WordList(GrammarError),
+        /// One of the grammar's option sources provided no options, and the rule had nothing to fall back on
+        OptionSource(GrammarError),
+        /// The grammar this one extends couldn't be loaded
+        Parent(String),
+        /// The grammars this one extends lead back to a grammar already in the chain, listed from this grammar onwards
+        ExtendsCycle(Vec<String>),
+    }
+
+    impl std::fmt::Display for GrammarLoadError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                GrammarLoadError::Io(error) => write!(f, "couldn't read the grammar: {error}"),
+                GrammarLoadError::Parse(error) => write!(f, "couldn't parse the grammar: {error}"),
+                GrammarLoadError::WordList(error) | GrammarLoadError::OptionSource(error) => {
+                    write!(f, "{error}")
+                }
+                GrammarLoadError::Parent(error) => {
+                    write!(f, "couldn't load the grammar it extends: {error}")
+                }
+                GrammarLoadError::ExtendsCycle(chain) => {
+                    write!(
+                        f,
+                        "the grammars it extends form a cycle: {}",
+                        chain.join(" -> ")
+                    )
+                }
+            }
+        }
+    }
+
+    impl std::error::Error for GrammarLoadError {}
+
+    impl AssetLoader for GrammarLoader {
+        type Asset = TraceryGrammar;
+        type Settings = ();
+        type Error = GrammarLoadError;
+
+        fn load<'a>(
+            &'a self,
+            reader: &'a mut Reader,
+            _settings: &'a (),
+            load_context: &'a mut LoadContext,
+        ) -> BoxedFuture<'a, Result<TraceryGrammar, GrammarLoadError>> {
+            Box::pin(async move {
+                let mut bytes = vec![];
+                reader
+                    .read_to_end(&mut bytes)
+                    .await
+                    .map_err(GrammarLoadError::Io)?;
+                let mut grammar = (self.parser.parse)(&bytes).map_err(GrammarLoadError::Parse)?;
+                let directory = load_context
+                    .path()
+                    .parent()
+                    .map(Path::to_path_buf)
+                    .unwrap_or_default();
+                let mut lists = HashMap::default();
+                for path in grammar.word_list_paths() {
+                    // Reading the lists through the load context makes them dependencies, so editing them reloads the grammar
+                    if let Ok(bytes) = load_context.read_asset_bytes(directory.join(&path)).await {
+                        lists.insert(path, String::from_utf8_lossy(&bytes).into_owned());
+                    }
+                }
+                grammar
+                    .resolve_word_lists(|path| lists.remove(path))
+                    .map_err(GrammarLoadError::WordList)?;
+                grammar
+                    .load_option_sources(&self.sources)
+                    .await
+                    .map_err(GrammarLoadError::OptionSource)?;
+                let Some(parent) = grammar.extends().map(|parent| directory.join(parent)) else {
+                    return Ok(grammar);
+                };
+                self.check_extends_cycle(load_context, &parent).await?;
+                // Loading the parent directly makes it a dependency too, so editing it reloads the grammar
+                let parent = load_context
+                    .load_direct(parent)
+                    .await
+                    .map_err(|error| GrammarLoadError::Parent(error.to_string()))?
+                    .take::<TraceryGrammar>()
+                    .ok_or_else(|| {
+                        GrammarLoadError::Parent("it isn't a tracery grammar".to_string())
+                    })?;
+                Ok(TraceryGrammar::with_overrides(&parent, &grammar))
+            })
+        }
+
+        fn extensions(&self) -> &[&str] {
+            self.parser.extensions
+        }
+    }
+
+    impl GrammarLoader {
+        /// Follows the chain of grammars the loading grammar extends, failing if it leads back to a grammar already in the chain -
+        /// since loading it would never finish. The chain ends early at grammars none of the loaders can read, which fail to load anyway.
+        async fn check_extends_cycle(
+            &self,
+            load_context: &mut LoadContext<'_>,
+            parent: &Path,
+        ) -> Result<(), GrammarLoadError> {
+            let mut chain = vec![normalize(load_context.path())];
+            let mut next = Some(normalize(parent));
+            while let Some(path) = next.take() {
+                if chain.contains(&path) {
+                    chain.push(path);
+                    return Err(GrammarLoadError::ExtendsCycle(
+                        chain
+                            .iter()
+                            .map(|path| path.display().to_string())
+                            .collect(),
+                    ));
+                }
+                let Some(parser) = self.parsers.iter().find(|parser| parser.parses(&path)) else {
+                    break;
+                };
+                let Ok(bytes) = load_context.read_asset_bytes(path.clone()).await else {
+                    break;
+                };
+                let Ok(grammar) = (parser.parse)(&bytes) else {
+                    break;
+                };
+                let directory = path.parent().map(Path::to_path_buf).unwrap_or_default();
+                next = grammar
+                    .extends()
+                    .map(|parent| normalize(&directory.join(parent)));
+                chain.push(path);
+            }
+            Ok(())
+        }
+    }
+
+    /// Resolves the `.` and `..` components of an asset path, so the same file is always written the same way
+    fn normalize(path: &Path) -> PathBuf {
+        let mut normalized = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    normalized.pop();
+                }
+                component => normalized.push(component),
+            }
+        }
+        normalized
+    }
+
+    /// Parses a `TraceryGrammar` written in JSON
+    #[cfg(feature = "json")]
+    pub(super) fn parse_json(bytes: &[u8]) -> Result<TraceryGrammar, String> {
+        TraceryGrammar::from_json_slice(bytes).map_err(|error| error.to_string())
+    }
+
+    /// Parses a `TraceryGrammar` written in RON
+    #[cfg(feature = "ron")]
+    pub(super) fn parse_ron(bytes: &[u8]) -> Result<TraceryGrammar, String> {
+        TraceryGrammar::from_ron_bytes(bytes).map_err(|error| error.to_string())
+    }
+
+    /// Parses a `GrammarDefinition` written in RON, converting it into a `TraceryGrammar`
+    #[cfg(feature = "ron")]
+    pub(super) fn parse_ron_definition(bytes: &[u8]) -> Result<TraceryGrammar, String> {
+        ron::de::from_bytes::<crate::tracery::GrammarDefinition>(bytes)
+            .map(Into::into)
+            .map_err(|error| error.to_string())
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+    use bevy::asset::LoadState;
+
+    use super::*;
+
+    /// Loads a grammar from a directory of grammar files, updating the app until it either loads or fails
+    fn load_state(name: &str, files: &[(&str, &str)], path: &str) -> Option<LoadState> {
+        let directory = std::env::temp_dir().join(format!(
+            "generative-grammars-assets-{name}-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        for (path, content) in files {
+            std::fs::write(directory.join(path), content).unwrap();
+        }
+        let mut app = App::new();
+        app.add_plugins((
+            bevy::core::TaskPoolPlugin::default(),
+            AssetPlugin {
+                file_path: directory.display().to_string(),
+                ..Default::default()
+            },
+            TraceryAssetPlugin::new().with_json(&["json"]),
+        ));
+        let handle: Handle<TraceryGrammar> =
+            app.world.resource::<AssetServer>().load(path.to_string());
+        for _ in 0..500 {
+            app.update();
+            match app.world.resource::<AssetServer>().get_load_state(&handle) {
+                Some(LoadState::Loading | LoadState::NotLoaded) => {
+                    std::thread::sleep(std::time::Duration::from_millis(2))
+                }
+                state => return state,
+            }
+        }
+        None
+    }
+
+    #[test]
+    pub fn grammars_extending_each_other_in_a_cycle_fail_to_load() {
+        let files = [
+            ("base.json", r#"{"origin": "Hello"}"#),
+            (
+                "child.json",
+                r#"{"rules": {"name": "Mia"}, "extends": "./base.json"}"#,
+            ),
+            (
+                "a.json",
+                r#"{"rules": {"origin": "A"}, "extends": "b.json"}"#,
+            ),
+            (
+                "b.json",
+                r#"{"rules": {"origin": "B"}, "extends": "./a.json"}"#,
+            ),
+        ];
+        assert_eq!(
+            load_state("extends", &files, "child.json"),
+            Some(LoadState::Loaded)
+        );
+        assert_eq!(
+            load_state("extends", &files, "a.json"),
+            Some(LoadState::Failed)
+        );
+    }
+}
@@ -0,0 +1,355 @@
+use bevy::{
+    asset::{AssetPath, LoadState},
+    ecs::{system::EntityCommands, world::EntityWorldMut},
+    prelude::*,
+};
+
+#[cfg(test)]
+use super::GenerationIssue;
+use super::{SandboxPolicy, StatefulStringGenerator, TraceryGrammar};
+use crate::generator::StatefulGenerator;
+
+/// This component waits for a grammar asset to load, and is then replaced by a `StatefulStringGenerator` using it,
+/// along with a `GeneratorReady` marker. It is usually added using `spawn_generator` or `insert_generator`,
+/// and requires the `TraceryAssetPlugin`.
+#[derive(Component, Debug, Clone)]
+pub struct PendingGenerator {
+    handle: Handle<TraceryGrammar>,
+    starting_point: String,
+    sandbox: Option<SandboxPolicy>,
+}
+
+impl PendingGenerator {
+    /// Waits for the provided grammar, generating from `starting_point` by default once it is loaded
+    pub fn new(handle: Handle<TraceryGrammar>, starting_point: impl Into<String>) -> Self {
+        Self {
+            handle,
+            starting_point: starting_point.into(),
+            sandbox: None,
+        }
+    }
+
+    /// Checks the grammar against a sandbox policy once it is loaded, and limits the generator using it. If the grammar breaks the policy,
+    /// an error is logged and no generator is added - so grammars from mods can't exhaust memory or tamper with the world.
+    pub fn with_sandbox(mut self, policy: SandboxPolicy) -> Self {
+        self.sandbox = Some(policy);
+        self
+    }
+}
+
+/// This marks entities whose `StatefulStringGenerator` was added once it's grammar loaded, so systems can query for
+/// `Added<GeneratorReady>` to start using it.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GeneratorReady;
+
+/// This adds `spawn_generator` to `Commands`, collapsing the boilerplate of loading a grammar asset and waiting for it before spawning a generator
+pub trait SpawnGeneratorExt<'w, 's> {
+    /// Spawns an entity that gets a `StatefulStringGenerator` once the grammar at the provided path is loaded, generating from `starting_point` by default.
+    /// The entity also gets a `GeneratorReady` marker at the same time.
+    fn spawn_generator<'a>(
+        &'a mut self,
+        path: impl Into<AssetPath<'static>>,
+        starting_point: impl Into<String>,
+    ) -> EntityCommands<'w, 's, 'a>;
+
+    /// Spawns an entity like `spawn_generator`, but checks the grammar against a sandbox policy once it is loaded.
+    /// See `PendingGenerator::with_sandbox`.
+    fn spawn_sandboxed_generator<'a>(
+        &'a mut self,
+        path: impl Into<AssetPath<'static>>,
+        starting_point: impl Into<String>,
+        policy: SandboxPolicy,
+    ) -> EntityCommands<'w, 's, 'a>;
+}
+
+impl<'w, 's> SpawnGeneratorExt<'w, 's> for Commands<'w, 's> {
+    fn spawn_generator<'a>(
+        &'a mut self,
+        path: impl Into<AssetPath<'static>>,
+        starting_point: impl Into<String>,
+    ) -> EntityCommands<'w, 's, 'a> {
+        let mut entity = self.spawn_empty();
+        entity.insert_generator(path, starting_point);
+        entity
+    }
+
+    fn spawn_sandboxed_generator<'a>(
+        &'a mut self,
+        path: impl Into<AssetPath<'static>>,
+        starting_point: impl Into<String>,
+        policy: SandboxPolicy,
+    ) -> EntityCommands<'w, 's, 'a> {
+        let mut entity = self.spawn_empty();
+        entity.insert_sandboxed_generator(path, starting_point, policy);
+        entity
+    }
+}
+
+/// This adds `insert_generator` to `EntityCommands`, for adding a generator using a grammar asset to an existing entity
+pub trait InsertGeneratorExt {
+    /// Loads the grammar at the provided path, and adds a `StatefulStringGenerator` using it to the entity once it is loaded -
+    /// generating from `starting_point` by default. The entity also gets a `GeneratorReady` marker at the same time.
+    fn insert_generator(
+        &mut self,
+        path: impl Into<AssetPath<'static>>,
+        starting_point: impl Into<String>,
+    ) -> &mut Self;
+
+    /// Adds a generator like `insert_generator`, but checks the grammar against a sandbox policy once it is loaded.
+    /// See `PendingGenerator::with_sandbox`.
+    fn insert_sandboxed_generator(
+        &mut self,
+        path: impl Into<AssetPath<'static>>,
+        starting_point: impl Into<String>,
+        policy: SandboxPolicy,
+    ) -> &mut Self;
+}
+
+impl<'w, 's, 'a> InsertGeneratorExt for EntityCommands<'w, 's, 'a> {
+    fn insert_generator(
+        &mut self,
+        path: impl Into<AssetPath<'static>>,
+        starting_point: impl Into<String>,
+    ) -> &mut Self {
+        insert_pending_generator(self, path.into(), starting_point.into(), None)
+    }
+
+    fn insert_sandboxed_generator(
+        &mut self,
+        path: impl Into<AssetPath<'static>>,
+        starting_point: impl Into<String>,
+        policy: SandboxPolicy,
+    ) -> &mut Self {
+        insert_pending_generator(self, path.into(), starting_point.into(), Some(policy))
+    }
+}
+
+fn insert_pending_generator<'b, 'w, 's, 'a>(
+    entity: &'b mut EntityCommands<'w, 's, 'a>,
+    path: AssetPath<'static>,
+    starting_point: String,
+    sandbox: Option<SandboxPolicy>,
+) -> &'b mut EntityCommands<'w, 's, 'a> {
+    entity.add(move |mut entity: EntityWorldMut| {
+        let handle = entity.world().resource::<AssetServer>().load(path);
+        let pending = PendingGenerator::new(handle, starting_point);
+        entity.insert(PendingGenerator { sandbox, ..pending });
+    })
+}
+
+impl StatefulStringGenerator {
+    /// This creates a stateful generator for a grammar asset, which it uses once the asset is loaded. Until then, generating
+    /// fails with `GenerationIssue::NotReady`. The `TraceryAssetPlugin` resolves the asset for generators on entities and the
+    /// generator resource, adding a `GeneratorReady` marker to the entity once it does - so there's no need to poll the asset yourself.
+    pub fn from_handle(handle: Handle<TraceryGrammar>) -> Self {
+        let mut generator = Self::from_grammar(TraceryGrammar::empty());
+        generator.pending = Some(handle);
+        generator
+    }
+
+    /// This checks whether the generator is ready to generate - which it isn't while it waits for it's grammar asset to load
+    pub fn is_ready(&self) -> bool {
+        self.pending.is_none()
+    }
+
+    /// This gets the grammar asset the generator is waiting for, if it is still waiting
+    pub fn pending_handle(&self) -> Option<&Handle<TraceryGrammar>> {
+        self.pending.as_ref()
+    }
+
+    /// Uses the grammar asset the generator is waiting for if it has loaded, returning whether it did.
+    /// Failed loads are logged, and the generator keeps waiting - so it can still pick up the asset if it is reloaded.
+    fn resolve_pending(
+        &mut self,
+        grammars: &Assets<TraceryGrammar>,
+        asset_server: &AssetServer,
+        owner: &dyn std::fmt::Debug,
+    ) -> bool {
+        let Some(handle) = self.pending.as_ref() else {
+            return false;
+        };
+        match grammars.get(handle) {
+            Some(grammar) => {
+                self.set_grammar(grammar);
+                self.pending = None;
+                true
+            }
+            None => {
+                if asset_server.get_load_state(handle) == Some(LoadState::Failed) {
+                    error!(
+                        "Couldn't load the grammar for {owner:?} from {:?}",
+                        handle.path()
+                    );
+                }
+                false
+            }
+        }
+    }
+}
+
+pub(crate) fn resolve_generator_handles(
+    mut commands: Commands,
+    mut generators: Query<(Entity, &mut StatefulStringGenerator)>,
+    resource: Option<ResMut<StatefulStringGenerator>>,
+    grammars: Res<Assets<TraceryGrammar>>,
+    asset_server: Res<AssetServer>,
+) {
+    for (entity, mut generator) in generators.iter_mut() {
+        if !generator.is_ready() && generator.resolve_pending(&grammars, &asset_server, &entity) {
+            commands.entity(entity).insert(GeneratorReady);
+        }
+    }
+    if let Some(mut generator) = resource {
+        if !generator.is_ready() {
+            generator.resolve_pending(&grammars, &asset_server, &"the generator resource");
+        }
+    }
+}
+
+pub(crate) fn attach_loaded_generators(
+    mut commands: Commands,
+    pending: Query<(Entity, &PendingGenerator)>,
+    grammars: Res<Assets<TraceryGrammar>>,
+    asset_server: Res<AssetServer>,
+) {
+    for (entity, pending) in pending.iter() {
+        if let Some(grammar) = grammars.get(&pending.handle) {
+            let mut grammar = grammar.clone();
+            grammar.starting_point = pending.starting_point.clone();
+            let grammar = match pending.sandbox.clone() {
+                Some(policy) => grammar.sandboxed(policy),
+                None => Ok(grammar),
+            };
+            let mut entity_commands = commands.entity(entity);
+            entity_commands.remove::<PendingGenerator>();
+            match grammar {
+                Ok(grammar) => {
+                    entity_commands.insert((
+                        StatefulStringGenerator::from_grammar(grammar),
+                        GeneratorReady,
+                    ));
+                }
+                Err(violation) => error!(
+                    "The grammar for {entity:?} from {:?} breaks it's sandbox: {violation}",
+                    pending.handle.path()
+                ),
+            }
+        } else if asset_server.get_load_state(&pending.handle) == Some(LoadState::Failed) {
+            error!(
+                "Couldn't load the grammar for {entity:?} from {:?}",
+                pending.handle.path()
+            );
+            commands.entity(entity).remove::<PendingGenerator>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generator::StatefulGenerator, tracery::tracery_asset::TraceryAssetPlugin};
+
+    #[test]
+    pub fn pending_generators_are_replaced_once_their_grammar_loads() {
+        let mut app = App::new();
+        app.add_plugins((
+            TaskPoolPlugin::default(),
+            AssetPlugin::default(),
+            TraceryAssetPlugin::new(),
+        ))
+        .init_asset::<TraceryGrammar>();
+        let grammar =
+            TraceryGrammar::new(&[("origin", &["Hello"]), ("farewell", &["Goodbye"])], None);
+        let handle = app
+            .world
+            .resource_mut::<Assets<TraceryGrammar>>()
+            .add(grammar);
+        let entity = app
+            .world
+            .spawn(PendingGenerator::new(handle, "farewell"))
+            .id();
+        app.update();
+
+        assert!(app.world.get::<PendingGenerator>(entity).is_none());
+        assert!(app.world.get::<GeneratorReady>(entity).is_some());
+        let mut generator = app
+            .world
+            .get_mut::<StatefulStringGenerator>(entity)
+            .unwrap();
+        assert_eq!(generator.generate(&mut 0).unwrap(), "Goodbye");
+    }
+
+    #[test]
+    pub fn generators_from_handles_resolve_once_their_grammar_loads() {
+        let mut app = App::new();
+        app.add_plugins((
+            TaskPoolPlugin::default(),
+            AssetPlugin::default(),
+            TraceryAssetPlugin::new(),
+        ))
+        .init_asset::<TraceryGrammar>();
+        let handle = app
+            .world
+            .resource::<Assets<TraceryGrammar>>()
+            .get_handle_provider()
+            .reserve_handle()
+            .typed::<TraceryGrammar>();
+        let mut generator = StatefulStringGenerator::from_handle(handle.clone());
+        assert_eq!(
+            generator.try_generate(&mut 0),
+            Err(GenerationIssue::NotReady)
+        );
+        let entity = app.world.spawn(generator).id();
+        app.world
+            .insert_resource(StatefulStringGenerator::from_handle(handle.clone()));
+        app.update();
+        assert!(app.world.get::<GeneratorReady>(entity).is_none());
+
+        app.world.resource_mut::<Assets<TraceryGrammar>>().insert(
+            &handle,
+            TraceryGrammar::new(&[("origin", &["Hello"])], None),
+        );
+        app.update();
+        assert!(app.world.get::<GeneratorReady>(entity).is_some());
+        let mut generator = app
+            .world
+            .get_mut::<StatefulStringGenerator>(entity)
+            .unwrap();
+        assert!(generator.is_ready());
+        assert_eq!(generator.generate(&mut 0).unwrap(), "Hello");
+        let mut generator = app.world.resource_mut::<StatefulStringGenerator>();
+        assert_eq!(generator.try_generate(&mut 0).unwrap(), "Hello");
+    }
+
+    #[test]
+    pub fn sandboxed_generators_are_only_added_for_grammars_that_pass() {
+        let mut app = App::new();
+        app.add_plugins((
+            TaskPoolPlugin::default(),
+            AssetPlugin::default(),
+            TraceryAssetPlugin::new(),
+        ))
+        .init_asset::<TraceryGrammar>();
+        let mut grammars = app.world.resource_mut::<Assets<TraceryGrammar>>();
+        let modest = grammars.add(TraceryGrammar::new(&[("origin", &["Hello"])], None));
+        let greedy = grammars.add(TraceryGrammar::new(
+            &[("origin", &["[global.gold:lots]Hello"])],
+            None,
+        ));
+        let policy = SandboxPolicy::new();
+        let passing = app
+            .world
+            .spawn(PendingGenerator::new(modest, "origin").with_sandbox(policy.clone()))
+            .id();
+        let failing = app
+            .world
+            .spawn(PendingGenerator::new(greedy, "origin").with_sandbox(policy.clone()))
+            .id();
+        app.update();
+
+        let generator = app.world.get::<StatefulStringGenerator>(passing).unwrap();
+        assert_eq!(generator.get_grammar().sandbox(), Some(&policy));
+        assert!(app.world.get::<PendingGenerator>(failing).is_none());
+        assert!(app.world.get::<StatefulStringGenerator>(failing).is_none());
+    }
+}
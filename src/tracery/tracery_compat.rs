@@ -0,0 +1,70 @@
+use serde::de::Error;
+use serde_json::Value;
+
+use super::TraceryGrammar;
+
+impl TraceryGrammar {
+    /// This parses a grammar written in the canonical Tracery JSON format - a single object mapping each rule
+    /// to either a string or an array of strings - so grammars written for the original Tracery can be used unmodified.
+    /// As in the original Tracery, the starting point is "origin".
+    pub fn from_tracery_json(json: &str) -> Result<Self, serde_json::Error> {
+        let rules = match serde_json::from_str::<Value>(json)? {
+            Value::Object(rules) => rules,
+            _ => {
+                return Err(serde_json::Error::custom(
+                    "a tracery grammar must be an object",
+                ))
+            }
+        };
+        let mut grammar = TraceryGrammar::empty();
+        for (key, value) in rules {
+            let options = match value {
+                Value::String(option) => vec![option],
+                Value::Array(options) => options
+                    .into_iter()
+                    .map(|option| match option {
+                        Value::String(option) => Ok(option),
+                        _ => Err(serde_json::Error::custom(format!(
+                            "the options of rule `{key}` must be strings"
+                        ))),
+                    })
+                    .collect::<Result<_, _>>()?,
+                _ => {
+                    return Err(serde_json::Error::custom(format!(
+                        "rule `{key}` must be a string or an array of strings"
+                    )))
+                }
+            };
+            grammar.keys.push(key.clone());
+            grammar.rules.insert(key, options);
+        }
+        Ok(grammar)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::Grammar;
+
+    #[test]
+    pub fn loads_string_and_array_rules() {
+        let grammar = TraceryGrammar::from_tracery_json(
+            r##"{"origin": "#animal#", "animal": ["cat", "dog"]}"##,
+        )
+        .unwrap();
+        assert_eq!(
+            grammar.get_rule_options(&"origin".to_string()),
+            Some(&vec!["#animal#".to_string()])
+        );
+        assert_eq!(
+            grammar
+                .get_rule_options(&"animal".to_string())
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(TraceryGrammar::from_tracery_json(r#"{"origin": 3}"#).is_err());
+        assert!(TraceryGrammar::from_tracery_json(r#"["origin"]"#).is_err());
+    }
+}
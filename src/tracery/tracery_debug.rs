@@ -0,0 +1,163 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+
+use super::{StatefulStringGenerator, StringGenerator, TraceryGrammar};
+use crate::generator::{Grammar, SeededRng};
+
+/// This adds a debug window listing the loaded `TraceryGrammar` assets. It lets you pick a rule and a seed,
+/// preview what the grammar generates from them, and see the variables and tokens of any stateful generators.
+/// The window can be shown or hidden using the `GrammarDebugWindow` resource.
+#[derive(Default)]
+pub struct GrammarDebugPlugin {
+    hidden: bool,
+}
+
+impl GrammarDebugPlugin {
+    /// Instantiates a new grammar debug plugin, with the window open
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts with the window hidden, so it can be opened later using the `GrammarDebugWindow` resource
+    pub fn hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
+}
+
+impl Plugin for GrammarDebugPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        app.insert_resource(GrammarDebugWindow {
+            open: !self.hidden,
+            ..Default::default()
+        })
+        .add_systems(Update, grammar_debug_window);
+    }
+}
+
+/// This is the state of the grammar debug window
+#[derive(Resource, Debug, Default)]
+pub struct GrammarDebugWindow {
+    /// Whether the window is shown
+    pub open: bool,
+    selected: Option<AssetId<TraceryGrammar>>,
+    rule: String,
+    seed: u64,
+    preview: Option<String>,
+}
+
+fn grammar_debug_window(
+    mut contexts: EguiContexts,
+    mut window: ResMut<GrammarDebugWindow>,
+    grammars: Res<Assets<TraceryGrammar>>,
+    asset_server: Res<AssetServer>,
+    generators: Query<(Entity, &StatefulStringGenerator)>,
+    generator_resource: Option<Res<StatefulStringGenerator>>,
+) {
+    if !window.open {
+        return;
+    }
+    let mut open = true;
+    egui::Window::new("Grammars")
+        .open(&mut open)
+        .show(contexts.ctx_mut(), |ui| {
+            let mut assets = grammars
+                .iter()
+                .map(|(id, grammar)| {
+                    let name = asset_server
+                        .get_path(id)
+                        .map(|path| path.to_string())
+                        .unwrap_or_else(|| format!("{id:?}"));
+                    (id, name, grammar)
+                })
+                .collect::<Vec<_>>();
+            assets.sort_by(|a, b| a.1.cmp(&b.1));
+
+            ui.heading("Loaded Grammars");
+            if assets.is_empty() {
+                ui.label("No grammars are loaded");
+            }
+            for (id, name, grammar) in assets.iter() {
+                let selected = window.selected == Some(*id);
+                if ui.selectable_label(selected, name).clicked() && !selected {
+                    window.selected = Some(*id);
+                    window.rule = grammar.default_starting_point().clone();
+                    window.preview = None;
+                }
+            }
+
+            let grammar = window.selected.and_then(|id| grammars.get(id));
+            if let Some(grammar) = grammar {
+                ui.separator();
+                let mut rules = grammar.rule_keys().clone();
+                rules.sort();
+                egui::ComboBox::from_label("Rule")
+                    .selected_text(window.rule.clone())
+                    .show_ui(ui, |ui| {
+                        for rule in rules {
+                            let label = rule.clone();
+                            ui.selectable_value(&mut window.rule, rule, label);
+                        }
+                    });
+                ui.horizontal(|ui| {
+                    ui.label("Seed");
+                    ui.add(egui::DragValue::new(&mut window.seed));
+                    if ui.button("Roll").clicked() {
+                        window.seed = SeededRng::new(window.seed).next_u64();
+                    }
+                    if ui.button("Generate").clicked() {
+                        let mut rng = SeededRng::new(window.seed);
+                        window.preview = Some(
+                            match StringGenerator::try_generate_at(&window.rule, grammar, &mut rng)
+                            {
+                                Ok(result) => result,
+                                Err(issue) => format!("Couldn't generate: {issue}"),
+                            },
+                        );
+                    }
+                });
+                if let Some(preview) = window.preview.as_ref() {
+                    ui.label(preview);
+                }
+            }
+
+            ui.separator();
+            ui.heading("Stateful Generators");
+            if let Some(generator) = generator_resource.as_ref() {
+                stateful_generator_details(ui, "Resource", generator);
+            }
+            for (entity, generator) in generators.iter() {
+                stateful_generator_details(ui, format!("{entity:?}"), generator);
+            }
+        });
+    window.open = open;
+}
+
+fn stateful_generator_details(
+    ui: &mut egui::Ui,
+    name: impl Into<String>,
+    generator: &StatefulStringGenerator,
+) {
+    let name = name.into();
+    egui::CollapsingHeader::new(name.clone())
+        .id_source(name)
+        .show(ui, |ui| {
+            ui.label(format!("Generations: {}", generator.generation_count()));
+            let mut variables = generator.variables().iter().collect::<Vec<_>>();
+            variables.sort_by(|a, b| a.0.cmp(b.0));
+            if variables.is_empty() {
+                ui.label("No variables");
+            }
+            for (key, values) in variables {
+                ui.label(format!("{key} = {}", values.join(" | ")));
+            }
+            let mut tokens = generator.tokens().collect::<Vec<_>>();
+            tokens.sort();
+            for (key, value) in tokens {
+                ui.label(format!("{key} (token) = {value}"));
+            }
+        });
+}
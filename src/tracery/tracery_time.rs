@@ -0,0 +1,188 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use super::StatefulStringGenerator;
+
+/// The token holding the number of whole seconds elapsed since the app started
+pub const ELAPSED_SECONDS_TOKEN: &str = "elapsed_seconds";
+
+/// This is a clock that maps the elapsed time onto named phases of a repeating day - such as morning or night.
+/// Its current phase is provided to grammars as a token named after the clock.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrammarClock {
+    token: String,
+    day_length: f64,
+    offset: f64,
+    phases: Vec<(f64, String)>,
+}
+
+impl GrammarClock {
+    /// Creates a new clock, providing the token it fills and the length of a day in seconds.
+    pub fn new(token: impl Into<String>, day_length: f64) -> Self {
+        Self {
+            token: token.into(),
+            day_length,
+            offset: 0.,
+            phases: vec![],
+        }
+    }
+
+    /// Creates a `time_of_day` clock with `night`, `morning`, `afternoon` and `evening` phases, starting at midnight.
+    pub fn day_night(day_length: f64) -> Self {
+        Self::new("time_of_day", day_length)
+            .with_phase(0., "night")
+            .with_phase(0.25, "morning")
+            .with_phase(0.5, "afternoon")
+            .with_phase(0.75, "evening")
+    }
+
+    /// Adds a phase, starting at the provided fraction of the day (between 0 and 1)
+    pub fn with_phase(mut self, start: f64, name: impl Into<String>) -> Self {
+        self.phases.push((start, name.into()));
+        self.phases.sort_by(|a, b| a.0.total_cmp(&b.0));
+        self
+    }
+
+    /// Sets how far into the day (in seconds) the clock is when the app starts
+    pub fn with_offset(mut self, offset: f64) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// The token this clock provides
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// Gets the phase the clock is in after `elapsed` seconds. Before the first phase starts, the day is still in the last phase of the previous one.
+    pub fn phase_at(&self, elapsed: f64) -> Option<&str> {
+        if self.day_length <= 0. {
+            return None;
+        }
+        let fraction = ((elapsed + self.offset) / self.day_length).rem_euclid(1.);
+        self.phases
+            .iter()
+            .rev()
+            .find(|(start, _)| *start <= fraction)
+            .or(self.phases.last())
+            .map(|(_, name)| name.as_str())
+    }
+}
+
+/// The clocks used to fill time based tokens. Clocks can be registered through the plugin, or added to this resource at runtime.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct GrammarClocks(pub Vec<GrammarClock>);
+
+/// The token values shared with every `StatefulStringGenerator` in the world. The time plugin keeps the time based tokens up to date,
+/// and you can set your own tokens here as well.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct GrammarTokens(HashMap<String, String>);
+
+impl GrammarTokens {
+    /// Sets a token - only triggering change detection if the value actually changed
+    pub fn set(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        let key = key.into();
+        let value = value.into();
+        if self.0.get(&key) != Some(&value) {
+            self.0.insert(key, value);
+        }
+    }
+
+    /// Gets the current value of a token
+    pub fn get(&self, key: &str) -> Option<&String> {
+        self.0.get(key)
+    }
+
+    /// Iterates over all the tokens
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.0.iter()
+    }
+}
+
+/// This plugin keeps time based tokens (`#elapsed_seconds#`, and one token per registered clock) up to date using `Res<Time>`,
+/// and shares all the `GrammarTokens` with the stateful generators in the world.
+/// It also keeps the generators' clocks in sync - at least once a second - for cooldowns measured in seconds.
+#[derive(Default)]
+pub struct GrammarTimePlugin {
+    clocks: Vec<GrammarClock>,
+}
+
+impl GrammarTimePlugin {
+    /// Creates a time plugin without any clocks
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a clock
+    pub fn with_clock(mut self, clock: GrammarClock) -> Self {
+        self.clocks.push(clock);
+        self
+    }
+}
+
+impl Plugin for GrammarTimePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GrammarClocks(self.clocks.clone()))
+            .init_resource::<GrammarTokens>()
+            .add_systems(
+                PreUpdate,
+                (update_time_tokens, apply_grammar_tokens).chain(),
+            );
+    }
+}
+
+fn update_time_tokens(time: Res<Time>, clocks: Res<GrammarClocks>, tokens: ResMut<GrammarTokens>) {
+    let elapsed = time.elapsed_seconds_f64();
+    let tokens = tokens.into_inner();
+    tokens.set(ELAPSED_SECONDS_TOKEN, (elapsed.floor() as u64).to_string());
+    for clock in clocks.0.iter() {
+        if let Some(phase) = clock.phase_at(elapsed) {
+            tokens.set(clock.token(), phase);
+        }
+    }
+}
+
+fn apply_grammar_tokens(
+    time: Res<Time>,
+    tokens: Res<GrammarTokens>,
+    mut generators: Query<&mut StatefulStringGenerator>,
+    generator_resource: Option<ResMut<StatefulStringGenerator>>,
+) {
+    let changed = tokens.is_changed();
+    let apply = |generator: &mut StatefulStringGenerator| {
+        generator.set_elapsed_seconds(time.elapsed_seconds_f64());
+        for (key, value) in tokens.iter() {
+            generator.set_token(key.clone(), value.clone());
+        }
+    };
+    for mut generator in generators.iter_mut() {
+        if changed || generator.is_added() {
+            apply(&mut generator);
+        }
+    }
+    if let Some(mut generator) = generator_resource {
+        if changed || generator.is_added() {
+            apply(&mut generator);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn clock_phases_follow_the_elapsed_time() {
+        let clock = GrammarClock::day_night(100.);
+        assert_eq!(clock.phase_at(10.), Some("night"));
+        assert_eq!(clock.phase_at(30.), Some("morning"));
+        assert_eq!(clock.phase_at(180.), Some("evening"));
+        let clock = GrammarClock::new("season", 4.)
+            .with_phase(0.5, "summer")
+            .with_phase(0.25, "spring")
+            .with_offset(1.);
+        assert_eq!(clock.phase_at(0.), Some("spring"));
+        assert_eq!(clock.phase_at(0.5), Some("spring"));
+        assert_eq!(clock.phase_at(2.), Some("summer"));
+        assert_eq!(clock.phase_at(3.), Some("summer"));
+    }
+}
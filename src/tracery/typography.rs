@@ -0,0 +1,233 @@
+use super::{
+    modifiers::{capitalize_all_with, capitalize_with},
+    TraceryGrammar,
+};
+
+/// The no-break space French places before `:` and inside guillemets
+pub const NO_BREAK_SPACE: char = '\u{00A0}';
+/// The narrow no-break space French places before `;`, `!` and `?`
+pub const NARROW_NO_BREAK_SPACE: char = '\u{202F}';
+
+/// This is the set of casing and punctuation conventions applied to a grammar's output, picked from the grammar's locale.
+/// Modifiers like `capitalize` follow them, and the finished text is adjusted to them - so generated text that isn't
+/// English isn't mangled by English conventions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Typography {
+    /// Text is left as it is written, and capitalized using the default unicode casing
+    #[default]
+    Default,
+    /// Turkish and Azerbaijani, where the dotted `i` capitalizes to `İ` rather than `I`
+    Turkic,
+    /// French, which places a no-break space before `:`, `;`, `!` and `?`, and inside `«` and `»`
+    French,
+    /// Chinese, which doesn't place spaces between words, and uses the full width comma `，`
+    Chinese,
+    /// Japanese, which doesn't place spaces between words, and uses the ideographic comma `、`
+    Japanese,
+}
+
+impl Typography {
+    /// Picks the conventions for a locale, like `tr` or `fr-CA`, using it's language
+    pub fn from_locale(locale: &str) -> Self {
+        let language = locale.split(['-', '_']).next().unwrap_or_default();
+        match language.to_ascii_lowercase().as_str() {
+            "tr" | "az" => Self::Turkic,
+            "fr" => Self::French,
+            "zh" => Self::Chinese,
+            "ja" => Self::Japanese,
+            _ => Self::Default,
+        }
+    }
+
+    /// Applies a built in modifier that differs under these conventions, or returns `None` so the usual modifier is used
+    pub(crate) fn modify(&self, name: &str, text: &str) -> Option<String> {
+        match (self, name) {
+            (Self::Turkic, "capitalize") => Some(capitalize_with(text, turkic_uppercase)),
+            (Self::Turkic, "capitalizeAll") => Some(capitalize_all_with(text, turkic_uppercase)),
+            (Self::Chinese, "comma") => Some(cjk_comma(text, '，')),
+            (Self::Japanese, "comma") => Some(cjk_comma(text, '、')),
+            _ => None,
+        }
+    }
+
+    /// Adjusts finished text to these conventions
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            Self::French => french_spacing(text),
+            Self::Chinese | Self::Japanese => join_cjk(text),
+            Self::Default | Self::Turkic => text.to_string(),
+        }
+    }
+}
+
+impl TraceryGrammar {
+    /// This sets the grammar's locale, returning the updated grammar. See `set_locale`.
+    pub fn with_locale(mut self, locale: impl Into<String>) -> Self {
+        self.set_locale(locale);
+        self
+    }
+
+    /// This sets the grammar's locale, like `tr` or `fr-CA`, which picks the casing and punctuation conventions it's output follows.
+    /// Grammars without a locale are left as they are written. Within a `LocalizedTraceryGrammar`, each locale uses the locale set by
+    /// the most specific grammar in it's fallback chain.
+    pub fn set_locale(&mut self, locale: impl Into<String>) {
+        self.locale = Some(locale.into());
+    }
+
+    /// This gets the grammar's locale, if it has one
+    pub fn locale(&self) -> Option<&str> {
+        self.locale.as_deref()
+    }
+
+    /// This gets the casing and punctuation conventions the grammar's output follows
+    pub fn typography(&self) -> Typography {
+        self.locale
+            .as_deref()
+            .map(Typography::from_locale)
+            .unwrap_or_default()
+    }
+}
+
+fn turkic_uppercase(character: char) -> String {
+    match character {
+        'i' => "İ".to_string(),
+        _ => character.to_uppercase().collect(),
+    }
+}
+
+fn cjk_comma(text: &str, comma: char) -> String {
+    match text.chars().last() {
+        Some(',' | '.' | '?' | '!' | '，' | '、' | '。' | '？' | '！') => text.to_string(),
+        _ => format!("{text}{comma}"),
+    }
+}
+
+fn french_spacing(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut characters = text.chars().peekable();
+    while let Some(character) = characters.next() {
+        let space = match character {
+            ';' | '!' | '?' => Some(NARROW_NO_BREAK_SPACE),
+            '»' => Some(NO_BREAK_SPACE),
+            // Colons within words - like in times or links - are left alone
+            ':' if characters.peek().is_none_or(|next| next.is_whitespace()) => {
+                Some(NO_BREAK_SPACE)
+            }
+            _ => None,
+        };
+        if let Some(space) = space {
+            if result.ends_with(' ') {
+                result.pop();
+                result.push(space);
+            } else if result
+                .chars()
+                .next_back()
+                .is_some_and(|previous| !previous.is_whitespace() && !";:!?«".contains(previous))
+            {
+                result.push(space);
+            }
+        }
+        if character == ' ' && result.ends_with('«') {
+            result.push(NO_BREAK_SPACE);
+            continue;
+        }
+        result.push(character);
+        if character == '«' && characters.peek().is_some_and(|next| !next.is_whitespace()) {
+            result.push(NO_BREAK_SPACE);
+        }
+    }
+    result
+}
+
+/// Checks if a character is Chinese or Japanese - ideographs, kana, and their punctuation and full width forms
+fn is_cjk(character: char) -> bool {
+    matches!(
+        character as u32,
+        0x3000..=0x30FF // Punctuation and kana
+            | 0x3400..=0x4DBF // Ideographs extension A
+            | 0x4E00..=0x9FFF // Unified ideographs
+            | 0xF900..=0xFAFF // Compatibility ideographs
+            | 0xFF00..=0xFFEF // Full width forms
+            | 0x20000..=0x2FA1F // Supplementary ideographs
+    )
+}
+
+/// Removes the spaces between CJK characters, which are usually left by the spaces joining references in an option
+fn join_cjk(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut spaces = String::new();
+    for character in text.chars() {
+        if character == ' ' {
+            spaces.push(character);
+            continue;
+        }
+        if !is_cjk(character) || !result.chars().next_back().is_some_and(is_cjk) {
+            result.push_str(&spaces);
+        }
+        spaces.clear();
+        result.push(character);
+    }
+    result + &spaces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracery::StringGenerator;
+
+    #[test]
+    pub fn locales_pick_their_conventions() {
+        assert_eq!(Typography::from_locale("tr"), Typography::Turkic);
+        assert_eq!(Typography::from_locale("fr-CA"), Typography::French);
+        assert_eq!(Typography::from_locale("zh_Hant"), Typography::Chinese);
+        assert_eq!(Typography::from_locale("en"), Typography::Default);
+
+        let french = Typography::French;
+        assert_eq!(
+            french.apply("Attention : le dragon arrive! Fuyez ?! Il est 10:30."),
+            "Attention\u{A0}: le dragon arrive\u{202F}! Fuyez\u{202F}?! Il est 10:30."
+        );
+        assert_eq!(french.apply("« Bonjour »"), "«\u{A0}Bonjour\u{A0}»");
+        assert_eq!(
+            Typography::Japanese.apply("勇者 は 城 へ 向かった。 Hello world"),
+            "勇者は城へ向かった。 Hello world"
+        );
+    }
+
+    #[test]
+    pub fn modifiers_and_output_follow_the_grammar_locale() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["#city.capitalize# #verb#"]),
+                ("city", &["istanbul"]),
+                ("verb", &["ılık"]),
+            ],
+            None,
+        );
+        assert_eq!(
+            StringGenerator::try_generate(&grammar, &mut 0).unwrap(),
+            "Istanbul ılık"
+        );
+        let grammar = grammar.with_locale("tr");
+        assert_eq!(grammar.locale(), Some("tr"));
+        assert_eq!(
+            StringGenerator::try_generate(&grammar, &mut 0).unwrap(),
+            "İstanbul ılık"
+        );
+
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["#hero.comma# #place# #action#"]),
+                ("hero", &["勇者"]),
+                ("place", &["城へ"]),
+                ("action", &["向かった"]),
+            ],
+            None,
+        )
+        .with_locale("ja");
+        assert_eq!(
+            StringGenerator::try_generate(&grammar, &mut 0).unwrap(),
+            "勇者、城へ向かった"
+        );
+    }
+}
@@ -0,0 +1,446 @@
+use std::fmt::Display;
+
+use super::{
+    annotations::{option_text, GENERATION_COUNT_KEY},
+    captures::{is_capture_key, split_key_and_modifiers},
+    completion::assigned_variables,
+    find_closing,
+    parameters::{named_arguments, RuleCall, POSITIONAL_PREFIX},
+    split_alternatives, TraceryGrammar,
+};
+use crate::generator::{Grammar, Replacable};
+
+/// This is a likely problem found while validating a grammar, in one of it's options
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintFinding {
+    /// The rule containing the problem
+    pub rule: String,
+    /// The index of the option containing the problem
+    pub option: usize,
+    /// The problem that was found
+    pub kind: LintKind,
+}
+
+/// The kinds of problems found while validating a grammar
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintKind {
+    /// The option references a key that isn't a rule, alias or variable assigned within the grammar.
+    /// It might still be provided at runtime - for example, as a token.
+    UndefinedRule(String),
+    /// A word in the option's text wasn't found in the dictionary, and might be a typo
+    UnknownWord(String),
+    /// The rule can never finish expanding, since every option leads back to it - holding the cycle of rules, starting and ending
+    /// with this rule. Generations reaching it only stop once they reach the grammar's depth limit.
+    InfiniteRecursion(Vec<String>),
+}
+
+impl Display for LintFinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let location = format!("{} (option {})", self.rule, self.option);
+        match &self.kind {
+            LintKind::UndefinedRule(key) => {
+                write!(f, "{location}: `{key}` isn't defined in the grammar")
+            }
+            LintKind::UnknownWord(word) => {
+                write!(f, "{location}: `{word}` isn't in the dictionary")
+            }
+            LintKind::InfiniteRecursion(cycle) => {
+                write!(
+                    f,
+                    "{location}: the rules never finish expanding - {}",
+                    cycle.join(" -> ")
+                )
+            }
+        }
+    }
+}
+
+/// This checks a grammar for likely problems, such as references to rules that don't exist.
+/// Optional stages, like checking words against a dictionary, can be added before validating.
+#[derive(Default)]
+pub struct GrammarValidator<'a> {
+    dictionary: Option<Box<dyn Fn(&str) -> bool + 'a>>,
+}
+
+impl<'a> GrammarValidator<'a> {
+    /// Creates a validator that only checks the grammar's references
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks each word in the grammar's text against a dictionary, reporting the words it doesn't know as likely typos.
+    /// The dictionary receives each word as it's written, and returns whether it's known.
+    pub fn with_dictionary(mut self, dictionary: impl Fn(&str) -> bool + 'a) -> Self {
+        self.dictionary = Some(Box::new(dictionary));
+        self
+    }
+
+    /// Validates the grammar, returning the problems found ordered by rule and option
+    pub fn validate(&self, grammar: &TraceryGrammar) -> Vec<LintFinding> {
+        let variables = grammar
+            .rules
+            .values()
+            .flatten()
+            .flat_map(|option| {
+                assigned_variables(option)
+                    .map(str::to_string)
+                    .chain(named_arguments(option))
+            })
+            .collect::<Vec<_>>();
+        let mut rules = grammar.keys.iter().collect::<Vec<_>>();
+        rules.sort();
+        let mut findings = vec![];
+        for rule in rules {
+            let Some(options) = grammar.rules.get(rule) else {
+                continue;
+            };
+            for (option, text) in options.iter().enumerate() {
+                let mut report = |kind| {
+                    if !findings.iter().any(|finding: &LintFinding| {
+                        finding.rule == *rule && finding.option == option && finding.kind == kind
+                    }) {
+                        findings.push(LintFinding {
+                            rule: rule.clone(),
+                            option,
+                            kind,
+                        });
+                    }
+                };
+                let text = option_text(text);
+                for key in referenced_keys(text) {
+                    let defined = grammar.rules.contains_key(grammar.resolve_alias(&key))
+                        || variables.contains(&key)
+                        || is_capture_key(&key)
+                        || key.starts_with(POSITIONAL_PREFIX)
+                        || key == GENERATION_COUNT_KEY;
+                    if !defined {
+                        report(LintKind::UndefinedRule(key.to_string()));
+                    }
+                }
+                if let Some(dictionary) = self.dictionary.as_ref() {
+                    for word in terminal_words(text).filter(|word| !dictionary(word)) {
+                        report(LintKind::UnknownWord(word.to_string()));
+                    }
+                }
+            }
+        }
+        for (cycle, option) in infinite_cycles(grammar, &variables) {
+            findings.push(LintFinding {
+                rule: cycle[0].clone(),
+                option,
+                kind: LintKind::InfiniteRecursion(cycle),
+            });
+        }
+        findings.sort_by(|a, b| a.rule.cmp(&b.rule).then(a.option.cmp(&b.option)));
+        findings
+    }
+}
+
+impl TraceryGrammar {
+    /// This checks the grammar for likely problems, such as references to rules that don't exist.
+    /// Use a `GrammarValidator` to add optional stages, like checking the grammar's text against a dictionary.
+    pub fn validate(&self) -> Vec<LintFinding> {
+        GrammarValidator::new().validate(self)
+    }
+}
+
+/// Finds the keys referenced within an option, without their modifiers or arguments
+pub(crate) fn referenced_keys(option: &str) -> Vec<String> {
+    let mut keys = vec![];
+    visit_tags(option, &mut |tag| {
+        keys.push(match RuleCall::parse(tag) {
+            Some(call) => call.rule.to_string(),
+            None => split_key_and_modifiers(tag)
+                .map_or(tag, |(key, _)| key)
+                .to_string(),
+        })
+    });
+    keys
+}
+
+/// Visits the tags within an option - the text between a pair of `#`s, after any leading actions. The option is read with the
+/// grammar's tokenizer, so tags within actions, inline alternatives and the arguments passed to rules are visited as well
+pub(crate) fn visit_tags(option: &str, visit: &mut impl FnMut(&str)) {
+    visit_stream_tags(&TraceryGrammar::empty(), option, visit);
+}
+
+fn visit_stream_tags(tokenizer: &TraceryGrammar, stream: &str, visit: &mut impl FnMut(&str)) {
+    for token in tokenizer.check_token_stream(&stream.to_string()).1 {
+        match token {
+            Replacable::Ready(_) => {}
+            Replacable::Replace(key) => {
+                let mut rest = key.as_str();
+                while let Some(inner) = rest.strip_prefix('[') {
+                    let end = find_closing(inner, ']');
+                    for action in tokenizer.parse_action(&inner[..end]) {
+                        visit_action_tags(tokenizer, action, visit);
+                    }
+                    rest = inner.get(end + 1..).unwrap_or_default();
+                }
+                if rest.is_empty() {
+                    continue;
+                }
+                visit(rest);
+                // Arguments are bound like actions, so the keys they pass are referenced as well
+                if let Some(call) = RuleCall::parse(rest) {
+                    for binding in call.bindings() {
+                        visit_action_tags(tokenizer, binding, visit);
+                    }
+                }
+            }
+            Replacable::Alternatives(alternatives) => {
+                for alternative in alternatives {
+                    visit_stream_tags(tokenizer, &alternative, visit);
+                }
+            }
+            action => visit_action_tags(tokenizer, action, visit),
+        }
+    }
+}
+
+fn visit_action_tags(
+    tokenizer: &TraceryGrammar,
+    action: Replacable<String, String>,
+    visit: &mut impl FnMut(&str),
+) {
+    match action {
+        Replacable::ImmediateMeta(_, value) | Replacable::DelayedMeta(_, value) => {
+            visit_stream_tags(tokenizer, &value, visit)
+        }
+        Replacable::Replace(key) => visit_stream_tags(tokenizer, &format!("#{key}#"), visit),
+        Replacable::Ready(_) | Replacable::Alternatives(_) => {}
+    }
+}
+
+/// Finds the cycles of rules that can never finish expanding - rules whose every option references a rule that can't finish.
+/// Each cycle starts and ends with it's alphabetically first rule, and is returned along with the index of that rule's option
+/// continuing the cycle. Keys assigned as variables within the grammar are assumed to finish, since they might shadow the rule.
+fn infinite_cycles(grammar: &TraceryGrammar, variables: &[String]) -> Vec<(Vec<String>, usize)> {
+    let rule_key = |key: &str| {
+        let key = grammar.resolve_alias(key);
+        (!variables.iter().any(|variable| variable == key))
+            .then(|| grammar.rules.get_key_value(key))
+            .flatten()
+            .map(|(key, _)| key.as_str())
+    };
+    let mut finishing: Vec<&str> = vec![];
+    loop {
+        let mut changed = false;
+        for (rule, options) in grammar.rules.iter() {
+            if finishing.contains(&rule.as_str()) {
+                continue;
+            }
+            let finishes = options.iter().any(|option| {
+                option_finishes(option_text(option), &|key| {
+                    rule_key(key).is_none_or(|key| finishing.contains(&key))
+                })
+            });
+            if finishes {
+                finishing.push(rule);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    // Every option of a rule that can't finish references another rule that can't, so following them always leads to a cycle
+    let next = |rule: &str| {
+        grammar
+            .rules
+            .get(rule)?
+            .iter()
+            .enumerate()
+            .find_map(|(index, option)| {
+                referenced_keys(option_text(option))
+                    .iter()
+                    .filter_map(|key| rule_key(key))
+                    .find(|key| !finishing.contains(key))
+                    .map(|key| (index, key))
+            })
+    };
+    let mut rules = grammar
+        .rules
+        .keys()
+        .map(String::as_str)
+        .filter(|rule| !finishing.contains(rule))
+        .collect::<Vec<_>>();
+    rules.sort();
+    let mut cycles: Vec<(Vec<String>, usize)> = vec![];
+    for rule in rules {
+        let mut path: Vec<(&str, usize)> = vec![];
+        let mut current = rule;
+        while !path.iter().any(|(visited, _)| *visited == current) {
+            let Some((option, reference)) = next(current) else {
+                break;
+            };
+            path.push((current, option));
+            current = reference;
+        }
+        let Some(start) = path.iter().position(|(visited, _)| *visited == current) else {
+            continue;
+        };
+        let mut cycle = path.split_off(start);
+        let first = (0..cycle.len())
+            .min_by_key(|index| cycle[*index].0)
+            .unwrap_or_default();
+        cycle.rotate_left(first);
+        let option = cycle[0].1;
+        let mut cycle = cycle
+            .into_iter()
+            .map(|(rule, _)| rule.to_string())
+            .collect::<Vec<_>>();
+        cycle.push(cycle[0].clone());
+        if !cycles.iter().any(|(found, _)| *found == cycle) {
+            cycles.push((cycle, option));
+        }
+    }
+    cycles
+}
+
+/// Checks whether an option can finish expanding, given whether each key it references can.
+/// Only one of each set of inline alternatives needs to finish.
+fn option_finishes(option: &str, finishes: &impl Fn(&str) -> bool) -> bool {
+    let mut rest = option;
+    while let Some(start) = rest.find(['#', '(']) {
+        let inner = &rest[start + 1..];
+        if rest[start..].starts_with('#') {
+            let Some(end) = inner.find('#') else {
+                return true;
+            };
+            let key = &inner[..end];
+            if !finishes(split_key_and_modifiers(key).map_or(key, |(key, _)| key)) {
+                return false;
+            }
+            rest = &inner[end + 1..];
+            continue;
+        }
+        rest = match split_alternatives(inner) {
+            Some((end, alternatives)) => {
+                if !alternatives
+                    .iter()
+                    .any(|alternative| option_finishes(alternative, finishes))
+                {
+                    return false;
+                }
+                inner.get(end + 1..).unwrap_or_default()
+            }
+            None => inner,
+        };
+    }
+    true
+}
+
+/// Finds the words written directly in an option, skipping references and the keys of actions
+fn terminal_words(option: &str) -> impl Iterator<Item = &str> {
+    let mut text = vec![];
+    let mut rest = option;
+    while let Some(start) = rest.find(['#', '[']) {
+        text.push(&rest[..start]);
+        let inner = &rest[start + 1..];
+        if rest[start..].starts_with('#') {
+            let end = inner.find('#').unwrap_or(inner.len());
+            rest = inner.get(end + 1..).unwrap_or_default();
+            continue;
+        }
+        // Only the value of an action holds text - the rest of it is parsed once the action is processed
+        let end = find_closing(inner, ']');
+        let action = &inner[..end];
+        if let Some(split) = action.find([':', '|']) {
+            text.extend(terminal_words(&action[split + 1..]));
+        }
+        rest = inner.get(end + 1..).unwrap_or_default();
+    }
+    text.push(rest);
+    text.into_iter()
+        .flat_map(|text| {
+            text.split(|character: char| !character.is_alphabetic() && character != '\'')
+        })
+        .map(|word| word.trim_matches('\''))
+        .filter(|word| !word.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn reports_undefined_rules_and_unknown_words() {
+        let grammar = TraceryGrammar::new(
+            &[
+                (
+                    "origin",
+                    &["[hero:#animal#]an adventerous #hero# found #place#"],
+                ),
+                ("animal", &["rabbit", "{if:gen_count>=1}fox"]),
+                ("went", &["[path:a challange]#path#", "#animal._name# went"]),
+            ],
+            None,
+        );
+        assert_eq!(
+            grammar.validate(),
+            vec![LintFinding {
+                rule: "origin".to_string(),
+                option: 0,
+                kind: LintKind::UndefinedRule("place".to_string()),
+            }]
+        );
+
+        let dictionary = [
+            "a",
+            "an",
+            "adventurous",
+            "found",
+            "rabbit",
+            "fox",
+            "challenge",
+            "went",
+        ];
+        let findings = GrammarValidator::new()
+            .with_dictionary(|word| dictionary.contains(&word))
+            .validate(&grammar)
+            .into_iter()
+            .map(|finding| finding.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            findings,
+            vec![
+                LintKind::UndefinedRule("place".to_string()),
+                LintKind::UnknownWord("adventerous".to_string()),
+                LintKind::UnknownWord("challange".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn reports_rules_that_never_finish_expanding() {
+        let grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["#story#", "#ending#"]),
+                ("story", &["#chapter#", "#chapter# and then #story#"]),
+                ("chapter", &["a chapter", "(#chapter#|#story#) again"]),
+                ("ending", &["#epilogue#"]),
+                ("epilogue", &["#twist#"]),
+                ("twist", &["(#ending#|#epilogue#)", "#ending# and #origin#"]),
+            ],
+            None,
+        );
+        assert_eq!(
+            grammar.validate(),
+            vec![LintFinding {
+                rule: "ending".to_string(),
+                option: 0,
+                kind: LintKind::InfiniteRecursion(
+                    ["ending", "epilogue", "twist", "ending"]
+                        .map(str::to_string)
+                        .to_vec()
+                ),
+            }]
+        );
+        assert_eq!(
+            grammar.validate()[0].to_string(),
+            "ending (option 0): the rules never finish expanding - ending -> epilogue -> twist -> ending"
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use super::sync::Fingerprint;
+use crate::generator::GrammarRandomNumberGenerator;
+#[cfg(feature = "bevy")]
+use bevy::utils::HashMap;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(not(feature = "bevy"))]
+use std::collections::HashMap;
+
+/// The prefix for assignments that write to the variables shared between generators, rather than the generator's own -
+/// like `[global.season:winter]`. With bevy, these are written to the `GrammarBlackboard`.
+pub const GLOBAL_PREFIX: &str = "global.";
+
+/// This controls how long a stateful generator keeps the variables assigned during a generation, like `[hero:#name#]`.
+/// Variables always live apart from the grammar's rules, so the grammar itself is never changed by generating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum VariableLifetime {
+    /// Variables are kept between generations, so later generations can refer back to them - this is the default
+    #[default]
+    Persistent,
+    /// Variables are dropped once the generation that assigned them completes, so every generation starts from the same
+    /// rules. Variables set from outside the grammar, using `StatefulStringGenerator::variables_mut`, are still kept.
+    Generation,
+}
+
+#[cfg(feature = "serde")]
+impl VariableLifetime {
+    pub(crate) fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// This holds the variables a stateful generator remembers between generations - those assigned using actions like `[name:#names#]`.
+/// They are kept apart from the grammar's rules, so they can be listed, changed or cleared without touching the grammar itself.
+/// When expanding, variables are used in place of base rules with the same key.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+pub struct VariableStore {
+    values: HashMap<String, Vec<String>>,
+}
+
+/// This is a copy of the variables in a `VariableStore` at some point, which can be restored later - for example, to checkpoint
+/// the state of a story.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+pub struct VariableSnapshot {
+    values: HashMap<String, Vec<String>>,
+}
+
+impl VariableStore {
+    /// This gets the options of a variable
+    pub fn get_var(&self, key: &str) -> Option<&Vec<String>> {
+        self.values.get(key)
+    }
+
+    /// This sets the options of a variable, replacing any it had before
+    pub fn set_var<T: Into<String>>(
+        &mut self,
+        key: impl Into<String>,
+        values: impl IntoIterator<Item = T>,
+    ) {
+        self.values
+            .insert(key.into(), values.into_iter().map(Into::into).collect());
+    }
+
+    /// This removes a variable, returning it's previous options
+    pub fn remove_var(&mut self, key: &str) -> Option<Vec<String>> {
+        self.values.remove(key)
+    }
+
+    /// This removes every variable
+    pub fn clear_vars(&mut self) {
+        self.values.clear();
+    }
+
+    /// Checks whether a variable is set
+    pub fn contains(&self, key: &str) -> bool {
+        self.values.contains_key(key)
+    }
+
+    /// The number of variables that are set
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Checks whether no variables are set
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// This iterates over the variables, along with their options
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Vec<String>)> {
+        self.values.iter()
+    }
+
+    /// Writes the variables into a fingerprint, in a stable order
+    pub(crate) fn write_fingerprint(&self, fingerprint: &mut Fingerprint) {
+        let mut values = self.values.iter().collect::<Vec<_>>();
+        values.sort_by_key(|(key, _)| *key);
+        for (key, options) in values {
+            fingerprint.write(key);
+            fingerprint.write_u64(options.len() as u64);
+            for option in options {
+                fingerprint.write(option);
+            }
+        }
+    }
+
+    /// This copies the current variables, so they can be restored later
+    pub fn snapshot(&self) -> VariableSnapshot {
+        VariableSnapshot {
+            values: self.values.clone(),
+        }
+    }
+
+    /// This replaces the current variables with those in a snapshot
+    pub fn restore(&mut self, snapshot: &VariableSnapshot) {
+        self.values.clone_from(&snapshot.values);
+    }
+
+    /// Chooses one of a variable's options at random, along with it's index
+    pub(crate) fn select<R: GrammarRandomNumberGenerator>(
+        &self,
+        key: &str,
+        rng: &mut R,
+    ) -> Option<(usize, &String)> {
+        let options = self.values.get(key)?;
+        if options.is_empty() {
+            return None;
+        }
+        let index = (options.len() - 1).min(rng.get_number(options.len()));
+        Some((index, &options[index]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn snapshots_restore_the_variables_they_copied() {
+        let mut store = VariableStore::default();
+        store.set_var("name", ["Mia"]);
+        let snapshot = store.snapshot();
+        store.set_var("name", ["Sam", "Arjun"]);
+        store.set_var("pet", ["cat"]);
+        assert_eq!(store.len(), 2);
+
+        store.restore(&snapshot);
+        assert_eq!(store.get_var("name").unwrap(), &vec!["Mia".to_string()]);
+        assert!(!store.contains("pet"));
+        store.clear_vars();
+        assert!(store.is_empty());
+    }
+}
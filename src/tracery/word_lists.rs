@@ -0,0 +1,203 @@
+#[cfg(feature = "bevy")]
+use bevy::utils::HashMap;
+#[cfg(not(feature = "bevy"))]
+use std::collections::HashMap;
+
+use super::{GrammarError, TraceryGrammar};
+
+/// The directive sourcing a rule's options from a sidecar word list - `@file(creatures.txt)`
+pub const WORD_LIST_DIRECTIVE: &str = "@file";
+
+/// Gets the path of the word list an option refers to, or `None` if the option isn't a word list reference
+pub fn word_list_path(option: &str) -> Option<&str> {
+    option
+        .trim()
+        .strip_prefix(WORD_LIST_DIRECTIVE)?
+        .strip_prefix('(')?
+        .strip_suffix(')')
+        .map(str::trim)
+}
+
+/// Parses the content of a word list into options, along with their weights. Lists ending in `.csv` hold an option in the first
+/// column of each row, with an optional weight in the second. Other lists hold one option per line. Blank lines are skipped.
+pub fn parse_word_list(path: &str, content: &str) -> Vec<(String, Option<u32>)> {
+    let csv = path.to_ascii_lowercase().ends_with(".csv");
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| match csv {
+            true => {
+                let mut fields = csv_fields(line).into_iter();
+                let option = fields.next().unwrap_or_default();
+                let weight = fields.next().and_then(|weight| weight.trim().parse().ok());
+                (option, weight)
+            }
+            false => (line.trim().to_string(), None),
+        })
+        .collect()
+}
+
+/// Splits a CSV row into it's fields. Fields can be quoted to hold commas, with doubled quotes standing for a single one.
+pub(crate) fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut characters = line.chars().peekable();
+    while let Some(character) = characters.next() {
+        match (character, quoted) {
+            ('"', true) if characters.peek() == Some(&'"') => {
+                characters.next();
+                field.push('"');
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => fields.push(std::mem::take(&mut field).trim().to_string()),
+            (character, _) => field.push(character),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+impl TraceryGrammar {
+    /// Gets the paths of the word lists referenced by the grammar's options, like `@file(creatures.txt)`, sorted and without duplicates
+    pub fn word_list_paths(&self) -> Vec<String> {
+        let mut paths = self
+            .rules
+            .values()
+            .flatten()
+            .filter_map(|option| word_list_path(option))
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+
+    /// Replaces the options referencing word lists with the options in those lists, keeping any other options of the rule.
+    /// `load` provides the content of each list from it's path, and the asset loader uses it to read lists next to the grammar.
+    /// Weights from CSV lists are kept, and every other option keeps it's weight.
+    pub fn resolve_word_lists(
+        &mut self,
+        mut load: impl FnMut(&str) -> Option<String>,
+    ) -> Result<(), GrammarError> {
+        let mut lists: HashMap<String, Vec<(String, Option<u32>)>> = HashMap::default();
+        for path in self.word_list_paths() {
+            let content = load(&path).ok_or_else(|| GrammarError::MissingWordList(path.clone()))?;
+            let words = parse_word_list(&path, &content);
+            lists.insert(path, words);
+        }
+        for key in self.keys.iter() {
+            let Some(options) = self.rules.get_mut(key) else {
+                continue;
+            };
+            if !options
+                .iter()
+                .any(|option| word_list_path(option).is_some())
+            {
+                continue;
+            }
+            let old_weights = self
+                .settings
+                .get(key)
+                .map(|settings| settings.weights.clone())
+                .unwrap_or_default();
+            let mut resolved = vec![];
+            let mut weights = vec![];
+            for (index, option) in options.drain(..).enumerate() {
+                match word_list_path(&option).and_then(|path| lists.get(path)) {
+                    Some(words) => {
+                        for (word, weight) in words {
+                            resolved.push(word.clone());
+                            weights.push(weight.unwrap_or(1));
+                        }
+                    }
+                    None => {
+                        resolved.push(option);
+                        weights.push(old_weights.get(index).copied().unwrap_or(1));
+                    }
+                }
+            }
+            *options = resolved;
+            if weights.iter().any(|weight| *weight != 1) || !old_weights.is_empty() {
+                self.settings.entry(key.clone()).or_default().weights = weights;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::Grammar;
+
+    #[test]
+    pub fn word_lists_are_parsed_from_lines_and_csv() {
+        assert_eq!(
+            word_list_path(" @file( creatures.txt )"),
+            Some("creatures.txt")
+        );
+        assert_eq!(word_list_path("a file"), None);
+        assert_eq!(
+            parse_word_list("creatures.txt", "wolf\n\n  bear  \n"),
+            vec![("wolf".to_string(), None), ("bear".to_string(), None)]
+        );
+        assert_eq!(
+            parse_word_list(
+                "creatures.CSV",
+                "wolf,3\n\"bear, brown\",x\n\"the \"\"beast\"\"\""
+            ),
+            vec![
+                ("wolf".to_string(), Some(3)),
+                ("bear, brown".to_string(), None),
+                ("the \"beast\"".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn word_lists_replace_the_options_referencing_them() {
+        let mut grammar = TraceryGrammar::new(
+            &[
+                ("origin", &["A #creature# in the #place#"]),
+                ("creature", &["dragon", "@file(creatures.csv)"]),
+                ("place", &["@file(places.txt)"]),
+            ],
+            None,
+        )
+        .with_weights("creature", [5]);
+        assert_eq!(
+            grammar.word_list_paths(),
+            vec!["creatures.csv".to_string(), "places.txt".to_string()]
+        );
+        grammar
+            .resolve_word_lists(|path| match path {
+                "creatures.csv" => Some("wolf,2\nbear".to_string()),
+                "places.txt" => Some("forest\nswamp".to_string()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(
+            grammar.get_rule_options(&"creature".to_string()).unwrap(),
+            &vec!["dragon".to_string(), "wolf".to_string(), "bear".to_string()]
+        );
+        assert_eq!(
+            grammar.rule_settings("creature").unwrap().weights,
+            vec![5, 2, 1]
+        );
+        assert_eq!(
+            grammar
+                .get_rule_options(&"place".to_string())
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(grammar.rule_settings("place").is_none());
+
+        let mut missing = TraceryGrammar::new(&[("origin", &["@file(missing.txt)"])], None);
+        assert_eq!(
+            missing.resolve_word_lists(|_| None),
+            Err(GrammarError::MissingWordList("missing.txt".to_string()))
+        );
+    }
+}
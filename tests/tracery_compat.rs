@@ -0,0 +1,111 @@
+use bevy_generative_grammars::{
+    generator::{Generator, StatefulGenerator},
+    tracery::{StatefulStringGenerator, StringGenerator, TraceryGrammar},
+};
+
+/// Each case is a grammar in the canonical Tracery JSON format, the option index the rng always picks,
+/// and the output the original Tracery produces for those picks.
+const CASES: &[(&str, usize, &str)] = &[
+    (
+        include_str!("tracery/animals.json"),
+        0,
+        "An owl hoped over two owls",
+    ),
+    (
+        include_str!("tracery/animals.json"),
+        1,
+        "A fox baked over three foxes",
+    ),
+    (
+        include_str!("tracery/animals.json"),
+        2,
+        "A pony cried over three ponies",
+    ),
+    (
+        include_str!("tracery/animals.json"),
+        3,
+        "A unicorn rushed over three unicorns",
+    ),
+    (
+        include_str!("tracery/animals.json"),
+        4,
+        "A dragon played over three dragons",
+    ),
+    (
+        include_str!("tracery/modifiers.json"),
+        0,
+        "The Hall Of Wishes: \"hello, bornoby\"",
+    ),
+    (
+        include_str!("tracery/modifiers.json"),
+        1,
+        "The Garden Of Stories: \"well! bornoby\"",
+    ),
+    (
+        include_str!("tracery/story.json"),
+        0,
+        "Arjun traveled with her pet unicorn.  Arjun was never vexed, for the unicorn was always too vexed.",
+    ),
+    (
+        include_str!("tracery/story.json"),
+        1,
+        "Yuuma traveled with her pet raven.  Yuuma was never indignant, for the raven was always too indignant.",
+    ),
+    (
+        r##"{"origin": "#[hero:#name.capitalize#]story#, but #hero# stays", "name": "mia", "story": "#hero# left"}"##,
+        0,
+        "Mia left, but #hero# stays",
+    ),
+    (
+        r##"{"origin": "#missing.capitalize# stays, #known.s# change", "known": "cat"}"##,
+        0,
+        "#missing.capitalize# stays, cats change",
+    ),
+];
+
+#[test]
+fn stateless_generator_matches_reference_outputs() {
+    for (json, pick, expected) in CASES {
+        let grammar = TraceryGrammar::from_tracery_json(json).unwrap();
+        let result = StringGenerator::generate(&grammar, &mut pick.clone());
+        assert_eq!(result.as_deref(), Some(*expected), "picking option {pick}");
+    }
+}
+
+#[test]
+fn stateful_generator_matches_reference_outputs() {
+    for (json, pick, expected) in CASES {
+        let grammar = TraceryGrammar::from_tracery_json(json).unwrap();
+        let mut generator = StatefulStringGenerator::from_grammar(grammar);
+        let result = generator.generate(&mut pick.clone());
+        assert_eq!(result.as_deref(), Some(*expected), "picking option {pick}");
+    }
+}
+
+#[test]
+fn long_expansions_are_not_truncated() {
+    let words = (0..100)
+        .map(|index| format!("#word{index}#"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let rules = (0..100)
+        .map(|index| format!(r#""word{index}": "w{index}""#))
+        .collect::<Vec<_>>()
+        .join(",");
+    let json = format!(r#"{{"origin": "{words}", {rules}}}"#);
+    let grammar = TraceryGrammar::from_tracery_json(&json).unwrap();
+    let result = StringGenerator::generate(&grammar, &mut 0).unwrap();
+    assert_eq!(result.split(' ').count(), 100);
+    assert!(result.ends_with("w99"));
+}
+
+#[test]
+fn custom_modifiers_are_applied_before_built_in_ones() {
+    let grammar =
+        TraceryGrammar::from_tracery_json(r##"{"origin": "#name.shout.a#", "name": "ogre"}"##)
+            .unwrap()
+            .with_modifier("shout", |text, _| format!("{}!", text.to_uppercase()))
+            .with_modifier("a", |text, _| format!("the {text}"));
+    let result = StringGenerator::generate(&grammar, &mut 0);
+    assert_eq!(result.as_deref(), Some("the OGRE!"));
+}
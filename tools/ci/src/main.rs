@@ -14,11 +14,26 @@ fn main() {
         .run()
         .expect("Please fix failing tests in output above.");
 
+    // Make sure the core builds and passes its tests without bevy, so it can be used in servers and tooling
+    cmd!("cargo test --no-default-features --features rand,serde,json_source,ron_source,toml_source,tracery_compat,persistence,packs,parallel,experimental")
+        .run()
+        .expect("Please fix the tests failing without bevy in output above.");
+
     // Run doc tests: these are ignored by `cargo test`
     cmd!("cargo test --doc --workspace")
         .run()
         .expect("Please fix failing doc-tests in output above.");
 
+    // Make sure the crate still builds for the browser, both without bevy and with the default features
+    cmd!("cargo check --target wasm32-unknown-unknown --no-default-features --features wasm,rand,serde,tracery_compat,persistence")
+        .run()
+        .expect("Please fix wasm build errors in output above.");
+    cmd!(
+        "cargo check --target wasm32-unknown-unknown --features wasm,parallel --example wasm_story"
+    )
+    .run()
+    .expect("Please fix wasm build errors in output above.");
+
     // See if clippy has any complaints.
     // - Type complexity must be ignored because we use huge templates for queries
     cmd!("cargo clippy --workspace --all-targets --all-features -- -D warnings -A clippy::type_complexity -W clippy::doc_markdown")